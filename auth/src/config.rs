@@ -1,38 +1,609 @@
-use serde::Deserialize;
+use crate::service::password_hasher::PasswordHashAlgorithm;
+use lightspeed_core::error::{ErrorDetails, LightSpeedError};
+use lightspeed_core::service::validator::order::{validate_ge, validate_le};
+use lightspeed_core::service::validator::urls::validate_url;
+use lightspeed_core::service::validator::{Validable, ERR_NOT_UNIQUE, ERR_VALUE_REQUIRED};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize)]
+/// The minimum length in bytes of the [`AuthConfig::token_hash_key`]
+pub const TOKEN_HASH_KEY_MIN_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct AuthConfig {
     /// Determines the activation token validity minutes
     pub activation_token_validity_minutes: i64,
 
+    /// The secret key of the hash with which the one-time tokens are saved. It should be a random value
+    /// of at least [`TOKEN_HASH_KEY_MIN_LENGTH`] bytes kept outside of the database.
+    /// Changing it invalidates all the tokens that have not been used yet.
+    pub token_hash_key: String,
+
+    /// Determines the validity minutes of the token sent to confirm a new email address
+    pub email_change_token_validity_minutes: i64,
+
+    /// Determines the default validity minutes of an invitation to create an account
+    pub invitation_token_validity_minutes: i64,
+
+    /// Determines the validity minutes of the single-use token sent by email for the passwordless login
+    pub magic_login_token_validity_minutes: i64,
+
+    /// Determines how many not expired magic login tokens an account can have.
+    /// Further requests are rejected until a token is used or expires.
+    pub magic_login_max_tokens_per_account: u32,
+
     /// Determines the maximum session validity minutes.
     /// Once the session expires it is not possible to refresh it
     /// and the user needs to reenter his credentials.
     pub auth_session_max_validity_minutes: i64,
     pub bcrypt_password_hash_cost: u32,
+
+    /// The algorithm used to hash the new passwords.
+    /// The hashes of the other algorithms are still verified and upgraded at the next successful login.
+    pub password_hash_algorithm: PasswordHashAlgorithm,
+
+    /// The Argon2id memory cost in KiB. It needs to be at least 8 times the parallelism.
+    pub argon2_memory_cost_kib: u32,
+
+    /// The Argon2id number of iterations
+    pub argon2_time_cost: u32,
+
+    /// The Argon2id degree of parallelism
+    pub argon2_parallelism: u32,
+
+    /// Determines how many of the last passwords, including the current one, cannot be reused.
+    /// Zero disables the check.
+    pub password_history_size: u32,
+
+    /// Determines after how many days a password expires and has to be changed.
+    /// The passwords never expire if not set.
+    pub max_password_age_days: Option<u32>,
+
     pub default_roles_on_account_creation: Vec<String>,
+
+    /// The issuer shown by the authenticator apps for the TOTP two-factor authentication
+    pub totp_issuer: String,
+
+    /// Determines how many 30 seconds time steps before and after the current one
+    /// are accepted when verifying a TOTP code, to compensate for clock drift
+    pub totp_allowed_skew_steps: i64,
+
+    /// Determines the validity minutes of the challenge returned by the login
+    /// when the second factor is required
+    pub second_factor_challenge_validity_minutes: i64,
+
+    /// Determines how many recovery codes are generated when the two-factor authentication is enabled
+    pub recovery_codes_count: u32,
+
+    /// The WebAuthn relying party id, i.e. the domain of the application (e.g. "example.com").
+    /// The passkeys are bound to it, so it should not be changed once they are in use.
+    pub webauthn_rp_id: String,
+
+    /// The name of the application shown by the authenticators when a passkey is registered
+    pub webauthn_rp_name: String,
+
+    /// The origins from which the WebAuthn ceremonies are accepted (e.g. "https://example.com")
+    pub webauthn_allowed_origins: Vec<String>,
+
+    /// Determines the validity minutes of the challenge of a WebAuthn ceremony
+    pub webauthn_challenge_validity_minutes: i64,
+
+    /// Determines how many consecutive failed logins lock a username
+    pub max_failed_logins_per_username: u32,
+
+    /// Determines how many consecutive failed logins lock a client (e.g. an IP address)
+    pub max_failed_logins_per_client: u32,
+
+    /// Determines the lockout minutes. The failed logins counters are also reset
+    /// when no failure happens for this amount of time.
+    pub failed_login_lockout_minutes: i64,
+
+    /// The delay applied after the first failed login; it doubles at every following failure
+    pub failed_login_base_delay_millis: u64,
+
+    /// The maximum delay applied after a failed login
+    pub failed_login_max_delay_millis: u64,
+
+    /// The permission required to impersonate another user
+    pub impersonation_permission: String,
+
+    /// Determines the validity minutes of the Auth issued to impersonate another user
+    pub impersonation_validity_minutes: i64,
+
+    /// Determines after how many days a disabled account is anonymized by the retention job.
+    /// The disabled accounts are never anonymized automatically if not set.
+    pub anonymize_disabled_accounts_after_days: Option<u32>,
+
+    /// Determines whether the expired tokens, OAuth2 tokens, sessions and failed logins counters
+    /// are periodically deleted when the scheduler is enabled
+    pub purge_expired_tokens_job_enabled: bool,
+
+    /// Determines every how many minutes the maintenance jobs of the module run when the scheduler is enabled
+    pub maintenance_job_interval_minutes: u64,
+
+    /// The custom attributes of the accounts (e.g. first name, locale, phone).
+    /// The attributes not listed here are rejected when updating the attributes of an account.
+    pub account_attributes: Vec<AccountAttributeConfig>,
+
+    /// The OpenID Connect identity providers available for the social login
+    pub oidc_providers: Vec<OidcProviderConfig>,
+
+    /// Determines how many seconds of clock skew are tolerated when verifying the expiration of an ID token
+    pub oidc_allowed_clock_skew_seconds: i64,
+
+    /// The scopes that the OAuth2 clients can request, with the permissions they grant
+    pub oauth2_scopes: Vec<OAuth2ScopeConfig>,
+
+    /// Determines the validity minutes of an OAuth2 authorization code
+    pub oauth2_authorization_code_validity_minutes: i64,
+
+    /// Determines the validity minutes of an OAuth2 access token
+    pub oauth2_access_token_validity_minutes: i64,
+
+    /// The secret key used to sign the OAuth2 access tokens.
+    /// It has to differ from the JWT secret of the application, so that the access tokens
+    /// are not accepted as session tokens.
+    pub oauth2_access_token_secret: String,
+}
+
+/// The maximum length of the name of an account attribute
+pub const ACCOUNT_ATTRIBUTE_NAME_MAX_LENGTH: usize = 32;
+
+/// The error code of an account attribute name not accepted by [`is_valid_account_attribute_name`]
+pub const ERR_INVALID_ATTRIBUTE_NAME: &str = "INVALID_ATTRIBUTE_NAME";
+
+/// A custom attribute of the accounts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccountAttributeConfig {
+    /// The unique name of the attribute (e.g. "first_name").
+    /// Only lowercase ASCII letters, digits and underscores are allowed.
+    pub name: String,
+
+    pub attribute_type: AccountAttributeType,
+
+    /// The maximum number of characters of a String attribute
+    pub max_length: Option<u32>,
+
+    /// If true, the attribute is indexed and the accounts can be searched by its value
+    pub searchable: bool,
+}
+
+impl Default for AccountAttributeConfig {
+    fn default() -> Self {
+        Self { name: "".to_owned(), attribute_type: AccountAttributeType::String, max_length: None, searchable: false }
+    }
+}
+
+impl Validable for AccountAttributeConfig {
+    fn validate(&self, error_details: &mut ErrorDetails) -> Result<(), LightSpeedError> {
+        if !is_valid_account_attribute_name(&self.name) {
+            error_details.add_detail("name", ERR_INVALID_ATTRIBUTE_NAME);
+        }
+        if let Some(max_length) = self.max_length {
+            validate_ge(error_details, "max_length", 1, max_length);
+        }
+        Ok(())
+    }
+}
+
+/// The type of the value of an account attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AccountAttributeType {
+    String,
+    Integer,
+    Boolean,
+}
+
+/// Returns true if the name is not empty, not longer than `ACCOUNT_ATTRIBUTE_NAME_MAX_LENGTH`
+/// and made only of lowercase ASCII letters, digits and underscores
+pub fn is_valid_account_attribute_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= ACCOUNT_ATTRIBUTE_NAME_MAX_LENGTH
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// The client registration at an OpenID Connect identity provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OidcProviderConfig {
+    /// The unique name of the provider (e.g. "google"). It is saved in the linked identities,
+    /// so it should not be changed once the provider is in use.
+    pub name: String,
+
+    /// The issuer URL. The endpoints are read from its `/.well-known/openid-configuration` discovery document.
+    pub issuer_url: String,
+
+    pub client_id: String,
+
+    /// The client secret. It can be omitted for the public clients, the authorization code is always bound to PKCE.
+    pub client_secret: Option<String>,
+
+    /// The URL to which the provider redirects the user after the authentication
+    pub redirect_url: String,
+
+    pub scopes: Vec<String>,
+
+    /// If true, an identity that is not linked yet is linked to the existing account with the same email,
+    /// provided that the provider declares the email as verified. Otherwise, the login fails if the email is
+    /// already used by another account.
+    pub link_accounts_by_verified_email: bool,
+}
+
+impl Default for OidcProviderConfig {
+    fn default() -> Self {
+        Self {
+            name: "".to_owned(),
+            issuer_url: "".to_owned(),
+            client_id: "".to_owned(),
+            client_secret: None,
+            redirect_url: "".to_owned(),
+            scopes: vec!["openid".to_owned(), "email".to_owned(), "profile".to_owned()],
+            link_accounts_by_verified_email: false,
+        }
+    }
+}
+
+impl Validable for OidcProviderConfig {
+    fn validate(&self, error_details: &mut ErrorDetails) -> Result<(), LightSpeedError> {
+        if self.name.trim().is_empty() {
+            error_details.add_detail("name", ERR_VALUE_REQUIRED);
+        }
+        if self.client_id.is_empty() {
+            error_details.add_detail("client_id", ERR_VALUE_REQUIRED);
+        }
+        validate_url(error_details, "issuer_url", &self.issuer_url);
+        validate_url(error_details, "redirect_url", &self.redirect_url);
+        if !self.scopes.iter().any(|scope| scope == "openid") {
+            error_details.add_detail("scopes", ERR_VALUE_REQUIRED);
+        }
+        Ok(())
+    }
+}
+
+/// A scope that the OAuth2 clients can request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OAuth2ScopeConfig {
+    /// The unique name of the scope (e.g. "orders:read")
+    pub name: String,
+
+    /// The description shown to the user when asking for the consent
+    pub description: String,
+
+    /// The permissions granted by the scope. An access token grants only the permissions of its scopes
+    /// that are also granted by the roles of the user, or of the client with the client credentials grant.
+    pub permissions: Vec<String>,
+}
+
+impl Validable for OAuth2ScopeConfig {
+    fn validate(&self, error_details: &mut ErrorDetails) -> Result<(), LightSpeedError> {
+        if self.name.is_empty() || self.name.contains(char::is_whitespace) {
+            error_details.add_detail("name", ERR_VALUE_REQUIRED);
+        }
+        Ok(())
+    }
 }
 
 impl Default for AuthConfig {
     fn default() -> Self {
         Self {
             activation_token_validity_minutes: 120,
+            token_hash_key: "".to_owned(),
+            email_change_token_validity_minutes: 120,
+            invitation_token_validity_minutes: 7 * 24 * 60,
+            magic_login_token_validity_minutes: 15,
+            magic_login_max_tokens_per_account: 3,
             auth_session_max_validity_minutes: 240,
             bcrypt_password_hash_cost: 10,
+            password_hash_algorithm: PasswordHashAlgorithm::Argon2id,
+            argon2_memory_cost_kib: 19456,
+            argon2_time_cost: 2,
+            argon2_parallelism: 1,
+            password_history_size: 5,
+            max_password_age_days: None,
             default_roles_on_account_creation: vec![],
+            totp_issuer: "lightspeed".to_owned(),
+            totp_allowed_skew_steps: 1,
+            second_factor_challenge_validity_minutes: 5,
+            recovery_codes_count: 10,
+            webauthn_rp_id: "localhost".to_owned(),
+            webauthn_rp_name: "lightspeed".to_owned(),
+            webauthn_allowed_origins: vec!["http://localhost".to_owned()],
+            webauthn_challenge_validity_minutes: 5,
+            max_failed_logins_per_username: 5,
+            max_failed_logins_per_client: 50,
+            failed_login_lockout_minutes: 15,
+            failed_login_base_delay_millis: 250,
+            failed_login_max_delay_millis: 4000,
+            impersonation_permission: "auth:impersonate".to_owned(),
+            impersonation_validity_minutes: 30,
+            anonymize_disabled_accounts_after_days: None,
+            purge_expired_tokens_job_enabled: false,
+            maintenance_job_interval_minutes: 60,
+            account_attributes: vec![],
+            oidc_providers: vec![],
+            oidc_allowed_clock_skew_seconds: 60,
+            oauth2_scopes: vec![],
+            oauth2_authorization_code_validity_minutes: 5,
+            oauth2_access_token_validity_minutes: 60,
+            oauth2_access_token_secret: "".to_owned(),
         }
     }
 }
 
+impl Validable for AuthConfig {
+    fn validate(&self, error_details: &mut ErrorDetails) -> Result<(), LightSpeedError> {
+        validate_ge(error_details, "activation_token_validity_minutes", 1, self.activation_token_validity_minutes);
+        validate_ge(error_details, "token_hash_key", TOKEN_HASH_KEY_MIN_LENGTH, self.token_hash_key.len());
+        validate_ge(error_details, "email_change_token_validity_minutes", 1, self.email_change_token_validity_minutes);
+        validate_ge(error_details, "invitation_token_validity_minutes", 1, self.invitation_token_validity_minutes);
+        validate_ge(error_details, "magic_login_token_validity_minutes", 1, self.magic_login_token_validity_minutes);
+        validate_ge(error_details, "magic_login_max_tokens_per_account", 1, self.magic_login_max_tokens_per_account);
+        validate_ge(error_details, "auth_session_max_validity_minutes", 1, self.auth_session_max_validity_minutes);
+        validate_ge(error_details, "bcrypt_password_hash_cost", 4, self.bcrypt_password_hash_cost);
+        validate_le(error_details, "bcrypt_password_hash_cost", 31, self.bcrypt_password_hash_cost);
+        validate_ge(error_details, "argon2_time_cost", 1, self.argon2_time_cost);
+        validate_ge(error_details, "argon2_parallelism", 1, self.argon2_parallelism);
+        validate_ge(
+            error_details,
+            "argon2_memory_cost_kib",
+            self.argon2_parallelism.saturating_mul(8),
+            self.argon2_memory_cost_kib,
+        );
+        if let Some(max_password_age_days) = self.max_password_age_days {
+            validate_ge(error_details, "max_password_age_days", 1, max_password_age_days);
+        }
+        validate_ge(error_details, "totp_allowed_skew_steps", 0, self.totp_allowed_skew_steps);
+        validate_le(error_details, "totp_allowed_skew_steps", 10, self.totp_allowed_skew_steps);
+        validate_ge(
+            error_details,
+            "second_factor_challenge_validity_minutes",
+            1,
+            self.second_factor_challenge_validity_minutes,
+        );
+        validate_ge(error_details, "recovery_codes_count", 1, self.recovery_codes_count);
+        if self.webauthn_rp_id.trim().is_empty() {
+            error_details.add_detail("webauthn_rp_id", ERR_VALUE_REQUIRED);
+        }
+        if self.webauthn_allowed_origins.is_empty() {
+            error_details.add_detail("webauthn_allowed_origins", ERR_VALUE_REQUIRED);
+        }
+        for (count, origin) in self.webauthn_allowed_origins.iter().enumerate() {
+            validate_url(error_details, format!("webauthn_allowed_origins[{count}]"), origin);
+        }
+        validate_ge(error_details, "webauthn_challenge_validity_minutes", 1, self.webauthn_challenge_validity_minutes);
+        validate_ge(error_details, "max_failed_logins_per_username", 1, self.max_failed_logins_per_username);
+        validate_ge(error_details, "max_failed_logins_per_client", 1, self.max_failed_logins_per_client);
+        validate_ge(error_details, "failed_login_lockout_minutes", 1, self.failed_login_lockout_minutes);
+        validate_ge(
+            error_details,
+            "failed_login_max_delay_millis",
+            self.failed_login_base_delay_millis,
+            self.failed_login_max_delay_millis,
+        );
+        if self.impersonation_permission.trim().is_empty() {
+            error_details.add_detail("impersonation_permission", ERR_VALUE_REQUIRED);
+        }
+        validate_ge(error_details, "impersonation_validity_minutes", 1, self.impersonation_validity_minutes);
+        if let Some(anonymize_after_days) = self.anonymize_disabled_accounts_after_days {
+            validate_ge(error_details, "anonymize_disabled_accounts_after_days", 1, anonymize_after_days);
+        }
+        validate_ge(error_details, "maintenance_job_interval_minutes", 1, self.maintenance_job_interval_minutes);
+        for (count, attribute) in self.account_attributes.iter().enumerate() {
+            let mut scoped_err = error_details.with_scope(format!("account_attributes[{count}]"));
+            attribute.validate(&mut scoped_err)?;
+            if self.account_attributes[..count].iter().any(|other| other.name == attribute.name) {
+                scoped_err.add_detail("name", ERR_NOT_UNIQUE);
+            }
+        }
+        validate_ge(error_details, "oidc_allowed_clock_skew_seconds", 0, self.oidc_allowed_clock_skew_seconds);
+        for (count, provider) in self.oidc_providers.iter().enumerate() {
+            let mut scoped_err = error_details.with_scope(format!("oidc_providers[{count}]"));
+            provider.validate(&mut scoped_err)?;
+            if self.oidc_providers[..count].iter().any(|other| other.name == provider.name) {
+                scoped_err.add_detail("name", ERR_NOT_UNIQUE);
+            }
+        }
+        validate_ge(
+            error_details,
+            "oauth2_authorization_code_validity_minutes",
+            1,
+            self.oauth2_authorization_code_validity_minutes,
+        );
+        validate_ge(
+            error_details,
+            "oauth2_access_token_validity_minutes",
+            1,
+            self.oauth2_access_token_validity_minutes,
+        );
+        for (count, scope) in self.oauth2_scopes.iter().enumerate() {
+            let mut scoped_err = error_details.with_scope(format!("oauth2_scopes[{count}]"));
+            scope.validate(&mut scoped_err)?;
+            if self.oauth2_scopes[..count].iter().any(|other| other.name == scope.name) {
+                scoped_err.add_detail("name", ERR_NOT_UNIQUE);
+            }
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
 
     use super::*;
+    use lightspeed_core::service::validator::Validator;
+
+    fn valid_config() -> AuthConfig {
+        AuthConfig { token_hash_key: "a".repeat(TOKEN_HASH_KEY_MIN_LENGTH), ..Default::default() }
+    }
 
     #[test]
     fn should_build_config() {
         let config: AuthConfig = config::Config::builder().build().unwrap().try_deserialize().unwrap();
         assert!(config.default_roles_on_account_creation.is_empty());
+        assert_eq!(5, config.second_factor_challenge_validity_minutes);
+    }
+
+    #[test]
+    fn should_not_validate_a_short_token_hash_key() {
+        assert!(Validator::validate(&AuthConfig::default()).is_err());
+        let config = AuthConfig { token_hash_key: "a".repeat(TOKEN_HASH_KEY_MIN_LENGTH - 1), ..Default::default() };
+        assert!(Validator::validate(&config).is_err());
+        assert!(Validator::validate(&valid_config()).is_ok());
+    }
+
+    #[test]
+    fn should_not_validate_out_of_range_bcrypt_cost() {
+        let config = AuthConfig { bcrypt_password_hash_cost: 32, ..valid_config() };
+        assert!(Validator::validate(&config).is_err());
+        assert!(Validator::validate(&valid_config()).is_ok());
+    }
+
+    #[test]
+    fn should_not_validate_argon2_memory_cost_lower_than_8_times_the_parallelism() {
+        let config = AuthConfig { argon2_memory_cost_kib: 31, argon2_parallelism: 4, ..valid_config() };
+        assert!(Validator::validate(&config).is_err());
+        let config = AuthConfig { argon2_memory_cost_kib: 32, argon2_parallelism: 4, ..valid_config() };
+        assert!(Validator::validate(&config).is_ok());
+    }
+
+    #[test]
+    fn should_not_validate_max_delay_lower_than_base_delay() {
+        let config =
+            AuthConfig { failed_login_base_delay_millis: 1000, failed_login_max_delay_millis: 999, ..valid_config() };
+        assert!(Validator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn should_not_validate_zero_max_password_age() {
+        let config = AuthConfig { max_password_age_days: Some(0), ..valid_config() };
+        assert!(Validator::validate(&config).is_err());
+        let config = AuthConfig { max_password_age_days: Some(90), ..valid_config() };
+        assert!(Validator::validate(&config).is_ok());
+    }
+
+    #[test]
+    fn should_not_validate_zero_magic_login_tokens_per_account() {
+        let config = AuthConfig { magic_login_max_tokens_per_account: 0, ..valid_config() };
+        assert!(Validator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn should_not_validate_an_empty_impersonation_permission() {
+        let config = AuthConfig { impersonation_permission: " ".to_owned(), ..valid_config() };
+        assert!(Validator::validate(&config).is_err());
+        let config = AuthConfig { impersonation_validity_minutes: 0, ..valid_config() };
+        assert!(Validator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn should_validate_the_webauthn_relying_party() {
+        let config = AuthConfig { webauthn_rp_id: "".to_owned(), ..valid_config() };
+        assert!(Validator::validate(&config).is_err());
+
+        let config = AuthConfig { webauthn_allowed_origins: vec![], ..valid_config() };
+        assert!(Validator::validate(&config).is_err());
+
+        let config = AuthConfig { webauthn_allowed_origins: vec!["example.com".to_owned()], ..valid_config() };
+        assert!(Validator::validate(&config).is_err());
+
+        let config = AuthConfig {
+            webauthn_rp_id: "example.com".to_owned(),
+            webauthn_allowed_origins: vec!["https://example.com".to_owned()],
+            ..valid_config()
+        };
+        assert!(Validator::validate(&config).is_ok());
+    }
+
+    #[test]
+    fn should_not_validate_zero_anonymization_retention() {
+        let config = AuthConfig { anonymize_disabled_accounts_after_days: Some(0), ..valid_config() };
+        assert!(Validator::validate(&config).is_err());
+        let config = AuthConfig { anonymize_disabled_accounts_after_days: Some(30), ..valid_config() };
+        assert!(Validator::validate(&config).is_ok());
+    }
+
+    #[test]
+    fn should_validate_the_account_attributes() {
+        let attribute = AccountAttributeConfig { name: "first_name".to_owned(), ..Default::default() };
+
+        let config = AuthConfig { account_attributes: vec![attribute.clone()], ..valid_config() };
+        assert!(Validator::validate(&config).is_ok());
+
+        let config = AuthConfig { account_attributes: vec![attribute.clone(), attribute.clone()], ..valid_config() };
+        assert!(Validator::validate(&config).is_err());
+
+        for name in ["", "First_Name", "first name", "first-name", &"a".repeat(ACCOUNT_ATTRIBUTE_NAME_MAX_LENGTH + 1)] {
+            let config = AuthConfig {
+                account_attributes: vec![AccountAttributeConfig { name: name.to_owned(), ..attribute.clone() }],
+                ..valid_config()
+            };
+            match Validator::validate(&config) {
+                Err(LightSpeedError::ValidationError { details }) => {
+                    assert_eq!(
+                        vec![ERR_INVALID_ATTRIBUTE_NAME.to_owned()],
+                        details.details["account_attributes[0].name"]
+                    );
+                }
+                _ => panic!(),
+            }
+        }
+
+        let config = AuthConfig {
+            account_attributes: vec![AccountAttributeConfig { max_length: Some(0), ..attribute }],
+            ..valid_config()
+        };
+        assert!(Validator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn should_validate_the_oidc_providers() {
+        let provider = OidcProviderConfig {
+            name: "google".to_owned(),
+            issuer_url: "https://accounts.google.com".to_owned(),
+            client_id: "client".to_owned(),
+            redirect_url: "https://example.com/oidc/callback".to_owned(),
+            ..Default::default()
+        };
+
+        let config = AuthConfig { oidc_providers: vec![provider.clone()], ..valid_config() };
+        assert!(Validator::validate(&config).is_ok());
+
+        let config = AuthConfig { oidc_providers: vec![provider.clone(), provider.clone()], ..valid_config() };
+        assert!(Validator::validate(&config).is_err());
+
+        let config = AuthConfig {
+            oidc_providers: vec![OidcProviderConfig { scopes: vec!["email".to_owned()], ..provider }],
+            ..valid_config()
+        };
+        assert!(Validator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn should_validate_the_oauth2_scopes() {
+        let scope = OAuth2ScopeConfig {
+            name: "orders:read".to_owned(),
+            description: "Read your orders".to_owned(),
+            permissions: vec!["orders_read".to_owned()],
+        };
+
+        let config = AuthConfig { oauth2_scopes: vec![scope.clone()], ..valid_config() };
+        assert!(Validator::validate(&config).is_ok());
+
+        let config = AuthConfig { oauth2_scopes: vec![scope.clone(), scope.clone()], ..valid_config() };
+        assert!(Validator::validate(&config).is_err());
+
+        let config = AuthConfig {
+            oauth2_scopes: vec![OAuth2ScopeConfig { name: "orders read".to_owned(), ..scope }],
+            ..valid_config()
+        };
+        assert!(Validator::validate(&config).is_err());
+    }
+
+    #[test]
+    fn should_not_validate_out_of_range_totp_skew() {
+        let config = AuthConfig { totp_allowed_skew_steps: -1, ..valid_config() };
+        assert!(Validator::validate(&config).is_err());
     }
 }
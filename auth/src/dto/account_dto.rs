@@ -0,0 +1,40 @@
+use crate::model::auth_account::{AuthAccountModel, AuthAccountStatus};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// An account without its credentials: the password hashes, the TOTP secret and the recovery codes are omitted.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AccountDto {
+    pub user_id: i64,
+    pub username: String,
+    pub email: String,
+    pub roles: Vec<String>,
+    pub status: AuthAccountStatus,
+    pub created_date_epoch_seconds: i64,
+    pub password_changed_epoch_seconds: i64,
+    pub two_factor_enabled: bool,
+    pub pending_email: Option<String>,
+    pub disabled_date_epoch_seconds: Option<i64>,
+    pub anonymized_date_epoch_seconds: Option<i64>,
+    pub attributes: BTreeMap<String, Value>,
+}
+
+impl From<AuthAccountModel> for AccountDto {
+    fn from(model: AuthAccountModel) -> Self {
+        Self {
+            user_id: model.id,
+            two_factor_enabled: model.data.is_two_factor_enabled(),
+            pending_email: model.data.pending_email_change.map(|pending| pending.email),
+            username: model.data.username,
+            email: model.data.email,
+            roles: model.data.roles,
+            status: model.data.status,
+            created_date_epoch_seconds: model.data.created_date_epoch_seconds,
+            password_changed_epoch_seconds: model.data.password_changed_epoch_seconds,
+            disabled_date_epoch_seconds: model.data.disabled_date_epoch_seconds,
+            anonymized_date_epoch_seconds: model.data.anonymized_date_epoch_seconds,
+            attributes: model.data.attributes,
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use lightspeed_core::error::{ErrorDetails, LightSpeedError};
+use lightspeed_core::service::auth::Owned;
+use lightspeed_core::service::validator::email::validate_email;
+use lightspeed_core::service::validator::Validable;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct ChangeEmailDto {
+    pub user_id: i64,
+    pub password: String,
+    pub new_email: String,
+}
+
+impl Owned for ChangeEmailDto {
+    fn get_owner_id(&self) -> i64 {
+        self.user_id
+    }
+}
+
+impl Validable for ChangeEmailDto {
+    fn validate(&self, error_details: &mut ErrorDetails) -> Result<(), LightSpeedError> {
+        validate_email(error_details, "new_email", &self.new_email);
+        Ok(())
+    }
+}
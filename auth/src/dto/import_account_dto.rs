@@ -0,0 +1,29 @@
+use crate::model::auth_account::AuthAccountStatus;
+use lightspeed_core::error::{ErrorDetails, LightSpeedError};
+use lightspeed_core::service::validator::email::validate_email;
+use lightspeed_core::service::validator::{Validable, ERR_VALUE_REQUIRED};
+use serde::{Deserialize, Serialize};
+
+/// An account imported from another system with its already hashed password.
+/// The supported hash formats are Argon2, bcrypt, PBKDF2-SHA256 (Django) and salted SHA (LDAP).
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct ImportAccountDto {
+    pub username: String,
+    pub email: String,
+    pub password_hash: String,
+    pub roles: Vec<String>,
+    pub status: AuthAccountStatus,
+    /// The creation date in the original system. If missing, the import date is used.
+    pub created_date_epoch_seconds: Option<i64>,
+}
+
+impl Validable for ImportAccountDto {
+    fn validate(&self, error_details: &mut ErrorDetails) -> Result<(), LightSpeedError> {
+        if self.username.is_empty() {
+            error_details.add_detail("username", ERR_VALUE_REQUIRED);
+        }
+        validate_email(error_details, "email", &self.email);
+        Ok(())
+    }
+}
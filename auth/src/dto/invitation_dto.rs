@@ -0,0 +1,77 @@
+use crate::model::token::TokenModel;
+use lightspeed_core::error::{ErrorDetails, LightSpeedError};
+use lightspeed_core::service::validator::boolean::validate_is_true;
+use lightspeed_core::service::validator::email::validate_email;
+use lightspeed_core::service::validator::must_match::validate_must_be_equals;
+use lightspeed_core::service::validator::order::validate_ge;
+use lightspeed_core::service::validator::Validable;
+use serde::{Deserialize, Serialize};
+
+/// An invitation to create an account with the given roles.
+/// The inviter is the user of the AuthContext.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct InviteAccountDto {
+    pub email: String,
+    /// The roles assigned to the account in addition to the default ones
+    pub roles: Vec<String>,
+    /// The validity of the invitation. If missing, the configured one is used.
+    pub validity_minutes: Option<i64>,
+}
+
+impl Validable for InviteAccountDto {
+    fn validate(&self, error_details: &mut ErrorDetails) -> Result<(), LightSpeedError> {
+        validate_email(error_details, "email", &self.email);
+        if let Some(validity_minutes) = self.validity_minutes {
+            validate_ge(error_details, "validity_minutes", 1, validity_minutes);
+        }
+        Ok(())
+    }
+}
+
+/// Completes the registration of an invited user. The email is the invited one.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct AcceptInvitationDto {
+    pub token: String,
+    /// If missing, the username is the email
+    pub username: Option<String>,
+    pub password: String,
+    pub password_confirm: String,
+    pub accept_privacy_policy: bool,
+}
+
+impl Validable for AcceptInvitationDto {
+    fn validate(&self, error_details: &mut ErrorDetails) -> Result<(), LightSpeedError> {
+        validate_must_be_equals(error_details, "password", &self.password, "password_confirm", &self.password_confirm);
+        validate_is_true(error_details, "accept_privacy_policy", self.accept_privacy_policy);
+        Ok(())
+    }
+}
+
+/// An invitation not accepted yet
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct InvitationDto {
+    pub invitation_id: i64,
+    pub email: String,
+    pub roles: Vec<String>,
+    pub invited_by_user_id: Option<i64>,
+    pub created_date_epoch_seconds: i64,
+    pub expire_at_epoch_seconds: i64,
+}
+
+impl InvitationDto {
+    /// Returns None if the token is not an invitation
+    pub fn from_token(token: TokenModel) -> Option<Self> {
+        let invitation = token.data.invitation?;
+        Some(Self {
+            invitation_id: token.id,
+            email: invitation.email,
+            roles: invitation.roles,
+            invited_by_user_id: invitation.invited_by_user_id,
+            created_date_epoch_seconds: invitation.created_date_epoch_seconds,
+            expire_at_epoch_seconds: token.data.expire_at_epoch_seconds,
+        })
+    }
+}
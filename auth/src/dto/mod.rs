@@ -1,9 +1,19 @@
+pub mod account_dto;
 pub mod auth_dto;
+pub mod change_email_dto;
 pub mod change_password_dto;
 pub mod create_login_dto;
+pub mod import_account_dto;
+pub mod invitation_dto;
 pub mod login_dto;
 pub mod login_response_dto;
+pub mod oauth2_dto;
+pub mod oidc_dto;
+pub mod personal_data_dto;
 pub mod reset_password_dto;
+pub mod search_accounts_dto;
 pub mod send_new_activation_token_dto;
 pub mod send_reset_password_dto;
 pub mod token_dto;
+pub mod two_factor_dto;
+pub mod webauthn_dto;
@@ -0,0 +1,130 @@
+use crate::model::oauth2_client::OAuth2GrantType;
+use lightspeed_core::error::{ErrorDetails, LightSpeedError};
+use lightspeed_core::service::validator::urls::validate_url;
+use lightspeed_core::service::validator::{Validable, ERR_VALUE_REQUIRED};
+use serde::{Deserialize, Serialize};
+
+/// The registration of a new OAuth2 client
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct RegisterOAuth2ClientDto {
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub grant_types: Vec<OAuth2GrantType>,
+    /// The scopes that the client is allowed to request; they have to be defined in the AuthConfig
+    pub scopes: Vec<String>,
+    /// The roles of the client for the client credentials grant
+    pub roles: Vec<String>,
+    /// If true, a client secret is generated. Only the confidential clients can use the client credentials grant.
+    pub confidential: bool,
+}
+
+impl Validable for RegisterOAuth2ClientDto {
+    fn validate(&self, error_details: &mut ErrorDetails) -> Result<(), LightSpeedError> {
+        if self.name.trim().is_empty() {
+            error_details.add_detail("name", ERR_VALUE_REQUIRED);
+        }
+        if self.grant_types.is_empty() {
+            error_details.add_detail("grant_types", ERR_VALUE_REQUIRED);
+        }
+        if self.grant_types.contains(&OAuth2GrantType::AuthorizationCode) && self.redirect_uris.is_empty() {
+            error_details.add_detail("redirect_uris", ERR_VALUE_REQUIRED);
+        }
+        if self.grant_types.contains(&OAuth2GrantType::ClientCredentials) && !self.confidential {
+            error_details.add_detail("confidential", ERR_VALUE_REQUIRED);
+        }
+        for (count, redirect_uri) in self.redirect_uris.iter().enumerate() {
+            validate_url(error_details, format!("redirect_uris[{count}]"), redirect_uri);
+        }
+        Ok(())
+    }
+}
+
+/// The query parameters of an authorization request of the authorization code grant.
+/// The PKCE code challenge with the S256 method is always required.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct OAuth2AuthorizationRequestDto {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    /// The space separated requested scopes. All the scopes allowed to the client are requested if not set.
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+/// A validated authorization request, with what has to be shown to the user to ask for the consent
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct OAuth2AuthorizationDetailsDto {
+    pub client_id: String,
+    pub client_name: String,
+    pub scopes: Vec<OAuth2ScopeDto>,
+    /// False if the user has already granted all the requested scopes to the client
+    pub consent_required: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct OAuth2ScopeDto {
+    pub name: String,
+    pub description: String,
+}
+
+/// The approved authorization request. The user has to be redirected to the `redirect_url`,
+/// which contains the authorization code and the state.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct OAuth2AuthorizationResponseDto {
+    pub redirect_url: String,
+    pub code: String,
+    pub state: Option<String>,
+}
+
+/// The parameters of a request to the token endpoint. The client credentials can be taken either from the
+/// HTTP basic authentication or from the request body.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct OAuth2TokenRequestDto {
+    pub grant_type: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    /// The space separated requested scopes of the client credentials grant
+    pub scope: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct OAuth2TokenResponseDto {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub scope: String,
+}
+
+/// The token introspection response as defined by RFC 7662.
+/// Only `active` is set if the token is not valid.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct OAuth2IntrospectionDto {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+}
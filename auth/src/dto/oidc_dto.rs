@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+/// A pending OpenID Connect authorization request.
+/// The user has to be redirected to the `authorization_url`, while the request has to be kept on the server side
+/// (e.g. in the session) until the provider redirects the user back, because it contains the PKCE code verifier.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct OidcAuthorizationRequestDto {
+    pub provider: String,
+    pub authorization_url: String,
+    pub state: String,
+    pub nonce: String,
+    pub pkce_code_verifier: String,
+}
+
+/// The query parameters received on the redirect URL at the end of the authentication on the provider
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct OidcCallbackDto {
+    pub state: String,
+    pub code: String,
+}
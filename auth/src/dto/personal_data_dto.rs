@@ -0,0 +1,89 @@
+use crate::model::auth_account::{AuthAccountModel, AuthAccountStatus};
+use crate::model::auth_session::AuthSessionData;
+use crate::model::external_identity::ExternalIdentityData;
+use crate::model::impersonation::ImpersonationData;
+use crate::model::oauth2_consent::OAuth2ConsentData;
+use crate::model::token::{TokenData, TokenType};
+use crate::model::webauthn_credential::WebAuthnCredentialData;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// The personal data held about a user, as exported on request of the user.
+/// The secrets, like the password hashes and the token values, are not exported.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PersonalDataExportDto {
+    pub exported_date_epoch_seconds: i64,
+    pub account: AccountPersonalDataDto,
+    pub tokens: Vec<TokenPersonalDataDto>,
+    pub sessions: Vec<AuthSessionData>,
+    pub external_identities: Vec<ExternalIdentityData>,
+    pub oauth2_consents: Vec<OAuth2ConsentData>,
+    pub webauthn_credentials: Vec<WebAuthnCredentialPersonalDataDto>,
+    /// The impersonations of the account, e.g. by the support staff, the most recent first
+    pub impersonations: Vec<ImpersonationData>,
+    /// The data contributed by the other modules, by contributor name
+    pub contributions: BTreeMap<String, Value>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AccountPersonalDataDto {
+    pub user_id: i64,
+    pub username: String,
+    pub email: String,
+    pub roles: Vec<String>,
+    pub status: AuthAccountStatus,
+    pub created_date_epoch_seconds: i64,
+    pub password_changed_epoch_seconds: i64,
+    pub two_factor_enabled: bool,
+    pub pending_email: Option<String>,
+    pub disabled_date_epoch_seconds: Option<i64>,
+    pub attributes: BTreeMap<String, Value>,
+}
+
+impl From<AuthAccountModel> for AccountPersonalDataDto {
+    fn from(model: AuthAccountModel) -> Self {
+        Self {
+            user_id: model.id,
+            two_factor_enabled: model.data.is_two_factor_enabled(),
+            pending_email: model.data.pending_email_change.map(|pending| pending.email),
+            username: model.data.username,
+            email: model.data.email,
+            roles: model.data.roles,
+            status: model.data.status,
+            created_date_epoch_seconds: model.data.created_date_epoch_seconds,
+            password_changed_epoch_seconds: model.data.password_changed_epoch_seconds,
+            disabled_date_epoch_seconds: model.data.disabled_date_epoch_seconds,
+            attributes: model.data.attributes,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TokenPersonalDataDto {
+    pub token_type: TokenType,
+    pub expire_at_epoch_seconds: i64,
+}
+
+impl From<TokenData> for TokenPersonalDataDto {
+    fn from(data: TokenData) -> Self {
+        Self { token_type: data.token_type, expire_at_epoch_seconds: data.expire_at_epoch_seconds }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WebAuthnCredentialPersonalDataDto {
+    pub name: String,
+    pub created_date_epoch_seconds: i64,
+    pub last_used_date_epoch_seconds: Option<i64>,
+}
+
+impl From<WebAuthnCredentialData> for WebAuthnCredentialPersonalDataDto {
+    fn from(data: WebAuthnCredentialData) -> Self {
+        Self {
+            name: data.name,
+            created_date_epoch_seconds: data.created_date_epoch_seconds,
+            last_used_date_epoch_seconds: data.last_used_date_epoch_seconds,
+        }
+    }
+}
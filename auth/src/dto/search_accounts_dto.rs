@@ -0,0 +1,110 @@
+use crate::config::{is_valid_account_attribute_name, ERR_INVALID_ATTRIBUTE_NAME};
+use crate::dto::account_dto::AccountDto;
+use crate::model::auth_account::AuthAccountStatus;
+use lightspeed_core::error::{ErrorDetails, LightSpeedError};
+use lightspeed_core::service::validator::order::{validate_ge, validate_le, validate_lt};
+use lightspeed_core::service::validator::Validable;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use strum::{AsRefStr, Display};
+
+/// The maximum number of accounts returned by a single search
+pub const SEARCH_ACCOUNTS_MAX_LIMIT: u32 = 1000;
+
+/// The filters, the sorting and the page of an accounts search. All the filters are optional.
+///
+/// The page is selected either by `offset` or, for the keyset pagination, by `after_user_id`,
+/// which is the id of the last account of the previous page.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct SearchAccountsDto {
+    /// Case-insensitive prefix of the username
+    pub username_prefix: Option<String>,
+    /// Case-insensitive prefix of the email
+    pub email_prefix: Option<String>,
+    pub role: Option<String>,
+    pub status: Option<AuthAccountStatus>,
+    /// Inclusive lower bound of the creation date
+    pub created_from_epoch_seconds: Option<i64>,
+    /// Exclusive upper bound of the creation date
+    pub created_to_epoch_seconds: Option<i64>,
+    /// Exact match on the text value of searchable attributes, by attribute name
+    #[serde(default)]
+    pub attributes: BTreeMap<String, String>,
+    pub sort_by: AccountSortField,
+    pub sort_direction: SortDirection,
+    pub offset: u32,
+    pub after_user_id: Option<i64>,
+    pub limit: u32,
+}
+
+impl Default for SearchAccountsDto {
+    fn default() -> Self {
+        Self {
+            username_prefix: None,
+            email_prefix: None,
+            role: None,
+            status: None,
+            created_from_epoch_seconds: None,
+            created_to_epoch_seconds: None,
+            attributes: BTreeMap::new(),
+            sort_by: AccountSortField::Id,
+            sort_direction: SortDirection::Asc,
+            offset: 0,
+            after_user_id: None,
+            limit: 100,
+        }
+    }
+}
+
+impl Validable for SearchAccountsDto {
+    fn validate(&self, error_details: &mut ErrorDetails) -> Result<(), LightSpeedError> {
+        validate_ge(error_details, "limit", 1, self.limit);
+        validate_le(error_details, "limit", SEARCH_ACCOUNTS_MAX_LIMIT, self.limit);
+        if self.after_user_id.is_some() {
+            validate_le(error_details, "offset", 0, self.offset);
+        }
+        if let (Some(created_from), Some(created_to)) = (self.created_from_epoch_seconds, self.created_to_epoch_seconds)
+        {
+            validate_lt(error_details, "created_from_epoch_seconds", created_to, created_from);
+        }
+        let mut scoped_err = error_details.with_scope("attributes");
+        for name in self.attributes.keys() {
+            if !is_valid_account_attribute_name(name) {
+                scoped_err.add_detail(name.as_str(), ERR_INVALID_ATTRIBUTE_NAME);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, AsRefStr, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Enum))]
+#[cfg_attr(feature = "poem_openapi", oai(rename_all = "snake_case"))]
+pub enum AccountSortField {
+    Id,
+    Username,
+    Email,
+    Status,
+    CreatedDate,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, AsRefStr, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Enum))]
+#[cfg_attr(feature = "poem_openapi", oai(rename_all = "snake_case"))]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+/// A page of the accounts matching a search
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SearchAccountsResultDto {
+    pub accounts: Vec<AccountDto>,
+    /// The number of all the accounts matching the filters, regardless of the page
+    pub total_count: u64,
+}
@@ -0,0 +1,35 @@
+use lightspeed_core::service::auth::Owned;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct TotpEnrollmentDto {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct TotpCodeDto {
+    pub user_id: i64,
+    pub code: String,
+}
+
+impl Owned for TotpCodeDto {
+    fn get_owner_id(&self) -> i64 {
+        self.user_id
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct SecondFactorLoginDto {
+    pub challenge_token: String,
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct RecoveryCodesDto {
+    pub recovery_codes: Vec<String>,
+}
@@ -0,0 +1,143 @@
+use crate::model::webauthn_credential::WebAuthnCredentialModel;
+use lightspeed_core::error::{ErrorDetails, LightSpeedError};
+use lightspeed_core::service::validator::{Validable, ERR_VALUE_REQUIRED};
+use serde::{Deserialize, Serialize};
+
+/// The options of `navigator.credentials.create()` in the JSON format of the WebAuthn specification,
+/// e.g. to be passed to `PublicKeyCredential.parseCreationOptionsFromJSON()`.
+/// The binary values are base64url encoded.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+#[cfg_attr(feature = "poem_openapi", oai(rename_all = "camelCase"))]
+pub struct WebAuthnRegistrationOptionsDto {
+    pub rp: WebAuthnRelyingPartyDto,
+    pub user: WebAuthnUserDto,
+    pub challenge: String,
+    pub pub_key_cred_params: Vec<WebAuthnCredentialParametersDto>,
+    /// The timeout of the ceremony in milliseconds
+    pub timeout: u64,
+    /// The credentials already registered by the user, which the authenticator should not register again
+    pub exclude_credentials: Vec<WebAuthnCredentialDescriptorDto>,
+    pub authenticator_selection: WebAuthnAuthenticatorSelectionDto,
+    pub attestation: String,
+}
+
+/// The options of `navigator.credentials.get()` in the JSON format of the WebAuthn specification,
+/// e.g. to be passed to `PublicKeyCredential.parseRequestOptionsFromJSON()`
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+#[cfg_attr(feature = "poem_openapi", oai(rename_all = "camelCase"))]
+pub struct WebAuthnAssertionOptionsDto {
+    pub challenge: String,
+    /// The timeout of the ceremony in milliseconds
+    pub timeout: u64,
+    pub rp_id: String,
+    /// The credentials accepted for the assertion. It is empty when the user is not known yet,
+    /// in which case the authenticator offers its discoverable credentials.
+    pub allow_credentials: Vec<WebAuthnCredentialDescriptorDto>,
+    pub user_verification: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct WebAuthnRelyingPartyDto {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+#[cfg_attr(feature = "poem_openapi", oai(rename_all = "camelCase"))]
+pub struct WebAuthnUserDto {
+    /// The user handle, see `user_handle`
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct WebAuthnCredentialParametersDto {
+    #[serde(rename = "type")]
+    #[cfg_attr(feature = "poem_openapi", oai(rename = "type"))]
+    pub credential_type: String,
+    /// The COSE identifier of the signature algorithm
+    pub alg: i64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct WebAuthnCredentialDescriptorDto {
+    #[serde(rename = "type")]
+    #[cfg_attr(feature = "poem_openapi", oai(rename = "type"))]
+    pub credential_type: String,
+    pub id: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+#[cfg_attr(feature = "poem_openapi", oai(rename_all = "camelCase"))]
+pub struct WebAuthnAuthenticatorSelectionDto {
+    pub resident_key: String,
+    pub user_verification: String,
+}
+
+/// The response of the authenticator to `navigator.credentials.create()`.
+/// The binary values are base64url encoded.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct WebAuthnRegistrationDto {
+    /// The name with which the user recognizes the credential, e.g. "Work laptop"
+    pub name: String,
+    pub client_data_json: String,
+    pub attestation_object: String,
+}
+
+impl Validable for WebAuthnRegistrationDto {
+    fn validate(&self, error_details: &mut ErrorDetails) -> Result<(), LightSpeedError> {
+        if self.name.trim().is_empty() {
+            error_details.add_detail("name", ERR_VALUE_REQUIRED);
+        }
+        Ok(())
+    }
+}
+
+/// The response of the authenticator to `navigator.credentials.get()`.
+/// The binary values are base64url encoded.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct WebAuthnAssertionDto {
+    pub credential_id: String,
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+    /// Returned by the authenticator for the discoverable credentials
+    pub user_handle: Option<String>,
+}
+
+/// A registered credential, without its public key
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Object))]
+pub struct WebAuthnCredentialDto {
+    pub id: i64,
+    pub name: String,
+    pub credential_id: String,
+    pub created_date_epoch_seconds: i64,
+    pub last_used_date_epoch_seconds: Option<i64>,
+}
+
+impl From<WebAuthnCredentialModel> for WebAuthnCredentialDto {
+    fn from(model: WebAuthnCredentialModel) -> Self {
+        WebAuthnCredentialDto {
+            id: model.id,
+            name: model.data.name,
+            credential_id: model.data.credential_id,
+            created_date_epoch_seconds: model.data.created_date_epoch_seconds,
+            last_used_date_epoch_seconds: model.data.last_used_date_epoch_seconds,
+        }
+    }
+}
@@ -1,7 +1,16 @@
 use crate::config::AuthConfig;
 use crate::repository::AuthRepositoryManager;
-use crate::service::auth_account::AuthAccountService;
+use crate::service::account_event::AccountEventPublisher;
+use crate::service::auth_account::{AuthAccountService, AuthAccountServices};
+use crate::service::auth_session::AuthSessionService;
+use crate::service::login_attempt::LoginAttemptService;
+use crate::service::oauth2::OAuth2Service;
+use crate::service::oidc::OidcService;
 use crate::service::password_codec::PasswordCodecService;
+use crate::service::personal_data::{PersonalDataContributor, PersonalDataService};
+use crate::service::totp::TotpService;
+use crate::service::webauthn::WebAuthnService;
+use lightspeed_core::clock::{Clock, SystemClock};
 use lightspeed_core::error::LightSpeedError;
 use log::*;
 use std::sync::Arc;
@@ -21,26 +30,129 @@ pub struct AuthModule<RepoManager: AuthRepositoryManager> {
     pub password_codec: Arc<service::password_codec::PasswordCodecService>,
     pub auth_account_service: Arc<service::auth_account::AuthAccountService<RepoManager>>,
     pub token_service: Arc<service::token::TokenService<RepoManager>>,
+    pub totp_service: Arc<service::totp::TotpService>,
+    pub webauthn_service: Arc<service::webauthn::WebAuthnService>,
+    pub login_attempt_service: Arc<service::login_attempt::LoginAttemptService<RepoManager>>,
+    pub oidc_service: Arc<service::oidc::OidcService<RepoManager>>,
+    pub auth_session_service: Arc<service::auth_session::AuthSessionService<RepoManager>>,
+    pub account_event_publisher: Arc<service::account_event::AccountEventPublisher<RepoManager>>,
+    pub clock: Arc<dyn Clock>,
 }
 
 impl<RepoManager: AuthRepositoryManager> AuthModule<RepoManager> {
-    pub fn new(repo_manager: RepoManager, auth_config: AuthConfig) -> Self {
+    /// Fails if the `token_hash_key` of the AuthConfig is too short, see `TokenService::new`
+    pub fn new(repo_manager: RepoManager, auth_config: AuthConfig) -> Result<Self, LightSpeedError> {
+        Self::new_with_clock(repo_manager, auth_config, SystemClock::shared())
+    }
+
+    /// Creates an AuthModule whose services read the time from the given clock
+    pub fn new_with_clock(
+        repo_manager: RepoManager,
+        auth_config: AuthConfig,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, LightSpeedError> {
         println!("Creating AuthModule");
         info!("Creating AuthModule");
 
-        let password_codec = Arc::new(PasswordCodecService::new(auth_config.bcrypt_password_hash_cost));
+        let password_codec = Arc::new(PasswordCodecService::new(&auth_config));
+
+        let token_service = Arc::new(service::token::TokenService::new(
+            repo_manager.c3p0().clone(),
+            auth_config.clone(),
+            repo_manager.token_repo(),
+            clock.clone(),
+        )?);
 
-        let token_service = Arc::new(service::token::TokenService::new(auth_config.clone(), repo_manager.token_repo()));
+        let totp_service = Arc::new(TotpService::new(&auth_config, clock.clone()));
 
-        let auth_account_service = Arc::new(AuthAccountService::new(
+        let webauthn_service = Arc::new(WebAuthnService::new(&auth_config));
+
+        let login_attempt_service = Arc::new(LoginAttemptService::new(
+            repo_manager.c3p0().clone(),
+            auth_config.clone(),
+            repo_manager.login_attempt_repo(),
+            clock.clone(),
+        ));
+
+        let auth_session_service = Arc::new(AuthSessionService::new(
             repo_manager.c3p0().clone(),
+            repo_manager.auth_session_repo(),
+            clock.clone(),
+        ));
+
+        let account_event_publisher = Arc::new(AccountEventPublisher::new());
+
+        let auth_account_service = Arc::new(AuthAccountService::new(
+            &repo_manager,
             auth_config.clone(),
-            token_service.clone(),
-            password_codec.clone(),
+            AuthAccountServices {
+                token_service: token_service.clone(),
+                password_service: password_codec.clone(),
+                totp_service: totp_service.clone(),
+                webauthn_service: webauthn_service.clone(),
+                login_attempt_service: login_attempt_service.clone(),
+                auth_session_service: auth_session_service.clone(),
+                account_event_publisher: account_event_publisher.clone(),
+            },
+            clock.clone(),
+        ));
+
+        let oidc_service = Arc::new(OidcService::new(
+            repo_manager.c3p0().clone(),
+            &auth_config,
+            auth_account_service.clone(),
+            account_event_publisher.clone(),
             repo_manager.auth_account_repo(),
+            repo_manager.external_identity_repo(),
+            clock.clone(),
         ));
 
-        AuthModule { auth_config, repo_manager, password_codec, auth_account_service, token_service }
+        Ok(AuthModule {
+            auth_config,
+            repo_manager,
+            password_codec,
+            auth_account_service,
+            token_service,
+            totp_service,
+            webauthn_service,
+            login_attempt_service,
+            oidc_service,
+            auth_session_service,
+            account_event_publisher,
+            clock,
+        })
+    }
+
+    /// Creates an OAuth2Service whose access tokens are signed with the `oauth2_access_token_secret`
+    /// of the AuthConfig. It fails if the secret is empty.
+    pub fn new_oauth2_service(&self) -> Result<OAuth2Service<RepoManager>, LightSpeedError> {
+        OAuth2Service::new(
+            &self.repo_manager,
+            self.auth_config.clone(),
+            self.password_codec.clone(),
+            self.clock.clone(),
+        )
+    }
+
+    /// Creates a PersonalDataService whose exports include the data of the given contributors
+    pub fn new_personal_data_service(
+        &self,
+        contributors: Vec<Arc<dyn PersonalDataContributor>>,
+    ) -> PersonalDataService<RepoManager> {
+        PersonalDataService::new(&self.repo_manager, self.token_service.clone(), contributors, self.clock.clone())
+    }
+
+    /// Returns the services used by the AuthAccountService of the module
+    pub fn auth_account_services(&self) -> AuthAccountServices<RepoManager> {
+        AuthAccountServices {
+            token_service: self.token_service.clone(),
+            password_service: self.password_codec.clone(),
+            totp_service: self.totp_service.clone(),
+            webauthn_service: self.webauthn_service.clone(),
+            login_attempt_service: self.login_attempt_service.clone(),
+            auth_session_service: self.auth_session_service.clone(),
+            account_event_publisher: self.account_event_publisher.clone(),
+        }
     }
 }
 
@@ -49,6 +161,7 @@ impl<RepoManager: AuthRepositoryManager> lightspeed_core::module::Module for Aut
     async fn start(&mut self) -> Result<(), LightSpeedError> {
         info!("Starting AuthModule");
         self.repo_manager.start().await?;
+        self.auth_account_service.create_attribute_indexes().await?;
         Ok(())
     }
 }
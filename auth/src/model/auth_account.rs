@@ -2,7 +2,9 @@ use c3p0::{C3p0Error, JsonCodec, Model};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use strum::{AsRefStr, Display};
+use unicode_normalization::UnicodeNormalization;
 
 pub type AuthAccountModel = Model<AuthAccountData>;
 
@@ -14,6 +16,131 @@ pub struct AuthAccountData {
     pub roles: Vec<String>,
     pub created_date_epoch_seconds: i64,
     pub status: AuthAccountStatus,
+    pub two_factor: Option<TwoFactorData>,
+    /// The hashes of the previous passwords, the most recent first
+    pub password_history: Vec<String>,
+    /// When the password has been set for the last time
+    pub password_changed_epoch_seconds: i64,
+    #[serde(default)]
+    pub pending_email_change: Option<PendingEmailChange>,
+    /// When the account has been disabled for the last time
+    #[serde(default)]
+    pub disabled_date_epoch_seconds: Option<i64>,
+    /// When the personal data of the account have been scrubbed. An anonymized account cannot be reactivated.
+    #[serde(default)]
+    pub anonymized_date_epoch_seconds: Option<i64>,
+    /// The custom attributes of the account, as declared by the `account_attributes` of the `AuthConfig`
+    #[serde(default)]
+    pub attributes: BTreeMap<String, Value>,
+}
+
+/// A requested email change waiting for the confirmation of the new address
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PendingEmailChange {
+    pub email: String,
+    /// The hash of the EmailChange token sent to the new address; only this token can confirm the change
+    pub token: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct AuthAccountDataV1 {
+    username: String,
+    email: String,
+    password: String,
+    roles: Vec<String>,
+    created_date_epoch_seconds: i64,
+    status: AuthAccountStatus,
+    #[serde(default)]
+    two_factor: Option<TwoFactorData>,
+}
+
+impl From<AuthAccountDataV1> for AuthAccountData {
+    fn from(data: AuthAccountDataV1) -> Self {
+        AuthAccountData {
+            password_changed_epoch_seconds: data.created_date_epoch_seconds,
+            username: data.username,
+            email: data.email,
+            password: data.password,
+            roles: data.roles,
+            created_date_epoch_seconds: data.created_date_epoch_seconds,
+            status: data.status,
+            two_factor: data.two_factor,
+            password_history: vec![],
+            pending_email_change: None,
+            disabled_date_epoch_seconds: None,
+            anonymized_date_epoch_seconds: None,
+            attributes: BTreeMap::new(),
+        }
+    }
+}
+
+/// The TOTP two-factor authentication settings of an account
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TwoFactorData {
+    /// The base32 encoded TOTP secret
+    pub totp_secret: String,
+    /// False until the enrollment is confirmed with a valid code
+    pub enabled: bool,
+    /// The hashes of the recovery codes not used yet
+    pub recovery_code_hashes: Vec<String>,
+    /// The time step of the last accepted code, used to prevent the reuse of a code
+    pub last_used_time_step: Option<i64>,
+}
+
+impl AuthAccountData {
+    /// Returns true if the account requires a second factor to login
+    pub fn is_two_factor_enabled(&self) -> bool {
+        self.two_factor.as_ref().map(|two_factor| two_factor.enabled).unwrap_or(false)
+    }
+
+    /// Replaces the password hash keeping the previous one in the history.
+    /// The history retains at most `history_size - 1` hashes, so that together with the current one
+    /// the last `history_size` passwords are known.
+    pub fn set_password(&mut self, password_hash: String, history_size: usize, now_epoch_seconds: i64) {
+        let previous = std::mem::replace(&mut self.password, password_hash);
+        self.password_history.insert(0, previous);
+        self.password_history.truncate(history_size.saturating_sub(1));
+        self.password_changed_epoch_seconds = now_epoch_seconds;
+    }
+
+    /// Scrubs the personal data keeping only the creation date. The username and the email are replaced
+    /// by unique placeholders derived from the user id; the password is replaced by the given unusable hash.
+    pub fn anonymize(&mut self, user_id: i64, unusable_password_hash: String, now_epoch_seconds: i64) {
+        self.username = format!("anonymized-{user_id}");
+        self.email = format!("anonymized-{user_id}@anonymized.invalid");
+        self.password = unusable_password_hash;
+        self.password_history.clear();
+        self.password_changed_epoch_seconds = now_epoch_seconds;
+        self.roles.clear();
+        self.two_factor = None;
+        self.pending_email_change = None;
+        self.attributes.clear();
+        if self.status != AuthAccountStatus::Disabled {
+            self.status = AuthAccountStatus::Disabled;
+            self.disabled_date_epoch_seconds = Some(now_epoch_seconds);
+        }
+        self.anonymized_date_epoch_seconds = Some(now_epoch_seconds);
+    }
+
+    pub fn is_anonymized(&self) -> bool {
+        self.anonymized_date_epoch_seconds.is_some()
+    }
+
+    /// Returns true if the password has not been changed for more than the given days
+    pub fn is_password_expired_at(&self, max_password_age_days: u32, now_epoch_seconds: i64) -> bool {
+        now_epoch_seconds - self.password_changed_epoch_seconds >= i64::from(max_password_age_days) * 24 * 60 * 60
+    }
+}
+
+/// Returns the canonical form of a username: trimmed, Unicode NFKC normalized and lowercase.
+/// Usernames that differ only by case or by compatibility characters identify the same account.
+pub fn normalize_username(username: &str) -> String {
+    username.trim().nfkc().collect::<String>().to_lowercase()
+}
+
+/// Returns the canonical form of an email: trimmed and lowercase
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize, AsRefStr, Display)]
@@ -27,7 +154,8 @@ pub enum AuthAccountStatus {
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(tag = "_json_tag")]
 enum AuthAccountDataVersioning<'a> {
-    V1(Cow<'a, AuthAccountData>),
+    V1(AuthAccountDataV1),
+    V2(Cow<'a, AuthAccountData>),
 }
 
 #[derive(Clone)]
@@ -37,12 +165,149 @@ impl JsonCodec<AuthAccountData> for AuthAccountDataCodec {
     fn data_from_value(&self, value: Value) -> Result<AuthAccountData, C3p0Error> {
         let versioning = serde_json::from_value(value)?;
         let data = match versioning {
-            AuthAccountDataVersioning::V1(data_v1) => data_v1.into_owned(),
+            AuthAccountDataVersioning::V1(data_v1) => data_v1.into(),
+            AuthAccountDataVersioning::V2(data_v2) => data_v2.into_owned(),
         };
         Ok(data)
     }
 
     fn data_to_value(&self, data: &AuthAccountData) -> Result<Value, C3p0Error> {
-        serde_json::to_value(AuthAccountDataVersioning::V1(Cow::Borrowed(data))).map_err(C3p0Error::from)
+        serde_json::to_value(AuthAccountDataVersioning::V2(Cow::Borrowed(data))).map_err(C3p0Error::from)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn should_read_accounts_saved_without_two_factor() {
+        let value = json!({
+            "_json_tag": "V1",
+            "username": "username",
+            "email": "email@email.fake",
+            "password": "password",
+            "roles": [],
+            "created_date_epoch_seconds": 0,
+            "status": "Active"
+        });
+
+        let data = AuthAccountDataCodec {}.data_from_value(value).unwrap();
+
+        assert!(data.two_factor.is_none());
+        assert!(!data.is_two_factor_enabled());
+    }
+
+    #[test]
+    fn should_normalize_usernames_and_emails() {
+        assert_eq!("bob", normalize_username("  Bob "));
+        assert_eq!("bob", normalize_username("ＢＯＢ"));
+        assert_eq!("bob@example.com", normalize_email(" Bob@Example.com\t"));
+        assert_eq!(normalize_username("ﬁle"), normalize_username("FILE"));
+    }
+
+    #[test]
+    fn should_migrate_v1_accounts_to_v2() {
+        let value = json!({
+            "_json_tag": "V1",
+            "username": "username",
+            "email": "email@email.fake",
+            "password": "password",
+            "roles": ["admin"],
+            "created_date_epoch_seconds": 123,
+            "status": "Active",
+            "two_factor": null
+        });
+
+        let codec = AuthAccountDataCodec {};
+        let data = codec.data_from_value(value).unwrap();
+
+        assert_eq!("password", data.password);
+        assert_eq!(vec!["admin".to_owned()], data.roles);
+        assert!(data.password_history.is_empty());
+        assert_eq!(123, data.password_changed_epoch_seconds);
+        assert!(data.pending_email_change.is_none());
+        assert!(data.attributes.is_empty());
+
+        let value = codec.data_to_value(&data).unwrap();
+        assert_eq!("V2", value["_json_tag"]);
+        assert_eq!(123, codec.data_from_value(value).unwrap().password_changed_epoch_seconds);
+    }
+
+    #[test]
+    fn should_keep_the_last_passwords_in_the_history() {
+        let mut data = AuthAccountDataCodec {}
+            .data_from_value(json!({
+                "_json_tag": "V1",
+                "username": "username",
+                "email": "email@email.fake",
+                "password": "hash_0",
+                "roles": [],
+                "created_date_epoch_seconds": 0,
+                "status": "Active"
+            }))
+            .unwrap();
+
+        data.set_password("hash_1".to_owned(), 3, 10);
+        data.set_password("hash_2".to_owned(), 3, 20);
+        data.set_password("hash_3".to_owned(), 3, 30);
+
+        assert_eq!("hash_3", data.password);
+        assert_eq!(vec!["hash_2".to_owned(), "hash_1".to_owned()], data.password_history);
+        assert_eq!(30, data.password_changed_epoch_seconds);
+
+        data.set_password("hash_4".to_owned(), 0, 40);
+        assert!(data.password_history.is_empty());
+    }
+
+    #[test]
+    fn should_expire_the_password_after_the_max_age() {
+        let mut data = AuthAccountDataCodec {}
+            .data_from_value(json!({
+                "_json_tag": "V1",
+                "username": "username",
+                "email": "email@email.fake",
+                "password": "hash_0",
+                "roles": [],
+                "created_date_epoch_seconds": 0,
+                "status": "Active"
+            }))
+            .unwrap();
+        data.password_changed_epoch_seconds = 1000;
+
+        assert!(!data.is_password_expired_at(1, 1000 + 24 * 60 * 60 - 1));
+        assert!(data.is_password_expired_at(1, 1000 + 24 * 60 * 60));
+    }
+
+    #[test]
+    fn should_scrub_the_personal_data() {
+        let mut data = AuthAccountDataCodec {}
+            .data_from_value(json!({
+                "_json_tag": "V1",
+                "username": "username",
+                "email": "email@email.fake",
+                "password": "hash_0",
+                "roles": ["admin"],
+                "created_date_epoch_seconds": 10,
+                "status": "Active"
+            }))
+            .unwrap();
+        data.set_password("hash_1".to_owned(), 3, 20);
+        data.attributes.insert("first_name".to_owned(), json!("Name"));
+
+        data.anonymize(123, "unusable".to_owned(), 30);
+
+        assert_eq!("anonymized-123", data.username);
+        assert_eq!("anonymized-123@anonymized.invalid", data.email);
+        assert_eq!("unusable", data.password);
+        assert!(data.password_history.is_empty());
+        assert!(data.roles.is_empty());
+        assert!(data.attributes.is_empty());
+        assert_eq!(AuthAccountStatus::Disabled, data.status);
+        assert_eq!(Some(30), data.disabled_date_epoch_seconds);
+        assert_eq!(10, data.created_date_epoch_seconds);
+        assert!(data.is_anonymized());
     }
 }
@@ -0,0 +1,49 @@
+use c3p0::{C3p0Error, JsonCodec, Model};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+
+pub type AuthSessionModel = Model<AuthSessionData>;
+
+/// A login session of a user. Its `session_id` is the one of the Auth issued by the login.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AuthSessionData {
+    pub session_id: String,
+    pub user_id: i64,
+    /// The user agent of the last request of the session, if known
+    pub user_agent: Option<String>,
+    /// The IP address of the last request of the session, if known
+    pub ip_address: Option<String>,
+    pub created_date_epoch_seconds: i64,
+    pub last_seen_epoch_seconds: i64,
+    pub expire_at_epoch_seconds: i64,
+}
+
+impl AuthSessionData {
+    pub fn is_expired_at(&self, epoch_seconds: i64) -> bool {
+        epoch_seconds > self.expire_at_epoch_seconds
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "_json_tag")]
+enum AuthSessionDataVersioning<'a> {
+    V1(Cow<'a, AuthSessionData>),
+}
+
+#[derive(Clone)]
+pub struct AuthSessionDataCodec {}
+
+impl JsonCodec<AuthSessionData> for AuthSessionDataCodec {
+    fn data_from_value(&self, value: Value) -> Result<AuthSessionData, C3p0Error> {
+        let versioning = serde_json::from_value(value)?;
+        let data = match versioning {
+            AuthSessionDataVersioning::V1(data_v1) => data_v1.into_owned(),
+        };
+        Ok(data)
+    }
+
+    fn data_to_value(&self, data: &AuthSessionData) -> Result<Value, C3p0Error> {
+        serde_json::to_value(AuthSessionDataVersioning::V1(Cow::Borrowed(data))).map_err(C3p0Error::from)
+    }
+}
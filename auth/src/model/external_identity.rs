@@ -0,0 +1,42 @@
+use c3p0::{C3p0Error, JsonCodec, Model};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+
+pub type ExternalIdentityModel = Model<ExternalIdentityData>;
+
+/// An identity of an external OpenID Connect provider linked to an account
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExternalIdentityData {
+    pub user_id: i64,
+    /// The name of the provider in the AuthConfig
+    pub provider: String,
+    /// The `sub` claim of the ID tokens, unique per provider
+    pub subject: String,
+    /// The email declared by the provider when the identity was linked
+    pub email: Option<String>,
+    pub created_date_epoch_seconds: i64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "_json_tag")]
+enum ExternalIdentityDataVersioning<'a> {
+    V1(Cow<'a, ExternalIdentityData>),
+}
+
+#[derive(Clone)]
+pub struct ExternalIdentityDataCodec {}
+
+impl JsonCodec<ExternalIdentityData> for ExternalIdentityDataCodec {
+    fn data_from_value(&self, value: Value) -> Result<ExternalIdentityData, C3p0Error> {
+        let versioning = serde_json::from_value(value)?;
+        let data = match versioning {
+            ExternalIdentityDataVersioning::V1(data_v1) => data_v1.into_owned(),
+        };
+        Ok(data)
+    }
+
+    fn data_to_value(&self, data: &ExternalIdentityData) -> Result<Value, C3p0Error> {
+        serde_json::to_value(ExternalIdentityDataVersioning::V1(Cow::Borrowed(data))).map_err(C3p0Error::from)
+    }
+}
@@ -0,0 +1,43 @@
+use c3p0::{C3p0Error, JsonCodec, Model};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+
+pub type ImpersonationModel = Model<ImpersonationData>;
+
+/// The audit record of a user impersonated by another one
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ImpersonationData {
+    /// The id of the impersonated user
+    pub user_id: i64,
+    pub impersonator_user_id: i64,
+    /// The session of the Auth issued to the impersonator
+    pub session_id: String,
+    /// The reason given by the impersonator, e.g. the id of a support ticket
+    pub reason: Option<String>,
+    pub created_date_epoch_seconds: i64,
+    pub expire_at_epoch_seconds: i64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "_json_tag")]
+enum ImpersonationDataVersioning<'a> {
+    V1(Cow<'a, ImpersonationData>),
+}
+
+#[derive(Clone)]
+pub struct ImpersonationDataCodec {}
+
+impl JsonCodec<ImpersonationData> for ImpersonationDataCodec {
+    fn data_from_value(&self, value: Value) -> Result<ImpersonationData, C3p0Error> {
+        let versioning = serde_json::from_value(value)?;
+        let data = match versioning {
+            ImpersonationDataVersioning::V1(data_v1) => data_v1.into_owned(),
+        };
+        Ok(data)
+    }
+
+    fn data_to_value(&self, data: &ImpersonationData) -> Result<Value, C3p0Error> {
+        serde_json::to_value(ImpersonationDataVersioning::V1(Cow::Borrowed(data))).map_err(C3p0Error::from)
+    }
+}
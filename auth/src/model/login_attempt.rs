@@ -0,0 +1,55 @@
+use c3p0::{C3p0Error, JsonCodec, Model};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use strum::{AsRefStr, Display};
+
+pub type LoginAttemptModel = Model<LoginAttemptData>;
+
+/// The failed login attempts counter of a username or of a client
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LoginAttemptData {
+    pub key: String,
+    pub key_type: LoginAttemptKeyType,
+    pub failed_attempts: u32,
+    pub last_failure_epoch_seconds: i64,
+    pub locked_until_epoch_seconds: Option<i64>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, AsRefStr, Display)]
+pub enum LoginAttemptKeyType {
+    /// The counter of the attempts for a username
+    Username,
+    /// The counter of the attempts from a client, e.g. an IP address
+    Client,
+}
+
+impl LoginAttemptData {
+    /// Returns true if the lockout is still active at the given time
+    pub fn is_locked_at(&self, epoch_seconds: i64) -> bool {
+        self.locked_until_epoch_seconds.map(|locked_until| locked_until > epoch_seconds).unwrap_or(false)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "_json_tag")]
+enum LoginAttemptDataVersioning<'a> {
+    V1(Cow<'a, LoginAttemptData>),
+}
+
+#[derive(Clone)]
+pub struct LoginAttemptDataCodec {}
+
+impl JsonCodec<LoginAttemptData> for LoginAttemptDataCodec {
+    fn data_from_value(&self, value: Value) -> Result<LoginAttemptData, C3p0Error> {
+        let versioning = serde_json::from_value(value)?;
+        let data = match versioning {
+            LoginAttemptDataVersioning::V1(data_v1) => data_v1.into_owned(),
+        };
+        Ok(data)
+    }
+
+    fn data_to_value(&self, data: &LoginAttemptData) -> Result<Value, C3p0Error> {
+        serde_json::to_value(LoginAttemptDataVersioning::V1(Cow::Borrowed(data))).map_err(C3p0Error::from)
+    }
+}
@@ -1,2 +1,10 @@
 pub mod auth_account;
+pub mod auth_session;
+pub mod external_identity;
+pub mod impersonation;
+pub mod login_attempt;
+pub mod oauth2_client;
+pub mod oauth2_consent;
+pub mod oauth2_token;
 pub mod token;
+pub mod webauthn_credential;
@@ -0,0 +1,81 @@
+use c3p0::{C3p0Error, JsonCodec, Model};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use strum::{AsRefStr, Display, EnumString};
+
+pub type OAuth2ClientModel = Model<OAuth2ClientData>;
+
+/// An API client registered to obtain OAuth2 access tokens
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OAuth2ClientData {
+    /// The public identifier of the client
+    pub client_id: String,
+    /// The hash of the client secret. The public clients (e.g. single page and mobile apps) have no secret.
+    pub client_secret_hash: Option<String>,
+    pub name: String,
+    /// The exact redirect URIs accepted in the authorization requests
+    pub redirect_uris: Vec<String>,
+    pub grant_types: Vec<OAuth2GrantType>,
+    /// The scopes that the client is allowed to request
+    pub scopes: Vec<String>,
+    /// The roles of the client when it acts on its own behalf with the client credentials grant
+    pub roles: Vec<String>,
+    pub created_date_epoch_seconds: i64,
+}
+
+impl OAuth2ClientData {
+    /// Returns true if the client authenticates with a secret
+    pub fn is_confidential(&self) -> bool {
+        self.client_secret_hash.is_some()
+    }
+}
+
+/// The grant types, serialized with the names used in the `grant_type` parameter of the token requests
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, AsRefStr, Display, EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+#[cfg_attr(feature = "poem_openapi", derive(poem_openapi::Enum))]
+#[cfg_attr(feature = "poem_openapi", oai(rename_all = "snake_case"))]
+pub enum OAuth2GrantType {
+    AuthorizationCode,
+    ClientCredentials,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "_json_tag")]
+enum OAuth2ClientDataVersioning<'a> {
+    V1(Cow<'a, OAuth2ClientData>),
+}
+
+#[derive(Clone)]
+pub struct OAuth2ClientDataCodec {}
+
+impl JsonCodec<OAuth2ClientData> for OAuth2ClientDataCodec {
+    fn data_from_value(&self, value: Value) -> Result<OAuth2ClientData, C3p0Error> {
+        let versioning = serde_json::from_value(value)?;
+        let data = match versioning {
+            OAuth2ClientDataVersioning::V1(data_v1) => data_v1.into_owned(),
+        };
+        Ok(data)
+    }
+
+    fn data_to_value(&self, data: &OAuth2ClientData) -> Result<Value, C3p0Error> {
+        serde_json::to_value(OAuth2ClientDataVersioning::V1(Cow::Borrowed(data))).map_err(C3p0Error::from)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn should_use_the_grant_type_parameter_values() {
+        assert_eq!("authorization_code", OAuth2GrantType::AuthorizationCode.as_ref());
+        assert_eq!("\"client_credentials\"", serde_json::to_string(&OAuth2GrantType::ClientCredentials).unwrap());
+        assert_eq!(OAuth2GrantType::ClientCredentials, OAuth2GrantType::from_str("client_credentials").unwrap());
+        assert!(OAuth2GrantType::from_str("password").is_err());
+    }
+}
@@ -0,0 +1,39 @@
+use c3p0::{C3p0Error, JsonCodec, Model};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+
+pub type OAuth2ConsentModel = Model<OAuth2ConsentData>;
+
+/// The scopes that a user has granted to an OAuth2 client.
+/// The user is not asked again for the consent while the client requests only these scopes.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OAuth2ConsentData {
+    pub user_id: i64,
+    pub client_id: String,
+    pub scopes: Vec<String>,
+    pub updated_date_epoch_seconds: i64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "_json_tag")]
+enum OAuth2ConsentDataVersioning<'a> {
+    V1(Cow<'a, OAuth2ConsentData>),
+}
+
+#[derive(Clone)]
+pub struct OAuth2ConsentDataCodec {}
+
+impl JsonCodec<OAuth2ConsentData> for OAuth2ConsentDataCodec {
+    fn data_from_value(&self, value: Value) -> Result<OAuth2ConsentData, C3p0Error> {
+        let versioning = serde_json::from_value(value)?;
+        let data = match versioning {
+            OAuth2ConsentDataVersioning::V1(data_v1) => data_v1.into_owned(),
+        };
+        Ok(data)
+    }
+
+    fn data_to_value(&self, data: &OAuth2ConsentData) -> Result<Value, C3p0Error> {
+        serde_json::to_value(OAuth2ConsentDataVersioning::V1(Cow::Borrowed(data))).map_err(C3p0Error::from)
+    }
+}
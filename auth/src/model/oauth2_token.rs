@@ -0,0 +1,52 @@
+use c3p0::{C3p0Error, JsonCodec, Model};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+
+pub type OAuth2TokenModel = Model<OAuth2TokenData>;
+
+/// An OAuth2 authorization code, or an issued access token that has not been revoked
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OAuth2TokenData {
+    /// The authorization code, or the id of the access token saved as `session_id` of its Auth
+    pub token: String,
+    pub token_type: OAuth2TokenType,
+    pub client_id: String,
+    /// The user that authorized the client; it is not set for the client credentials grant
+    pub user_id: Option<i64>,
+    pub scopes: Vec<String>,
+    /// The redirect URI of the authorization request, only for the authorization codes
+    pub redirect_uri: Option<String>,
+    /// The PKCE S256 code challenge of the authorization request, only for the authorization codes
+    pub code_challenge: Option<String>,
+    pub expire_at_epoch_seconds: i64,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum OAuth2TokenType {
+    AuthorizationCode,
+    AccessToken,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "_json_tag")]
+enum OAuth2TokenDataVersioning<'a> {
+    V1(Cow<'a, OAuth2TokenData>),
+}
+
+#[derive(Clone)]
+pub struct OAuth2TokenDataCodec {}
+
+impl JsonCodec<OAuth2TokenData> for OAuth2TokenDataCodec {
+    fn data_from_value(&self, value: Value) -> Result<OAuth2TokenData, C3p0Error> {
+        let versioning = serde_json::from_value(value)?;
+        let data = match versioning {
+            OAuth2TokenDataVersioning::V1(data_v1) => data_v1.into_owned(),
+        };
+        Ok(data)
+    }
+
+    fn data_to_value(&self, data: &OAuth2TokenData) -> Result<Value, C3p0Error> {
+        serde_json::to_value(OAuth2TokenDataVersioning::V1(Cow::Borrowed(data))).map_err(C3p0Error::from)
+    }
+}
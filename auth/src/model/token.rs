@@ -1,25 +1,49 @@
 use c3p0::*;
+use lightspeed_core::clock::{Clock, SystemClock};
 use lightspeed_core::error::{ErrorDetails, LightSpeedError};
 use lightspeed_core::service::validator::Validable;
-use lightspeed_core::utils::current_epoch_seconds;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::borrow::Cow;
+use strum::AsRefStr;
 
 pub type TokenModel = Model<TokenData>;
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct TokenData {
     pub token: String,
+    /// The username of the account the token belongs to; empty for an Invitation token,
+    /// whose email is in the invitation data
     pub username: String,
     pub token_type: TokenType,
     pub expire_at_epoch_seconds: i64,
+    /// Present only in the Invitation tokens
+    #[serde(default)]
+    pub invitation: Option<InvitationData>,
 }
 
-#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+/// The account to be created when an invitation is accepted
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InvitationData {
+    /// The invited email
+    pub email: String,
+    /// The roles assigned to the account in addition to the default ones
+    pub roles: Vec<String>,
+    /// The id of the user who created the invitation, if any
+    pub invited_by_user_id: Option<i64>,
+    pub created_date_epoch_seconds: i64,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, AsRefStr)]
 pub enum TokenType {
     AccountActivation,
+    EmailChange,
+    Invitation,
+    MagicLogin,
     ResetPassword,
+    SecondFactorChallenge,
+    WebAuthnLogin,
+    WebAuthnRegistration,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -45,20 +69,33 @@ impl JsonCodec<TokenData> for TokenDataCodec {
     }
 }
 
-impl Validable for TokenData {
-    fn validate(&self, error_details: &mut ErrorDetails) -> Result<(), LightSpeedError> {
-        if current_epoch_seconds() > self.expire_at_epoch_seconds {
+impl TokenData {
+    /// Validates the token expiration against the given clock
+    pub fn validate_with_clock(
+        &self,
+        clock: &dyn Clock,
+        error_details: &mut ErrorDetails,
+    ) -> Result<(), LightSpeedError> {
+        if clock.epoch_seconds() > self.expire_at_epoch_seconds {
             error_details.add_detail("expire_at_epoch", "expired");
         }
         Ok(())
     }
 }
 
+impl Validable for TokenData {
+    fn validate(&self, error_details: &mut ErrorDetails) -> Result<(), LightSpeedError> {
+        self.validate_with_clock(&SystemClock, error_details)
+    }
+}
+
 #[cfg(test)]
 pub mod test {
 
     use super::*;
+    use lightspeed_core::clock::MockClock;
     use lightspeed_core::service::validator::Validator;
+    use lightspeed_core::utils::current_epoch_seconds;
 
     #[test]
     pub fn token_not_expired_should_be_valid() {
@@ -67,6 +104,7 @@ pub mod test {
             token_type: TokenType::AccountActivation,
             username: "".to_owned(),
             expire_at_epoch_seconds: current_epoch_seconds() + 1000,
+            invitation: None,
         };
 
         assert!(Validator::validate(&token).is_ok())
@@ -79,6 +117,7 @@ pub mod test {
             token_type: TokenType::AccountActivation,
             username: "".to_owned(),
             expire_at_epoch_seconds: current_epoch_seconds() - 1000,
+            invitation: None,
         };
 
         let result = Validator::validate(&token);
@@ -91,4 +130,28 @@ pub mod test {
             _ => panic!(),
         }
     }
+
+    #[test]
+    pub fn token_should_expire_according_to_the_clock() {
+        let clock = MockClock::from_epoch_seconds(1000);
+        let token = TokenData {
+            token: "".to_owned(),
+            token_type: TokenType::AccountActivation,
+            username: "".to_owned(),
+            expire_at_epoch_seconds: 1100,
+            invitation: None,
+        };
+
+        clock.advance(std::time::Duration::from_secs(100));
+        assert!(Validator::validate(
+            &|error_details: &mut ErrorDetails| token.validate_with_clock(&clock, error_details)
+        )
+        .is_ok());
+
+        clock.advance(std::time::Duration::from_secs(1));
+        assert!(Validator::validate(
+            &|error_details: &mut ErrorDetails| token.validate_with_clock(&clock, error_details)
+        )
+        .is_err());
+    }
 }
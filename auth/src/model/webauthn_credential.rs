@@ -0,0 +1,47 @@
+use c3p0::{C3p0Error, JsonCodec, Model};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+
+pub type WebAuthnCredentialModel = Model<WebAuthnCredentialData>;
+
+/// A WebAuthn credential (passkey) registered by a user
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WebAuthnCredentialData {
+    pub user_id: i64,
+    /// The name with which the user recognizes the credential
+    pub name: String,
+    /// The base64url encoded id assigned by the authenticator
+    pub credential_id: String,
+    /// The COSE identifier of the signature algorithm of the public key
+    pub algorithm: i64,
+    /// The base64url encoded public key in the SEC1 uncompressed format
+    pub public_key: String,
+    /// The last signature counter returned by the authenticator; it stays zero if the authenticator has no counter
+    pub sign_count: u32,
+    pub created_date_epoch_seconds: i64,
+    pub last_used_date_epoch_seconds: Option<i64>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "_json_tag")]
+enum WebAuthnCredentialDataVersioning<'a> {
+    V1(Cow<'a, WebAuthnCredentialData>),
+}
+
+#[derive(Clone)]
+pub struct WebAuthnCredentialDataCodec {}
+
+impl JsonCodec<WebAuthnCredentialData> for WebAuthnCredentialDataCodec {
+    fn data_from_value(&self, value: Value) -> Result<WebAuthnCredentialData, C3p0Error> {
+        let versioning = serde_json::from_value(value)?;
+        let data = match versioning {
+            WebAuthnCredentialDataVersioning::V1(data_v1) => data_v1.into_owned(),
+        };
+        Ok(data)
+    }
+
+    fn data_to_value(&self, data: &WebAuthnCredentialData) -> Result<Value, C3p0Error> {
+        serde_json::to_value(WebAuthnCredentialDataVersioning::V1(Cow::Borrowed(data))).map_err(C3p0Error::from)
+    }
+}
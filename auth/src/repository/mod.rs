@@ -1,5 +1,14 @@
+use crate::dto::search_accounts_dto::SearchAccountsDto;
 use crate::model::auth_account::{AuthAccountData, AuthAccountModel, AuthAccountStatus};
-use crate::model::token::{TokenData, TokenModel};
+use crate::model::auth_session::{AuthSessionData, AuthSessionModel};
+use crate::model::external_identity::{ExternalIdentityData, ExternalIdentityModel};
+use crate::model::impersonation::{ImpersonationData, ImpersonationModel};
+use crate::model::login_attempt::{LoginAttemptData, LoginAttemptKeyType, LoginAttemptModel};
+use crate::model::oauth2_client::{OAuth2ClientData, OAuth2ClientModel};
+use crate::model::oauth2_consent::{OAuth2ConsentData, OAuth2ConsentModel};
+use crate::model::oauth2_token::{OAuth2TokenData, OAuth2TokenModel};
+use crate::model::token::{TokenData, TokenModel, TokenType};
+use crate::model::webauthn_credential::{WebAuthnCredentialData, WebAuthnCredentialModel};
 use c3p0::*;
 use lightspeed_core::error::LightSpeedError;
 
@@ -11,11 +20,27 @@ pub trait AuthRepositoryManager: Clone + Send + Sync {
     type C3P0: C3p0Pool<Conn = Self::Conn>;
     type AuthAccountRepo: AuthAccountRepository<Conn = Self::Conn>;
     type TokenRepo: TokenRepository<Conn = Self::Conn>;
+    type LoginAttemptRepo: LoginAttemptRepository<Conn = Self::Conn>;
+    type ExternalIdentityRepo: ExternalIdentityRepository<Conn = Self::Conn>;
+    type OAuth2ClientRepo: OAuth2ClientRepository<Conn = Self::Conn>;
+    type OAuth2ConsentRepo: OAuth2ConsentRepository<Conn = Self::Conn>;
+    type OAuth2TokenRepo: OAuth2TokenRepository<Conn = Self::Conn>;
+    type AuthSessionRepo: AuthSessionRepository<Conn = Self::Conn>;
+    type ImpersonationRepo: ImpersonationRepository<Conn = Self::Conn>;
+    type WebAuthnCredentialRepo: WebAuthnCredentialRepository<Conn = Self::Conn>;
 
     fn c3p0(&self) -> &Self::C3P0;
     async fn start(&self) -> Result<(), LightSpeedError>;
     fn auth_account_repo(&self) -> Self::AuthAccountRepo;
     fn token_repo(&self) -> Self::TokenRepo;
+    fn login_attempt_repo(&self) -> Self::LoginAttemptRepo;
+    fn external_identity_repo(&self) -> Self::ExternalIdentityRepo;
+    fn oauth2_client_repo(&self) -> Self::OAuth2ClientRepo;
+    fn oauth2_consent_repo(&self) -> Self::OAuth2ConsentRepo;
+    fn oauth2_token_repo(&self) -> Self::OAuth2TokenRepo;
+    fn auth_session_repo(&self) -> Self::AuthSessionRepo;
+    fn impersonation_repo(&self) -> Self::ImpersonationRepo;
+    fn webauthn_credential_repo(&self) -> Self::WebAuthnCredentialRepo;
 }
 
 #[async_trait::async_trait]
@@ -30,6 +55,24 @@ pub trait AuthAccountRepository: Clone + Send + Sync {
         limit: u32,
     ) -> Result<Vec<AuthAccountModel>, LightSpeedError>;
 
+    /// Returns the disabled accounts that have not been anonymized yet
+    async fn fetch_all_disabled_not_anonymized(
+        &self,
+        conn: &mut Self::Conn,
+        start_user_id: i64,
+        limit: u32,
+    ) -> Result<Vec<AuthAccountModel>, LightSpeedError>;
+
+    /// Returns the page of the accounts matching the filters of the search
+    async fn fetch_all_by_search(
+        &self,
+        conn: &mut Self::Conn,
+        search: &SearchAccountsDto,
+    ) -> Result<Vec<AuthAccountModel>, LightSpeedError>;
+
+    /// Counts all the accounts matching the filters of the search, regardless of the page
+    async fn count_by_search(&self, conn: &mut Self::Conn, search: &SearchAccountsDto) -> Result<u64, LightSpeedError>;
+
     async fn fetch_by_id(&self, conn: &mut Self::Conn, user_id: i64) -> Result<AuthAccountModel, LightSpeedError>;
 
     async fn fetch_by_username(
@@ -38,12 +81,14 @@ pub trait AuthAccountRepository: Clone + Send + Sync {
         username: &str,
     ) -> Result<AuthAccountModel, LightSpeedError>;
 
+    /// The username is compared in its normalized form, see `normalize_username`
     async fn fetch_by_username_optional(
         &self,
         conn: &mut Self::Conn,
         username: &str,
     ) -> Result<Option<AuthAccountModel>, LightSpeedError>;
 
+    /// The email is compared in its normalized form, see `normalize_email`
     async fn fetch_by_email_optional(
         &self,
         conn: &mut Self::Conn,
@@ -66,13 +111,20 @@ pub trait AuthAccountRepository: Clone + Send + Sync {
         -> Result<AuthAccountModel, LightSpeedError>;
 
     async fn delete_by_id(&self, conn: &mut Self::Conn, user_id: i64) -> Result<u64, LightSpeedError>;
+
+    /// Creates, if it does not exist, the index used to search the accounts by the value of the attribute.
+    /// The name has to be a valid attribute name, see `is_valid_account_attribute_name`.
+    async fn create_attribute_index(&self, conn: &mut Self::Conn, name: &str) -> Result<(), LightSpeedError>;
 }
 
 #[async_trait::async_trait]
 pub trait TokenRepository: Clone + Send + Sync {
     type Conn: SqlConnection;
 
-    async fn fetch_by_token(&self, conn: &mut Self::Conn, token_string: &str) -> Result<TokenModel, LightSpeedError>;
+    /// Fetches a token by the hash with which it is saved
+    async fn fetch_by_token(&self, conn: &mut Self::Conn, token_hash: &str) -> Result<TokenModel, LightSpeedError>;
+
+    async fn fetch_by_id(&self, conn: &mut Self::Conn, id: i64) -> Result<TokenModel, LightSpeedError>;
 
     async fn fetch_by_username(
         &self,
@@ -80,7 +132,311 @@ pub trait TokenRepository: Clone + Send + Sync {
         username: &str,
     ) -> Result<Vec<TokenModel>, LightSpeedError>;
 
+    /// Fetches the Invitation tokens of the given email
+    async fn fetch_invitations_by_email(
+        &self,
+        conn: &mut Self::Conn,
+        email: &str,
+    ) -> Result<Vec<TokenModel>, LightSpeedError>;
+
+    /// Fetches all the tokens of the given type, ordered by id
+    async fn fetch_all_by_type(
+        &self,
+        conn: &mut Self::Conn,
+        token_type: TokenType,
+    ) -> Result<Vec<TokenModel>, LightSpeedError>;
+
     async fn save(&self, conn: &mut Self::Conn, model: NewModel<TokenData>) -> Result<TokenModel, LightSpeedError>;
 
     async fn delete(&self, conn: &mut Self::Conn, model: TokenModel) -> Result<TokenModel, LightSpeedError>;
+
+    /// Deletes the tokens expired before the given epoch seconds. Returns the number of deleted tokens.
+    async fn delete_expired(&self, conn: &mut Self::Conn, epoch_seconds: i64) -> Result<u64, LightSpeedError>;
+}
+
+#[async_trait::async_trait]
+pub trait LoginAttemptRepository: Clone + Send + Sync {
+    type Conn: SqlConnection;
+
+    async fn fetch_by_key_optional(
+        &self,
+        conn: &mut Self::Conn,
+        key_type: LoginAttemptKeyType,
+        key: &str,
+    ) -> Result<Option<LoginAttemptModel>, LightSpeedError>;
+
+    /// Returns the counter of the key of the given data, creating it from the data if missing,
+    /// and locks it until the end of the transaction.
+    /// This serializes the concurrent failures of the same key, that would otherwise get lost.
+    async fn fetch_or_create_for_update(
+        &self,
+        conn: &mut Self::Conn,
+        data: LoginAttemptData,
+    ) -> Result<LoginAttemptModel, LightSpeedError>;
+
+    async fn update(
+        &self,
+        conn: &mut Self::Conn,
+        model: LoginAttemptModel,
+    ) -> Result<LoginAttemptModel, LightSpeedError>;
+
+    async fn delete(
+        &self,
+        conn: &mut Self::Conn,
+        model: LoginAttemptModel,
+    ) -> Result<LoginAttemptModel, LightSpeedError>;
+
+    /// Deletes the counters that are not locked at the given epoch seconds and whose last failure
+    /// is before `last_failure_before_epoch_seconds`. Returns the number of deleted counters.
+    async fn delete_expired(
+        &self,
+        conn: &mut Self::Conn,
+        epoch_seconds: i64,
+        last_failure_before_epoch_seconds: i64,
+    ) -> Result<u64, LightSpeedError>;
+}
+
+#[async_trait::async_trait]
+pub trait ExternalIdentityRepository: Clone + Send + Sync {
+    type Conn: SqlConnection;
+
+    async fn fetch_by_provider_and_subject_optional(
+        &self,
+        conn: &mut Self::Conn,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<ExternalIdentityModel>, LightSpeedError>;
+
+    async fn fetch_all_by_user_id(
+        &self,
+        conn: &mut Self::Conn,
+        user_id: i64,
+    ) -> Result<Vec<ExternalIdentityModel>, LightSpeedError>;
+
+    async fn save(
+        &self,
+        conn: &mut Self::Conn,
+        model: NewModel<ExternalIdentityData>,
+    ) -> Result<ExternalIdentityModel, LightSpeedError>;
+
+    async fn delete(
+        &self,
+        conn: &mut Self::Conn,
+        model: ExternalIdentityModel,
+    ) -> Result<ExternalIdentityModel, LightSpeedError>;
+
+    async fn delete_by_user_id(&self, conn: &mut Self::Conn, user_id: i64) -> Result<u64, LightSpeedError>;
+}
+
+#[async_trait::async_trait]
+pub trait OAuth2ClientRepository: Clone + Send + Sync {
+    type Conn: SqlConnection;
+
+    async fn fetch_by_client_id_optional(
+        &self,
+        conn: &mut Self::Conn,
+        client_id: &str,
+    ) -> Result<Option<OAuth2ClientModel>, LightSpeedError>;
+
+    async fn save(
+        &self,
+        conn: &mut Self::Conn,
+        model: NewModel<OAuth2ClientData>,
+    ) -> Result<OAuth2ClientModel, LightSpeedError>;
+
+    async fn update(
+        &self,
+        conn: &mut Self::Conn,
+        model: OAuth2ClientModel,
+    ) -> Result<OAuth2ClientModel, LightSpeedError>;
+
+    async fn delete(
+        &self,
+        conn: &mut Self::Conn,
+        model: OAuth2ClientModel,
+    ) -> Result<OAuth2ClientModel, LightSpeedError>;
+}
+
+#[async_trait::async_trait]
+pub trait OAuth2ConsentRepository: Clone + Send + Sync {
+    type Conn: SqlConnection;
+
+    async fn fetch_by_user_id_and_client_id_optional(
+        &self,
+        conn: &mut Self::Conn,
+        user_id: i64,
+        client_id: &str,
+    ) -> Result<Option<OAuth2ConsentModel>, LightSpeedError>;
+
+    async fn fetch_all_by_user_id(
+        &self,
+        conn: &mut Self::Conn,
+        user_id: i64,
+    ) -> Result<Vec<OAuth2ConsentModel>, LightSpeedError>;
+
+    async fn save(
+        &self,
+        conn: &mut Self::Conn,
+        model: NewModel<OAuth2ConsentData>,
+    ) -> Result<OAuth2ConsentModel, LightSpeedError>;
+
+    async fn update(
+        &self,
+        conn: &mut Self::Conn,
+        model: OAuth2ConsentModel,
+    ) -> Result<OAuth2ConsentModel, LightSpeedError>;
+
+    async fn delete(
+        &self,
+        conn: &mut Self::Conn,
+        model: OAuth2ConsentModel,
+    ) -> Result<OAuth2ConsentModel, LightSpeedError>;
+
+    async fn delete_by_user_id(&self, conn: &mut Self::Conn, user_id: i64) -> Result<u64, LightSpeedError>;
+
+    async fn delete_by_client_id(&self, conn: &mut Self::Conn, client_id: &str) -> Result<u64, LightSpeedError>;
+}
+
+#[async_trait::async_trait]
+pub trait OAuth2TokenRepository: Clone + Send + Sync {
+    type Conn: SqlConnection;
+
+    async fn fetch_by_token_optional(
+        &self,
+        conn: &mut Self::Conn,
+        token: &str,
+    ) -> Result<Option<OAuth2TokenModel>, LightSpeedError>;
+
+    async fn save(
+        &self,
+        conn: &mut Self::Conn,
+        model: NewModel<OAuth2TokenData>,
+    ) -> Result<OAuth2TokenModel, LightSpeedError>;
+
+    async fn delete(&self, conn: &mut Self::Conn, model: OAuth2TokenModel)
+        -> Result<OAuth2TokenModel, LightSpeedError>;
+
+    async fn delete_by_user_id(&self, conn: &mut Self::Conn, user_id: i64) -> Result<u64, LightSpeedError>;
+
+    async fn delete_by_user_id_and_client_id(
+        &self,
+        conn: &mut Self::Conn,
+        user_id: i64,
+        client_id: &str,
+    ) -> Result<u64, LightSpeedError>;
+
+    async fn delete_by_client_id(&self, conn: &mut Self::Conn, client_id: &str) -> Result<u64, LightSpeedError>;
+
+    /// Deletes the codes and the access tokens expired before the given epoch seconds.
+    /// Returns the number of deleted tokens.
+    async fn delete_expired(&self, conn: &mut Self::Conn, epoch_seconds: i64) -> Result<u64, LightSpeedError>;
+}
+
+#[async_trait::async_trait]
+pub trait AuthSessionRepository: Clone + Send + Sync {
+    type Conn: SqlConnection;
+
+    async fn fetch_by_session_id_optional(
+        &self,
+        conn: &mut Self::Conn,
+        session_id: &str,
+    ) -> Result<Option<AuthSessionModel>, LightSpeedError>;
+
+    async fn fetch_all_by_user_id(
+        &self,
+        conn: &mut Self::Conn,
+        user_id: i64,
+    ) -> Result<Vec<AuthSessionModel>, LightSpeedError>;
+
+    async fn save(
+        &self,
+        conn: &mut Self::Conn,
+        model: NewModel<AuthSessionData>,
+    ) -> Result<AuthSessionModel, LightSpeedError>;
+
+    async fn update(&self, conn: &mut Self::Conn, model: AuthSessionModel)
+        -> Result<AuthSessionModel, LightSpeedError>;
+
+    /// Sets the last seen epoch seconds, and the user agent and IP address if present, of the session
+    /// if it is not expired at the given epoch seconds. Returns the number of updated sessions.
+    /// The version is not checked, so the concurrent requests of the same session do not conflict.
+    async fn touch(
+        &self,
+        conn: &mut Self::Conn,
+        session_id: &str,
+        epoch_seconds: i64,
+        user_agent: Option<&str>,
+        ip_address: Option<&str>,
+    ) -> Result<u64, LightSpeedError>;
+
+    async fn delete(&self, conn: &mut Self::Conn, model: AuthSessionModel)
+        -> Result<AuthSessionModel, LightSpeedError>;
+
+    async fn delete_by_user_id(&self, conn: &mut Self::Conn, user_id: i64) -> Result<u64, LightSpeedError>;
+
+    /// Deletes the sessions expired before the given epoch seconds
+    async fn delete_expired(&self, conn: &mut Self::Conn, epoch_seconds: i64) -> Result<u64, LightSpeedError>;
+}
+
+#[async_trait::async_trait]
+pub trait ImpersonationRepository: Clone + Send + Sync {
+    type Conn: SqlConnection;
+
+    /// Fetches the impersonations of the given user, the most recent first
+    async fn fetch_all_by_user_id(
+        &self,
+        conn: &mut Self::Conn,
+        user_id: i64,
+    ) -> Result<Vec<ImpersonationModel>, LightSpeedError>;
+
+    /// Fetches the impersonations performed by the given user, the most recent first
+    async fn fetch_all_by_impersonator_user_id(
+        &self,
+        conn: &mut Self::Conn,
+        impersonator_user_id: i64,
+    ) -> Result<Vec<ImpersonationModel>, LightSpeedError>;
+
+    async fn save(
+        &self,
+        conn: &mut Self::Conn,
+        model: NewModel<ImpersonationData>,
+    ) -> Result<ImpersonationModel, LightSpeedError>;
+}
+
+#[async_trait::async_trait]
+pub trait WebAuthnCredentialRepository: Clone + Send + Sync {
+    type Conn: SqlConnection;
+
+    /// Fetches a credential by the base64url encoded id assigned by the authenticator
+    async fn fetch_by_credential_id_optional(
+        &self,
+        conn: &mut Self::Conn,
+        credential_id: &str,
+    ) -> Result<Option<WebAuthnCredentialModel>, LightSpeedError>;
+
+    async fn fetch_all_by_user_id(
+        &self,
+        conn: &mut Self::Conn,
+        user_id: i64,
+    ) -> Result<Vec<WebAuthnCredentialModel>, LightSpeedError>;
+
+    async fn save(
+        &self,
+        conn: &mut Self::Conn,
+        model: NewModel<WebAuthnCredentialData>,
+    ) -> Result<WebAuthnCredentialModel, LightSpeedError>;
+
+    async fn update(
+        &self,
+        conn: &mut Self::Conn,
+        model: WebAuthnCredentialModel,
+    ) -> Result<WebAuthnCredentialModel, LightSpeedError>;
+
+    async fn delete(
+        &self,
+        conn: &mut Self::Conn,
+        model: WebAuthnCredentialModel,
+    ) -> Result<WebAuthnCredentialModel, LightSpeedError>;
+
+    async fn delete_by_user_id(&self, conn: &mut Self::Conn, user_id: i64) -> Result<u64, LightSpeedError>;
 }
@@ -1,12 +1,28 @@
 use crate::repository::pg::pg_auth_account::PgAuthAccountRepository;
+use crate::repository::pg::pg_auth_session::PgAuthSessionRepository;
+use crate::repository::pg::pg_external_identity::PgExternalIdentityRepository;
+use crate::repository::pg::pg_impersonation::PgImpersonationRepository;
+use crate::repository::pg::pg_login_attempt::PgLoginAttemptRepository;
+use crate::repository::pg::pg_oauth2_client::PgOAuth2ClientRepository;
+use crate::repository::pg::pg_oauth2_consent::PgOAuth2ConsentRepository;
+use crate::repository::pg::pg_oauth2_token::PgOAuth2TokenRepository;
 use crate::repository::pg::pg_token::PgTokenRepository;
+use crate::repository::pg::pg_webauthn_credential::PgWebAuthnCredentialRepository;
 use crate::repository::AuthRepositoryManager;
 use c3p0::postgres::*;
 use c3p0::*;
 use lightspeed_core::error::LightSpeedError;
 
 pub mod pg_auth_account;
+pub mod pg_auth_session;
+pub mod pg_external_identity;
+pub mod pg_impersonation;
+pub mod pg_login_attempt;
+pub mod pg_oauth2_client;
+pub mod pg_oauth2_consent;
+pub mod pg_oauth2_token;
 pub mod pg_token;
+pub mod pg_webauthn_credential;
 
 const MIGRATIONS: include_dir::Dir = include_dir::include_dir!("$CARGO_MANIFEST_DIR/src_resources/db/pg/migrations");
 
@@ -27,6 +43,14 @@ impl AuthRepositoryManager for PgAuthRepositoryManager {
     type C3P0 = PgC3p0Pool;
     type AuthAccountRepo = PgAuthAccountRepository;
     type TokenRepo = PgTokenRepository;
+    type LoginAttemptRepo = PgLoginAttemptRepository;
+    type ExternalIdentityRepo = PgExternalIdentityRepository;
+    type OAuth2ClientRepo = PgOAuth2ClientRepository;
+    type OAuth2ConsentRepo = PgOAuth2ConsentRepository;
+    type OAuth2TokenRepo = PgOAuth2TokenRepository;
+    type AuthSessionRepo = PgAuthSessionRepository;
+    type ImpersonationRepo = PgImpersonationRepository;
+    type WebAuthnCredentialRepo = PgWebAuthnCredentialRepository;
 
     fn c3p0(&self) -> &PgC3p0Pool {
         &self.c3p0
@@ -54,4 +78,36 @@ impl AuthRepositoryManager for PgAuthRepositoryManager {
     fn token_repo(&self) -> Self::TokenRepo {
         PgTokenRepository::default()
     }
+
+    fn login_attempt_repo(&self) -> Self::LoginAttemptRepo {
+        PgLoginAttemptRepository::default()
+    }
+
+    fn external_identity_repo(&self) -> Self::ExternalIdentityRepo {
+        PgExternalIdentityRepository::default()
+    }
+
+    fn oauth2_client_repo(&self) -> Self::OAuth2ClientRepo {
+        PgOAuth2ClientRepository::default()
+    }
+
+    fn oauth2_consent_repo(&self) -> Self::OAuth2ConsentRepo {
+        PgOAuth2ConsentRepository::default()
+    }
+
+    fn oauth2_token_repo(&self) -> Self::OAuth2TokenRepo {
+        PgOAuth2TokenRepository::default()
+    }
+
+    fn auth_session_repo(&self) -> Self::AuthSessionRepo {
+        PgAuthSessionRepository::default()
+    }
+
+    fn impersonation_repo(&self) -> Self::ImpersonationRepo {
+        PgImpersonationRepository::default()
+    }
+
+    fn webauthn_credential_repo(&self) -> Self::WebAuthnCredentialRepo {
+        PgWebAuthnCredentialRepository::default()
+    }
 }
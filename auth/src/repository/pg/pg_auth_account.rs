@@ -1,5 +1,10 @@
-use crate::model::auth_account::{AuthAccountData, AuthAccountDataCodec, AuthAccountModel, AuthAccountStatus};
+use crate::config::is_valid_account_attribute_name;
+use crate::dto::search_accounts_dto::{AccountSortField, SearchAccountsDto, SortDirection};
+use crate::model::auth_account::{
+    normalize_email, normalize_username, AuthAccountData, AuthAccountDataCodec, AuthAccountModel, AuthAccountStatus,
+};
 use crate::repository::AuthAccountRepository;
+use c3p0::postgres::tokio_postgres::types::ToSql;
 use c3p0::postgres::*;
 use c3p0::*;
 use lightspeed_core::error::{ErrorCodes, LightSpeedError};
@@ -41,6 +46,76 @@ impl AuthAccountRepository for PgAuthAccountRepository {
         Ok(self.repo.fetch_all_with_sql(conn, &sql, &[&start_user_id, &status.as_ref(), &(limit as i64)]).await?)
     }
 
+    async fn fetch_all_disabled_not_anonymized(
+        &self,
+        conn: &mut Self::Conn,
+        start_user_id: i64,
+        limit: u32,
+    ) -> Result<Vec<AuthAccountModel>, LightSpeedError> {
+        let sql = format!(
+            r#"
+            {}
+            where id >= $1 and DATA ->> 'status' = $2 and DATA ->> 'anonymized_date_epoch_seconds' is null
+            order by id asc
+            limit $3
+        "#,
+            self.queries().find_base_sql_query
+        );
+        let status = AuthAccountStatus::Disabled;
+        Ok(self.repo.fetch_all_with_sql(conn, &sql, &[&start_user_id, &status.as_ref(), &(limit as i64)]).await?)
+    }
+
+    async fn fetch_all_by_search(
+        &self,
+        conn: &mut Self::Conn,
+        search: &SearchAccountsDto,
+    ) -> Result<Vec<AuthAccountModel>, LightSpeedError> {
+        let (mut filters, mut params) = search_filters(search);
+        let sort_field = sort_field_sql(search.sort_by);
+        let sort_direction = match search.sort_direction {
+            SortDirection::Asc => "asc",
+            SortDirection::Desc => "desc",
+        };
+
+        if let Some(after_user_id) = search.after_user_id {
+            // The keyset of the last account of the previous page; the id makes it unique
+            params.push(Box::new(after_user_id));
+            filters.push(format!(
+                "({sort_field}, id) {} (select {sort_field}, id from LS_AUTH_ACCOUNT where id = ${})",
+                if search.sort_direction == SortDirection::Asc { ">" } else { "<" },
+                params.len()
+            ));
+        }
+
+        let sql = format!(
+            r#"
+            {}
+            {}
+            order by {sort_field} {sort_direction}, id {sort_direction}
+            limit {}
+            offset {}
+        "#,
+            self.queries().find_base_sql_query,
+            where_clause(&filters),
+            search.limit,
+            search.offset
+        );
+        Ok(self.repo.fetch_all_with_sql(conn, &sql, &param_refs(&params)).await?)
+    }
+
+    async fn count_by_search(&self, conn: &mut Self::Conn, search: &SearchAccountsDto) -> Result<u64, LightSpeedError> {
+        let (filters, params) = search_filters(search);
+        let sql = format!(
+            r#"
+            select count(*) from LS_AUTH_ACCOUNT
+            {}
+        "#,
+            where_clause(&filters)
+        );
+        let count: i64 = conn.fetch_one_value(&sql, &param_refs(&params)).await?;
+        Ok(count as u64)
+    }
+
     async fn fetch_by_id(
         &self,
         conn: &mut Self::Conn,
@@ -68,12 +143,12 @@ impl AuthAccountRepository for PgAuthAccountRepository {
         let sql = format!(
             r#"
             {}
-            where DATA ->> 'username' = $1
+            where lower(DATA ->> 'username') = lower($1)
             limit 1
         "#,
             self.queries().find_base_sql_query
         );
-        Ok(self.repo.fetch_one_optional_with_sql(conn, &sql, &[&username]).await?)
+        Ok(self.repo.fetch_one_optional_with_sql(conn, &sql, &[&normalize_username(username)]).await?)
     }
 
     async fn fetch_by_email_optional(
@@ -84,12 +159,12 @@ impl AuthAccountRepository for PgAuthAccountRepository {
         let sql = format!(
             r#"
             {}
-            where DATA ->> 'email' = $1
+            where lower(DATA ->> 'email') = lower($1)
             limit 1
         "#,
             self.queries().find_base_sql_query
         );
-        Ok(self.repo.fetch_one_optional_with_sql(conn, &sql, &[&email]).await?)
+        Ok(self.repo.fetch_one_optional_with_sql(conn, &sql, &[&normalize_email(email)]).await?)
     }
 
     async fn save(
@@ -119,6 +194,23 @@ impl AuthAccountRepository for PgAuthAccountRepository {
     async fn delete_by_id(&self, conn: &mut Self::Conn, user_id: i64) -> Result<u64, LightSpeedError> {
         Ok(self.repo.delete_by_id(conn, &user_id).await?)
     }
+
+    async fn create_attribute_index(&self, conn: &mut Self::Conn, name: &str) -> Result<(), LightSpeedError> {
+        // The name is part of the SQL, the index can be used only if the attribute is not a parameter
+        if !is_valid_account_attribute_name(name) {
+            return Err(LightSpeedError::InternalServerError {
+                message: format!("Cannot create the index of the attribute [{name}]: invalid name"),
+            });
+        }
+        let sql = format!(
+            r#"
+            CREATE INDEX IF NOT EXISTS LS_AUTH_ACCOUNT_ATTRIBUTE_{name}
+            ON LS_AUTH_ACCOUNT( (DATA->'attributes'->>'{name}') )
+        "#
+        );
+        conn.execute(&sql, &[]).await?;
+        Ok(())
+    }
 }
 
 impl Deref for PgAuthAccountRepository {
@@ -128,3 +220,71 @@ impl Deref for PgAuthAccountRepository {
         &self.repo
     }
 }
+
+type SqlParam = Box<dyn ToSql + Sync + Send>;
+
+/// Returns the conditions and the parameters of the filters of an accounts search
+fn search_filters(search: &SearchAccountsDto) -> (Vec<String>, Vec<SqlParam>) {
+    let mut filters = vec![];
+    let mut params: Vec<SqlParam> = vec![];
+
+    if let Some(username_prefix) = &search.username_prefix {
+        params.push(Box::new(format!("{}%", escape_like(&normalize_username(username_prefix)))));
+        filters.push(format!("lower(DATA ->> 'username') like ${}", params.len()));
+    }
+    if let Some(email_prefix) = &search.email_prefix {
+        params.push(Box::new(format!("{}%", escape_like(&normalize_email(email_prefix)))));
+        filters.push(format!("lower(DATA ->> 'email') like ${}", params.len()));
+    }
+    if let Some(role) = &search.role {
+        params.push(Box::new(role.clone()));
+        filters.push(format!("DATA -> 'roles' ? ${}", params.len()));
+    }
+    if let Some(status) = &search.status {
+        params.push(Box::new(status.as_ref().to_owned()));
+        filters.push(format!("DATA ->> 'status' = ${}", params.len()));
+    }
+    if let Some(created_from) = search.created_from_epoch_seconds {
+        params.push(Box::new(created_from));
+        filters.push(format!("(DATA ->> 'created_date_epoch_seconds')::bigint >= ${}", params.len()));
+    }
+    if let Some(created_to) = search.created_to_epoch_seconds {
+        params.push(Box::new(created_to));
+        filters.push(format!("(DATA ->> 'created_date_epoch_seconds')::bigint < ${}", params.len()));
+    }
+    for (name, value) in &search.attributes {
+        // The name is part of the SQL to match the expression of the attribute index; it is validated
+        // by the search, the quotes are escaped anyway
+        params.push(Box::new(value.clone()));
+        filters.push(format!("DATA -> 'attributes' ->> '{}' = ${}", name.replace('\'', "''"), params.len()));
+    }
+
+    (filters, params)
+}
+
+fn where_clause(filters: &[String]) -> String {
+    if filters.is_empty() {
+        "".to_owned()
+    } else {
+        format!("where {}", filters.join(" and "))
+    }
+}
+
+fn sort_field_sql(sort_field: AccountSortField) -> &'static str {
+    match sort_field {
+        AccountSortField::Id => "id",
+        AccountSortField::Username => "lower(DATA ->> 'username')",
+        AccountSortField::Email => "lower(DATA ->> 'email')",
+        AccountSortField::Status => "DATA ->> 'status'",
+        AccountSortField::CreatedDate => "(DATA ->> 'created_date_epoch_seconds')::bigint",
+    }
+}
+
+fn param_refs(params: &[SqlParam]) -> Vec<&(dyn ToSql + Sync)> {
+    params.iter().map(|param| param.as_ref() as &(dyn ToSql + Sync)).collect()
+}
+
+/// Escapes the wildcards of a LIKE pattern
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
@@ -0,0 +1,124 @@
+use crate::model::auth_session::{AuthSessionData, AuthSessionDataCodec, AuthSessionModel};
+use crate::repository::AuthSessionRepository;
+use c3p0::postgres::*;
+use c3p0::*;
+use lightspeed_core::error::LightSpeedError;
+use std::ops::Deref;
+
+#[derive(Clone)]
+pub struct PgAuthSessionRepository {
+    repo: PgC3p0Json<AuthSessionData, AuthSessionDataCodec>,
+}
+
+impl Deref for PgAuthSessionRepository {
+    type Target = PgC3p0Json<AuthSessionData, AuthSessionDataCodec>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.repo
+    }
+}
+
+impl Default for PgAuthSessionRepository {
+    fn default() -> Self {
+        PgAuthSessionRepository {
+            repo: C3p0JsonBuilder::new("LS_AUTH_SESSION").build_with_codec(AuthSessionDataCodec {}),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthSessionRepository for PgAuthSessionRepository {
+    type Conn = PgConnection;
+
+    async fn fetch_by_session_id_optional(
+        &self,
+        conn: &mut Self::Conn,
+        session_id: &str,
+    ) -> Result<Option<AuthSessionModel>, LightSpeedError> {
+        let sql = format!(
+            r#"
+            {}
+            where DATA ->> 'session_id' = $1
+            limit 1
+        "#,
+            self.queries().find_base_sql_query
+        );
+        Ok(self.repo.fetch_one_optional_with_sql(conn, &sql, &[&session_id]).await?)
+    }
+
+    async fn fetch_all_by_user_id(
+        &self,
+        conn: &mut Self::Conn,
+        user_id: i64,
+    ) -> Result<Vec<AuthSessionModel>, LightSpeedError> {
+        let sql = format!(
+            r#"
+            {}
+            where (DATA ->> 'user_id')::bigint = $1
+            order by id asc
+        "#,
+            self.queries().find_base_sql_query
+        );
+        Ok(self.repo.fetch_all_with_sql(conn, &sql, &[&user_id]).await?)
+    }
+
+    async fn save(
+        &self,
+        conn: &mut Self::Conn,
+        model: NewModel<AuthSessionData>,
+    ) -> Result<AuthSessionModel, LightSpeedError> {
+        Ok(self.repo.save(conn, model).await?)
+    }
+
+    async fn update(
+        &self,
+        conn: &mut Self::Conn,
+        model: AuthSessionModel,
+    ) -> Result<AuthSessionModel, LightSpeedError> {
+        Ok(self.repo.update(conn, model).await?)
+    }
+
+    async fn touch(
+        &self,
+        conn: &mut Self::Conn,
+        session_id: &str,
+        epoch_seconds: i64,
+        user_agent: Option<&str>,
+        ip_address: Option<&str>,
+    ) -> Result<u64, LightSpeedError> {
+        let sql = r#"
+            update LS_AUTH_SESSION
+            set update_epoch_millis = $5,
+                DATA = DATA || jsonb_strip_nulls(jsonb_build_object(
+                    'last_seen_epoch_seconds', $2::bigint, 'user_agent', $3::text, 'ip_address', $4::text
+                ))
+            where DATA ->> 'session_id' = $1 and (DATA ->> 'expire_at_epoch_seconds')::bigint >= $2::bigint
+        "#;
+        let update_epoch_millis = c3p0::time::utils::get_current_epoch_millis();
+        Ok(conn.execute(sql, &[&session_id, &epoch_seconds, &user_agent, &ip_address, &update_epoch_millis]).await?)
+    }
+
+    async fn delete(
+        &self,
+        conn: &mut Self::Conn,
+        model: AuthSessionModel,
+    ) -> Result<AuthSessionModel, LightSpeedError> {
+        Ok(self.repo.delete(conn, model).await?)
+    }
+
+    async fn delete_by_user_id(&self, conn: &mut Self::Conn, user_id: i64) -> Result<u64, LightSpeedError> {
+        let sql = r#"
+            delete from LS_AUTH_SESSION
+            where (DATA ->> 'user_id')::bigint = $1
+        "#;
+        Ok(conn.execute(sql, &[&user_id]).await?)
+    }
+
+    async fn delete_expired(&self, conn: &mut Self::Conn, epoch_seconds: i64) -> Result<u64, LightSpeedError> {
+        let sql = r#"
+            delete from LS_AUTH_SESSION
+            where (DATA ->> 'expire_at_epoch_seconds')::bigint < $1
+        "#;
+        Ok(conn.execute(sql, &[&epoch_seconds]).await?)
+    }
+}
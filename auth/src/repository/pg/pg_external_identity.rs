@@ -0,0 +1,89 @@
+use crate::model::external_identity::{ExternalIdentityData, ExternalIdentityDataCodec, ExternalIdentityModel};
+use crate::repository::ExternalIdentityRepository;
+use c3p0::postgres::*;
+use c3p0::*;
+use lightspeed_core::error::LightSpeedError;
+use std::ops::Deref;
+
+#[derive(Clone)]
+pub struct PgExternalIdentityRepository {
+    repo: PgC3p0Json<ExternalIdentityData, ExternalIdentityDataCodec>,
+}
+
+impl Deref for PgExternalIdentityRepository {
+    type Target = PgC3p0Json<ExternalIdentityData, ExternalIdentityDataCodec>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.repo
+    }
+}
+
+impl Default for PgExternalIdentityRepository {
+    fn default() -> Self {
+        PgExternalIdentityRepository {
+            repo: C3p0JsonBuilder::new("LS_AUTH_EXTERNAL_IDENTITY").build_with_codec(ExternalIdentityDataCodec {}),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ExternalIdentityRepository for PgExternalIdentityRepository {
+    type Conn = PgConnection;
+
+    async fn fetch_by_provider_and_subject_optional(
+        &self,
+        conn: &mut Self::Conn,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<ExternalIdentityModel>, LightSpeedError> {
+        let sql = format!(
+            r#"
+            {}
+            where DATA ->> 'provider' = $1 and DATA ->> 'subject' = $2
+            limit 1
+        "#,
+            self.queries().find_base_sql_query
+        );
+        Ok(self.repo.fetch_one_optional_with_sql(conn, &sql, &[&provider, &subject]).await?)
+    }
+
+    async fn fetch_all_by_user_id(
+        &self,
+        conn: &mut Self::Conn,
+        user_id: i64,
+    ) -> Result<Vec<ExternalIdentityModel>, LightSpeedError> {
+        let sql = format!(
+            r#"
+            {}
+            where (DATA ->> 'user_id')::bigint = $1
+            order by id asc
+        "#,
+            self.queries().find_base_sql_query
+        );
+        Ok(self.repo.fetch_all_with_sql(conn, &sql, &[&user_id]).await?)
+    }
+
+    async fn save(
+        &self,
+        conn: &mut Self::Conn,
+        model: NewModel<ExternalIdentityData>,
+    ) -> Result<ExternalIdentityModel, LightSpeedError> {
+        Ok(self.repo.save(conn, model).await?)
+    }
+
+    async fn delete(
+        &self,
+        conn: &mut Self::Conn,
+        model: ExternalIdentityModel,
+    ) -> Result<ExternalIdentityModel, LightSpeedError> {
+        Ok(self.repo.delete(conn, model).await?)
+    }
+
+    async fn delete_by_user_id(&self, conn: &mut Self::Conn, user_id: i64) -> Result<u64, LightSpeedError> {
+        let sql = r#"
+            delete from LS_AUTH_EXTERNAL_IDENTITY
+            where (DATA ->> 'user_id')::bigint = $1
+        "#;
+        Ok(conn.execute(sql, &[&user_id]).await?)
+    }
+}
@@ -0,0 +1,72 @@
+use crate::model::impersonation::{ImpersonationData, ImpersonationDataCodec, ImpersonationModel};
+use crate::repository::ImpersonationRepository;
+use c3p0::postgres::*;
+use c3p0::*;
+use lightspeed_core::error::LightSpeedError;
+use std::ops::Deref;
+
+#[derive(Clone)]
+pub struct PgImpersonationRepository {
+    repo: PgC3p0Json<ImpersonationData, ImpersonationDataCodec>,
+}
+
+impl Deref for PgImpersonationRepository {
+    type Target = PgC3p0Json<ImpersonationData, ImpersonationDataCodec>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.repo
+    }
+}
+
+impl Default for PgImpersonationRepository {
+    fn default() -> Self {
+        PgImpersonationRepository {
+            repo: C3p0JsonBuilder::new("LS_AUTH_IMPERSONATION").build_with_codec(ImpersonationDataCodec {}),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ImpersonationRepository for PgImpersonationRepository {
+    type Conn = PgConnection;
+
+    async fn fetch_all_by_user_id(
+        &self,
+        conn: &mut Self::Conn,
+        user_id: i64,
+    ) -> Result<Vec<ImpersonationModel>, LightSpeedError> {
+        let sql = format!(
+            r#"
+            {}
+            where (DATA ->> 'user_id')::bigint = $1
+            order by id desc
+        "#,
+            self.queries().find_base_sql_query
+        );
+        Ok(self.repo.fetch_all_with_sql(conn, &sql, &[&user_id]).await?)
+    }
+
+    async fn fetch_all_by_impersonator_user_id(
+        &self,
+        conn: &mut Self::Conn,
+        impersonator_user_id: i64,
+    ) -> Result<Vec<ImpersonationModel>, LightSpeedError> {
+        let sql = format!(
+            r#"
+            {}
+            where (DATA ->> 'impersonator_user_id')::bigint = $1
+            order by id desc
+        "#,
+            self.queries().find_base_sql_query
+        );
+        Ok(self.repo.fetch_all_with_sql(conn, &sql, &[&impersonator_user_id]).await?)
+    }
+
+    async fn save(
+        &self,
+        conn: &mut Self::Conn,
+        model: NewModel<ImpersonationData>,
+    ) -> Result<ImpersonationModel, LightSpeedError> {
+        Ok(self.repo.save(conn, model).await?)
+    }
+}
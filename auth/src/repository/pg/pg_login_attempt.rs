@@ -0,0 +1,105 @@
+use crate::model::login_attempt::{LoginAttemptData, LoginAttemptDataCodec, LoginAttemptKeyType, LoginAttemptModel};
+use crate::repository::LoginAttemptRepository;
+use c3p0::postgres::*;
+use c3p0::*;
+use lightspeed_core::error::LightSpeedError;
+use std::ops::Deref;
+
+#[derive(Clone)]
+pub struct PgLoginAttemptRepository {
+    repo: PgC3p0Json<LoginAttemptData, LoginAttemptDataCodec>,
+}
+
+impl Deref for PgLoginAttemptRepository {
+    type Target = PgC3p0Json<LoginAttemptData, LoginAttemptDataCodec>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.repo
+    }
+}
+
+impl Default for PgLoginAttemptRepository {
+    fn default() -> Self {
+        PgLoginAttemptRepository {
+            repo: C3p0JsonBuilder::new("LS_AUTH_LOGIN_ATTEMPT").build_with_codec(LoginAttemptDataCodec {}),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptRepository for PgLoginAttemptRepository {
+    type Conn = PgConnection;
+
+    async fn fetch_by_key_optional(
+        &self,
+        conn: &mut Self::Conn,
+        key_type: LoginAttemptKeyType,
+        key: &str,
+    ) -> Result<Option<LoginAttemptModel>, LightSpeedError> {
+        let sql = format!(
+            r#"
+            {}
+            where DATA ->> 'key_type' = $1 and DATA ->> 'key' = $2
+            limit 1
+        "#,
+            self.queries().find_base_sql_query
+        );
+        Ok(self.repo.fetch_one_optional_with_sql(conn, &sql, &[&key_type.as_ref(), &key]).await?)
+    }
+
+    async fn fetch_or_create_for_update(
+        &self,
+        conn: &mut Self::Conn,
+        data: LoginAttemptData,
+    ) -> Result<LoginAttemptModel, LightSpeedError> {
+        // The unique index on the key makes the insert a no-op if the counter exists,
+        // also when it is created by a concurrent transaction
+        let insert_sql = r#"
+            insert into LS_AUTH_LOGIN_ATTEMPT (VERSION, create_epoch_millis, update_epoch_millis, DATA)
+            values (0, $1, $1, $2)
+            on conflict do nothing
+        "#;
+        let json_data = LoginAttemptDataCodec {}.data_to_value(&data)?;
+        conn.execute(insert_sql, &[&c3p0::time::utils::get_current_epoch_millis(), &json_data]).await?;
+
+        let sql = format!(
+            r#"
+            {}
+            where DATA ->> 'key_type' = $1 and DATA ->> 'key' = $2
+            for update
+        "#,
+            self.queries().find_base_sql_query
+        );
+        Ok(self.repo.fetch_one_with_sql(conn, &sql, &[&data.key_type.as_ref(), &data.key]).await?)
+    }
+
+    async fn update(
+        &self,
+        conn: &mut Self::Conn,
+        model: LoginAttemptModel,
+    ) -> Result<LoginAttemptModel, LightSpeedError> {
+        Ok(self.repo.update(conn, model).await?)
+    }
+
+    async fn delete(
+        &self,
+        conn: &mut Self::Conn,
+        model: LoginAttemptModel,
+    ) -> Result<LoginAttemptModel, LightSpeedError> {
+        Ok(self.repo.delete(conn, model).await?)
+    }
+
+    async fn delete_expired(
+        &self,
+        conn: &mut Self::Conn,
+        epoch_seconds: i64,
+        last_failure_before_epoch_seconds: i64,
+    ) -> Result<u64, LightSpeedError> {
+        let sql = r#"
+            delete from LS_AUTH_LOGIN_ATTEMPT
+            where coalesce((DATA ->> 'locked_until_epoch_seconds')::bigint, $1) <= $1
+            and (DATA ->> 'last_failure_epoch_seconds')::bigint < $2
+        "#;
+        Ok(conn.execute(sql, &[&epoch_seconds, &last_failure_before_epoch_seconds]).await?)
+    }
+}
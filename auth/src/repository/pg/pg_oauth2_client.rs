@@ -0,0 +1,72 @@
+use crate::model::oauth2_client::{OAuth2ClientData, OAuth2ClientDataCodec, OAuth2ClientModel};
+use crate::repository::OAuth2ClientRepository;
+use c3p0::postgres::*;
+use c3p0::*;
+use lightspeed_core::error::LightSpeedError;
+use std::ops::Deref;
+
+#[derive(Clone)]
+pub struct PgOAuth2ClientRepository {
+    repo: PgC3p0Json<OAuth2ClientData, OAuth2ClientDataCodec>,
+}
+
+impl Deref for PgOAuth2ClientRepository {
+    type Target = PgC3p0Json<OAuth2ClientData, OAuth2ClientDataCodec>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.repo
+    }
+}
+
+impl Default for PgOAuth2ClientRepository {
+    fn default() -> Self {
+        PgOAuth2ClientRepository {
+            repo: C3p0JsonBuilder::new("LS_AUTH_OAUTH2_CLIENT").build_with_codec(OAuth2ClientDataCodec {}),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl OAuth2ClientRepository for PgOAuth2ClientRepository {
+    type Conn = PgConnection;
+
+    async fn fetch_by_client_id_optional(
+        &self,
+        conn: &mut Self::Conn,
+        client_id: &str,
+    ) -> Result<Option<OAuth2ClientModel>, LightSpeedError> {
+        let sql = format!(
+            r#"
+            {}
+            where DATA ->> 'client_id' = $1
+            limit 1
+        "#,
+            self.queries().find_base_sql_query
+        );
+        Ok(self.repo.fetch_one_optional_with_sql(conn, &sql, &[&client_id]).await?)
+    }
+
+    async fn save(
+        &self,
+        conn: &mut Self::Conn,
+        model: NewModel<OAuth2ClientData>,
+    ) -> Result<OAuth2ClientModel, LightSpeedError> {
+        Ok(self.repo.save(conn, model).await?)
+    }
+
+    async fn update(
+        &self,
+        conn: &mut Self::Conn,
+        model: OAuth2ClientModel,
+    ) -> Result<OAuth2ClientModel, LightSpeedError> {
+        Ok(self.repo.update(conn, model).await?)
+    }
+
+    async fn delete(
+        &self,
+        conn: &mut Self::Conn,
+        model: OAuth2ClientModel,
+    ) -> Result<OAuth2ClientModel, LightSpeedError> {
+        Ok(self.repo.delete(conn, model).await?)
+    }
+}
@@ -0,0 +1,105 @@
+use crate::model::oauth2_consent::{OAuth2ConsentData, OAuth2ConsentDataCodec, OAuth2ConsentModel};
+use crate::repository::OAuth2ConsentRepository;
+use c3p0::postgres::*;
+use c3p0::*;
+use lightspeed_core::error::LightSpeedError;
+use std::ops::Deref;
+
+#[derive(Clone)]
+pub struct PgOAuth2ConsentRepository {
+    repo: PgC3p0Json<OAuth2ConsentData, OAuth2ConsentDataCodec>,
+}
+
+impl Deref for PgOAuth2ConsentRepository {
+    type Target = PgC3p0Json<OAuth2ConsentData, OAuth2ConsentDataCodec>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.repo
+    }
+}
+
+impl Default for PgOAuth2ConsentRepository {
+    fn default() -> Self {
+        PgOAuth2ConsentRepository {
+            repo: C3p0JsonBuilder::new("LS_AUTH_OAUTH2_CONSENT").build_with_codec(OAuth2ConsentDataCodec {}),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl OAuth2ConsentRepository for PgOAuth2ConsentRepository {
+    type Conn = PgConnection;
+
+    async fn fetch_by_user_id_and_client_id_optional(
+        &self,
+        conn: &mut Self::Conn,
+        user_id: i64,
+        client_id: &str,
+    ) -> Result<Option<OAuth2ConsentModel>, LightSpeedError> {
+        let sql = format!(
+            r#"
+            {}
+            where (DATA ->> 'user_id')::bigint = $1 and DATA ->> 'client_id' = $2
+            limit 1
+        "#,
+            self.queries().find_base_sql_query
+        );
+        Ok(self.repo.fetch_one_optional_with_sql(conn, &sql, &[&user_id, &client_id]).await?)
+    }
+
+    async fn fetch_all_by_user_id(
+        &self,
+        conn: &mut Self::Conn,
+        user_id: i64,
+    ) -> Result<Vec<OAuth2ConsentModel>, LightSpeedError> {
+        let sql = format!(
+            r#"
+            {}
+            where (DATA ->> 'user_id')::bigint = $1
+            order by id asc
+        "#,
+            self.queries().find_base_sql_query
+        );
+        Ok(self.repo.fetch_all_with_sql(conn, &sql, &[&user_id]).await?)
+    }
+
+    async fn save(
+        &self,
+        conn: &mut Self::Conn,
+        model: NewModel<OAuth2ConsentData>,
+    ) -> Result<OAuth2ConsentModel, LightSpeedError> {
+        Ok(self.repo.save(conn, model).await?)
+    }
+
+    async fn update(
+        &self,
+        conn: &mut Self::Conn,
+        model: OAuth2ConsentModel,
+    ) -> Result<OAuth2ConsentModel, LightSpeedError> {
+        Ok(self.repo.update(conn, model).await?)
+    }
+
+    async fn delete(
+        &self,
+        conn: &mut Self::Conn,
+        model: OAuth2ConsentModel,
+    ) -> Result<OAuth2ConsentModel, LightSpeedError> {
+        Ok(self.repo.delete(conn, model).await?)
+    }
+
+    async fn delete_by_user_id(&self, conn: &mut Self::Conn, user_id: i64) -> Result<u64, LightSpeedError> {
+        let sql = r#"
+            delete from LS_AUTH_OAUTH2_CONSENT
+            where (DATA ->> 'user_id')::bigint = $1
+        "#;
+        Ok(conn.execute(sql, &[&user_id]).await?)
+    }
+
+    async fn delete_by_client_id(&self, conn: &mut Self::Conn, client_id: &str) -> Result<u64, LightSpeedError> {
+        let sql = r#"
+            delete from LS_AUTH_OAUTH2_CONSENT
+            where DATA ->> 'client_id' = $1
+        "#;
+        Ok(conn.execute(sql, &[&client_id]).await?)
+    }
+}
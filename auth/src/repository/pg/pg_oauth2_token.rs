@@ -0,0 +1,101 @@
+use crate::model::oauth2_token::{OAuth2TokenData, OAuth2TokenDataCodec, OAuth2TokenModel};
+use crate::repository::OAuth2TokenRepository;
+use c3p0::postgres::*;
+use c3p0::*;
+use lightspeed_core::error::LightSpeedError;
+use std::ops::Deref;
+
+#[derive(Clone)]
+pub struct PgOAuth2TokenRepository {
+    repo: PgC3p0Json<OAuth2TokenData, OAuth2TokenDataCodec>,
+}
+
+impl Deref for PgOAuth2TokenRepository {
+    type Target = PgC3p0Json<OAuth2TokenData, OAuth2TokenDataCodec>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.repo
+    }
+}
+
+impl Default for PgOAuth2TokenRepository {
+    fn default() -> Self {
+        PgOAuth2TokenRepository {
+            repo: C3p0JsonBuilder::new("LS_AUTH_OAUTH2_TOKEN").build_with_codec(OAuth2TokenDataCodec {}),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl OAuth2TokenRepository for PgOAuth2TokenRepository {
+    type Conn = PgConnection;
+
+    async fn fetch_by_token_optional(
+        &self,
+        conn: &mut Self::Conn,
+        token: &str,
+    ) -> Result<Option<OAuth2TokenModel>, LightSpeedError> {
+        let sql = format!(
+            r#"
+            {}
+            where DATA ->> 'token' = $1
+            limit 1
+        "#,
+            self.queries().find_base_sql_query
+        );
+        Ok(self.repo.fetch_one_optional_with_sql(conn, &sql, &[&token]).await?)
+    }
+
+    async fn save(
+        &self,
+        conn: &mut Self::Conn,
+        model: NewModel<OAuth2TokenData>,
+    ) -> Result<OAuth2TokenModel, LightSpeedError> {
+        Ok(self.repo.save(conn, model).await?)
+    }
+
+    async fn delete(
+        &self,
+        conn: &mut Self::Conn,
+        model: OAuth2TokenModel,
+    ) -> Result<OAuth2TokenModel, LightSpeedError> {
+        Ok(self.repo.delete(conn, model).await?)
+    }
+
+    async fn delete_by_user_id(&self, conn: &mut Self::Conn, user_id: i64) -> Result<u64, LightSpeedError> {
+        let sql = r#"
+            delete from LS_AUTH_OAUTH2_TOKEN
+            where (DATA ->> 'user_id')::bigint = $1
+        "#;
+        Ok(conn.execute(sql, &[&user_id]).await?)
+    }
+
+    async fn delete_by_user_id_and_client_id(
+        &self,
+        conn: &mut Self::Conn,
+        user_id: i64,
+        client_id: &str,
+    ) -> Result<u64, LightSpeedError> {
+        let sql = r#"
+            delete from LS_AUTH_OAUTH2_TOKEN
+            where (DATA ->> 'user_id')::bigint = $1 and DATA ->> 'client_id' = $2
+        "#;
+        Ok(conn.execute(sql, &[&user_id, &client_id]).await?)
+    }
+
+    async fn delete_by_client_id(&self, conn: &mut Self::Conn, client_id: &str) -> Result<u64, LightSpeedError> {
+        let sql = r#"
+            delete from LS_AUTH_OAUTH2_TOKEN
+            where DATA ->> 'client_id' = $1
+        "#;
+        Ok(conn.execute(sql, &[&client_id]).await?)
+    }
+
+    async fn delete_expired(&self, conn: &mut Self::Conn, epoch_seconds: i64) -> Result<u64, LightSpeedError> {
+        let sql = r#"
+            delete from LS_AUTH_OAUTH2_TOKEN
+            where (DATA ->> 'expire_at_epoch_seconds')::bigint < $1
+        "#;
+        Ok(conn.execute(sql, &[&epoch_seconds]).await?)
+    }
+}
@@ -1,4 +1,4 @@
-use crate::model::token::{TokenData, TokenDataCodec, TokenModel};
+use crate::model::token::{TokenData, TokenDataCodec, TokenModel, TokenType};
 use crate::repository::TokenRepository;
 use c3p0::postgres::*;
 use c3p0::*;
@@ -28,7 +28,7 @@ impl Default for PgTokenRepository {
 impl TokenRepository for PgTokenRepository {
     type Conn = PgConnection;
 
-    async fn fetch_by_token(&self, conn: &mut PgConnection, token_string: &str) -> Result<TokenModel, LightSpeedError> {
+    async fn fetch_by_token(&self, conn: &mut PgConnection, token_hash: &str) -> Result<TokenModel, LightSpeedError> {
         let sql = format!(
             r#"
             {}
@@ -37,7 +37,11 @@ impl TokenRepository for PgTokenRepository {
         "#,
             self.queries().find_base_sql_query
         );
-        Ok(self.repo.fetch_one_with_sql(conn, &sql, &[&token_string]).await?)
+        Ok(self.repo.fetch_one_with_sql(conn, &sql, &[&token_hash]).await?)
+    }
+
+    async fn fetch_by_id(&self, conn: &mut Self::Conn, id: i64) -> Result<TokenModel, LightSpeedError> {
+        Ok(self.repo.fetch_one_by_id(conn, &id).await?)
     }
 
     async fn fetch_by_username(
@@ -55,6 +59,37 @@ impl TokenRepository for PgTokenRepository {
         Ok(self.repo.fetch_all_with_sql(conn, &sql, &[&username]).await?)
     }
 
+    async fn fetch_invitations_by_email(
+        &self,
+        conn: &mut PgConnection,
+        email: &str,
+    ) -> Result<Vec<TokenModel>, LightSpeedError> {
+        let sql = format!(
+            r#"
+            {}
+            where data ->> 'token_type' = $1 and data -> 'invitation' ->> 'email' = $2
+        "#,
+            self.queries().find_base_sql_query
+        );
+        Ok(self.repo.fetch_all_with_sql(conn, &sql, &[&TokenType::Invitation.as_ref(), &email]).await?)
+    }
+
+    async fn fetch_all_by_type(
+        &self,
+        conn: &mut Self::Conn,
+        token_type: TokenType,
+    ) -> Result<Vec<TokenModel>, LightSpeedError> {
+        let sql = format!(
+            r#"
+            {}
+            where data ->> 'token_type' = $1
+            order by id asc
+        "#,
+            self.queries().find_base_sql_query
+        );
+        Ok(self.repo.fetch_all_with_sql(conn, &sql, &[&token_type.as_ref()]).await?)
+    }
+
     async fn save(
         &self,
         conn: &mut Self::Conn,
@@ -70,4 +105,12 @@ impl TokenRepository for PgTokenRepository {
     ) -> Result<Model<TokenData>, LightSpeedError> {
         Ok(self.repo.delete(conn, model).await?)
     }
+
+    async fn delete_expired(&self, conn: &mut Self::Conn, epoch_seconds: i64) -> Result<u64, LightSpeedError> {
+        let sql = r#"
+            delete from LS_AUTH_TOKEN
+            where (DATA ->> 'expire_at_epoch_seconds')::bigint < $1
+        "#;
+        Ok(conn.execute(sql, &[&epoch_seconds]).await?)
+    }
 }
@@ -0,0 +1,96 @@
+use crate::model::webauthn_credential::{WebAuthnCredentialData, WebAuthnCredentialDataCodec, WebAuthnCredentialModel};
+use crate::repository::WebAuthnCredentialRepository;
+use c3p0::postgres::*;
+use c3p0::*;
+use lightspeed_core::error::LightSpeedError;
+use std::ops::Deref;
+
+#[derive(Clone)]
+pub struct PgWebAuthnCredentialRepository {
+    repo: PgC3p0Json<WebAuthnCredentialData, WebAuthnCredentialDataCodec>,
+}
+
+impl Deref for PgWebAuthnCredentialRepository {
+    type Target = PgC3p0Json<WebAuthnCredentialData, WebAuthnCredentialDataCodec>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.repo
+    }
+}
+
+impl Default for PgWebAuthnCredentialRepository {
+    fn default() -> Self {
+        PgWebAuthnCredentialRepository {
+            repo: C3p0JsonBuilder::new("LS_AUTH_WEBAUTHN_CREDENTIAL").build_with_codec(WebAuthnCredentialDataCodec {}),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl WebAuthnCredentialRepository for PgWebAuthnCredentialRepository {
+    type Conn = PgConnection;
+
+    async fn fetch_by_credential_id_optional(
+        &self,
+        conn: &mut Self::Conn,
+        credential_id: &str,
+    ) -> Result<Option<WebAuthnCredentialModel>, LightSpeedError> {
+        let sql = format!(
+            r#"
+            {}
+            where DATA ->> 'credential_id' = $1
+            limit 1
+        "#,
+            self.queries().find_base_sql_query
+        );
+        Ok(self.repo.fetch_one_optional_with_sql(conn, &sql, &[&credential_id]).await?)
+    }
+
+    async fn fetch_all_by_user_id(
+        &self,
+        conn: &mut Self::Conn,
+        user_id: i64,
+    ) -> Result<Vec<WebAuthnCredentialModel>, LightSpeedError> {
+        let sql = format!(
+            r#"
+            {}
+            where (DATA ->> 'user_id')::bigint = $1
+            order by id asc
+        "#,
+            self.queries().find_base_sql_query
+        );
+        Ok(self.repo.fetch_all_with_sql(conn, &sql, &[&user_id]).await?)
+    }
+
+    async fn save(
+        &self,
+        conn: &mut Self::Conn,
+        model: NewModel<WebAuthnCredentialData>,
+    ) -> Result<WebAuthnCredentialModel, LightSpeedError> {
+        Ok(self.repo.save(conn, model).await?)
+    }
+
+    async fn update(
+        &self,
+        conn: &mut Self::Conn,
+        model: WebAuthnCredentialModel,
+    ) -> Result<WebAuthnCredentialModel, LightSpeedError> {
+        Ok(self.repo.update(conn, model).await?)
+    }
+
+    async fn delete(
+        &self,
+        conn: &mut Self::Conn,
+        model: WebAuthnCredentialModel,
+    ) -> Result<WebAuthnCredentialModel, LightSpeedError> {
+        Ok(self.repo.delete(conn, model).await?)
+    }
+
+    async fn delete_by_user_id(&self, conn: &mut Self::Conn, user_id: i64) -> Result<u64, LightSpeedError> {
+        let sql = r#"
+            delete from LS_AUTH_WEBAUTHN_CREDENTIAL
+            where (DATA ->> 'user_id')::bigint = $1
+        "#;
+        Ok(conn.execute(sql, &[&user_id]).await?)
+    }
+}
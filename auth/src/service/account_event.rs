@@ -0,0 +1,122 @@
+use crate::dto::account_dto::AccountDto;
+use crate::model::token::TokenModel;
+use crate::repository::AuthRepositoryManager;
+use lightspeed_core::error::LightSpeedError;
+use log::*;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// A change in the lifecycle of an account.
+/// The accounts are carried without their credentials. The tokens are meant to be sent to the users,
+/// so they are not serialized, e.g. by the listeners that write an audit log.
+#[derive(Clone, Serialize)]
+pub enum AccountEvent {
+    /// An invitation has been created and should be sent to the invited email,
+    /// which is in the invitation data of the token
+    Invited {
+        #[serde(skip_serializing)]
+        invitation_token: TokenModel,
+    },
+    /// A new account has been created. The activation token is present only for the accounts
+    /// created in the PendingActivation status.
+    AccountCreated {
+        user: AccountDto,
+        #[serde(skip_serializing)]
+        activation_token: Option<TokenModel>,
+    },
+    Activated {
+        user: AccountDto,
+    },
+    /// A reset password token has been generated and should be sent to the user
+    PasswordResetRequested {
+        user: AccountDto,
+        #[serde(skip_serializing)]
+        reset_token: TokenModel,
+    },
+    PasswordChanged {
+        user: AccountDto,
+    },
+    RolesChanged {
+        user: AccountDto,
+    },
+    Disabled {
+        user: AccountDto,
+    },
+    Deleted {
+        user_id: i64,
+    },
+    /// An identity of an OpenID Connect provider has been linked to the account
+    IdentityLinked {
+        user_id: i64,
+        provider: String,
+    },
+}
+
+/// Receives the lifecycle events of the accounts, e.g. to send the notification emails or to write an audit log.
+#[async_trait::async_trait]
+pub trait AccountEventListener<RepoManager: AuthRepositoryManager>: Send + Sync {
+    /// Called within the transaction that changed the account. An error rolls the transaction back.
+    async fn on_event_with_conn(
+        &self,
+        _conn: &mut RepoManager::Conn,
+        _event: &AccountEvent,
+    ) -> Result<(), LightSpeedError> {
+        Ok(())
+    }
+
+    /// Called after the transaction that changed the account has been committed.
+    /// An error is logged and does not affect the other listeners.
+    async fn on_event(&self, _event: &AccountEvent) -> Result<(), LightSpeedError> {
+        Ok(())
+    }
+}
+
+/// Dispatches the account events to the registered listeners.
+///
+/// The listeners are called within the transaction by the `_with_conn` methods of the `AuthAccountService`,
+/// while the `on_event` callbacks are called only by the methods that own their transaction, after the commit.
+/// A caller that runs a `_with_conn` method in its own transaction is responsible to call `publish_after_commit`.
+pub struct AccountEventPublisher<RepoManager: AuthRepositoryManager> {
+    listeners: RwLock<Vec<Arc<dyn AccountEventListener<RepoManager>>>>,
+}
+
+impl<RepoManager: AuthRepositoryManager> Default for AccountEventPublisher<RepoManager> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<RepoManager: AuthRepositoryManager> AccountEventPublisher<RepoManager> {
+    pub fn new() -> Self {
+        AccountEventPublisher { listeners: RwLock::new(vec![]) }
+    }
+
+    /// Registers a listener. The listeners are called in registration order.
+    pub async fn add_listener(&self, listener: Arc<dyn AccountEventListener<RepoManager>>) {
+        self.listeners.write().await.push(listener);
+    }
+
+    /// Notifies the listeners within the current transaction. Stops at the first error.
+    pub async fn publish_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        event: &AccountEvent,
+    ) -> Result<(), LightSpeedError> {
+        let listeners = self.listeners.read().await.clone();
+        for listener in listeners {
+            listener.on_event_with_conn(conn, event).await?;
+        }
+        Ok(())
+    }
+
+    /// Notifies the listeners once the transaction has been committed
+    pub async fn publish_after_commit(&self, event: &AccountEvent) {
+        let listeners = self.listeners.read().await.clone();
+        for listener in listeners {
+            if let Err(err) = listener.on_event(event).await {
+                error!("An account event listener failed after commit: {:?}", err);
+            }
+        }
+    }
+}
@@ -1,56 +1,185 @@
-use crate::config::AuthConfig;
+use crate::config::{AccountAttributeType, AuthConfig};
+use crate::dto::account_dto::AccountDto;
+use crate::dto::change_email_dto::ChangeEmailDto;
 use crate::dto::change_password_dto::ChangePasswordDto;
 use crate::dto::create_login_dto::CreateLoginDto;
+use crate::dto::import_account_dto::ImportAccountDto;
+use crate::dto::invitation_dto::{AcceptInvitationDto, InvitationDto, InviteAccountDto};
 use crate::dto::reset_password_dto::ResetPasswordDto;
-use crate::model::auth_account::{AuthAccountData, AuthAccountModel, AuthAccountStatus};
-use crate::model::token::{TokenModel, TokenType};
-use crate::repository::{AuthAccountRepository, AuthRepositoryManager};
+use crate::dto::search_accounts_dto::{SearchAccountsDto, SearchAccountsResultDto};
+use crate::dto::two_factor_dto::{TotpCodeDto, TotpEnrollmentDto};
+use crate::dto::webauthn_dto::{
+    WebAuthnAssertionDto, WebAuthnAssertionOptionsDto, WebAuthnRegistrationDto, WebAuthnRegistrationOptionsDto,
+};
+use crate::model::auth_account::{
+    normalize_email, normalize_username, AuthAccountData, AuthAccountModel, AuthAccountStatus, PendingEmailChange,
+    TwoFactorData,
+};
+use crate::model::impersonation::{ImpersonationData, ImpersonationModel};
+use crate::model::login_attempt::LoginAttemptKeyType;
+use crate::model::token::{InvitationData, TokenModel, TokenType};
+use crate::model::webauthn_credential::{WebAuthnCredentialData, WebAuthnCredentialModel};
+use crate::repository::{
+    AuthAccountRepository, AuthRepositoryManager, ExternalIdentityRepository, ImpersonationRepository,
+    OAuth2ConsentRepository, OAuth2TokenRepository, WebAuthnCredentialRepository,
+};
+use crate::service::account_event::{AccountEvent, AccountEventPublisher};
+use crate::service::auth_session::AuthSessionService;
+use crate::service::login_attempt::LoginAttemptService;
 use crate::service::password_codec::PasswordCodecService;
 use crate::service::token::TokenService;
+use crate::service::totp::TotpService;
+use crate::service::webauthn::WebAuthnService;
 use c3p0::*;
+use lightspeed_core::clock::Clock;
 use lightspeed_core::error::*;
-use lightspeed_core::service::auth::Auth;
-use lightspeed_core::service::validator::{Validator, ERR_NOT_UNIQUE};
-use lightspeed_core::utils::current_epoch_seconds;
+use lightspeed_core::service::auth::{Auth, AuthContext};
+use lightspeed_core::service::random::RandomService;
+use lightspeed_core::service::validator::email::validate_email;
+use lightspeed_core::service::validator::order::validate_le;
+use lightspeed_core::service::validator::{Validable, Validator, ERR_NOT_UNIQUE};
+use lightspeed_core::utils::new_hyphenated_uuid;
 use log::*;
+use serde_json::Value;
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 pub const WRONG_TYPE: &str = "WRONG_TYPE";
+pub const ERR_UNSUPPORTED_HASH: &str = "UNSUPPORTED_HASH";
+pub const ERR_PASSWORD_ALREADY_USED: &str = "PASSWORD_ALREADY_USED";
+pub const ERR_UNKNOWN_ATTRIBUTE: &str = "UNKNOWN_ATTRIBUTE";
+pub const ERR_WRONG_ATTRIBUTE_TYPE: &str = "WRONG_ATTRIBUTE_TYPE";
+pub const ERR_NOT_SEARCHABLE_ATTRIBUTE: &str = "NOT_SEARCHABLE_ATTRIBUTE";
+
+/// The number of disabled accounts checked in each transaction of the retention job
+const RETENTION_BATCH_SIZE: u32 = 100;
+
+/// The result of the first step of the login
+pub enum LoginOutcome {
+    /// The credentials are valid and no second factor is required
+    Authenticated(Auth),
+    /// The credentials are valid but the account has the two-factor authentication enabled or a WebAuthn credential.
+    /// The login has to be completed with the challenge token and a TOTP or recovery code, or a WebAuthn assertion.
+    SecondFactorRequired { challenge: TokenModel },
+    /// The credentials are valid but the password is older than the configured maximum age.
+    /// The password has to be changed with the reset token before logging in.
+    PasswordExpired { reset_token: TokenModel },
+}
 
 #[derive(Clone)]
 pub struct AuthAccountService<RepoManager: AuthRepositoryManager> {
     c3p0: RepoManager::C3P0,
     auth_config: AuthConfig,
     auth_repo: RepoManager::AuthAccountRepo,
+    external_identity_repo: RepoManager::ExternalIdentityRepo,
+    oauth2_consent_repo: RepoManager::OAuth2ConsentRepo,
+    oauth2_token_repo: RepoManager::OAuth2TokenRepo,
+    impersonation_repo: RepoManager::ImpersonationRepo,
+    webauthn_credential_repo: RepoManager::WebAuthnCredentialRepo,
     password_service: Arc<PasswordCodecService>,
     token_service: Arc<TokenService<RepoManager>>,
+    totp_service: Arc<TotpService>,
+    webauthn_service: Arc<WebAuthnService>,
+    login_attempt_service: Arc<LoginAttemptService<RepoManager>>,
+    auth_session_service: Arc<AuthSessionService<RepoManager>>,
+    account_event_publisher: Arc<AccountEventPublisher<RepoManager>>,
+    clock: Arc<dyn Clock>,
+}
+
+/// The services used by the AuthAccountService.
+/// The ones of an AuthModule are returned by `AuthModule::auth_account_services`.
+#[derive(Clone)]
+pub struct AuthAccountServices<RepoManager: AuthRepositoryManager> {
+    pub token_service: Arc<TokenService<RepoManager>>,
+    pub password_service: Arc<PasswordCodecService>,
+    pub totp_service: Arc<TotpService>,
+    pub webauthn_service: Arc<WebAuthnService>,
+    pub login_attempt_service: Arc<LoginAttemptService<RepoManager>>,
+    pub auth_session_service: Arc<AuthSessionService<RepoManager>>,
+    pub account_event_publisher: Arc<AccountEventPublisher<RepoManager>>,
 }
 
 impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
     pub fn new(
-        c3p0: RepoManager::C3P0,
+        repo_manager: &RepoManager,
         auth_config: AuthConfig,
-        token_service: Arc<TokenService<RepoManager>>,
-        password_service: Arc<PasswordCodecService>,
-        auth_repo: RepoManager::AuthAccountRepo,
+        services: AuthAccountServices<RepoManager>,
+        clock: Arc<dyn Clock>,
     ) -> Self {
-        AuthAccountService { c3p0, auth_config, auth_repo, password_service, token_service }
+        AuthAccountService {
+            c3p0: repo_manager.c3p0().clone(),
+            auth_config,
+            auth_repo: repo_manager.auth_account_repo(),
+            external_identity_repo: repo_manager.external_identity_repo(),
+            oauth2_consent_repo: repo_manager.oauth2_consent_repo(),
+            oauth2_token_repo: repo_manager.oauth2_token_repo(),
+            impersonation_repo: repo_manager.impersonation_repo(),
+            webauthn_credential_repo: repo_manager.webauthn_credential_repo(),
+            password_service: services.password_service,
+            token_service: services.token_service,
+            totp_service: services.totp_service,
+            webauthn_service: services.webauthn_service,
+            login_attempt_service: services.login_attempt_service,
+            auth_session_service: services.auth_session_service,
+            account_event_publisher: services.account_event_publisher,
+            clock,
+        }
     }
 
-    pub async fn login(&self, username: &str, password: &str) -> Result<Auth, LightSpeedError> {
-        self.c3p0.transaction(|conn| async { self.login_with_conn(conn, username, password).await }).await
+    /// Logs in a user without a client key, see `authenticate` for the returned outcome.
+    pub async fn login(&self, username: &str, password: &str) -> Result<LoginOutcome, LightSpeedError> {
+        self.authenticate(username, password, None).await
     }
 
+    /// See `authenticate_with_conn` for the handling of the failed attempts
     pub async fn login_with_conn(
         &self,
         conn: &mut RepoManager::Conn,
         username: &str,
         password: &str,
-    ) -> Result<Auth, LightSpeedError> {
+    ) -> Result<LoginOutcome, LightSpeedError> {
+        self.authenticate_with_conn(conn, username, password, None).await
+    }
+
+    /// First step of the login. If the account has the two-factor authentication enabled,
+    /// it returns a short-lived challenge instead of the Auth. If the password has expired,
+    /// it returns a reset password token instead; the expiry is checked before the second factor.
+    ///
+    /// The failed attempts are counted per username and, if a `client_key` (e.g. the IP address) is provided,
+    /// per client; every failure is followed by a progressive delay and, when the configured thresholds
+    /// are reached, the username or the client are temporarily locked.
+    ///
+    /// Every successful login opens a new session, see `AuthSessionService`.
+    pub async fn authenticate(
+        &self,
+        username: &str,
+        password: &str,
+        client_key: Option<&str>,
+    ) -> Result<LoginOutcome, LightSpeedError> {
+        let result = self
+            .c3p0
+            .transaction(|conn| async { self.authenticate_with_conn(conn, username, password, client_key).await })
+            .await;
+        self.register_failed_login(username, client_key, result).await
+    }
+
+    /// Verifies that neither the username nor the client are locked and then checks the credentials.
+    /// The failed attempts are not registered here, because they would be rolled back together with
+    /// the transaction; callers should register them with the `LoginAttemptService` in a separate transaction.
+    pub async fn authenticate_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        username: &str,
+        password: &str,
+        client_key: Option<&str>,
+    ) -> Result<LoginOutcome, LightSpeedError> {
         debug!("login attempt with username [{}]", username);
+        self.login_attempt_service.check_not_locked_with_conn(conn, username, client_key).await?;
+
         let model = self.auth_repo.fetch_by_username_optional(conn, username).await?;
 
-        if let Some(user) = model {
+        if let Some(mut user) = model {
             if self.password_service.verify_match(password, &user.data.password)? {
                 match &user.data.status {
                     AuthAccountStatus::Active => {}
@@ -62,17 +191,39 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
                     }
                 };
 
-                let creation_ts_seconds = current_epoch_seconds();
-                let expiration_ts_seconds =
-                    creation_ts_seconds + (self.auth_config.auth_session_max_validity_minutes * 60);
+                if self.password_service.needs_rehash(&user.data.password) {
+                    debug!("Upgrade the password hash of username [{}]", username);
+                    user.data.password = self.password_service.hash_password(password)?;
+                    user = self.auth_repo.update(conn, user).await?;
+                }
+
+                if let Some(max_password_age_days) = self.auth_config.max_password_age_days {
+                    if user.data.is_password_expired_at(max_password_age_days, self.clock.epoch_seconds()) {
+                        debug!("Password expired for username [{}]", username);
+                        let reset_token = self
+                            .token_service
+                            .generate_and_save_token_with_conn(conn, &user.data.username, TokenType::ResetPassword)
+                            .await?;
+                        return Ok(LoginOutcome::PasswordExpired { reset_token });
+                    }
+                }
+
+                if self.is_second_factor_required_with_conn(conn, &user).await? {
+                    debug!("Second factor required for username [{}]", username);
+                    let challenge = self
+                        .token_service
+                        .generate_and_save_token_with_validity_with_conn(
+                            conn,
+                            &user.data.username,
+                            TokenType::SecondFactorChallenge,
+                            self.auth_config.second_factor_challenge_validity_minutes,
+                        )
+                        .await?;
+                    return Ok(LoginOutcome::SecondFactorRequired { challenge });
+                }
 
-                return Ok(Auth::new(
-                    user.id,
-                    user.data.username,
-                    user.data.roles,
-                    creation_ts_seconds,
-                    expiration_ts_seconds,
-                ));
+                self.login_attempt_service.reset_with_conn(conn, LoginAttemptKeyType::Username, username).await?;
+                return Ok(LoginOutcome::Authenticated(self.new_auth_with_conn(conn, user).await?));
             }
         };
 
@@ -82,13 +233,260 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
         })
     }
 
+    /// Completes the login of a user whose identity has been verified by an external provider.
+    /// The password expiry is not checked, while the second factor is still required if enabled.
+    pub async fn authenticate_external_user_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+    ) -> Result<LoginOutcome, LightSpeedError> {
+        let user = self.auth_repo.fetch_by_id(conn, user_id).await?;
+        match &user.data.status {
+            AuthAccountStatus::Active => {}
+            _ => {
+                return Err(LightSpeedError::BadRequest {
+                    message: format!("User [{}] not in status Active", user.data.username),
+                    code: ErrorCodes::INACTIVE_USER,
+                })
+            }
+        };
+
+        if self.is_second_factor_required_with_conn(conn, &user).await? {
+            debug!("Second factor required for username [{}]", user.data.username);
+            let challenge = self
+                .token_service
+                .generate_and_save_token_with_validity_with_conn(
+                    conn,
+                    &user.data.username,
+                    TokenType::SecondFactorChallenge,
+                    self.auth_config.second_factor_challenge_validity_minutes,
+                )
+                .await?;
+            return Ok(LoginOutcome::SecondFactorRequired { challenge });
+        }
+
+        Ok(LoginOutcome::Authenticated(self.new_auth_with_conn(conn, user).await?))
+    }
+
+    /// Second step of the login of a user with the two-factor authentication enabled
+    pub async fn login_with_totp_code(&self, challenge_token: &str, code: &str) -> Result<Auth, LightSpeedError> {
+        let result = self
+            .c3p0
+            .transaction(|conn| async { self.login_with_totp_code_with_conn(conn, challenge_token, code).await })
+            .await;
+        self.register_failed_second_factor(challenge_token, result).await
+    }
+
+    pub async fn login_with_totp_code_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        challenge_token: &str,
+        code: &str,
+    ) -> Result<Auth, LightSpeedError> {
+        debug!("Second factor login called with challenge [{}]", challenge_token);
+        let (token, mut user) = self.fetch_second_factor_challenge_with_conn(conn, challenge_token).await?;
+
+        if !self.verify_totp_code(&mut user, code)? {
+            return Err(LightSpeedError::BadRequest {
+                message: "Wrong second factor code".to_owned(),
+                code: ErrorCodes::WRONG_CREDENTIALS,
+            });
+        }
+
+        self.token_service.delete_with_conn(conn, token).await?;
+        self.login_attempt_service.reset_with_conn(conn, LoginAttemptKeyType::Username, &user.data.username).await?;
+        let user = self.auth_repo.update(conn, user).await?;
+        self.new_auth_with_conn(conn, user).await
+    }
+
+    /// Second step of the login of a user that cannot access the authenticator app.
+    /// Each recovery code can be used only once.
+    pub async fn login_with_recovery_code(
+        &self,
+        challenge_token: &str,
+        recovery_code: &str,
+    ) -> Result<Auth, LightSpeedError> {
+        let result = self
+            .c3p0
+            .transaction(|conn| async {
+                self.login_with_recovery_code_with_conn(conn, challenge_token, recovery_code).await
+            })
+            .await;
+        self.register_failed_second_factor(challenge_token, result).await
+    }
+
+    pub async fn login_with_recovery_code_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        challenge_token: &str,
+        recovery_code: &str,
+    ) -> Result<Auth, LightSpeedError> {
+        debug!("Recovery code login called with challenge [{}]", challenge_token);
+        let (token, mut user) = self.fetch_second_factor_challenge_with_conn(conn, challenge_token).await?;
+
+        let recovery_code_hash = self.totp_service.hash_recovery_code(recovery_code);
+        let recovery_code_found = match user.data.two_factor.as_mut() {
+            Some(two_factor) => {
+                let previous_len = two_factor.recovery_code_hashes.len();
+                two_factor.recovery_code_hashes.retain(|hash| hash != &recovery_code_hash);
+                two_factor.recovery_code_hashes.len() < previous_len
+            }
+            None => false,
+        };
+
+        if !recovery_code_found {
+            return Err(LightSpeedError::BadRequest {
+                message: "Wrong recovery code".to_owned(),
+                code: ErrorCodes::WRONG_CREDENTIALS,
+            });
+        }
+
+        info!("Recovery code used by user [{}]", user.data.username);
+
+        self.token_service.delete_with_conn(conn, token).await?;
+        self.login_attempt_service.reset_with_conn(conn, LoginAttemptKeyType::Username, &user.data.username).await?;
+        let user = self.auth_repo.update(conn, user).await?;
+        self.new_auth_with_conn(conn, user).await
+    }
+
+    async fn fetch_second_factor_challenge_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        challenge_token: &str,
+    ) -> Result<(TokenModel, AuthAccountModel), LightSpeedError> {
+        let token = self.token_service.fetch_by_token_with_conn(conn, challenge_token, true).await?;
+
+        Validator::validate(&|error_details: &mut ErrorDetails| {
+            match &token.data.token_type {
+                TokenType::SecondFactorChallenge => {}
+                _ => error_details.add_detail("token_type", WRONG_TYPE),
+            };
+            Ok(())
+        })?;
+
+        self.login_attempt_service.check_not_locked_with_conn(conn, &token.data.username, None).await?;
+
+        let user = self.auth_repo.fetch_by_username(conn, &token.data.username).await?;
+
+        match &user.data.status {
+            AuthAccountStatus::Active => {}
+            _ => {
+                return Err(LightSpeedError::BadRequest {
+                    message: format!("User [{}] not in status Active", token.data.username),
+                    code: ErrorCodes::INACTIVE_USER,
+                })
+            }
+        };
+
+        if !self.is_second_factor_required_with_conn(conn, &user).await? {
+            return Err(LightSpeedError::BadRequest {
+                message: format!("User [{}] has not the two-factor authentication enabled", token.data.username),
+                code: ErrorCodes::TWO_FACTOR_NOT_ENABLED,
+            });
+        }
+
+        Ok((token, user))
+    }
+
+    /// The second factor is required if the user has the TOTP two-factor authentication enabled
+    /// or has registered at least a WebAuthn credential
+    async fn is_second_factor_required_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user: &AuthAccountModel,
+    ) -> Result<bool, LightSpeedError> {
+        if user.data.is_two_factor_enabled() {
+            return Ok(true);
+        }
+        Ok(!self.webauthn_credential_repo.fetch_all_by_user_id(conn, user.id).await?.is_empty())
+    }
+
+    /// Verifies the TOTP code of the user and, if valid, marks it as used.
+    /// The caller is responsible for persisting the user.
+    fn verify_totp_code(&self, user: &mut AuthAccountModel, code: &str) -> Result<bool, LightSpeedError> {
+        if let Some(two_factor) = user.data.two_factor.as_mut() {
+            if let Some(time_step) =
+                self.totp_service.verify_code(&two_factor.totp_secret, code, two_factor.last_used_time_step)?
+            {
+                two_factor.last_used_time_step = Some(time_step);
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Registers the failed login in a dedicated transaction, so that it is not rolled back
+    /// together with the login, and then applies the progressive delay.
+    /// If the failure cannot be registered the maximum delay is applied.
+    async fn register_failed_login<T>(
+        &self,
+        username: &str,
+        client_key: Option<&str>,
+        result: Result<T, LightSpeedError>,
+    ) -> Result<T, LightSpeedError> {
+        if is_wrong_credentials(&result) {
+            match self
+                .c3p0
+                .transaction(|conn| async {
+                    self.login_attempt_service.register_failure_with_conn(conn, username, client_key).await
+                })
+                .await
+            {
+                Ok(delay) => tokio::time::sleep(delay).await,
+                Err(err) => {
+                    warn!("Cannot register the failed login of username [{}]. Err: {:?}", username, err);
+                    tokio::time::sleep(Duration::from_millis(self.auth_config.failed_login_max_delay_millis)).await
+                }
+            }
+        }
+        result
+    }
+
+    async fn register_failed_second_factor<T>(
+        &self,
+        challenge_token: &str,
+        result: Result<T, LightSpeedError>,
+    ) -> Result<T, LightSpeedError> {
+        if is_wrong_credentials(&result) {
+            let token = self
+                .c3p0
+                .transaction(|conn| async {
+                    self.token_service.fetch_by_token_with_conn(conn, challenge_token, false).await
+                })
+                .await?;
+            return self.register_failed_login(&token.data.username, None, result).await;
+        }
+        result
+    }
+
+    /// Creates the Auth of a successful login and records its session
+    async fn new_auth_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user: AuthAccountModel,
+    ) -> Result<Auth, LightSpeedError> {
+        let creation_ts_seconds = self.clock.epoch_seconds();
+        let expiration_ts_seconds = creation_ts_seconds + (self.auth_config.auth_session_max_validity_minutes * 60);
+        let mut auth =
+            Auth::new(user.id, user.data.username, user.data.roles, creation_ts_seconds, expiration_ts_seconds);
+        auth.session_id = new_hyphenated_uuid();
+        self.auth_session_service.start_session_with_conn(conn, &auth).await?;
+        Ok(auth)
+    }
+
     pub async fn create_user(
         &self,
         create_login_dto: CreateLoginDto,
     ) -> Result<(AuthAccountModel, TokenModel), LightSpeedError> {
-        self.c3p0
-            .transaction(|conn| async { self.create_user_with_conn(conn, create_login_dto).await })
-            .await
+        let (user, token) =
+            self.c3p0.transaction(|conn| async { self.create_user_with_conn(conn, create_login_dto).await }).await?;
+        self.account_event_publisher
+            .publish_after_commit(&AccountEvent::AccountCreated {
+                user: user.clone().into(),
+                activation_token: Some(token.clone()),
+            })
+            .await;
+        Ok((user, token))
     }
 
     pub async fn create_user_with_conn(
@@ -102,20 +500,20 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
         );
         let hashed_password = self.password_service.hash_password(&create_login_dto.password)?;
 
-        let username = match &create_login_dto.username {
+        let username = normalize_username(match &create_login_dto.username {
             Some(username) => {
-                if !username.is_empty() {
+                if !username.trim().is_empty() {
                     username
                 } else {
                     &create_login_dto.email
                 }
             }
             None => &create_login_dto.email,
-        }
-        .clone();
+        });
+        let email = normalize_email(&create_login_dto.email);
 
         let existing_user = self.auth_repo.fetch_by_username_optional(conn, &username).await?;
-        let existing_email = self.auth_repo.fetch_by_email_optional(conn, &create_login_dto.email).await?;
+        let existing_email = self.auth_repo.fetch_by_email_optional(conn, &email).await?;
         Validator::validate(&(&create_login_dto, &|error_details: &mut ErrorDetails| {
             if existing_user.is_some() {
                 error_details.add_detail("username", ERR_NOT_UNIQUE);
@@ -132,105 +530,458 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
                 conn,
                 NewModel::new(AuthAccountData {
                     username,
-                    email: create_login_dto.email,
+                    email,
                     password: hashed_password,
                     roles: self.auth_config.default_roles_on_account_creation.clone(),
-                    created_date_epoch_seconds: current_epoch_seconds(),
+                    created_date_epoch_seconds: self.clock.epoch_seconds(),
                     status: AuthAccountStatus::PendingActivation,
+                    two_factor: None,
+                    password_history: vec![],
+                    password_changed_epoch_seconds: self.clock.epoch_seconds(),
+                    pending_email_change: None,
+                    disabled_date_epoch_seconds: None,
+                    anonymized_date_epoch_seconds: None,
+                    attributes: BTreeMap::new(),
                 }),
             )
             .await?;
 
         let token = self.generate_activation_token_with_conn(conn, &auth_account_model.data.username).await?;
+        self.account_event_publisher
+            .publish_with_conn(
+                conn,
+                &AccountEvent::AccountCreated {
+                    user: auth_account_model.clone().into(),
+                    activation_token: Some(token.clone()),
+                },
+            )
+            .await?;
         Ok((auth_account_model, token))
     }
 
-    async fn generate_activation_token_with_conn(
+    /// Creates an active account for a user authenticated by an external identity provider.
+    /// The preferred username is used if it is not taken, otherwise the username is the email.
+    /// The account gets a random password that the user can replace through the reset password flow.
+    pub async fn create_external_user_with_conn(
         &self,
         conn: &mut RepoManager::Conn,
-        username: &str,
-    ) -> Result<TokenModel, LightSpeedError> {
-        debug!("Generate activation token for username [{}]", username);
-        self.token_service.generate_and_save_token_with_conn(conn, username, TokenType::AccountActivation).await
+        preferred_username: Option<&str>,
+        email: &str,
+    ) -> Result<AuthAccountModel, LightSpeedError> {
+        info!("Create external user with preferred username [{:?}] and email [{}]", preferred_username, email);
+        let email = normalize_email(email);
+
+        let mut username = normalize_username(&email);
+        if let Some(preferred_username) =
+            preferred_username.map(normalize_username).filter(|preferred_username| !preferred_username.is_empty())
+        {
+            if self.auth_repo.fetch_by_username_optional(conn, &preferred_username).await?.is_none() {
+                username = preferred_username;
+            }
+        }
+
+        let existing_user = self.auth_repo.fetch_by_username_optional(conn, &username).await?;
+        let existing_email = self.auth_repo.fetch_by_email_optional(conn, &email).await?;
+        Validator::validate(&|error_details: &mut ErrorDetails| {
+            validate_email(error_details, "email", &email);
+            if existing_user.is_some() {
+                error_details.add_detail("username", ERR_NOT_UNIQUE);
+            }
+            if existing_email.is_some() {
+                error_details.add_detail("email", ERR_NOT_UNIQUE);
+            }
+            Ok(())
+        })?;
+
+        let hashed_password = self.password_service.hash_password(&RandomService::random_string(32))?;
+        let user = self
+            .auth_repo
+            .save(
+                conn,
+                NewModel::new(AuthAccountData {
+                    username,
+                    email,
+                    password: hashed_password,
+                    roles: self.auth_config.default_roles_on_account_creation.clone(),
+                    created_date_epoch_seconds: self.clock.epoch_seconds(),
+                    status: AuthAccountStatus::Active,
+                    two_factor: None,
+                    password_history: vec![],
+                    password_changed_epoch_seconds: self.clock.epoch_seconds(),
+                    pending_email_change: None,
+                    disabled_date_epoch_seconds: None,
+                    anonymized_date_epoch_seconds: None,
+                    attributes: BTreeMap::new(),
+                }),
+            )
+            .await?;
+        self.account_event_publisher
+            .publish_with_conn(
+                conn,
+                &AccountEvent::AccountCreated { user: user.clone().into(), activation_token: None },
+            )
+            .await?;
+        Ok(user)
     }
 
-    pub async fn generate_new_activation_token_by_username_and_email(
+    /// Imports accounts whose passwords have already been hashed by another system.
+    /// The hashes are saved as they are and are upgraded to the current algorithm at the first successful login.
+    /// No activation token is generated for the accounts imported in the PendingActivation status.
+    /// No AccountCreated event is published for the imported accounts.
+    pub async fn import_accounts(
         &self,
-        username: &str,
-        email: &str,
-    ) -> Result<(AuthAccountModel, TokenModel), LightSpeedError> {
-        self.c3p0
-            .transaction(|conn| async {
-                self.generate_new_activation_token_by_username_and_email_with_conn(conn, username, email).await
-            })
-            .await
+        accounts: Vec<ImportAccountDto>,
+    ) -> Result<Vec<AuthAccountModel>, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.import_accounts_with_conn(conn, accounts).await }).await
     }
 
-    pub async fn generate_new_activation_token_by_username_and_email_with_conn(
+    pub async fn import_accounts_with_conn(
         &self,
         conn: &mut RepoManager::Conn,
-        username: &str,
-        email: &str,
-    ) -> Result<(AuthAccountModel, TokenModel), LightSpeedError> {
-        debug!("Generate new activation token for username [{}] and email [{}]", username, email);
-        let auth_account = self.fetch_by_username_with_conn(conn, username).await?;
+        accounts: Vec<ImportAccountDto>,
+    ) -> Result<Vec<AuthAccountModel>, LightSpeedError> {
+        info!("Import [{}] accounts", accounts.len());
 
+        let accounts = accounts
+            .into_iter()
+            .map(|mut account| {
+                account.username = normalize_username(&account.username);
+                account.email = normalize_email(&account.email);
+                account
+            })
+            .collect::<Vec<_>>();
+
+        let mut existing = Vec::with_capacity(accounts.len());
+        for account in &accounts {
+            existing.push((
+                self.auth_repo.fetch_by_username_optional(conn, &account.username).await?.is_some(),
+                self.auth_repo.fetch_by_email_optional(conn, &account.email).await?.is_some(),
+            ));
+        }
+
+        let password_service = &self.password_service;
         Validator::validate(&|error_details: &mut ErrorDetails| {
-            if auth_account.data.email != email {
-                error_details.add_detail("email", "WRONG_EMAIL");
-            };
+            for (count, account) in accounts.iter().enumerate() {
+                let mut scoped_err = error_details.with_scope(format!("accounts[{count}]"));
+                account.validate(&mut scoped_err)?;
+
+                let (existing_username, existing_email) = existing[count];
+                let previous = &accounts[..count];
+                if existing_username || previous.iter().any(|other| other.username == account.username) {
+                    scoped_err.add_detail("username", ERR_NOT_UNIQUE);
+                }
+                if existing_email || previous.iter().any(|other| other.email == account.email) {
+                    scoped_err.add_detail("email", ERR_NOT_UNIQUE);
+                }
+                if !password_service.is_supported(&account.password_hash) {
+                    scoped_err.add_detail("password_hash", ERR_UNSUPPORTED_HASH);
+                }
+            }
             Ok(())
         })?;
 
-        let previous_activation_token = self
-            .token_service
-            .fetch_all_by_username_with_conn(conn, username)
-            .await?
-            .into_iter()
-            .filter(|token| token.data.token_type == TokenType::AccountActivation)
-            .map(|token| token.data.token)
-            .collect::<Vec<String>>()
-            .first()
-            .cloned()
-            .ok_or_else(|| LightSpeedError::BadRequest {
-                message: format!("Previous activation token not found for user [{username}]"),
-                code: ErrorCodes::NOT_PENDING_USER,
-            })?;
-        self.generate_new_activation_token_by_token_with_conn(conn, &previous_activation_token).await
+        let now = self.clock.epoch_seconds();
+        let mut imported = Vec::with_capacity(accounts.len());
+        for account in accounts {
+            debug!("Import account with username [{}]", account.username);
+            imported.push(
+                self.auth_repo
+                    .save(
+                        conn,
+                        NewModel::new(AuthAccountData {
+                            username: account.username,
+                            email: account.email,
+                            password: account.password_hash,
+                            roles: account.roles,
+                            created_date_epoch_seconds: account.created_date_epoch_seconds.unwrap_or(now),
+                            status: account.status,
+                            two_factor: None,
+                            password_history: vec![],
+                            password_changed_epoch_seconds: now,
+                            pending_email_change: None,
+                            disabled_date_epoch_seconds: None,
+                            anonymized_date_epoch_seconds: None,
+                            attributes: BTreeMap::new(),
+                        }),
+                    )
+                    .await?,
+            );
+        }
+        Ok(imported)
     }
 
-    pub async fn generate_new_activation_token_by_token(
+    /// Invites the email to create an account with the given roles.
+    /// The inviter is the user of the AuthContext, who needs all the invited roles and cannot be impersonated.
+    /// Returns the Invitation token to be sent to the invited email.
+    pub async fn invite(
         &self,
-        previous_activation_token: &str,
-    ) -> Result<(AuthAccountModel, TokenModel), LightSpeedError> {
-        self.c3p0
-            .transaction(|conn| async {
-                self.generate_new_activation_token_by_token_with_conn(conn, previous_activation_token).await
-            })
-            .await
+        auth_context: &AuthContext<'_>,
+        dto: InviteAccountDto,
+    ) -> Result<TokenModel, LightSpeedError> {
+        let token =
+            self.c3p0.transaction(|conn| async { self.invite_with_conn(conn, auth_context, dto).await }).await?;
+        self.account_event_publisher
+            .publish_after_commit(&AccountEvent::Invited { invitation_token: token.clone() })
+            .await;
+        Ok(token)
     }
 
-    pub async fn generate_new_activation_token_by_token_with_conn(
+    pub async fn invite_with_conn(
         &self,
         conn: &mut RepoManager::Conn,
-        previous_activation_token: &str,
-    ) -> Result<(AuthAccountModel, TokenModel), LightSpeedError> {
-        debug!("Generate new activation token from previous token [{}]", previous_activation_token);
-        let token = self.token_service.fetch_by_token_with_conn(conn, previous_activation_token, false).await?;
+        auth_context: &AuthContext<'_>,
+        dto: InviteAccountDto,
+    ) -> Result<TokenModel, LightSpeedError> {
+        let invited_roles: Vec<&str> = dto.roles.iter().map(String::as_str).collect();
+        auth_context.has_all_roles(&invited_roles)?.is_not_impersonated()?;
+        let invited_by_user_id = auth_context.auth.id;
+        info!("User_id [{}] invites email [{}] with roles [{:?}]", invited_by_user_id, dto.email, dto.roles);
+        let email = normalize_email(&dto.email);
+
+        let existing_email = self.auth_repo.fetch_by_email_optional(conn, &email).await?;
+        Validator::validate(&(&dto, &|error_details: &mut ErrorDetails| {
+            if existing_email.is_some() {
+                error_details.add_detail("email", ERR_NOT_UNIQUE);
+            }
+            Ok(())
+        }))?;
+
+        let token = self
+            .token_service
+            .generate_and_save_invitation_token_with_conn(
+                conn,
+                InvitationData {
+                    email,
+                    roles: dto.roles,
+                    invited_by_user_id: Some(invited_by_user_id),
+                    created_date_epoch_seconds: self.clock.epoch_seconds(),
+                },
+                dto.validity_minutes.unwrap_or(self.auth_config.invitation_token_validity_minutes),
+            )
+            .await?;
+        self.account_event_publisher
+            .publish_with_conn(conn, &AccountEvent::Invited { invitation_token: token.clone() })
+            .await?;
+        Ok(token)
+    }
+
+    /// Creates the account of an invited user in the Active status, with the default roles and the invited ones.
+    /// All the invitations of the email are deleted.
+    pub async fn accept_invitation(&self, dto: AcceptInvitationDto) -> Result<AuthAccountModel, LightSpeedError> {
+        let user = self.c3p0.transaction(|conn| async { self.accept_invitation_with_conn(conn, dto).await }).await?;
+        self.account_event_publisher
+            .publish_after_commit(&AccountEvent::AccountCreated { user: user.clone().into(), activation_token: None })
+            .await;
+        Ok(user)
+    }
+
+    pub async fn accept_invitation_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        dto: AcceptInvitationDto,
+    ) -> Result<AuthAccountModel, LightSpeedError> {
+        debug!("Accept invitation called with token [{}]", dto.token);
+        Validator::validate(&dto)?;
 
+        let token = self.token_service.fetch_by_token_with_conn(conn, &dto.token, true).await?;
         Validator::validate(&|error_details: &mut ErrorDetails| {
             match &token.data.token_type {
-                TokenType::AccountActivation => {}
+                TokenType::Invitation => {}
                 _ => error_details.add_detail("token_type", WRONG_TYPE),
             };
             Ok(())
         })?;
+        let invitation = token.data.invitation.clone().ok_or_else(|| LightSpeedError::InternalServerError {
+            message: format!("The invitation token with id [{}] has no invitation data", token.id),
+        })?;
 
-        info!("Send new activation token to user [{}]", token.data.username);
-
-        let user = self.auth_repo.fetch_by_username(conn, &token.data.username).await?;
+        let email = invitation.email.clone();
+        info!("Accept the invitation of email [{}]", email);
 
-        match &user.data.status {
+        let username = normalize_username(match &dto.username {
+            Some(username) if !username.trim().is_empty() => username,
+            _ => &email,
+        });
+        let existing_user = self.auth_repo.fetch_by_username_optional(conn, &username).await?;
+        let existing_email = self.auth_repo.fetch_by_email_optional(conn, &email).await?;
+        Validator::validate(&|error_details: &mut ErrorDetails| {
+            if existing_user.is_some() {
+                error_details.add_detail("username", ERR_NOT_UNIQUE);
+            }
+            if existing_email.is_some() {
+                error_details.add_detail("email", ERR_NOT_UNIQUE);
+            }
+            Ok(())
+        })?;
+
+        for invitation_token in self.token_service.fetch_all_invitations_by_email_with_conn(conn, &email).await? {
+            self.token_service.delete_with_conn(conn, invitation_token).await?;
+        }
+
+        let mut roles = self.auth_config.default_roles_on_account_creation.clone();
+        for role in invitation.roles {
+            if !roles.contains(&role) {
+                roles.push(role);
+            }
+        }
+
+        let user = self
+            .auth_repo
+            .save(
+                conn,
+                NewModel::new(AuthAccountData {
+                    username,
+                    email,
+                    password: self.password_service.hash_password(&dto.password)?,
+                    roles,
+                    created_date_epoch_seconds: self.clock.epoch_seconds(),
+                    status: AuthAccountStatus::Active,
+                    two_factor: None,
+                    password_history: vec![],
+                    password_changed_epoch_seconds: self.clock.epoch_seconds(),
+                    pending_email_change: None,
+                    disabled_date_epoch_seconds: None,
+                    anonymized_date_epoch_seconds: None,
+                    attributes: BTreeMap::new(),
+                }),
+            )
+            .await?;
+        self.account_event_publisher
+            .publish_with_conn(
+                conn,
+                &AccountEvent::AccountCreated { user: user.clone().into(), activation_token: None },
+            )
+            .await?;
+        Ok(user)
+    }
+
+    /// Returns the invitations not accepted yet, including the expired ones
+    pub async fn fetch_all_invitations(&self) -> Result<Vec<InvitationDto>, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.fetch_all_invitations_with_conn(conn).await }).await
+    }
+
+    pub async fn fetch_all_invitations_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+    ) -> Result<Vec<InvitationDto>, LightSpeedError> {
+        debug!("Fetch all invitations");
+        let tokens = self.token_service.fetch_all_by_type_with_conn(conn, TokenType::Invitation).await?;
+        Ok(tokens.into_iter().filter_map(InvitationDto::from_token).collect())
+    }
+
+    /// Deletes an invitation so that it cannot be accepted anymore
+    pub async fn revoke_invitation(&self, invitation_id: i64) -> Result<(), LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.revoke_invitation_with_conn(conn, invitation_id).await }).await
+    }
+
+    pub async fn revoke_invitation_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        invitation_id: i64,
+    ) -> Result<(), LightSpeedError> {
+        info!("Revoke invitation with id [{}]", invitation_id);
+        let token = self.token_service.fetch_by_id_with_conn(conn, invitation_id).await?;
+        Validator::validate(&|error_details: &mut ErrorDetails| {
+            match &token.data.token_type {
+                TokenType::Invitation => {}
+                _ => error_details.add_detail("token_type", WRONG_TYPE),
+            };
+            Ok(())
+        })?;
+        self.token_service.delete_with_conn(conn, token).await?;
+        Ok(())
+    }
+
+    async fn generate_activation_token_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        username: &str,
+    ) -> Result<TokenModel, LightSpeedError> {
+        debug!("Generate activation token for username [{}]", username);
+        self.token_service.generate_and_save_token_with_conn(conn, username, TokenType::AccountActivation).await
+    }
+
+    pub async fn generate_new_activation_token_by_username_and_email(
+        &self,
+        username: &str,
+        email: &str,
+    ) -> Result<(AuthAccountModel, TokenModel), LightSpeedError> {
+        self.c3p0
+            .transaction(|conn| async {
+                self.generate_new_activation_token_by_username_and_email_with_conn(conn, username, email).await
+            })
+            .await
+    }
+
+    pub async fn generate_new_activation_token_by_username_and_email_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        username: &str,
+        email: &str,
+    ) -> Result<(AuthAccountModel, TokenModel), LightSpeedError> {
+        debug!("Generate new activation token for username [{}] and email [{}]", username, email);
+        let auth_account = self.fetch_by_username_with_conn(conn, username).await?;
+
+        Validator::validate(&|error_details: &mut ErrorDetails| {
+            if auth_account.data.email != email {
+                error_details.add_detail("email", "WRONG_EMAIL");
+            };
+            Ok(())
+        })?;
+
+        let previous_activation_token = self
+            .token_service
+            .fetch_all_by_username_with_conn(conn, username)
+            .await?
+            .into_iter()
+            .find(|token| token.data.token_type == TokenType::AccountActivation)
+            .ok_or_else(|| LightSpeedError::BadRequest {
+                message: format!("Previous activation token not found for user [{username}]"),
+                code: ErrorCodes::NOT_PENDING_USER,
+            })?;
+        self.replace_activation_token_with_conn(conn, previous_activation_token).await
+    }
+
+    pub async fn generate_new_activation_token_by_token(
+        &self,
+        previous_activation_token: &str,
+    ) -> Result<(AuthAccountModel, TokenModel), LightSpeedError> {
+        self.c3p0
+            .transaction(|conn| async {
+                self.generate_new_activation_token_by_token_with_conn(conn, previous_activation_token).await
+            })
+            .await
+    }
+
+    pub async fn generate_new_activation_token_by_token_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        previous_activation_token: &str,
+    ) -> Result<(AuthAccountModel, TokenModel), LightSpeedError> {
+        debug!("Generate new activation token from previous token [{}]", previous_activation_token);
+        let token = self.token_service.fetch_by_token_with_conn(conn, previous_activation_token, false).await?;
+        self.replace_activation_token_with_conn(conn, token).await
+    }
+
+    /// Replaces the activation token of a user pending activation with a new one
+    async fn replace_activation_token_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        token: TokenModel,
+    ) -> Result<(AuthAccountModel, TokenModel), LightSpeedError> {
+        Validator::validate(&|error_details: &mut ErrorDetails| {
+            match &token.data.token_type {
+                TokenType::AccountActivation => {}
+                _ => error_details.add_detail("token_type", WRONG_TYPE),
+            };
+            Ok(())
+        })?;
+
+        info!("Send new activation token to user [{}]", token.data.username);
+
+        let user = self.auth_repo.fetch_by_username(conn, &token.data.username).await?;
+
+        match &user.data.status {
             AuthAccountStatus::PendingActivation => {}
             _ => {
                 return Err(LightSpeedError::BadRequest {
@@ -247,9 +998,10 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
     }
 
     pub async fn activate_user(&self, activation_token: &str) -> Result<AuthAccountModel, LightSpeedError> {
-        self.c3p0
-            .transaction(|conn| async { self.activate_user_with_conn(conn, activation_token).await })
-            .await
+        let user =
+            self.c3p0.transaction(|conn| async { self.activate_user_with_conn(conn, activation_token).await }).await?;
+        self.account_event_publisher.publish_after_commit(&AccountEvent::Activated { user: user.clone().into() }).await;
+        Ok(user)
     }
 
     pub async fn activate_user_with_conn(
@@ -287,6 +1039,9 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
 
         user.data.status = AuthAccountStatus::Active;
         user = self.auth_repo.update(conn, user).await?;
+        self.account_event_publisher
+            .publish_with_conn(conn, &AccountEvent::Activated { user: user.clone().into() })
+            .await?;
         Ok(user)
     }
 
@@ -294,11 +1049,17 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
         &self,
         username: &str,
     ) -> Result<(AuthAccountModel, TokenModel), LightSpeedError> {
-        self.c3p0
-            .transaction(
-                |conn| async { self.generate_reset_password_token_with_conn(conn, username).await },
-            )
-            .await
+        let (user, token) = self
+            .c3p0
+            .transaction(|conn| async { self.generate_reset_password_token_with_conn(conn, username).await })
+            .await?;
+        self.account_event_publisher
+            .publish_after_commit(&AccountEvent::PasswordResetRequested {
+                user: user.clone().into(),
+                reset_token: token.clone(),
+            })
+            .await;
+        Ok((user, token))
     }
 
     pub async fn generate_reset_password_token_with_conn(
@@ -320,8 +1081,16 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
             }
         };
 
-        let token =
-            self.token_service.generate_and_save_token_with_conn(conn, username, TokenType::ResetPassword).await?;
+        let token = self
+            .token_service
+            .generate_and_save_token_with_conn(conn, &user.data.username, TokenType::ResetPassword)
+            .await?;
+        self.account_event_publisher
+            .publish_with_conn(
+                conn,
+                &AccountEvent::PasswordResetRequested { user: user.clone().into(), reset_token: token.clone() },
+            )
+            .await?;
 
         Ok((user, token))
     }
@@ -330,11 +1099,14 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
         &self,
         reset_password_dto: ResetPasswordDto,
     ) -> Result<AuthAccountModel, LightSpeedError> {
-        self.c3p0
-            .transaction(|conn| async {
-                self.reset_password_by_token_with_conn(conn, reset_password_dto).await
-            })
-            .await
+        let user = self
+            .c3p0
+            .transaction(|conn| async { self.reset_password_by_token_with_conn(conn, reset_password_dto).await })
+            .await?;
+        self.account_event_publisher
+            .publish_after_commit(&AccountEvent::PasswordChanged { user: user.clone().into() })
+            .await;
+        Ok(user)
     }
 
     pub async fn reset_password_by_token_with_conn(
@@ -359,60 +1131,896 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
 
         let mut user = self.auth_repo.fetch_by_username(conn, &token.data.username).await?;
 
-        match &user.data.status {
-            AuthAccountStatus::Active => {}
-            _ => {
-                return Err(LightSpeedError::BadRequest {
-                    message: format!("User [{}] not in status Active", token.data.username),
-                    code: ErrorCodes::INACTIVE_USER,
-                })
-            }
-        };
+        match &user.data.status {
+            AuthAccountStatus::Active => {}
+            _ => {
+                return Err(LightSpeedError::BadRequest {
+                    message: format!("User [{}] not in status Active", token.data.username),
+                    code: ErrorCodes::INACTIVE_USER,
+                })
+            }
+        };
+
+        self.validate_password_not_reused(&user, "password", &reset_password_dto.password)?;
+
+        self.token_service.delete_with_conn(conn, token).await?;
+
+        self.set_password(&mut user, &reset_password_dto.password)?;
+        user = self.auth_repo.update(conn, user).await?;
+        self.auth_session_service.revoke_all_by_user_id_with_conn(conn, user.id).await?;
+        self.account_event_publisher
+            .publish_with_conn(conn, &AccountEvent::PasswordChanged { user: user.clone().into() })
+            .await?;
+        Ok(user)
+    }
+
+    /// Generates the single-use token of a passwordless login link to be sent to the given email.
+    /// Returns None if no account has the email, so that the caller can answer in the same way
+    /// whether the email is registered or not.
+    /// Returns a TOO_MANY_REQUESTS error if the account has already too many not expired magic login tokens.
+    pub async fn generate_magic_login_token(
+        &self,
+        email: &str,
+    ) -> Result<Option<(AuthAccountModel, TokenModel)>, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.generate_magic_login_token_with_conn(conn, email).await }).await
+    }
+
+    pub async fn generate_magic_login_token_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        email: &str,
+    ) -> Result<Option<(AuthAccountModel, TokenModel)>, LightSpeedError> {
+        info!("Generate magic login token for email [{}]", email);
+
+        let user = match self.auth_repo.fetch_by_email_optional(conn, &normalize_email(email)).await? {
+            Some(user) => user,
+            None => {
+                debug!("No user found with email [{}], no magic login token generated", email);
+                return Ok(None);
+            }
+        };
+
+        self.login_attempt_service.check_not_locked_with_conn(conn, &user.data.username, None).await?;
+
+        match &user.data.status {
+            AuthAccountStatus::Active => {}
+            _ => {
+                return Err(LightSpeedError::BadRequest {
+                    message: format!("User [{}] not in status Active", user.data.username),
+                    code: ErrorCodes::INACTIVE_USER,
+                })
+            }
+        };
+
+        let now = self.clock.epoch_seconds();
+        let active_tokens = self
+            .token_service
+            .fetch_all_by_username_with_conn(conn, &user.data.username)
+            .await?
+            .into_iter()
+            .filter(|token| token.data.token_type == TokenType::MagicLogin && token.data.expire_at_epoch_seconds >= now)
+            .count();
+        if active_tokens >= self.auth_config.magic_login_max_tokens_per_account as usize {
+            return Err(LightSpeedError::BadRequest {
+                message: format!("Too many magic login tokens requested for user [{}]", user.data.username),
+                code: ErrorCodes::TOO_MANY_REQUESTS,
+            });
+        }
+
+        let token = self
+            .token_service
+            .generate_and_save_token_with_conn(conn, &user.data.username, TokenType::MagicLogin)
+            .await?;
+
+        Ok(Some((user, token)))
+    }
+
+    /// Logs in a user with the token of a magic login link.
+    /// The token is consumed together with all the other magic login tokens of the user;
+    /// the second factor is still required if enabled.
+    pub async fn login_with_magic_token(&self, magic_token: &str) -> Result<LoginOutcome, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.login_with_magic_token_with_conn(conn, magic_token).await }).await
+    }
+
+    pub async fn login_with_magic_token_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        magic_token: &str,
+    ) -> Result<LoginOutcome, LightSpeedError> {
+        debug!("Magic login called with token [{}]", magic_token);
+
+        let token = self.token_service.fetch_by_token_with_conn(conn, magic_token, true).await?;
+
+        Validator::validate(&|error_details: &mut ErrorDetails| {
+            match &token.data.token_type {
+                TokenType::MagicLogin => {}
+                _ => error_details.add_detail("token_type", WRONG_TYPE),
+            };
+            Ok(())
+        })?;
+
+        self.login_attempt_service.check_not_locked_with_conn(conn, &token.data.username, None).await?;
+
+        let user = self.auth_repo.fetch_by_username(conn, &token.data.username).await?;
+
+        for magic_token in self.token_service.fetch_all_by_username_with_conn(conn, &user.data.username).await? {
+            if magic_token.data.token_type == TokenType::MagicLogin {
+                self.token_service.delete_with_conn(conn, magic_token).await?;
+            }
+        }
+
+        self.authenticate_external_user_with_conn(conn, user.id).await
+    }
+
+    /// Changes the password of the user after checking the old one.
+    /// All the sessions of the user are terminated but `current_session_id`, the session of the Auth
+    /// that changes the password if any.
+    /// The caller has to reject the impersonated Auths, see `impersonate`.
+    pub async fn change_password(
+        &self,
+        dto: ChangePasswordDto,
+        current_session_id: Option<&str>,
+    ) -> Result<AuthAccountModel, LightSpeedError> {
+        let user = self
+            .c3p0
+            .transaction(|conn| async { self.change_password_with_conn(conn, dto, current_session_id).await })
+            .await?;
+        self.account_event_publisher
+            .publish_after_commit(&AccountEvent::PasswordChanged { user: user.clone().into() })
+            .await;
+        Ok(user)
+    }
+
+    pub async fn change_password_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        dto: ChangePasswordDto,
+        current_session_id: Option<&str>,
+    ) -> Result<AuthAccountModel, LightSpeedError> {
+        info!("Reset password of user_id [{}]", dto.user_id);
+
+        Validator::validate(&dto)?;
+
+        let mut user = self.auth_repo.fetch_by_id(conn, dto.user_id).await?;
+        info!("Change password of user [{}]", user.data.username);
+
+        match &user.data.status {
+            AuthAccountStatus::Active => {}
+            _ => {
+                return Err(LightSpeedError::BadRequest {
+                    message: format!("User [{}] not in status Active", user.data.username),
+                    code: ErrorCodes::INACTIVE_USER,
+                })
+            }
+        };
+
+        if !self.password_service.verify_match(&dto.old_password, &user.data.password)? {
+            return Err(LightSpeedError::BadRequest {
+                message: "Wrong credentials".to_owned(),
+                code: ErrorCodes::WRONG_CREDENTIALS,
+            });
+        }
+
+        self.validate_password_not_reused(&user, "new_password", &dto.new_password)?;
+        self.set_password(&mut user, &dto.new_password)?;
+
+        user = self.auth_repo.update(conn, user).await?;
+        match current_session_id {
+            Some(session_id) => {
+                self.auth_session_service.revoke_all_but_one_by_user_id_with_conn(conn, user.id, session_id).await?
+            }
+            None => self.auth_session_service.revoke_all_by_user_id_with_conn(conn, user.id).await?,
+        };
+        self.account_event_publisher
+            .publish_with_conn(conn, &AccountEvent::PasswordChanged { user: user.clone().into() })
+            .await?;
+        Ok(user)
+    }
+
+    /// Returns a validation error if the password matches one of the last `password_history_size` passwords
+    fn validate_password_not_reused(
+        &self,
+        user: &AuthAccountModel,
+        field_name: &str,
+        plain_password: &str,
+    ) -> Result<(), LightSpeedError> {
+        let mut reused = false;
+        let last_password_hashes = std::iter::once(&user.data.password).chain(user.data.password_history.iter());
+        for hash in last_password_hashes.take(self.auth_config.password_history_size as usize) {
+            if self.password_service.verify_match(plain_password, hash)? {
+                reused = true;
+                break;
+            }
+        }
+
+        Validator::validate(&|error_details: &mut ErrorDetails| {
+            if reused {
+                error_details.add_detail(field_name, ERR_PASSWORD_ALREADY_USED);
+            }
+            Ok(())
+        })
+    }
+
+    fn set_password(&self, user: &mut AuthAccountModel, plain_password: &str) -> Result<(), LightSpeedError> {
+        let password_hash = self.password_service.hash_password(plain_password)?;
+        user.data.set_password(
+            password_hash,
+            self.auth_config.password_history_size as usize,
+            self.clock.epoch_seconds(),
+        );
+        Ok(())
+    }
+
+    /// Starts the change of the email of a user.
+    /// The new address is stored as pending until it is confirmed with the returned EmailChange token,
+    /// which should be sent to the new address. A new request replaces the pending one.
+    /// The caller has to reject the impersonated Auths, see `impersonate`.
+    pub async fn request_email_change(
+        &self,
+        dto: ChangeEmailDto,
+    ) -> Result<(AuthAccountModel, TokenModel), LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.request_email_change_with_conn(conn, dto).await }).await
+    }
+
+    pub async fn request_email_change_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        mut dto: ChangeEmailDto,
+    ) -> Result<(AuthAccountModel, TokenModel), LightSpeedError> {
+        info!("Request email change of user_id [{}] to [{}]", dto.user_id, dto.new_email);
+        dto.new_email = normalize_email(&dto.new_email);
+
+        let mut user = self.auth_repo.fetch_by_id(conn, dto.user_id).await?;
+
+        match &user.data.status {
+            AuthAccountStatus::Active => {}
+            _ => {
+                return Err(LightSpeedError::BadRequest {
+                    message: format!("User [{}] not in status Active", user.data.username),
+                    code: ErrorCodes::INACTIVE_USER,
+                })
+            }
+        };
+
+        if !self.password_service.verify_match(&dto.password, &user.data.password)? {
+            return Err(LightSpeedError::BadRequest {
+                message: "Wrong credentials".to_owned(),
+                code: ErrorCodes::WRONG_CREDENTIALS,
+            });
+        }
+
+        let existing_email = self.auth_repo.fetch_by_email_optional(conn, &dto.new_email).await?;
+        Validator::validate(&(&dto, &|error_details: &mut ErrorDetails| {
+            if existing_email.is_some() {
+                error_details.add_detail("new_email", ERR_NOT_UNIQUE);
+            }
+            Ok(())
+        }))?;
+
+        let token = self
+            .token_service
+            .generate_and_save_token_with_validity_with_conn(
+                conn,
+                &user.data.username,
+                TokenType::EmailChange,
+                self.auth_config.email_change_token_validity_minutes,
+            )
+            .await?;
+
+        user.data.pending_email_change =
+            Some(PendingEmailChange { email: dto.new_email, token: self.token_service.hash_token(&token.data.token) });
+        user = self.auth_repo.update(conn, user).await?;
+        Ok((user, token))
+    }
+
+    /// Applies the pending email change of the token owner.
+    /// Returns the updated user and the previous email, which should be notified of the change.
+    pub async fn confirm_email_change(
+        &self,
+        email_change_token: &str,
+    ) -> Result<(AuthAccountModel, String), LightSpeedError> {
+        self.c3p0
+            .transaction(|conn| async { self.confirm_email_change_with_conn(conn, email_change_token).await })
+            .await
+    }
+
+    pub async fn confirm_email_change_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        email_change_token: &str,
+    ) -> Result<(AuthAccountModel, String), LightSpeedError> {
+        debug!("Confirm email change called with token [{}]", email_change_token);
+
+        let token = self.token_service.fetch_by_token_with_conn(conn, email_change_token, true).await?;
+
+        Validator::validate(&|error_details: &mut ErrorDetails| {
+            match &token.data.token_type {
+                TokenType::EmailChange => {}
+                _ => error_details.add_detail("token_type", WRONG_TYPE),
+            };
+            Ok(())
+        })?;
+
+        let mut user = self.auth_repo.fetch_by_username(conn, &token.data.username).await?;
+
+        let new_email = match user.data.pending_email_change.take() {
+            Some(pending) if pending.token == token.data.token => pending.email,
+            _ => {
+                return Err(LightSpeedError::BadRequest {
+                    message: format!("No pending email change of user [{}] for the token", user.data.username),
+                    code: ErrorCodes::NOT_FOUND,
+                })
+            }
+        };
+
+        let existing_email = self.auth_repo.fetch_by_email_optional(conn, &new_email).await?;
+        Validator::validate(&|error_details: &mut ErrorDetails| {
+            if existing_email.is_some() {
+                error_details.add_detail("email", ERR_NOT_UNIQUE);
+            }
+            Ok(())
+        })?;
+
+        info!("Change email of user [{}] to [{}]", user.data.username, new_email);
+
+        self.token_service.delete_with_conn(conn, token).await?;
+
+        let old_email = std::mem::replace(&mut user.data.email, new_email);
+        user = self.auth_repo.update(conn, user).await?;
+        Ok((user, old_email))
+    }
+
+    /// Starts the TOTP enrollment generating a new secret.
+    /// The two-factor authentication is enabled only after the enrollment is confirmed with a valid code.
+    /// The caller has to reject the impersonated Auths, see `impersonate`.
+    pub async fn enroll_totp(&self, user_id: i64) -> Result<TotpEnrollmentDto, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.enroll_totp_with_conn(conn, user_id).await }).await
+    }
+
+    pub async fn enroll_totp_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+    ) -> Result<TotpEnrollmentDto, LightSpeedError> {
+        info!("Start TOTP enrollment of user_id [{}]", user_id);
+
+        let mut user = self.auth_repo.fetch_by_id(conn, user_id).await?;
+
+        match &user.data.status {
+            AuthAccountStatus::Active => {}
+            _ => {
+                return Err(LightSpeedError::BadRequest {
+                    message: format!("User [{}] not in status Active", user.data.username),
+                    code: ErrorCodes::INACTIVE_USER,
+                })
+            }
+        };
+
+        if user.data.is_two_factor_enabled() {
+            return Err(LightSpeedError::BadRequest {
+                message: format!("User [{}] has already the two-factor authentication enabled", user.data.username),
+                code: ErrorCodes::TWO_FACTOR_ALREADY_ENABLED,
+            });
+        }
+
+        let secret = self.totp_service.generate_secret();
+        let otpauth_uri = self.totp_service.otpauth_uri(&secret, &user.data.username);
+
+        user.data.two_factor = Some(TwoFactorData {
+            totp_secret: secret.clone(),
+            enabled: false,
+            recovery_code_hashes: vec![],
+            last_used_time_step: None,
+        });
+        self.auth_repo.update(conn, user).await?;
+
+        Ok(TotpEnrollmentDto { secret, otpauth_uri })
+    }
+
+    /// Confirms the TOTP enrollment and enables the two-factor authentication.
+    /// Returns the recovery codes; they are stored hashed, so this is the only time they are available.
+    pub async fn confirm_totp_enrollment(
+        &self,
+        dto: TotpCodeDto,
+    ) -> Result<(AuthAccountModel, Vec<String>), LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.confirm_totp_enrollment_with_conn(conn, dto).await }).await
+    }
+
+    pub async fn confirm_totp_enrollment_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        dto: TotpCodeDto,
+    ) -> Result<(AuthAccountModel, Vec<String>), LightSpeedError> {
+        info!("Confirm TOTP enrollment of user_id [{}]", dto.user_id);
+
+        let mut user = self.auth_repo.fetch_by_id(conn, dto.user_id).await?;
+
+        match &user.data.two_factor {
+            Some(two_factor) if two_factor.enabled => {
+                return Err(LightSpeedError::BadRequest {
+                    message: format!("User [{}] has already the two-factor authentication enabled", user.data.username),
+                    code: ErrorCodes::TWO_FACTOR_ALREADY_ENABLED,
+                })
+            }
+            Some(_) => {}
+            None => {
+                return Err(LightSpeedError::BadRequest {
+                    message: format!("User [{}] has not started the TOTP enrollment", user.data.username),
+                    code: ErrorCodes::TWO_FACTOR_NOT_ENABLED,
+                })
+            }
+        };
+
+        if !self.verify_totp_code(&mut user, &dto.code)? {
+            return Err(LightSpeedError::BadRequest {
+                message: "Wrong second factor code".to_owned(),
+                code: ErrorCodes::WRONG_CREDENTIALS,
+            });
+        }
+
+        let recovery_codes = self.replace_recovery_codes(&mut user);
+        if let Some(two_factor) = user.data.two_factor.as_mut() {
+            two_factor.enabled = true;
+        }
+
+        info!("Two-factor authentication enabled for user [{}]", user.data.username);
+        let user = self.auth_repo.update(conn, user).await?;
+        Ok((user, recovery_codes))
+    }
+
+    /// Replaces the recovery codes of a user with the two-factor authentication enabled
+    pub async fn regenerate_recovery_codes(
+        &self,
+        dto: TotpCodeDto,
+    ) -> Result<(AuthAccountModel, Vec<String>), LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.regenerate_recovery_codes_with_conn(conn, dto).await }).await
+    }
+
+    pub async fn regenerate_recovery_codes_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        dto: TotpCodeDto,
+    ) -> Result<(AuthAccountModel, Vec<String>), LightSpeedError> {
+        info!("Regenerate recovery codes of user_id [{}]", dto.user_id);
+
+        let mut user = self.fetch_by_user_id_with_two_factor_code_with_conn(conn, &dto).await?;
+        let recovery_codes = self.replace_recovery_codes(&mut user);
+
+        let user = self.auth_repo.update(conn, user).await?;
+        Ok((user, recovery_codes))
+    }
+
+    /// Disables the two-factor authentication of a user. A valid TOTP code is required.
+    pub async fn disable_two_factor(&self, dto: TotpCodeDto) -> Result<AuthAccountModel, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.disable_two_factor_with_conn(conn, dto).await }).await
+    }
+
+    pub async fn disable_two_factor_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        dto: TotpCodeDto,
+    ) -> Result<AuthAccountModel, LightSpeedError> {
+        info!("Disable two-factor authentication of user_id [{}]", dto.user_id);
+
+        let mut user = self.fetch_by_user_id_with_two_factor_code_with_conn(conn, &dto).await?;
+        user.data.two_factor = None;
+        self.auth_repo.update(conn, user).await
+    }
+
+    /// Removes the two-factor authentication of a user without requiring any code.
+    /// This is meant to be used by the administrators, e.g. when a user lost both the device and the recovery codes.
+    pub async fn reset_two_factor_by_user_id(&self, user_id: i64) -> Result<AuthAccountModel, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.reset_two_factor_by_user_id_with_conn(conn, user_id).await }).await
+    }
+
+    pub async fn reset_two_factor_by_user_id_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+    ) -> Result<AuthAccountModel, LightSpeedError> {
+        info!("Reset two-factor authentication of user_id [{}]", user_id);
+
+        let mut user = self.auth_repo.fetch_by_id(conn, user_id).await?;
+        user.data.two_factor = None;
+        self.auth_repo.update(conn, user).await
+    }
+
+    async fn fetch_by_user_id_with_two_factor_code_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        dto: &TotpCodeDto,
+    ) -> Result<AuthAccountModel, LightSpeedError> {
+        let mut user = self.auth_repo.fetch_by_id(conn, dto.user_id).await?;
+
+        if !user.data.is_two_factor_enabled() {
+            return Err(LightSpeedError::BadRequest {
+                message: format!("User [{}] has not the two-factor authentication enabled", user.data.username),
+                code: ErrorCodes::TWO_FACTOR_NOT_ENABLED,
+            });
+        }
+
+        if !self.verify_totp_code(&mut user, &dto.code)? {
+            return Err(LightSpeedError::BadRequest {
+                message: "Wrong second factor code".to_owned(),
+                code: ErrorCodes::WRONG_CREDENTIALS,
+            });
+        }
+
+        Ok(user)
+    }
+
+    /// Generates new recovery codes for the user, replacing the previous ones. Returns the plain codes.
+    fn replace_recovery_codes(&self, user: &mut AuthAccountModel) -> Vec<String> {
+        let recovery_codes = self.totp_service.generate_recovery_codes(self.auth_config.recovery_codes_count);
+        if let Some(two_factor) = user.data.two_factor.as_mut() {
+            two_factor.recovery_code_hashes =
+                recovery_codes.iter().map(|code| self.totp_service.hash_recovery_code(code)).collect();
+        }
+        recovery_codes
+    }
+
+    /// Starts the registration of a WebAuthn credential (passkey) of the user.
+    /// The returned options have to be passed to `navigator.credentials.create()`.
+    /// The caller has to reject the impersonated Auths, see `impersonate`.
+    pub async fn start_webauthn_registration(
+        &self,
+        user_id: i64,
+    ) -> Result<WebAuthnRegistrationOptionsDto, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.start_webauthn_registration_with_conn(conn, user_id).await }).await
+    }
+
+    pub async fn start_webauthn_registration_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+    ) -> Result<WebAuthnRegistrationOptionsDto, LightSpeedError> {
+        info!("Start WebAuthn registration of user_id [{}]", user_id);
+
+        let user = self.auth_repo.fetch_by_id(conn, user_id).await?;
+
+        match &user.data.status {
+            AuthAccountStatus::Active => {}
+            _ => {
+                return Err(LightSpeedError::BadRequest {
+                    message: format!("User [{}] not in status Active", user.data.username),
+                    code: ErrorCodes::INACTIVE_USER,
+                })
+            }
+        };
+
+        let challenge = self
+            .token_service
+            .generate_and_save_token_with_conn(conn, &user.data.username, TokenType::WebAuthnRegistration)
+            .await?;
+        let credential_ids = self.fetch_webauthn_credential_ids_with_conn(conn, user_id).await?;
+
+        Ok(self.webauthn_service.registration_options(
+            &challenge.data.token,
+            user.id,
+            &user.data.username,
+            credential_ids,
+        ))
+    }
+
+    /// Completes the registration of a WebAuthn credential with the response of the authenticator
+    pub async fn finish_webauthn_registration(
+        &self,
+        user_id: i64,
+        dto: WebAuthnRegistrationDto,
+    ) -> Result<WebAuthnCredentialModel, LightSpeedError> {
+        self.c3p0
+            .transaction(|conn| async { self.finish_webauthn_registration_with_conn(conn, user_id, dto).await })
+            .await
+    }
+
+    pub async fn finish_webauthn_registration_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+        dto: WebAuthnRegistrationDto,
+    ) -> Result<WebAuthnCredentialModel, LightSpeedError> {
+        info!("Finish WebAuthn registration of user_id [{}]", user_id);
+        Validator::validate(&dto)?;
+
+        let challenge = self.webauthn_service.client_data_challenge(&dto.client_data_json)?;
+        let token = self.token_service.fetch_by_token_with_conn(conn, &challenge, true).await?;
+
+        Validator::validate(&|error_details: &mut ErrorDetails| {
+            match &token.data.token_type {
+                TokenType::WebAuthnRegistration => {}
+                _ => error_details.add_detail("token_type", WRONG_TYPE),
+            };
+            Ok(())
+        })?;
+
+        let user = self.auth_repo.fetch_by_id(conn, user_id).await?;
+        if token.data.username != user.data.username {
+            return Err(LightSpeedError::BadRequest {
+                message: format!("No WebAuthn registration of user [{}] for the challenge", user.data.username),
+                code: ErrorCodes::NOT_FOUND,
+            });
+        }
+
+        let credential =
+            self.webauthn_service.verify_registration(&challenge, &dto.client_data_json, &dto.attestation_object)?;
+
+        let existing_credential =
+            self.webauthn_credential_repo.fetch_by_credential_id_optional(conn, &credential.credential_id).await?;
+        Validator::validate(&|error_details: &mut ErrorDetails| {
+            if existing_credential.is_some() {
+                error_details.add_detail("credential_id", ERR_NOT_UNIQUE);
+            }
+            Ok(())
+        })?;
+
+        self.token_service.delete_with_conn(conn, token).await?;
+
+        info!("WebAuthn credential registered by user [{}]", user.data.username);
+        self.webauthn_credential_repo
+            .save(
+                conn,
+                NewModel::new(WebAuthnCredentialData {
+                    user_id,
+                    name: dto.name.trim().to_owned(),
+                    credential_id: credential.credential_id,
+                    algorithm: credential.algorithm,
+                    public_key: credential.public_key,
+                    sign_count: credential.sign_count,
+                    created_date_epoch_seconds: self.clock.epoch_seconds(),
+                    last_used_date_epoch_seconds: None,
+                }),
+            )
+            .await
+    }
+
+    /// Starts the passwordless login with a WebAuthn credential.
+    /// If the username is provided only its credentials are accepted, otherwise the authenticator
+    /// offers its discoverable credentials. The returned options have to be passed to `navigator.credentials.get()`.
+    pub async fn start_webauthn_login(
+        &self,
+        username: Option<&str>,
+    ) -> Result<WebAuthnAssertionOptionsDto, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.start_webauthn_login_with_conn(conn, username).await }).await
+    }
+
+    pub async fn start_webauthn_login_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        username: Option<&str>,
+    ) -> Result<WebAuthnAssertionOptionsDto, LightSpeedError> {
+        debug!("Start WebAuthn login with username [{:?}]", username);
+
+        // The challenge of a discoverable credential login is not bound to any username.
+        // An unknown username gets no credentials, without revealing that it does not exist.
+        let (challenge_username, credential_ids) = match username {
+            Some(username) => match self.auth_repo.fetch_by_username_optional(conn, username).await? {
+                Some(user) => {
+                    let credential_ids = self.fetch_webauthn_credential_ids_with_conn(conn, user.id).await?;
+                    (user.data.username, credential_ids)
+                }
+                None => (username.to_owned(), vec![]),
+            },
+            None => ("".to_owned(), vec![]),
+        };
+
+        let challenge = self
+            .token_service
+            .generate_and_save_token_with_conn(conn, challenge_username, TokenType::WebAuthnLogin)
+            .await?;
+
+        Ok(self.webauthn_service.assertion_options(&challenge.data.token, credential_ids, true))
+    }
+
+    /// Completes the passwordless login with the response of the authenticator.
+    /// The authenticator has to verify the user (e.g. with a biometric or a PIN), so the second factor
+    /// is not required; the password expiry is not checked either.
+    pub async fn login_with_webauthn(&self, dto: WebAuthnAssertionDto) -> Result<Auth, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.login_with_webauthn_with_conn(conn, dto).await }).await
+    }
+
+    pub async fn login_with_webauthn_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        dto: WebAuthnAssertionDto,
+    ) -> Result<Auth, LightSpeedError> {
+        debug!("WebAuthn login called with credential [{}]", dto.credential_id);
+
+        let challenge = self.webauthn_service.client_data_challenge(&dto.client_data_json)?;
+        let token = self.token_service.fetch_by_token_with_conn(conn, &challenge, true).await?;
+
+        Validator::validate(&|error_details: &mut ErrorDetails| {
+            match &token.data.token_type {
+                TokenType::WebAuthnLogin => {}
+                _ => error_details.add_detail("token_type", WRONG_TYPE),
+            };
+            Ok(())
+        })?;
+
+        let credential = self.fetch_webauthn_credential_with_conn(conn, &dto).await?;
+        let user = self.auth_repo.fetch_by_id(conn, credential.data.user_id).await?;
+        if !token.data.username.is_empty() && token.data.username != user.data.username {
+            return Err(LightSpeedError::BadRequest {
+                message: format!("The WebAuthn credential does not belong to user [{}]", token.data.username),
+                code: ErrorCodes::WRONG_CREDENTIALS,
+            });
+        }
+
+        self.login_attempt_service.check_not_locked_with_conn(conn, &user.data.username, None).await?;
+        self.verify_webauthn_assertion_with_conn(conn, &challenge, credential, &dto, true).await?;
+
+        match &user.data.status {
+            AuthAccountStatus::Active => {}
+            _ => {
+                return Err(LightSpeedError::BadRequest {
+                    message: format!("User [{}] not in status Active", user.data.username),
+                    code: ErrorCodes::INACTIVE_USER,
+                })
+            }
+        };
+
+        self.token_service.delete_with_conn(conn, token).await?;
+        self.login_attempt_service.reset_with_conn(conn, LoginAttemptKeyType::Username, &user.data.username).await?;
+        self.new_auth_with_conn(conn, user).await
+    }
+
+    /// Starts the second step of the login with a WebAuthn credential, as an alternative to the TOTP code.
+    /// The challenge token of the login is the challenge of the ceremony.
+    /// The returned options have to be passed to `navigator.credentials.get()`.
+    pub async fn start_webauthn_second_factor(
+        &self,
+        challenge_token: &str,
+    ) -> Result<WebAuthnAssertionOptionsDto, LightSpeedError> {
+        self.c3p0
+            .transaction(|conn| async { self.start_webauthn_second_factor_with_conn(conn, challenge_token).await })
+            .await
+    }
+
+    pub async fn start_webauthn_second_factor_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        challenge_token: &str,
+    ) -> Result<WebAuthnAssertionOptionsDto, LightSpeedError> {
+        debug!("Start WebAuthn second factor with challenge [{}]", challenge_token);
+        let (_, user) = self.fetch_second_factor_challenge_with_conn(conn, challenge_token).await?;
+
+        let credential_ids = self.fetch_webauthn_credential_ids_with_conn(conn, user.id).await?;
+        if credential_ids.is_empty() {
+            return Err(LightSpeedError::BadRequest {
+                message: format!("User [{}] has no WebAuthn credentials", user.data.username),
+                code: ErrorCodes::TWO_FACTOR_NOT_ENABLED,
+            });
+        }
+
+        Ok(self.webauthn_service.assertion_options(challenge_token, credential_ids, false))
+    }
+
+    /// Second step of the login of a user with a WebAuthn credential
+    pub async fn login_with_webauthn_second_factor(
+        &self,
+        challenge_token: &str,
+        dto: WebAuthnAssertionDto,
+    ) -> Result<Auth, LightSpeedError> {
+        let result = self
+            .c3p0
+            .transaction(|conn| async {
+                self.login_with_webauthn_second_factor_with_conn(conn, challenge_token, dto).await
+            })
+            .await;
+        self.register_failed_second_factor(challenge_token, result).await
+    }
+
+    pub async fn login_with_webauthn_second_factor_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        challenge_token: &str,
+        dto: WebAuthnAssertionDto,
+    ) -> Result<Auth, LightSpeedError> {
+        debug!("WebAuthn second factor login called with challenge [{}]", challenge_token);
+        let (token, user) = self.fetch_second_factor_challenge_with_conn(conn, challenge_token).await?;
+
+        let credential = self.fetch_webauthn_credential_with_conn(conn, &dto).await?;
+        if credential.data.user_id != user.id {
+            return Err(LightSpeedError::BadRequest {
+                message: format!("The WebAuthn credential does not belong to user [{}]", user.data.username),
+                code: ErrorCodes::WRONG_CREDENTIALS,
+            });
+        }
+        self.verify_webauthn_assertion_with_conn(conn, challenge_token, credential, &dto, false).await?;
 
         self.token_service.delete_with_conn(conn, token).await?;
-
-        user.data.password = self.password_service.hash_password(&reset_password_dto.password)?;
-        user = self.auth_repo.update(conn, user).await?;
-        Ok(user)
+        self.login_attempt_service.reset_with_conn(conn, LoginAttemptKeyType::Username, &user.data.username).await?;
+        self.new_auth_with_conn(conn, user).await
     }
 
-    pub async fn change_password(&self, dto: ChangePasswordDto) -> Result<AuthAccountModel, LightSpeedError> {
-        self.c3p0.transaction(|conn| async { self.change_password_with_conn(conn, dto).await }).await
+    /// Returns the WebAuthn credentials of the user
+    pub async fn fetch_webauthn_credentials_by_user_id(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<WebAuthnCredentialModel>, LightSpeedError> {
+        self.c3p0
+            .transaction(|conn| async { self.fetch_webauthn_credentials_by_user_id_with_conn(conn, user_id).await })
+            .await
     }
 
-    pub async fn change_password_with_conn(
+    pub async fn fetch_webauthn_credentials_by_user_id_with_conn(
         &self,
         conn: &mut RepoManager::Conn,
-        dto: ChangePasswordDto,
-    ) -> Result<AuthAccountModel, LightSpeedError> {
-        info!("Reset password of user_id [{}]", dto.user_id);
+        user_id: i64,
+    ) -> Result<Vec<WebAuthnCredentialModel>, LightSpeedError> {
+        debug!("Fetch the WebAuthn credentials of user_id [{}]", user_id);
+        self.webauthn_credential_repo.fetch_all_by_user_id(conn, user_id).await
+    }
 
-        Validator::validate(&dto)?;
+    /// Deletes a WebAuthn credential of the user. The id is the one of the saved model.
+    pub async fn delete_webauthn_credential(&self, user_id: i64, id: i64) -> Result<(), LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.delete_webauthn_credential_with_conn(conn, user_id, id).await }).await
+    }
 
-        let mut user = self.auth_repo.fetch_by_id(conn, dto.user_id).await?;
-        info!("Change password of user [{}]", user.data.username);
+    pub async fn delete_webauthn_credential_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+        id: i64,
+    ) -> Result<(), LightSpeedError> {
+        info!("Delete WebAuthn credential with id [{}] of user_id [{}]", id, user_id);
+        let credential = self
+            .webauthn_credential_repo
+            .fetch_all_by_user_id(conn, user_id)
+            .await?
+            .into_iter()
+            .find(|credential| credential.id == id)
+            .ok_or_else(|| LightSpeedError::BadRequest {
+                message: format!("No WebAuthn credential with id [{id}] for user_id [{user_id}]"),
+                code: ErrorCodes::NOT_FOUND,
+            })?;
+        self.webauthn_credential_repo.delete(conn, credential).await?;
+        Ok(())
+    }
 
-        match &user.data.status {
-            AuthAccountStatus::Active => {}
-            _ => {
-                return Err(LightSpeedError::BadRequest {
-                    message: format!("User [{}] not in status Active", user.data.username),
-                    code: ErrorCodes::INACTIVE_USER,
-                })
-            }
-        };
+    async fn fetch_webauthn_credential_ids_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+    ) -> Result<Vec<String>, LightSpeedError> {
+        let credentials = self.webauthn_credential_repo.fetch_all_by_user_id(conn, user_id).await?;
+        Ok(credentials.into_iter().map(|credential| credential.data.credential_id).collect())
+    }
 
-        if !self.password_service.verify_match(&dto.old_password, &user.data.password)? {
-            return Err(LightSpeedError::BadRequest {
-                message: "Wrong credentials".to_owned(),
-                code: ErrorCodes::WRONG_CREDENTIALS,
+    /// Fetches the credential of the assertion. An unknown credential, or a user handle that does not match
+    /// the owner of the credential, is reported as wrong credentials.
+    async fn fetch_webauthn_credential_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        dto: &WebAuthnAssertionDto,
+    ) -> Result<WebAuthnCredentialModel, LightSpeedError> {
+        let credential = self
+            .webauthn_credential_repo
+            .fetch_by_credential_id_optional(conn, dto.credential_id.trim_end_matches('='))
+            .await?
+            .filter(|credential| match &dto.user_handle {
+                Some(user_handle) => user_handle == &self.webauthn_service.user_handle(credential.data.user_id),
+                None => true,
             });
-        }
 
-        user.data.password = self.password_service.hash_password(&dto.new_password)?;
+        credential.ok_or_else(|| LightSpeedError::BadRequest {
+            message: "Unknown WebAuthn credential".to_owned(),
+            code: ErrorCodes::WRONG_CREDENTIALS,
+        })
+    }
 
-        user = self.auth_repo.update(conn, user).await?;
-        Ok(user)
+    /// Verifies the assertion and records the new signature counter and the last use of the credential
+    async fn verify_webauthn_assertion_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        challenge: &str,
+        mut credential: WebAuthnCredentialModel,
+        dto: &WebAuthnAssertionDto,
+        user_verification_required: bool,
+    ) -> Result<WebAuthnCredentialModel, LightSpeedError> {
+        credential.data.sign_count =
+            self.webauthn_service.verify_assertion(challenge, &credential.data, dto, user_verification_required)?;
+        credential.data.last_used_date_epoch_seconds = Some(self.clock.epoch_seconds());
+        self.webauthn_credential_repo.update(conn, credential).await
     }
 
     pub async fn fetch_by_user_id(&self, user_id: i64) -> Result<AuthAccountModel, LightSpeedError> {
@@ -429,9 +2037,7 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
     }
 
     pub async fn fetch_by_username(&self, username: &str) -> Result<AuthAccountModel, LightSpeedError> {
-        self.c3p0
-            .transaction(|conn| async { self.fetch_by_username_with_conn(conn, username).await })
-            .await
+        self.c3p0.transaction(|conn| async { self.fetch_by_username_with_conn(conn, username).await }).await
     }
 
     pub async fn fetch_by_username_with_conn(
@@ -450,9 +2056,7 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
         limit: u32,
     ) -> Result<Vec<AuthAccountModel>, LightSpeedError> {
         self.c3p0
-            .transaction(|conn| async {
-                self.fetch_all_by_status_with_conn(conn, status, start_user_id, limit).await
-            })
+            .transaction(|conn| async { self.fetch_all_by_status_with_conn(conn, status, start_user_id, limit).await })
             .await
     }
 
@@ -467,8 +2071,41 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
         self.auth_repo.fetch_all_by_status(conn, status, start_user_id, limit).await
     }
 
+    /// Searches the accounts by the given filters and returns the requested page with the total count
+    pub async fn search_accounts(
+        &self,
+        search: &SearchAccountsDto,
+    ) -> Result<SearchAccountsResultDto, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.search_accounts_with_conn(conn, search).await }).await
+    }
+
+    pub async fn search_accounts_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        search: &SearchAccountsDto,
+    ) -> Result<SearchAccountsResultDto, LightSpeedError> {
+        debug!("Search accounts with limit {}, offset {}", search.limit, search.offset);
+        Validator::validate(&(search, &|error_details: &mut ErrorDetails| {
+            let mut scoped_err = error_details.with_scope("attributes");
+            for name in search.attributes.keys() {
+                if !self.auth_config.account_attributes.iter().any(|config| config.searchable && &config.name == name) {
+                    scoped_err.add_detail(name.as_str(), ERR_NOT_SEARCHABLE_ATTRIBUTE);
+                }
+            }
+            Ok(())
+        }))?;
+        let accounts =
+            self.auth_repo.fetch_all_by_search(conn, search).await?.into_iter().map(AccountDto::from).collect();
+        let total_count = self.auth_repo.count_by_search(conn, search).await?;
+        Ok(SearchAccountsResultDto { accounts, total_count })
+    }
+
     pub async fn add_roles(&self, user_id: i64, roles: &[String]) -> Result<AuthAccountModel, LightSpeedError> {
-        self.c3p0.transaction(|conn| async { self.add_roles_with_conn(conn, user_id, roles).await }).await
+        let user = self.c3p0.transaction(|conn| async { self.add_roles_with_conn(conn, user_id, roles).await }).await?;
+        self.account_event_publisher
+            .publish_after_commit(&AccountEvent::RolesChanged { user: user.clone().into() })
+            .await;
+        Ok(user)
     }
 
     pub async fn add_roles_with_conn(
@@ -485,13 +2122,20 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
                 account.data.roles.push(role.to_owned())
             }
         }
-        self.auth_repo.update(conn, account).await
+        let account = self.auth_repo.update(conn, account).await?;
+        self.account_event_publisher
+            .publish_with_conn(conn, &AccountEvent::RolesChanged { user: account.clone().into() })
+            .await?;
+        Ok(account)
     }
 
     pub async fn delete_roles(&self, user_id: i64, roles: &[String]) -> Result<AuthAccountModel, LightSpeedError> {
-        self.c3p0
-            .transaction(|conn| async { self.delete_roles_with_conn(conn, user_id, roles).await })
-            .await
+        let user =
+            self.c3p0.transaction(|conn| async { self.delete_roles_with_conn(conn, user_id, roles).await }).await?;
+        self.account_event_publisher
+            .publish_after_commit(&AccountEvent::RolesChanged { user: user.clone().into() })
+            .await;
+        Ok(user)
     }
 
     pub async fn delete_roles_with_conn(
@@ -506,9 +2150,16 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
         for role in roles {
             account.data.roles.retain(|r| r != role);
         }
-        self.auth_repo.update(conn, account).await
+        let account = self.auth_repo.update(conn, account).await?;
+        self.account_event_publisher
+            .publish_with_conn(conn, &AccountEvent::RolesChanged { user: account.clone().into() })
+            .await?;
+        Ok(account)
     }
 
+    /// Changes the username and/or the email of a user without any confirmation.
+    /// It is an administrative override: the users change their own email with `request_email_change`.
+    /// A new email discards the pending email change of the user together with its tokens.
     pub async fn change_user_data(
         &self,
         user_id: i64,
@@ -516,9 +2167,7 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
         new_email: Option<String>,
     ) -> Result<AuthAccountModel, LightSpeedError> {
         self.c3p0
-            .transaction(|conn| async {
-                self.change_user_data_with_conn(conn, user_id, new_username, new_email).await
-            })
+            .transaction(|conn| async { self.change_user_data_with_conn(conn, user_id, new_username, new_email).await })
             .await
     }
 
@@ -535,6 +2184,35 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
         );
 
         let mut user = self.auth_repo.fetch_by_id(conn, user_id).await?;
+        let new_username = new_username.as_deref().map(normalize_username);
+        let new_email = new_email.as_deref().map(normalize_email);
+
+        let mut existing_user = None;
+        if let Some(username) = &new_username {
+            existing_user = self.auth_repo.fetch_by_username_optional(conn, username).await?;
+        }
+        let mut existing_email = None;
+        if let Some(email) = &new_email {
+            existing_email = self.auth_repo.fetch_by_email_optional(conn, email).await?;
+        }
+        Validator::validate(&|error_details: &mut ErrorDetails| {
+            if existing_user.as_ref().map(|existing| existing.id != user_id).unwrap_or(false) {
+                error_details.add_detail("username", ERR_NOT_UNIQUE);
+            }
+            if existing_email.as_ref().map(|existing| existing.id != user_id).unwrap_or(false) {
+                error_details.add_detail("email", ERR_NOT_UNIQUE);
+            }
+            Ok(())
+        })?;
+
+        if new_email.is_some() {
+            for token in self.token_service.fetch_all_by_username_with_conn(conn, &user.data.username).await? {
+                if token.data.token_type == TokenType::EmailChange {
+                    self.token_service.delete_with_conn(conn, token).await?;
+                }
+            }
+            user.data.pending_email_change = None;
+        }
 
         if let Some(username) = new_username {
             info!(
@@ -552,12 +2230,224 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
         self.auth_repo.update(conn, user).await
     }
 
-    pub async fn disable_by_user_id(&self, user_id: i64) -> Result<AuthAccountModel, LightSpeedError> {
+    pub async fn fetch_attributes_by_user_id(&self, user_id: i64) -> Result<BTreeMap<String, Value>, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.fetch_attributes_by_user_id_with_conn(conn, user_id).await }).await
+    }
+
+    pub async fn fetch_attributes_by_user_id_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+    ) -> Result<BTreeMap<String, Value>, LightSpeedError> {
+        debug!("Fetch the attributes of user_id [{}]", user_id);
+        Ok(self.auth_repo.fetch_by_id(conn, user_id).await?.data.attributes)
+    }
+
+    /// Merges the given attributes into the attributes of the user; a null value removes the attribute.
+    /// Every attribute has to be declared in the `account_attributes` of the `AuthConfig` with a matching type.
+    pub async fn update_attributes(
+        &self,
+        user_id: i64,
+        attributes: BTreeMap<String, Value>,
+    ) -> Result<AuthAccountModel, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.update_attributes_with_conn(conn, user_id, attributes).await }).await
+    }
+
+    pub async fn update_attributes_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+        attributes: BTreeMap<String, Value>,
+    ) -> Result<AuthAccountModel, LightSpeedError> {
+        info!("Update the attributes {:?} of user_id [{}]", attributes.keys().collect::<Vec<_>>(), user_id);
+
+        Validator::validate(&|error_details: &mut ErrorDetails| {
+            let mut scoped_err = error_details.with_scope("attributes");
+            for (name, value) in &attributes {
+                self.validate_attribute(&mut scoped_err, name, value);
+            }
+            Ok(())
+        })?;
+
+        let mut user = self.auth_repo.fetch_by_id(conn, user_id).await?;
+        for (name, value) in attributes {
+            if value.is_null() {
+                user.data.attributes.remove(&name);
+            } else {
+                user.data.attributes.insert(name, value);
+            }
+        }
+        self.auth_repo.update(conn, user).await
+    }
+
+    /// Creates the missing indexes of the searchable attributes
+    pub async fn create_attribute_indexes(&self) -> Result<(), LightSpeedError> {
+        self.c3p0
+            .transaction(|conn| async {
+                for attribute in self.auth_config.account_attributes.iter().filter(|attribute| attribute.searchable) {
+                    info!("Create the index of the searchable attribute [{}]", attribute.name);
+                    self.auth_repo.create_attribute_index(conn, &attribute.name).await?;
+                }
+                Ok(())
+            })
+            .await
+    }
+
+    fn validate_attribute(&self, error_details: &mut ErrorDetails, name: &str, value: &Value) {
+        let config = match self.auth_config.account_attributes.iter().find(|config| config.name == name) {
+            Some(config) => config,
+            None => {
+                error_details.add_detail(name, ERR_UNKNOWN_ATTRIBUTE);
+                return;
+            }
+        };
+        match (config.attribute_type, value) {
+            (_, Value::Null) => {}
+            (AccountAttributeType::String, Value::String(text)) => {
+                if let Some(max_length) = config.max_length {
+                    validate_le(error_details, name, max_length as usize, text.chars().count());
+                }
+            }
+            (AccountAttributeType::Integer, Value::Number(number)) if number.is_i64() => {}
+            (AccountAttributeType::Boolean, Value::Bool(_)) => {}
+            _ => error_details.add_detail(name, ERR_WRONG_ATTRIBUTE_TYPE),
+        }
+    }
+
+    /// Issues an Auth of the given user to the user of the AuthContext, e.g. a member of the support staff.
+    /// The Auth carries the id of the impersonator in `impersonated_by` and expires after the configured
+    /// `impersonation_validity_minutes`; every impersonation is recorded for audit.
+    /// The impersonator needs the configured `impersonation_permission` and cannot be impersonated in turn.
+    /// The target cannot have roles that the impersonator does not have, nor the `impersonation_permission`,
+    /// so that an impersonation never grants more rights than the impersonator already has.
+    ///
+    /// The operations reserved to the owner of an account take a user id and cannot tell whether the caller
+    /// is impersonated: the applications have to reject the impersonated Auths with
+    /// `AuthContext::is_not_impersonated` before changing the password or the email and before enrolling
+    /// a TOTP or a WebAuthn credential. The invitations reject them by themselves.
+    pub async fn impersonate(
+        &self,
+        auth_context: &AuthContext<'_>,
+        user_id: i64,
+        reason: Option<String>,
+    ) -> Result<Auth, LightSpeedError> {
+        self.c3p0
+            .transaction(|conn| async { self.impersonate_with_conn(conn, auth_context, user_id, reason).await })
+            .await
+    }
+
+    pub async fn impersonate_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        auth_context: &AuthContext<'_>,
+        user_id: i64,
+        reason: Option<String>,
+    ) -> Result<Auth, LightSpeedError> {
+        auth_context.has_permission(&self.auth_config.impersonation_permission)?.is_not_impersonated()?;
+        let impersonator_user_id = auth_context.auth.id;
+        info!("User_id [{}] impersonates user_id [{}]. Reason: [{:?}]", impersonator_user_id, user_id, reason);
+
+        if impersonator_user_id == user_id {
+            return Err(LightSpeedError::ForbiddenError {
+                message: format!("User [{user_id}] cannot impersonate its own account"),
+            });
+        }
+
+        let user = self.auth_repo.fetch_by_id(conn, user_id).await?;
+        match &user.data.status {
+            AuthAccountStatus::Active => {}
+            _ => {
+                return Err(LightSpeedError::BadRequest {
+                    message: format!("User [{}] not in status Active", user.data.username),
+                    code: ErrorCodes::INACTIVE_USER,
+                })
+            }
+        };
+
+        if let Some(role) = user.data.roles.iter().find(|role| !auth_context.auth.roles.contains(role)) {
+            return Err(LightSpeedError::ForbiddenError {
+                message: format!("User [{impersonator_user_id}] does not have the role [{role}] of user [{user_id}]"),
+            });
+        }
+        if auth_context.roles_have_permission(&user.data.roles, &self.auth_config.impersonation_permission) {
+            return Err(LightSpeedError::ForbiddenError {
+                message: format!("User [{user_id}] has the impersonation permission and cannot be impersonated"),
+            });
+        }
+
+        let creation_ts_seconds = self.clock.epoch_seconds();
+        let expiration_ts_seconds = creation_ts_seconds + (self.auth_config.impersonation_validity_minutes * 60);
+        let mut auth =
+            Auth::new(user.id, user.data.username, user.data.roles, creation_ts_seconds, expiration_ts_seconds);
+        auth.session_id = new_hyphenated_uuid();
+        auth.impersonated_by = Some(impersonator_user_id);
+        self.auth_session_service.start_session_with_conn(conn, &auth).await?;
+
+        self.impersonation_repo
+            .save(
+                conn,
+                NewModel::new(ImpersonationData {
+                    user_id,
+                    impersonator_user_id,
+                    session_id: auth.session_id.clone(),
+                    reason,
+                    created_date_epoch_seconds: creation_ts_seconds,
+                    expire_at_epoch_seconds: expiration_ts_seconds,
+                }),
+            )
+            .await?;
+        Ok(auth)
+    }
+
+    /// Returns the impersonations of the user, the most recent first
+    pub async fn fetch_impersonations_by_user_id(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<ImpersonationModel>, LightSpeedError> {
+        self.c3p0
+            .transaction(|conn| async { self.fetch_impersonations_by_user_id_with_conn(conn, user_id).await })
+            .await
+    }
+
+    pub async fn fetch_impersonations_by_user_id_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+    ) -> Result<Vec<ImpersonationModel>, LightSpeedError> {
+        debug!("Fetch the impersonations of user_id [{}]", user_id);
+        self.impersonation_repo.fetch_all_by_user_id(conn, user_id).await
+    }
+
+    /// Returns the impersonations performed by the user, the most recent first
+    pub async fn fetch_impersonations_by_impersonator_user_id(
+        &self,
+        impersonator_user_id: i64,
+    ) -> Result<Vec<ImpersonationModel>, LightSpeedError> {
         self.c3p0
-            .transaction(|conn| async { self.disable_by_user_id_with_conn(conn, user_id).await })
+            .transaction(|conn| async {
+                self.fetch_impersonations_by_impersonator_user_id_with_conn(conn, impersonator_user_id).await
+            })
             .await
     }
 
+    pub async fn fetch_impersonations_by_impersonator_user_id_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        impersonator_user_id: i64,
+    ) -> Result<Vec<ImpersonationModel>, LightSpeedError> {
+        debug!("Fetch the impersonations performed by user_id [{}]", impersonator_user_id);
+        self.impersonation_repo.fetch_all_by_impersonator_user_id(conn, impersonator_user_id).await
+    }
+
+    /// Disables the user and terminates all the user sessions.
+    /// The issued Auths are rejected only by the applications that call `AuthSessionService::touch`.
+    pub async fn disable_by_user_id(&self, user_id: i64) -> Result<AuthAccountModel, LightSpeedError> {
+        let user =
+            self.c3p0.transaction(|conn| async { self.disable_by_user_id_with_conn(conn, user_id).await }).await?;
+        self.account_event_publisher.publish_after_commit(&AccountEvent::Disabled { user: user.clone().into() }).await;
+        Ok(user)
+    }
+
     pub async fn disable_by_user_id_with_conn(
         &self,
         conn: &mut RepoManager::Conn,
@@ -577,14 +2467,18 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
         };
 
         user.data.status = AuthAccountStatus::Disabled;
-        self.auth_repo.update(conn, user).await
+        user.data.disabled_date_epoch_seconds = Some(self.clock.epoch_seconds());
+        self.auth_session_service.revoke_all_by_user_id_with_conn(conn, user_id).await?;
+        let user = self.auth_repo.update(conn, user).await?;
+        self.account_event_publisher
+            .publish_with_conn(conn, &AccountEvent::Disabled { user: user.clone().into() })
+            .await?;
+        Ok(user)
     }
 
     pub async fn reactivate_disabled_user_by_user_id(&self, user_id: i64) -> Result<AuthAccountModel, LightSpeedError> {
         self.c3p0
-            .transaction(|conn| async {
-                self.reactivate_disabled_user_by_user_id_with_conn(conn, user_id).await
-            })
+            .transaction(|conn| async { self.reactivate_disabled_user_by_user_id_with_conn(conn, user_id).await })
             .await
     }
 
@@ -606,14 +2500,138 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
             }
         };
 
+        if user.data.is_anonymized() {
+            return Err(LightSpeedError::BadRequest {
+                message: format!("User [{user_id}] has been anonymized"),
+                code: ErrorCodes::ANONYMIZED_USER,
+            });
+        }
+
         user.data.status = AuthAccountStatus::Active;
+        user.data.disabled_date_epoch_seconds = None;
+        self.auth_repo.update(conn, user).await
+    }
+
+    /// Scrubs the personal data of the account while keeping its id, so that the references to the user
+    /// are preserved. The account is disabled and its tokens, sessions, linked identities, WebAuthn credentials
+    /// and OAuth2 grants are deleted. Anonymizing an already anonymized account has no effect.
+    pub async fn anonymize_by_user_id(&self, user_id: i64) -> Result<AuthAccountModel, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.anonymize_by_user_id_with_conn(conn, user_id).await }).await
+    }
+
+    pub async fn anonymize_by_user_id_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+    ) -> Result<AuthAccountModel, LightSpeedError> {
+        info!("Anonymize user with user_id [{}]", user_id);
+        let mut user = self.auth_repo.fetch_by_id(conn, user_id).await?;
+        if user.data.is_anonymized() {
+            return Ok(user);
+        }
+
+        for token in self.token_service.fetch_all_by_username_with_conn(conn, &user.data.username).await? {
+            self.token_service.delete_with_conn(conn, token).await?;
+        }
+        self.login_attempt_service.reset_with_conn(conn, LoginAttemptKeyType::Username, &user.data.username).await?;
+        self.auth_session_service.revoke_all_by_user_id_with_conn(conn, user_id).await?;
+        self.external_identity_repo.delete_by_user_id(conn, user_id).await?;
+        self.webauthn_credential_repo.delete_by_user_id(conn, user_id).await?;
+        self.oauth2_token_repo.delete_by_user_id(conn, user_id).await?;
+        self.oauth2_consent_repo.delete_by_user_id(conn, user_id).await?;
+
+        let unusable_password_hash = self.password_service.hash_password(&new_hyphenated_uuid())?;
+        user.data.anonymize(user_id, unusable_password_hash, self.clock.epoch_seconds());
         self.auth_repo.update(conn, user).await
     }
 
+    /// Anonymizes the accounts disabled for longer than `anonymize_disabled_accounts_after_days`.
+    /// The accounts disabled before their disable date was recorded start their retention period now.
+    /// Returns the number of anonymized accounts.
+    pub async fn anonymize_expired_disabled_accounts(&self) -> Result<u64, LightSpeedError> {
+        let anonymize_after_days = match self.auth_config.anonymize_disabled_accounts_after_days {
+            Some(anonymize_after_days) => anonymize_after_days,
+            None => return Ok(0),
+        };
+        let retention_seconds = i64::from(anonymize_after_days) * 24 * 60 * 60;
+
+        let mut anonymized = 0;
+        let mut start_user_id = 0;
+        loop {
+            let (batch_size, last_user_id, batch_anonymized) = self
+                .c3p0
+                .transaction(|conn| async {
+                    let now = self.clock.epoch_seconds();
+                    let users = self
+                        .auth_repo
+                        .fetch_all_disabled_not_anonymized(conn, start_user_id, RETENTION_BATCH_SIZE)
+                        .await?;
+                    let batch_size = users.len();
+                    let last_user_id = users.last().map(|user| user.id).unwrap_or(start_user_id);
+                    let mut batch_anonymized = 0;
+                    for mut user in users {
+                        match user.data.disabled_date_epoch_seconds {
+                            Some(disabled_date) if now - disabled_date >= retention_seconds => {
+                                self.anonymize_by_user_id_with_conn(conn, user.id).await?;
+                                batch_anonymized += 1;
+                            }
+                            Some(_) => {}
+                            None => {
+                                user.data.disabled_date_epoch_seconds = Some(now);
+                                self.auth_repo.update(conn, user).await?;
+                            }
+                        }
+                    }
+                    Ok::<_, LightSpeedError>((batch_size, last_user_id, batch_anonymized))
+                })
+                .await?;
+
+            anonymized += batch_anonymized;
+            if batch_size < RETENTION_BATCH_SIZE as usize {
+                break;
+            }
+            start_user_id = last_user_id + 1;
+        }
+
+        info!("Anonymized [{}] accounts disabled for more than [{}] days", anonymized, anonymize_after_days);
+        Ok(anonymized)
+    }
+
+    /// Removes the lockout and the failed logins counter of a username
+    pub async fn unlock_by_username(&self, username: &str) -> Result<(), LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.unlock_by_username_with_conn(conn, username).await }).await
+    }
+
+    pub async fn unlock_by_username_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        username: &str,
+    ) -> Result<(), LightSpeedError> {
+        info!("Unlock username [{}]", username);
+        self.login_attempt_service.reset_with_conn(conn, LoginAttemptKeyType::Username, username).await
+    }
+
+    /// Removes the lockout and the failed logins counter of a client
+    pub async fn unlock_by_client_key(&self, client_key: &str) -> Result<(), LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.unlock_by_client_key_with_conn(conn, client_key).await }).await
+    }
+
+    pub async fn unlock_by_client_key_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        client_key: &str,
+    ) -> Result<(), LightSpeedError> {
+        info!("Unlock client [{}]", client_key);
+        self.login_attempt_service.reset_with_conn(conn, LoginAttemptKeyType::Client, client_key).await
+    }
+
     pub async fn delete_by_user_id(&self, user_id: i64) -> Result<u64, LightSpeedError> {
-        self.c3p0
-            .transaction(|conn| async { self.delete_by_user_id_with_conn(conn, user_id).await })
-            .await
+        let deleted =
+            self.c3p0.transaction(|conn| async { self.delete_by_user_id_with_conn(conn, user_id).await }).await?;
+        if deleted > 0 {
+            self.account_event_publisher.publish_after_commit(&AccountEvent::Deleted { user_id }).await;
+        }
+        Ok(deleted)
     }
 
     pub async fn delete_by_user_id_with_conn(
@@ -622,6 +2640,19 @@ impl<RepoManager: AuthRepositoryManager> AuthAccountService<RepoManager> {
         user_id: i64,
     ) -> Result<u64, LightSpeedError> {
         debug!("Delete user with user_id [{}]", user_id);
-        self.auth_repo.delete_by_id(conn, user_id).await
+        self.external_identity_repo.delete_by_user_id(conn, user_id).await?;
+        self.webauthn_credential_repo.delete_by_user_id(conn, user_id).await?;
+        self.oauth2_token_repo.delete_by_user_id(conn, user_id).await?;
+        self.oauth2_consent_repo.delete_by_user_id(conn, user_id).await?;
+        self.auth_session_service.revoke_all_by_user_id_with_conn(conn, user_id).await?;
+        let deleted = self.auth_repo.delete_by_id(conn, user_id).await?;
+        if deleted > 0 {
+            self.account_event_publisher.publish_with_conn(conn, &AccountEvent::Deleted { user_id }).await?;
+        }
+        Ok(deleted)
     }
 }
+
+fn is_wrong_credentials<T>(result: &Result<T, LightSpeedError>) -> bool {
+    matches!(result, Err(LightSpeedError::BadRequest { code, .. }) if *code == ErrorCodes::WRONG_CREDENTIALS)
+}
@@ -0,0 +1,177 @@
+use crate::model::auth_session::{AuthSessionData, AuthSessionModel};
+use crate::repository::{AuthRepositoryManager, AuthSessionRepository};
+use c3p0::*;
+use lightspeed_core::clock::Clock;
+use lightspeed_core::error::{ErrorCodes, LightSpeedError};
+use lightspeed_core::service::auth::Auth;
+use log::*;
+use std::sync::Arc;
+
+/// Keeps track of the sessions opened by the logins, so that the users can see where they are logged in
+/// and terminate their sessions remotely.
+///
+/// The Auth tokens are stateless, so a revoked session is rejected only by the applications that call `touch`
+/// when they receive an authenticated request.
+#[derive(Clone)]
+pub struct AuthSessionService<RepoManager: AuthRepositoryManager> {
+    c3p0: RepoManager::C3P0,
+    session_repo: RepoManager::AuthSessionRepo,
+    clock: Arc<dyn Clock>,
+}
+
+impl<RepoManager: AuthRepositoryManager> AuthSessionService<RepoManager> {
+    pub fn new(c3p0: RepoManager::C3P0, session_repo: RepoManager::AuthSessionRepo, clock: Arc<dyn Clock>) -> Self {
+        AuthSessionService { c3p0, session_repo, clock }
+    }
+
+    /// Records the session of a new Auth. The expired sessions of the same user are removed.
+    pub async fn start_session_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        auth: &Auth,
+    ) -> Result<AuthSessionModel, LightSpeedError> {
+        debug!("Start session [{}] for user_id [{}]", auth.session_id, auth.id);
+        let now = self.clock.epoch_seconds();
+
+        for session in self.session_repo.fetch_all_by_user_id(conn, auth.id).await? {
+            if session.data.is_expired_at(now) {
+                self.session_repo.delete(conn, session).await?;
+            }
+        }
+
+        self.session_repo
+            .save(
+                conn,
+                NewModel::new(AuthSessionData {
+                    session_id: auth.session_id.clone(),
+                    user_id: auth.id,
+                    user_agent: None,
+                    ip_address: None,
+                    created_date_epoch_seconds: now,
+                    last_seen_epoch_seconds: now,
+                    expire_at_epoch_seconds: auth.expiration_ts_seconds,
+                }),
+            )
+            .await
+    }
+
+    /// Registers a request of the session with the user agent and the IP address of the client.
+    /// Returns an UnauthenticatedError if the session has been revoked or is expired.
+    ///
+    /// The web extractors of lightspeed_core only verify the Auth token: the applications that need
+    /// the revoked sessions to be rejected have to call it for every authenticated request.
+    pub async fn touch(
+        &self,
+        session_id: &str,
+        user_agent: Option<&str>,
+        ip_address: Option<&str>,
+    ) -> Result<AuthSessionModel, LightSpeedError> {
+        self.c3p0
+            .transaction(|conn| async { self.touch_with_conn(conn, session_id, user_agent, ip_address).await })
+            .await
+    }
+
+    pub async fn touch_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        session_id: &str,
+        user_agent: Option<&str>,
+        ip_address: Option<&str>,
+    ) -> Result<AuthSessionModel, LightSpeedError> {
+        let now = self.clock.epoch_seconds();
+        if self.session_repo.touch(conn, session_id, now, user_agent, ip_address).await? == 0 {
+            debug!("Session [{}] not found or expired", session_id);
+            return Err(LightSpeedError::UnauthenticatedError);
+        }
+        self.session_repo
+            .fetch_by_session_id_optional(conn, session_id)
+            .await?
+            .ok_or(LightSpeedError::UnauthenticatedError)
+    }
+
+    /// Returns the sessions of the user that are not expired
+    pub async fn fetch_all_by_user_id(&self, user_id: i64) -> Result<Vec<AuthSessionModel>, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.fetch_all_by_user_id_with_conn(conn, user_id).await }).await
+    }
+
+    pub async fn fetch_all_by_user_id_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+    ) -> Result<Vec<AuthSessionModel>, LightSpeedError> {
+        let now = self.clock.epoch_seconds();
+        Ok(self
+            .session_repo
+            .fetch_all_by_user_id(conn, user_id)
+            .await?
+            .into_iter()
+            .filter(|session| !session.data.is_expired_at(now))
+            .collect())
+    }
+
+    /// Terminates a session of the user. Returns a NOT_FOUND error if the session does not belong to the user.
+    /// The Auth of the session is still valid until its expiration for the applications that do not call `touch`.
+    pub async fn revoke(&self, user_id: i64, session_id: &str) -> Result<AuthSessionModel, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.revoke_with_conn(conn, user_id, session_id).await }).await
+    }
+
+    pub async fn revoke_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+        session_id: &str,
+    ) -> Result<AuthSessionModel, LightSpeedError> {
+        info!("Revoke session [{}] of user_id [{}]", session_id, user_id);
+        match self.session_repo.fetch_by_session_id_optional(conn, session_id).await? {
+            Some(session) if session.data.user_id == user_id => self.session_repo.delete(conn, session).await,
+            _ => Err(LightSpeedError::BadRequest {
+                message: format!("No session [{session_id}] found for user_id [{user_id}]"),
+                code: ErrorCodes::NOT_FOUND,
+            }),
+        }
+    }
+
+    /// Terminates all the sessions of the user. Returns the number of terminated sessions.
+    /// The Auths of the sessions are still valid until their expiration for the applications that do not call `touch`.
+    pub async fn revoke_all_by_user_id(&self, user_id: i64) -> Result<u64, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.revoke_all_by_user_id_with_conn(conn, user_id).await }).await
+    }
+
+    pub async fn revoke_all_by_user_id_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+    ) -> Result<u64, LightSpeedError> {
+        info!("Revoke all the sessions of user_id [{}]", user_id);
+        self.session_repo.delete_by_user_id(conn, user_id).await
+    }
+
+    /// Terminates all the sessions of the user but the given one. Returns the number of terminated sessions.
+    pub async fn revoke_all_but_one_by_user_id_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+        session_id: &str,
+    ) -> Result<u64, LightSpeedError> {
+        info!("Revoke all the sessions of user_id [{}] but [{}]", user_id, session_id);
+        let mut revoked = 0;
+        for session in self.session_repo.fetch_all_by_user_id(conn, user_id).await? {
+            if session.data.session_id != session_id {
+                self.session_repo.delete(conn, session).await?;
+                revoked += 1;
+            }
+        }
+        Ok(revoked)
+    }
+
+    /// Deletes the expired sessions of all the users. Returns the number of deleted sessions.
+    pub async fn purge_expired(&self) -> Result<u64, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.purge_expired_with_conn(conn).await }).await
+    }
+
+    pub async fn purge_expired_with_conn(&self, conn: &mut RepoManager::Conn) -> Result<u64, LightSpeedError> {
+        let purged = self.session_repo.delete_expired(conn, self.clock.epoch_seconds()).await?;
+        debug!("Purged [{}] expired sessions", purged);
+        Ok(purged)
+    }
+}
@@ -0,0 +1,206 @@
+use crate::config::AuthConfig;
+use crate::model::auth_account::normalize_username;
+use crate::model::login_attempt::{LoginAttemptData, LoginAttemptKeyType};
+use crate::repository::{AuthRepositoryManager, LoginAttemptRepository};
+use c3p0::*;
+use lightspeed_core::clock::Clock;
+use lightspeed_core::error::{ErrorCodes, LightSpeedError};
+use log::*;
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Keeps track of the failed logins per username and per client and locks them
+/// when the configured thresholds are reached.
+/// The counters are persisted, so they are shared by all the application instances.
+/// The usernames are normalized, so that the variants of the same username share the same counter.
+#[derive(Clone)]
+pub struct LoginAttemptService<RepoManager: AuthRepositoryManager> {
+    c3p0: RepoManager::C3P0,
+    auth_config: AuthConfig,
+    login_attempt_repo: RepoManager::LoginAttemptRepo,
+    clock: Arc<dyn Clock>,
+}
+
+impl<RepoManager: AuthRepositoryManager> LoginAttemptService<RepoManager> {
+    pub fn new(
+        c3p0: RepoManager::C3P0,
+        auth_config: AuthConfig,
+        login_attempt_repo: RepoManager::LoginAttemptRepo,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        LoginAttemptService { c3p0, auth_config, login_attempt_repo, clock }
+    }
+
+    /// Returns a LOCKED_USER error if either the username or the client are locked
+    pub async fn check_not_locked_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        username: &str,
+        client_key: Option<&str>,
+    ) -> Result<(), LightSpeedError> {
+        let now = self.clock.epoch_seconds();
+        let keys = std::iter::once((LoginAttemptKeyType::Username, username))
+            .chain(client_key.map(|client_key| (LoginAttemptKeyType::Client, client_key)));
+
+        for (key_type, key) in keys {
+            let key = normalized_key(key_type, key);
+            if let Some(attempt) = self.login_attempt_repo.fetch_by_key_optional(conn, key_type, &key).await? {
+                if attempt.data.is_locked_at(now) {
+                    return Err(LightSpeedError::BadRequest {
+                        message: format!(
+                            "{} [{}] is locked until epoch seconds [{}]",
+                            key_type,
+                            key,
+                            attempt.data.locked_until_epoch_seconds.unwrap_or_default()
+                        ),
+                        code: ErrorCodes::LOCKED_USER,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Registers a failed login for the username and, if present, for the client.
+    /// Returns the delay to be applied before answering to the client.
+    pub async fn register_failure_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        username: &str,
+        client_key: Option<&str>,
+    ) -> Result<Duration, LightSpeedError> {
+        let failed_attempts = self
+            .increment_with_conn(
+                conn,
+                LoginAttemptKeyType::Username,
+                username,
+                self.auth_config.max_failed_logins_per_username,
+            )
+            .await?;
+
+        if let Some(client_key) = client_key {
+            self.increment_with_conn(
+                conn,
+                LoginAttemptKeyType::Client,
+                client_key,
+                self.auth_config.max_failed_logins_per_client,
+            )
+            .await?;
+        }
+
+        Ok(progressive_delay(
+            self.auth_config.failed_login_base_delay_millis,
+            self.auth_config.failed_login_max_delay_millis,
+            failed_attempts,
+        ))
+    }
+
+    /// Removes the failed logins counter, and the lockout if any, of the given key
+    pub async fn reset_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        key_type: LoginAttemptKeyType,
+        key: &str,
+    ) -> Result<(), LightSpeedError> {
+        let key = normalized_key(key_type, key);
+        if let Some(attempt) = self.login_attempt_repo.fetch_by_key_optional(conn, key_type, &key).await? {
+            debug!("Reset failed logins of {} [{}]", key_type, key);
+            self.login_attempt_repo.delete(conn, attempt).await?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the counters that are not locked and whose last failure is older than the lockout window;
+    /// they would be reset by the next failure anyway. Returns the number of deleted counters.
+    pub async fn purge_expired(&self) -> Result<u64, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.purge_expired_with_conn(conn).await }).await
+    }
+
+    pub async fn purge_expired_with_conn(&self, conn: &mut RepoManager::Conn) -> Result<u64, LightSpeedError> {
+        let now = self.clock.epoch_seconds();
+        let lockout_seconds = self.auth_config.failed_login_lockout_minutes * 60;
+        let purged = self.login_attempt_repo.delete_expired(conn, now, now - lockout_seconds).await?;
+        debug!("Purged [{}] expired failed logins counters", purged);
+        Ok(purged)
+    }
+
+    async fn increment_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        key_type: LoginAttemptKeyType,
+        key: &str,
+        max_failed_logins: u32,
+    ) -> Result<u32, LightSpeedError> {
+        let now = self.clock.epoch_seconds();
+        let lockout_seconds = self.auth_config.failed_login_lockout_minutes * 60;
+        let key = normalized_key(key_type, key);
+
+        // The counter is locked until the end of the transaction, so the concurrent failures are all counted
+        let mut attempt = self
+            .login_attempt_repo
+            .fetch_or_create_for_update(
+                conn,
+                LoginAttemptData {
+                    key: key.to_string(),
+                    key_type,
+                    failed_attempts: 0,
+                    last_failure_epoch_seconds: now,
+                    locked_until_epoch_seconds: None,
+                },
+            )
+            .await?;
+
+        let lockout_expired =
+            attempt.data.locked_until_epoch_seconds.map(|locked_until| locked_until <= now).unwrap_or(false);
+        if lockout_expired || now - attempt.data.last_failure_epoch_seconds >= lockout_seconds {
+            attempt.data.failed_attempts = 0;
+            attempt.data.locked_until_epoch_seconds = None;
+        }
+
+        attempt.data.failed_attempts += 1;
+        attempt.data.last_failure_epoch_seconds = now;
+        if attempt.data.failed_attempts >= max_failed_logins && attempt.data.locked_until_epoch_seconds.is_none() {
+            warn!("Lock {} [{}] after {} failed logins", key_type, key, attempt.data.failed_attempts);
+            attempt.data.locked_until_epoch_seconds = Some(now + lockout_seconds);
+        }
+        let attempt = self.login_attempt_repo.update(conn, attempt).await?;
+
+        debug!("{} [{}] has {} failed logins", key_type, key, attempt.data.failed_attempts);
+        Ok(attempt.data.failed_attempts)
+    }
+}
+
+fn normalized_key(key_type: LoginAttemptKeyType, key: &str) -> Cow<'_, str> {
+    match key_type {
+        LoginAttemptKeyType::Username => Cow::Owned(normalize_username(key)),
+        LoginAttemptKeyType::Client => Cow::Borrowed(key),
+    }
+}
+
+/// Returns the delay after the given number of consecutive failures.
+/// The delay starts from `base_delay_millis` and doubles at every failure up to `max_delay_millis`.
+pub fn progressive_delay(base_delay_millis: u64, max_delay_millis: u64, failed_attempts: u32) -> Duration {
+    if failed_attempts == 0 {
+        return Duration::ZERO;
+    }
+    let factor = 2u64.saturating_pow(failed_attempts - 1);
+    Duration::from_millis(base_delay_millis.saturating_mul(factor).min(max_delay_millis))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn should_double_the_delay_at_every_failure() {
+        assert_eq!(Duration::ZERO, progressive_delay(250, 4000, 0));
+        assert_eq!(Duration::from_millis(250), progressive_delay(250, 4000, 1));
+        assert_eq!(Duration::from_millis(500), progressive_delay(250, 4000, 2));
+        assert_eq!(Duration::from_millis(2000), progressive_delay(250, 4000, 4));
+        assert_eq!(Duration::from_millis(4000), progressive_delay(250, 4000, 5));
+        assert_eq!(Duration::from_millis(4000), progressive_delay(250, 4000, u32::MAX));
+        assert_eq!(Duration::ZERO, progressive_delay(0, 4000, 10));
+    }
+}
@@ -1,3 +1,13 @@
+pub mod account_event;
 pub mod auth_account;
+pub mod auth_session;
+pub mod login_attempt;
+pub mod oauth2;
+pub mod oidc;
+pub mod oidc_client;
 pub mod password_codec;
+pub mod password_hasher;
+pub mod personal_data;
 pub mod token;
+pub mod totp;
+pub mod webauthn;
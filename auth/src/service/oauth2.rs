@@ -0,0 +1,800 @@
+use crate::config::AuthConfig;
+use crate::dto::oauth2_dto::{
+    OAuth2AuthorizationDetailsDto, OAuth2AuthorizationRequestDto, OAuth2AuthorizationResponseDto,
+    OAuth2IntrospectionDto, OAuth2ScopeDto, OAuth2TokenRequestDto, OAuth2TokenResponseDto, RegisterOAuth2ClientDto,
+};
+use crate::model::auth_account::AuthAccountStatus;
+use crate::model::oauth2_client::{OAuth2ClientData, OAuth2ClientModel, OAuth2GrantType};
+use crate::model::oauth2_consent::{OAuth2ConsentData, OAuth2ConsentModel};
+use crate::model::oauth2_token::{OAuth2TokenData, OAuth2TokenModel, OAuth2TokenType};
+use crate::repository::{
+    AuthAccountRepository, AuthRepositoryManager, OAuth2ClientRepository, OAuth2ConsentRepository,
+    OAuth2TokenRepository,
+};
+use crate::service::oidc_client::pkce_code_challenge;
+use crate::service::password_codec::PasswordCodecService;
+use c3p0::*;
+use lightspeed_core::clock::Clock;
+use lightspeed_core::config::JwtConfig;
+use lightspeed_core::error::{ErrorCodes, ErrorDetails, LightSpeedError};
+use lightspeed_core::service::auth::Auth;
+use lightspeed_core::service::jwt::{JwtService, JWT};
+use lightspeed_core::service::random::RandomService;
+use lightspeed_core::service::validator::Validator;
+use log::*;
+use reqwest::Url;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The error codes of the OAuth2 endpoints, with the values defined by RFC 6749
+pub const OAUTH2_INVALID_CLIENT: &str = "invalid_client";
+pub const OAUTH2_INVALID_GRANT: &str = "invalid_grant";
+pub const OAUTH2_INVALID_REQUEST: &str = "invalid_request";
+pub const OAUTH2_INVALID_SCOPE: &str = "invalid_scope";
+pub const OAUTH2_UNAUTHORIZED_CLIENT: &str = "unauthorized_client";
+pub const OAUTH2_UNSUPPORTED_GRANT_TYPE: &str = "unsupported_grant_type";
+pub const OAUTH2_UNSUPPORTED_RESPONSE_TYPE: &str = "unsupported_response_type";
+
+pub const ERR_UNKNOWN_SCOPE: &str = "UNKNOWN_SCOPE";
+
+pub const OAUTH2_CLIENT_ID_LENGTH: usize = 32;
+pub const OAUTH2_CLIENT_SECRET_LENGTH: usize = 48;
+pub const OAUTH2_TOKEN_LENGTH: usize = 48;
+
+const PKCE_CODE_CHALLENGE_METHOD: &str = "S256";
+const BEARER_TOKEN_TYPE: &str = "Bearer";
+
+/// An OAuth2 authorization server for the API clients of the application.
+///
+/// The access tokens are JWTs signed with the `oauth2_access_token_secret` of the AuthConfig,
+/// so they are not accepted as session tokens by the web extractors.
+/// Their payload is an `Auth`: with the authorization code grant it is the Auth of the user that gave the consent,
+/// with the client credentials grant it has the client_id as username and the roles of the client.
+/// In both cases the `scope_permissions` restrict the permissions of the AuthContext to the ones
+/// of the granted scopes, and the role and owner checks of the AuthContext fail.
+/// Every access token is also saved with its `session_id`, so that it can be revoked before its expiration.
+/// The resource servers verify the access tokens with `validate_access_token`, which honours the revocation.
+#[derive(Clone)]
+pub struct OAuth2Service<RepoManager: AuthRepositoryManager> {
+    c3p0: RepoManager::C3P0,
+    auth_config: AuthConfig,
+    jwt_service: Arc<JwtService>,
+    password_service: Arc<PasswordCodecService>,
+    auth_repo: RepoManager::AuthAccountRepo,
+    client_repo: RepoManager::OAuth2ClientRepo,
+    consent_repo: RepoManager::OAuth2ConsentRepo,
+    token_repo: RepoManager::OAuth2TokenRepo,
+    clock: Arc<dyn Clock>,
+}
+
+impl<RepoManager: AuthRepositoryManager> OAuth2Service<RepoManager> {
+    /// Fails if the `oauth2_access_token_secret` of the AuthConfig is empty
+    pub fn new(
+        repo_manager: &RepoManager,
+        auth_config: AuthConfig,
+        password_service: Arc<PasswordCodecService>,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, LightSpeedError> {
+        let jwt_service = Arc::new(JwtService::new_with_clock(
+            &JwtConfig { secret: auth_config.oauth2_access_token_secret.clone(), ..Default::default() },
+            clock.clone(),
+        )?);
+        Ok(OAuth2Service {
+            c3p0: repo_manager.c3p0().clone(),
+            auth_config,
+            jwt_service,
+            password_service,
+            auth_repo: repo_manager.auth_account_repo(),
+            client_repo: repo_manager.oauth2_client_repo(),
+            consent_repo: repo_manager.oauth2_consent_repo(),
+            token_repo: repo_manager.oauth2_token_repo(),
+            clock,
+        })
+    }
+
+    /// Registers a new client. The client secret of a confidential client is returned only here,
+    /// only its hash is saved.
+    pub async fn register_client(
+        &self,
+        dto: RegisterOAuth2ClientDto,
+    ) -> Result<(OAuth2ClientModel, Option<String>), LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.register_client_with_conn(conn, dto).await }).await
+    }
+
+    pub async fn register_client_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        dto: RegisterOAuth2ClientDto,
+    ) -> Result<(OAuth2ClientModel, Option<String>), LightSpeedError> {
+        Validator::validate(&(&dto, &|error_details: &mut ErrorDetails| {
+            for (count, scope) in dto.scopes.iter().enumerate() {
+                if !self.auth_config.oauth2_scopes.iter().any(|scope_config| &scope_config.name == scope) {
+                    error_details.add_detail(format!("scopes[{count}]"), ERR_UNKNOWN_SCOPE);
+                }
+            }
+            Ok(())
+        }))?;
+
+        let client_secret =
+            if dto.confidential { Some(RandomService::random_string(OAUTH2_CLIENT_SECRET_LENGTH)) } else { None };
+        let client_secret_hash = match &client_secret {
+            Some(client_secret) => Some(self.password_service.hash_password(client_secret)?),
+            None => None,
+        };
+
+        let client = self
+            .client_repo
+            .save(
+                conn,
+                NewModel::new(OAuth2ClientData {
+                    client_id: RandomService::random_string(OAUTH2_CLIENT_ID_LENGTH),
+                    client_secret_hash,
+                    name: dto.name,
+                    redirect_uris: dto.redirect_uris,
+                    grant_types: dto.grant_types,
+                    scopes: dto.scopes,
+                    roles: dto.roles,
+                    created_date_epoch_seconds: self.clock.epoch_seconds(),
+                }),
+            )
+            .await?;
+        info!("Registered OAuth2 client [{}] with client_id [{}]", client.data.name, client.data.client_id);
+        Ok((client, client_secret))
+    }
+
+    pub async fn fetch_client_by_client_id(&self, client_id: &str) -> Result<OAuth2ClientModel, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.fetch_client_by_client_id_with_conn(conn, client_id).await }).await
+    }
+
+    pub async fn fetch_client_by_client_id_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        client_id: &str,
+    ) -> Result<OAuth2ClientModel, LightSpeedError> {
+        self.client_repo.fetch_by_client_id_optional(conn, client_id).await?.ok_or_else(|| {
+            LightSpeedError::BadRequest {
+                message: format!("OAuth2 client [{client_id}] not found"),
+                code: ErrorCodes::NOT_FOUND,
+            }
+        })
+    }
+
+    /// Replaces the secret of a confidential client. The new secret is returned only here.
+    pub async fn regenerate_client_secret(
+        &self,
+        client_id: &str,
+    ) -> Result<(OAuth2ClientModel, String), LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.regenerate_client_secret_with_conn(conn, client_id).await }).await
+    }
+
+    pub async fn regenerate_client_secret_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        client_id: &str,
+    ) -> Result<(OAuth2ClientModel, String), LightSpeedError> {
+        let mut client = self.fetch_client_by_client_id_with_conn(conn, client_id).await?;
+        if !client.data.is_confidential() {
+            return Err(LightSpeedError::BadRequest {
+                message: format!("OAuth2 client [{client_id}] is a public client"),
+                code: OAUTH2_UNAUTHORIZED_CLIENT,
+            });
+        }
+        info!("Regenerate the secret of OAuth2 client [{}]", client_id);
+        let client_secret = RandomService::random_string(OAUTH2_CLIENT_SECRET_LENGTH);
+        client.data.client_secret_hash = Some(self.password_service.hash_password(&client_secret)?);
+        Ok((self.client_repo.update(conn, client).await?, client_secret))
+    }
+
+    /// Deletes the client together with its consents and tokens
+    pub async fn delete_client(&self, client_id: &str) -> Result<(), LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.delete_client_with_conn(conn, client_id).await }).await
+    }
+
+    pub async fn delete_client_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        client_id: &str,
+    ) -> Result<(), LightSpeedError> {
+        info!("Delete OAuth2 client [{}]", client_id);
+        let client = self.fetch_client_by_client_id_with_conn(conn, client_id).await?;
+        self.token_repo.delete_by_client_id(conn, client_id).await?;
+        self.consent_repo.delete_by_client_id(conn, client_id).await?;
+        self.client_repo.delete(conn, client).await?;
+        Ok(())
+    }
+
+    /// Validates the authorization request of the logged user and returns the details to show
+    /// in the consent page. If the consent is not required, the request can be approved immediately.
+    pub async fn validate_authorization_request(
+        &self,
+        user_id: i64,
+        request: &OAuth2AuthorizationRequestDto,
+    ) -> Result<OAuth2AuthorizationDetailsDto, LightSpeedError> {
+        self.c3p0
+            .transaction(|conn| async { self.validate_authorization_request_with_conn(conn, user_id, request).await })
+            .await
+    }
+
+    pub async fn validate_authorization_request_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+        request: &OAuth2AuthorizationRequestDto,
+    ) -> Result<OAuth2AuthorizationDetailsDto, LightSpeedError> {
+        let (client, scopes) = self.check_authorization_request_with_conn(conn, request).await?;
+        let consent_required = match self
+            .consent_repo
+            .fetch_by_user_id_and_client_id_optional(conn, user_id, &client.data.client_id)
+            .await?
+        {
+            Some(consent) => !scopes.iter().all(|scope| consent.data.scopes.contains(scope)),
+            None => true,
+        };
+
+        Ok(OAuth2AuthorizationDetailsDto {
+            client_id: client.data.client_id,
+            client_name: client.data.name,
+            scopes: scopes
+                .iter()
+                .filter_map(|scope| {
+                    self.auth_config.oauth2_scopes.iter().find(|scope_config| &scope_config.name == scope)
+                })
+                .map(|scope_config| OAuth2ScopeDto {
+                    name: scope_config.name.clone(),
+                    description: scope_config.description.clone(),
+                })
+                .collect(),
+            consent_required,
+        })
+    }
+
+    /// Approves the authorization request on behalf of the logged user: the consent to the requested scopes
+    /// is saved and a single-use authorization code is issued.
+    pub async fn authorize(
+        &self,
+        user_id: i64,
+        request: &OAuth2AuthorizationRequestDto,
+    ) -> Result<OAuth2AuthorizationResponseDto, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.authorize_with_conn(conn, user_id, request).await }).await
+    }
+
+    pub async fn authorize_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+        request: &OAuth2AuthorizationRequestDto,
+    ) -> Result<OAuth2AuthorizationResponseDto, LightSpeedError> {
+        let (client, scopes) = self.check_authorization_request_with_conn(conn, request).await?;
+        let user = self.auth_repo.fetch_by_id(conn, user_id).await?;
+        if user.data.status != AuthAccountStatus::Active {
+            return Err(LightSpeedError::BadRequest {
+                message: format!("User [{}] not in status Active", user.data.username),
+                code: ErrorCodes::INACTIVE_USER,
+            });
+        }
+
+        self.save_consent_with_conn(conn, user_id, &client.data.client_id, &scopes).await?;
+
+        info!("User [{}] authorized OAuth2 client [{}]", user.data.username, client.data.client_id);
+        let code = self
+            .token_repo
+            .save(
+                conn,
+                NewModel::new(OAuth2TokenData {
+                    token: RandomService::random_string(OAUTH2_TOKEN_LENGTH),
+                    token_type: OAuth2TokenType::AuthorizationCode,
+                    client_id: client.data.client_id,
+                    user_id: Some(user_id),
+                    scopes,
+                    redirect_uri: Some(request.redirect_uri.clone()),
+                    code_challenge: request.code_challenge.clone(),
+                    expire_at_epoch_seconds: self.clock.epoch_seconds()
+                        + (self.auth_config.oauth2_authorization_code_validity_minutes * 60),
+                }),
+            )
+            .await?;
+
+        let mut redirect_url = Url::parse(&request.redirect_uri).map_err(|err| LightSpeedError::BadRequest {
+            message: format!("Invalid OAuth2 redirect_uri: {err:?}"),
+            code: OAUTH2_INVALID_REQUEST,
+        })?;
+        redirect_url.query_pairs_mut().append_pair("code", &code.data.token);
+        if let Some(state) = &request.state {
+            redirect_url.query_pairs_mut().append_pair("state", state);
+        }
+
+        Ok(OAuth2AuthorizationResponseDto {
+            redirect_url: redirect_url.into(),
+            code: code.data.token,
+            state: request.state.clone(),
+        })
+    }
+
+    /// The token endpoint. It issues an access token with the authorization code grant, which always requires
+    /// the PKCE code verifier, or with the client credentials grant, which is allowed only to the confidential
+    /// clients.
+    /// An authorization code is consumed also when the grant fails, so it cannot be tried again.
+    pub async fn token(&self, request: &OAuth2TokenRequestDto) -> Result<OAuth2TokenResponseDto, LightSpeedError> {
+        let result = self.c3p0.transaction(|conn| async { self.token_with_conn(conn, request).await }).await;
+        self.consume_code_of_failed_grant(request, result).await
+    }
+
+    /// The authorization code is deleted with the given connection, so it is restored if the transaction
+    /// is rolled back; see `token`.
+    pub async fn token_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        request: &OAuth2TokenRequestDto,
+    ) -> Result<OAuth2TokenResponseDto, LightSpeedError> {
+        let grant_type = OAuth2GrantType::from_str(&request.grant_type).map_err(|_| LightSpeedError::BadRequest {
+            message: format!("Unsupported OAuth2 grant_type [{}]", request.grant_type),
+            code: OAUTH2_UNSUPPORTED_GRANT_TYPE,
+        })?;
+
+        let client =
+            self.authenticate_client_with_conn(conn, &request.client_id, request.client_secret.as_deref()).await?;
+        if !client.data.grant_types.contains(&grant_type) {
+            return Err(LightSpeedError::BadRequest {
+                message: format!(
+                    "OAuth2 client [{}] cannot use the grant_type [{}]",
+                    client.data.client_id, grant_type
+                ),
+                code: OAUTH2_UNAUTHORIZED_CLIENT,
+            });
+        }
+
+        match grant_type {
+            OAuth2GrantType::AuthorizationCode => self.authorization_code_grant_with_conn(conn, &client, request).await,
+            OAuth2GrantType::ClientCredentials => {
+                let scopes = self.requested_scopes(&client, request.scope.as_deref())?;
+                debug!("Issue an OAuth2 access token to client [{}]", client.data.client_id);
+                let auth = Auth {
+                    username: client.data.client_id.clone(),
+                    roles: client.data.roles.clone(),
+                    ..Default::default()
+                };
+                self.issue_access_token_with_conn(conn, &client, None, auth, scopes).await
+            }
+        }
+    }
+
+    /// The token introspection endpoint as defined by RFC 7662.
+    /// Only the confidential clients (e.g. the resource servers) can introspect the tokens.
+    pub async fn introspect(
+        &self,
+        client_id: &str,
+        client_secret: Option<&str>,
+        access_token: &str,
+    ) -> Result<OAuth2IntrospectionDto, LightSpeedError> {
+        self.c3p0
+            .transaction(|conn| async { self.introspect_with_conn(conn, client_id, client_secret, access_token).await })
+            .await
+    }
+
+    pub async fn introspect_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        client_id: &str,
+        client_secret: Option<&str>,
+        access_token: &str,
+    ) -> Result<OAuth2IntrospectionDto, LightSpeedError> {
+        let client = self.authenticate_client_with_conn(conn, client_id, client_secret).await?;
+        if !client.data.is_confidential() {
+            return Err(LightSpeedError::BadRequest {
+                message: format!("OAuth2 client [{client_id}] is not allowed to introspect the tokens"),
+                code: OAUTH2_UNAUTHORIZED_CLIENT,
+            });
+        }
+
+        Ok(match self.active_access_token_with_conn(conn, access_token).await? {
+            Some((jwt, token)) => OAuth2IntrospectionDto {
+                active: true,
+                scope: Some(token.data.scopes.join(" ")),
+                client_id: Some(token.data.client_id),
+                username: Some(jwt.payload.username),
+                sub: Some(jwt.sub),
+                token_type: Some(BEARER_TOKEN_TYPE.to_owned()),
+                exp: Some(jwt.exp),
+                iat: Some(jwt.iat),
+            },
+            None => OAuth2IntrospectionDto::default(),
+        })
+    }
+
+    /// The token revocation endpoint as defined by RFC 7009. A client can revoke only its own tokens;
+    /// the unknown, invalid and expired tokens are ignored.
+    pub async fn revoke(
+        &self,
+        client_id: &str,
+        client_secret: Option<&str>,
+        access_token: &str,
+    ) -> Result<(), LightSpeedError> {
+        self.c3p0
+            .transaction(|conn| async { self.revoke_with_conn(conn, client_id, client_secret, access_token).await })
+            .await
+    }
+
+    pub async fn revoke_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        client_id: &str,
+        client_secret: Option<&str>,
+        access_token: &str,
+    ) -> Result<(), LightSpeedError> {
+        let client = self.authenticate_client_with_conn(conn, client_id, client_secret).await?;
+        if let Some((_, token)) = self.active_access_token_with_conn(conn, access_token).await? {
+            if token.data.client_id == client.data.client_id {
+                info!("OAuth2 client [{}] revoked an access token", client_id);
+                self.token_repo.delete(conn, token).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the Auth of a valid access token that has not been revoked
+    pub async fn validate_access_token(&self, access_token: &str) -> Result<Auth, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.validate_access_token_with_conn(conn, access_token).await }).await
+    }
+
+    pub async fn validate_access_token_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        access_token: &str,
+    ) -> Result<Auth, LightSpeedError> {
+        match self.active_access_token_with_conn(conn, access_token).await? {
+            Some((jwt, _)) => Ok(jwt.payload),
+            None => {
+                Err(LightSpeedError::InvalidTokenError { message: "The OAuth2 access token is not active".to_owned() })
+            }
+        }
+    }
+
+    /// Returns the consents given by the user to the OAuth2 clients
+    pub async fn fetch_consents_by_user_id(&self, user_id: i64) -> Result<Vec<OAuth2ConsentModel>, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.fetch_consents_by_user_id_with_conn(conn, user_id).await }).await
+    }
+
+    pub async fn fetch_consents_by_user_id_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+    ) -> Result<Vec<OAuth2ConsentModel>, LightSpeedError> {
+        self.consent_repo.fetch_all_by_user_id(conn, user_id).await
+    }
+
+    /// Removes the consent of the user to the client and revokes all the tokens issued to the client for the user
+    pub async fn revoke_consent(&self, user_id: i64, client_id: &str) -> Result<(), LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.revoke_consent_with_conn(conn, user_id, client_id).await }).await
+    }
+
+    pub async fn revoke_consent_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+        client_id: &str,
+    ) -> Result<(), LightSpeedError> {
+        info!("Revoke the consent of user_id [{}] to OAuth2 client [{}]", user_id, client_id);
+        if let Some(consent) =
+            self.consent_repo.fetch_by_user_id_and_client_id_optional(conn, user_id, client_id).await?
+        {
+            self.consent_repo.delete(conn, consent).await?;
+        }
+        self.token_repo.delete_by_user_id_and_client_id(conn, user_id, client_id).await?;
+        Ok(())
+    }
+
+    /// Deletes the expired authorization codes and access tokens of all the clients.
+    /// Returns the number of deleted tokens.
+    pub async fn purge_expired(&self) -> Result<u64, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.purge_expired_with_conn(conn).await }).await
+    }
+
+    pub async fn purge_expired_with_conn(&self, conn: &mut RepoManager::Conn) -> Result<u64, LightSpeedError> {
+        let purged = self.token_repo.delete_expired(conn, self.clock.epoch_seconds()).await?;
+        debug!("Purged [{}] expired OAuth2 tokens", purged);
+        Ok(purged)
+    }
+
+    async fn check_authorization_request_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        request: &OAuth2AuthorizationRequestDto,
+    ) -> Result<(OAuth2ClientModel, Vec<String>), LightSpeedError> {
+        let client =
+            self.client_repo.fetch_by_client_id_optional(conn, &request.client_id).await?.ok_or_else(|| {
+                LightSpeedError::BadRequest {
+                    message: format!("Unknown OAuth2 client [{}]", request.client_id),
+                    code: OAUTH2_INVALID_CLIENT,
+                }
+            })?;
+        if !client.data.redirect_uris.contains(&request.redirect_uri) {
+            return Err(LightSpeedError::BadRequest {
+                message: format!(
+                    "The redirect_uri [{}] is not registered for OAuth2 client [{}]",
+                    request.redirect_uri, request.client_id
+                ),
+                code: OAUTH2_INVALID_REQUEST,
+            });
+        }
+        if request.response_type != "code" {
+            return Err(LightSpeedError::BadRequest {
+                message: format!("Unsupported OAuth2 response_type [{}]", request.response_type),
+                code: OAUTH2_UNSUPPORTED_RESPONSE_TYPE,
+            });
+        }
+        if !client.data.grant_types.contains(&OAuth2GrantType::AuthorizationCode) {
+            return Err(LightSpeedError::BadRequest {
+                message: format!("OAuth2 client [{}] cannot use the authorization code grant", request.client_id),
+                code: OAUTH2_UNAUTHORIZED_CLIENT,
+            });
+        }
+        if request.code_challenge.as_deref().unwrap_or_default().is_empty()
+            || request.code_challenge_method.as_deref() != Some(PKCE_CODE_CHALLENGE_METHOD)
+        {
+            return Err(LightSpeedError::BadRequest {
+                message: format!("The PKCE code challenge with the {PKCE_CODE_CHALLENGE_METHOD} method is required"),
+                code: OAUTH2_INVALID_REQUEST,
+            });
+        }
+        let scopes = self.requested_scopes(&client, request.scope.as_deref())?;
+        Ok((client, scopes))
+    }
+
+    async fn authorization_code_grant_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        client: &OAuth2ClientModel,
+        request: &OAuth2TokenRequestDto,
+    ) -> Result<OAuth2TokenResponseDto, LightSpeedError> {
+        let invalid_grant = |message: &str| LightSpeedError::BadRequest {
+            message: format!("OAuth2 client [{}] - {message}", client.data.client_id),
+            code: OAUTH2_INVALID_GRANT,
+        };
+
+        let code = match request.code.as_deref() {
+            Some(code) => self.token_repo.fetch_by_token_optional(conn, code).await?,
+            None => None,
+        }
+        .filter(|code| code.data.token_type == OAuth2TokenType::AuthorizationCode)
+        .ok_or_else(|| invalid_grant("unknown authorization code"))?;
+
+        // The code can be used only once
+        let code = self.token_repo.delete(conn, code).await?;
+
+        if code.data.client_id != client.data.client_id {
+            return Err(invalid_grant("the authorization code was issued to another client"));
+        }
+        if code.data.expire_at_epoch_seconds < self.clock.epoch_seconds() {
+            return Err(invalid_grant("the authorization code has expired"));
+        }
+        if code.data.redirect_uri != request.redirect_uri {
+            return Err(invalid_grant("the redirect_uri does not match the one of the authorization request"));
+        }
+        let code_verifier = request.code_verifier.as_deref().unwrap_or_default();
+        if code.data.code_challenge.as_deref() != Some(pkce_code_challenge(code_verifier).as_str()) {
+            return Err(invalid_grant("the PKCE code verifier does not match the code challenge"));
+        }
+
+        let user_id = code.data.user_id.ok_or_else(|| invalid_grant("the authorization code has no user"))?;
+        let user = self.auth_repo.fetch_by_id(conn, user_id).await?;
+        if user.data.status != AuthAccountStatus::Active {
+            return Err(invalid_grant("the user is not active"));
+        }
+
+        debug!("Issue an OAuth2 access token to client [{}] for user_id [{}]", client.data.client_id, user_id);
+        let auth = Auth { id: user.id, username: user.data.username, roles: user.data.roles, ..Default::default() };
+        self.issue_access_token_with_conn(conn, client, Some(user_id), auth, code.data.scopes).await
+    }
+
+    async fn consume_code_of_failed_grant<T>(
+        &self,
+        request: &OAuth2TokenRequestDto,
+        result: Result<T, LightSpeedError>,
+    ) -> Result<T, LightSpeedError> {
+        let is_invalid_grant =
+            matches!(&result, Err(LightSpeedError::BadRequest { code, .. }) if *code == OAUTH2_INVALID_GRANT);
+        if let (true, Some(code)) = (is_invalid_grant, request.code.as_deref()) {
+            if let Err(err) = self
+                .c3p0
+                .transaction(|conn| async {
+                    if let Some(code) = self.token_repo.fetch_by_token_optional(conn, code).await? {
+                        if code.data.token_type == OAuth2TokenType::AuthorizationCode {
+                            self.token_repo.delete(conn, code).await?;
+                        }
+                    }
+                    Ok::<_, LightSpeedError>(())
+                })
+                .await
+            {
+                warn!("Cannot delete the authorization code of a failed OAuth2 grant. Err: {:?}", err);
+            }
+        }
+        result
+    }
+
+    async fn issue_access_token_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        client: &OAuth2ClientModel,
+        user_id: Option<i64>,
+        auth: Auth,
+        scopes: Vec<String>,
+    ) -> Result<OAuth2TokenResponseDto, LightSpeedError> {
+        let issued_at = self.clock.epoch_seconds();
+        let expires_in = self.auth_config.oauth2_access_token_validity_minutes * 60;
+
+        let token = self
+            .token_repo
+            .save(
+                conn,
+                NewModel::new(OAuth2TokenData {
+                    token: RandomService::random_string(OAUTH2_TOKEN_LENGTH),
+                    token_type: OAuth2TokenType::AccessToken,
+                    client_id: client.data.client_id.clone(),
+                    user_id,
+                    scopes,
+                    redirect_uri: None,
+                    code_challenge: None,
+                    expire_at_epoch_seconds: issued_at + expires_in,
+                }),
+            )
+            .await?;
+
+        let auth = Auth {
+            session_id: token.data.token.clone(),
+            creation_ts_seconds: issued_at,
+            expiration_ts_seconds: issued_at + expires_in,
+            scope_permissions: Some(self.scope_permissions(&token.data.scopes)),
+            ..auth
+        };
+        let access_token = self.jwt_service.generate_from_token(&JWT {
+            sub: auth.username.clone(),
+            exp: auth.expiration_ts_seconds,
+            iat: issued_at,
+            payload: auth,
+        })?;
+
+        Ok(OAuth2TokenResponseDto {
+            access_token,
+            token_type: BEARER_TOKEN_TYPE.to_owned(),
+            expires_in,
+            scope: token.data.scopes.join(" "),
+        })
+    }
+
+    /// Returns the JWT and the saved token of an access token that is valid, not revoked
+    /// and, if issued for a user, whose user is still active
+    async fn active_access_token_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        access_token: &str,
+    ) -> Result<Option<(JWT<Auth>, OAuth2TokenModel)>, LightSpeedError> {
+        let jwt = match self.jwt_service.parse_token::<Auth>(access_token) {
+            Ok(jwt) => jwt,
+            Err(err) => {
+                debug!("Invalid OAuth2 access token: {:?}", err);
+                return Ok(None);
+            }
+        };
+        let token = match self.token_repo.fetch_by_token_optional(conn, &jwt.payload.session_id).await? {
+            Some(token)
+                if token.data.token_type == OAuth2TokenType::AccessToken
+                    && token.data.expire_at_epoch_seconds >= self.clock.epoch_seconds() =>
+            {
+                token
+            }
+            _ => return Ok(None),
+        };
+        if let Some(user_id) = token.data.user_id {
+            let user = self.auth_repo.fetch_by_id(conn, user_id).await?;
+            if user.data.status != AuthAccountStatus::Active {
+                return Ok(None);
+            }
+        }
+        Ok(Some((jwt, token)))
+    }
+
+    /// Verifies the client credentials. The confidential clients must send their secret,
+    /// while the public clients are identified by the client_id only.
+    async fn authenticate_client_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        client_id: &str,
+        client_secret: Option<&str>,
+    ) -> Result<OAuth2ClientModel, LightSpeedError> {
+        let client = self.client_repo.fetch_by_client_id_optional(conn, client_id).await?;
+        let authenticated = match (&client, client_secret) {
+            (Some(client), Some(client_secret)) => match &client.data.client_secret_hash {
+                Some(client_secret_hash) => self.password_service.verify_match(client_secret, client_secret_hash)?,
+                None => false,
+            },
+            (Some(client), None) => !client.data.is_confidential(),
+            (None, _) => false,
+        };
+        match client {
+            Some(client) if authenticated => Ok(client),
+            _ => Err(LightSpeedError::BadRequest {
+                message: format!("OAuth2 client [{client_id}] authentication failed"),
+                code: OAUTH2_INVALID_CLIENT,
+            }),
+        }
+    }
+
+    /// Parses the space separated scopes of a request. All the scopes of the client are requested if none is given.
+    fn requested_scopes(
+        &self,
+        client: &OAuth2ClientModel,
+        scope: Option<&str>,
+    ) -> Result<Vec<String>, LightSpeedError> {
+        let mut scopes: Vec<String> = vec![];
+        for scope in scope.unwrap_or_default().split_whitespace() {
+            if !scopes.iter().any(|other| other == scope) {
+                scopes.push(scope.to_owned());
+            }
+        }
+        if scopes.is_empty() {
+            scopes = client.data.scopes.clone();
+        }
+
+        for scope in &scopes {
+            if !client.data.scopes.contains(scope)
+                || !self.auth_config.oauth2_scopes.iter().any(|scope_config| &scope_config.name == scope)
+            {
+                return Err(LightSpeedError::BadRequest {
+                    message: format!("OAuth2 client [{}] cannot request the scope [{scope}]", client.data.client_id),
+                    code: OAUTH2_INVALID_SCOPE,
+                });
+            }
+        }
+        Ok(scopes)
+    }
+
+    /// Returns the permissions granted by the scopes
+    fn scope_permissions(&self, scopes: &[String]) -> Vec<String> {
+        let mut permissions: Vec<String> = vec![];
+        for scope_config in
+            self.auth_config.oauth2_scopes.iter().filter(|scope_config| scopes.contains(&scope_config.name))
+        {
+            for permission in &scope_config.permissions {
+                if !permissions.contains(permission) {
+                    permissions.push(permission.clone());
+                }
+            }
+        }
+        permissions
+    }
+
+    async fn save_consent_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+        client_id: &str,
+        scopes: &[String],
+    ) -> Result<OAuth2ConsentModel, LightSpeedError> {
+        let updated_date_epoch_seconds = self.clock.epoch_seconds();
+        match self.consent_repo.fetch_by_user_id_and_client_id_optional(conn, user_id, client_id).await? {
+            Some(mut consent) => {
+                for scope in scopes {
+                    if !consent.data.scopes.contains(scope) {
+                        consent.data.scopes.push(scope.clone());
+                    }
+                }
+                consent.data.updated_date_epoch_seconds = updated_date_epoch_seconds;
+                self.consent_repo.update(conn, consent).await
+            }
+            None => {
+                self.consent_repo
+                    .save(
+                        conn,
+                        NewModel::new(OAuth2ConsentData {
+                            user_id,
+                            client_id: client_id.to_owned(),
+                            scopes: scopes.to_vec(),
+                            updated_date_epoch_seconds,
+                        }),
+                    )
+                    .await
+            }
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use crate::config::AuthConfig;
+use crate::dto::oidc_dto::{OidcAuthorizationRequestDto, OidcCallbackDto};
+use crate::model::external_identity::{ExternalIdentityData, ExternalIdentityModel};
+use crate::repository::{AuthAccountRepository, AuthRepositoryManager, ExternalIdentityRepository};
+use crate::service::account_event::{AccountEvent, AccountEventPublisher};
+use crate::service::auth_account::{AuthAccountService, LoginOutcome};
+use crate::service::oidc_client::{OidcClient, OidcIdentity};
+use c3p0::*;
+use lightspeed_core::clock::Clock;
+use lightspeed_core::error::{ErrorDetails, LightSpeedError};
+use lightspeed_core::service::validator::{Validator, ERR_NOT_UNIQUE, ERR_VALUE_REQUIRED};
+use log::*;
+use std::sync::Arc;
+
+pub const ERR_EMAIL_NOT_VERIFIED: &str = "EMAIL_NOT_VERIFIED";
+
+/// The social login with the OpenID Connect providers of the AuthConfig.
+/// The identities are linked to the accounts by provider and subject. At the first login of an identity
+/// a new account is created with the default roles or, if the provider allows it, the identity is linked
+/// to the existing account with the same verified email.
+/// The first login is rejected if the provider has not verified the email of the identity.
+///
+/// The AccountCreated and IdentityLinked events are published after the commit only by `login` and `link_identity`;
+/// the callers of the `_with_conn` methods are responsible to publish them, see `AccountEventPublisher`.
+#[derive(Clone)]
+pub struct OidcService<RepoManager: AuthRepositoryManager> {
+    c3p0: RepoManager::C3P0,
+    oidc_client: OidcClient,
+    auth_account_service: Arc<AuthAccountService<RepoManager>>,
+    account_event_publisher: Arc<AccountEventPublisher<RepoManager>>,
+    auth_repo: RepoManager::AuthAccountRepo,
+    external_identity_repo: RepoManager::ExternalIdentityRepo,
+    clock: Arc<dyn Clock>,
+}
+
+impl<RepoManager: AuthRepositoryManager> OidcService<RepoManager> {
+    pub fn new(
+        c3p0: RepoManager::C3P0,
+        auth_config: &AuthConfig,
+        auth_account_service: Arc<AuthAccountService<RepoManager>>,
+        account_event_publisher: Arc<AccountEventPublisher<RepoManager>>,
+        auth_repo: RepoManager::AuthAccountRepo,
+        external_identity_repo: RepoManager::ExternalIdentityRepo,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        OidcService {
+            c3p0,
+            oidc_client: OidcClient::new(auth_config, clock.clone()),
+            auth_account_service,
+            account_event_publisher,
+            auth_repo,
+            external_identity_repo,
+            clock,
+        }
+    }
+
+    /// Starts the login with the given provider. The user has to be redirected to the `authorization_url`
+    /// of the returned request, which has to be kept to complete the login.
+    pub async fn authorization_request(&self, provider: &str) -> Result<OidcAuthorizationRequestDto, LightSpeedError> {
+        self.oidc_client.authorization_request(provider).await
+    }
+
+    /// Completes the login when the provider redirects the user back to the redirect URL
+    pub async fn login(
+        &self,
+        request: &OidcAuthorizationRequestDto,
+        callback: &OidcCallbackDto,
+    ) -> Result<LoginOutcome, LightSpeedError> {
+        let identity = self.oidc_client.exchange_code(request, callback).await?;
+        let (outcome, events) = self
+            .c3p0
+            .transaction(|conn| async {
+                let mut events = vec![];
+                let outcome = self.login_with_identity_and_events_with_conn(conn, &identity, &mut events).await?;
+                Ok::<_, LightSpeedError>((outcome, events))
+            })
+            .await?;
+        self.publish_after_commit(&events).await;
+        Ok(outcome)
+    }
+
+    pub async fn login_with_identity_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        identity: &OidcIdentity,
+    ) -> Result<LoginOutcome, LightSpeedError> {
+        self.login_with_identity_and_events_with_conn(conn, identity, &mut vec![]).await
+    }
+
+    /// Links the identity authenticated by the provider to an existing account, e.g. to the one of the logged user
+    pub async fn link_identity(
+        &self,
+        user_id: i64,
+        request: &OidcAuthorizationRequestDto,
+        callback: &OidcCallbackDto,
+    ) -> Result<ExternalIdentityModel, LightSpeedError> {
+        let identity = self.oidc_client.exchange_code(request, callback).await?;
+        let (external_identity, events) = self
+            .c3p0
+            .transaction(|conn| async {
+                let mut events = vec![];
+                let external_identity =
+                    self.link_identity_and_events_with_conn(conn, user_id, &identity, &mut events).await?;
+                Ok::<_, LightSpeedError>((external_identity, events))
+            })
+            .await?;
+        self.publish_after_commit(&events).await;
+        Ok(external_identity)
+    }
+
+    pub async fn link_identity_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+        identity: &OidcIdentity,
+    ) -> Result<ExternalIdentityModel, LightSpeedError> {
+        self.link_identity_and_events_with_conn(conn, user_id, identity, &mut vec![]).await
+    }
+
+    async fn link_identity_and_events_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+        identity: &OidcIdentity,
+        events: &mut Vec<AccountEvent>,
+    ) -> Result<ExternalIdentityModel, LightSpeedError> {
+        let existing_identity = self
+            .external_identity_repo
+            .fetch_by_provider_and_subject_optional(conn, &identity.provider, &identity.subject)
+            .await?;
+        Validator::validate(&|error_details: &mut ErrorDetails| {
+            if let Some(existing_identity) = &existing_identity {
+                if existing_identity.data.user_id != user_id {
+                    error_details.add_detail("subject", ERR_NOT_UNIQUE);
+                }
+            }
+            Ok(())
+        })?;
+
+        match existing_identity {
+            Some(existing_identity) => Ok(existing_identity),
+            None => {
+                self.auth_repo.fetch_by_id(conn, user_id).await?;
+                self.save_identity_with_conn(conn, user_id, identity, events).await
+            }
+        }
+    }
+
+    pub async fn fetch_identities_by_user_id(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<ExternalIdentityModel>, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.fetch_identities_by_user_id_with_conn(conn, user_id).await }).await
+    }
+
+    pub async fn fetch_identities_by_user_id_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+    ) -> Result<Vec<ExternalIdentityModel>, LightSpeedError> {
+        self.external_identity_repo.fetch_all_by_user_id(conn, user_id).await
+    }
+
+    /// Removes the identities of the given provider linked to the account
+    pub async fn unlink_identities(&self, user_id: i64, provider: &str) -> Result<u64, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.unlink_identities_with_conn(conn, user_id, provider).await }).await
+    }
+
+    pub async fn unlink_identities_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+        provider: &str,
+    ) -> Result<u64, LightSpeedError> {
+        info!("Unlink the identities of OIDC provider [{}] from user_id [{}]", provider, user_id);
+        let mut count = 0;
+        for external_identity in self.external_identity_repo.fetch_all_by_user_id(conn, user_id).await? {
+            if external_identity.data.provider == provider {
+                self.external_identity_repo.delete(conn, external_identity).await?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Logs in the user of the identity. The events to publish after the commit are added to `events`.
+    async fn login_with_identity_and_events_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        identity: &OidcIdentity,
+        events: &mut Vec<AccountEvent>,
+    ) -> Result<LoginOutcome, LightSpeedError> {
+        debug!("OIDC login with subject [{}] of provider [{}]", identity.subject, identity.provider);
+        let user_id = match self
+            .external_identity_repo
+            .fetch_by_provider_and_subject_optional(conn, &identity.provider, &identity.subject)
+            .await?
+        {
+            Some(external_identity) => external_identity.data.user_id,
+            None => {
+                let user_id = self.find_or_create_account_with_conn(conn, identity, events).await?;
+                self.save_identity_with_conn(conn, user_id, identity, events).await?.data.user_id
+            }
+        };
+        self.auth_account_service.authenticate_external_user_with_conn(conn, user_id).await
+    }
+
+    async fn find_or_create_account_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        identity: &OidcIdentity,
+        events: &mut Vec<AccountEvent>,
+    ) -> Result<i64, LightSpeedError> {
+        Validator::validate(&|error_details: &mut ErrorDetails| {
+            if identity.email.is_none() {
+                error_details.add_detail("email", ERR_VALUE_REQUIRED);
+            } else if !identity.email_verified {
+                error_details.add_detail("email", ERR_EMAIL_NOT_VERIFIED);
+            }
+            Ok(())
+        })?;
+        let email = identity.email.as_deref().unwrap_or_default();
+
+        let provider = self.oidc_client.provider(&identity.provider)?;
+        if provider.link_accounts_by_verified_email {
+            if let Some(user) = self.auth_repo.fetch_by_email_optional(conn, email).await? {
+                info!(
+                    "Link subject [{}] of OIDC provider [{}] to the user_id [{}] with the same verified email",
+                    identity.subject, identity.provider, user.id
+                );
+                return Ok(user.id);
+            }
+        }
+
+        let user = self
+            .auth_account_service
+            .create_external_user_with_conn(conn, identity.preferred_username.as_deref(), email)
+            .await?;
+        let user_id = user.id;
+        events.push(AccountEvent::AccountCreated { user: user.into(), activation_token: None });
+        Ok(user_id)
+    }
+
+    async fn save_identity_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+        identity: &OidcIdentity,
+        events: &mut Vec<AccountEvent>,
+    ) -> Result<ExternalIdentityModel, LightSpeedError> {
+        info!("Link subject [{}] of OIDC provider [{}] to user_id [{}]", identity.subject, identity.provider, user_id);
+        let external_identity = self
+            .external_identity_repo
+            .save(
+                conn,
+                NewModel::new(ExternalIdentityData {
+                    user_id,
+                    provider: identity.provider.clone(),
+                    subject: identity.subject.clone(),
+                    email: identity.email.clone(),
+                    created_date_epoch_seconds: self.clock.epoch_seconds(),
+                }),
+            )
+            .await?;
+        let event = AccountEvent::IdentityLinked { user_id, provider: identity.provider.clone() };
+        self.account_event_publisher.publish_with_conn(conn, &event).await?;
+        events.push(event);
+        Ok(external_identity)
+    }
+
+    async fn publish_after_commit(&self, events: &[AccountEvent]) {
+        for event in events {
+            self.account_event_publisher.publish_after_commit(event).await;
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use crate::config::{AuthConfig, OidcProviderConfig};
+use crate::dto::oidc_dto::{OidcAuthorizationRequestDto, OidcCallbackDto};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use lightspeed_core::clock::Clock;
+use lightspeed_core::error::{ErrorCodes, LightSpeedError};
+use lightspeed_core::service::random::RandomService;
+use log::*;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+pub const OIDC_STATE_LENGTH: usize = 32;
+pub const OIDC_NONCE_LENGTH: usize = 32;
+pub const PKCE_CODE_VERIFIER_LENGTH: usize = 64;
+pub const OIDC_HTTP_TIMEOUT_SECONDS: u64 = 10;
+
+/// An identity whose ID token has been verified
+#[derive(Debug, Clone)]
+pub struct OidcIdentity {
+    pub provider: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub preferred_username: Option<String>,
+}
+
+#[derive(Clone, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    exp: i64,
+    nonce: Option<String>,
+    azp: Option<String>,
+    email: Option<String>,
+    email_verified: Option<Value>,
+    preferred_username: Option<String>,
+}
+
+/// The relying party side of the OpenID Connect authorization code flow with PKCE.
+/// The discovery documents and the JWKS of the providers are fetched at the first use and cached;
+/// the JWKS is fetched again when an ID token is signed with an unknown key, to follow the key rotations.
+#[derive(Clone)]
+pub struct OidcClient {
+    http_client: reqwest::Client,
+    providers: HashMap<String, OidcProviderConfig>,
+    allowed_clock_skew_seconds: i64,
+    metadata: Arc<RwLock<HashMap<String, Arc<ProviderMetadata>>>>,
+    jwks: Arc<RwLock<HashMap<String, Arc<JwkSet>>>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl OidcClient {
+    pub fn new(auth_config: &AuthConfig, clock: Arc<dyn Clock>) -> Self {
+        OidcClient {
+            http_client: reqwest::Client::new(),
+            providers: auth_config
+                .oidc_providers
+                .iter()
+                .map(|provider| (provider.name.clone(), provider.clone()))
+                .collect(),
+            allowed_clock_skew_seconds: auth_config.oidc_allowed_clock_skew_seconds,
+            metadata: Default::default(),
+            jwks: Default::default(),
+            clock,
+        }
+    }
+
+    pub fn provider(&self, provider_name: &str) -> Result<&OidcProviderConfig, LightSpeedError> {
+        self.providers.get(provider_name).ok_or_else(|| LightSpeedError::BadRequest {
+            message: format!("Unknown OIDC provider [{provider_name}]"),
+            code: ErrorCodes::NOT_FOUND,
+        })
+    }
+
+    /// Starts the login creating a new random state, nonce and PKCE code verifier
+    pub async fn authorization_request(
+        &self,
+        provider_name: &str,
+    ) -> Result<OidcAuthorizationRequestDto, LightSpeedError> {
+        let provider = self.provider(provider_name)?;
+        let metadata = self.metadata(provider).await?;
+
+        let state = RandomService::random_string(OIDC_STATE_LENGTH);
+        let nonce = RandomService::random_string(OIDC_NONCE_LENGTH);
+        let pkce_code_verifier = RandomService::random_string(PKCE_CODE_VERIFIER_LENGTH);
+
+        let mut authorization_url =
+            Url::parse(&metadata.authorization_endpoint).map_err(|err| LightSpeedError::InternalServerError {
+                message: format!("OIDC provider [{provider_name}] - invalid authorization endpoint: {err:?}"),
+            })?;
+        authorization_url
+            .query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &provider.client_id)
+            .append_pair("redirect_uri", &provider.redirect_url)
+            .append_pair("scope", &provider.scopes.join(" "))
+            .append_pair("state", &state)
+            .append_pair("nonce", &nonce)
+            .append_pair("code_challenge", &pkce_code_challenge(&pkce_code_verifier))
+            .append_pair("code_challenge_method", "S256");
+
+        Ok(OidcAuthorizationRequestDto {
+            provider: provider_name.to_owned(),
+            authorization_url: authorization_url.into(),
+            state,
+            nonce,
+            pkce_code_verifier,
+        })
+    }
+
+    /// Verifies the state received on the redirect URL, exchanges the authorization code
+    /// and returns the identity of the verified ID token
+    pub async fn exchange_code(
+        &self,
+        request: &OidcAuthorizationRequestDto,
+        callback: &OidcCallbackDto,
+    ) -> Result<OidcIdentity, LightSpeedError> {
+        if request.state.is_empty() || request.state != callback.state {
+            return Err(LightSpeedError::InvalidTokenError {
+                message: "The OIDC state does not match the authorization request".to_owned(),
+            });
+        }
+
+        let provider = self.provider(&request.provider)?;
+        let metadata = self.metadata(provider).await?;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", callback.code.as_str()),
+            ("redirect_uri", provider.redirect_url.as_str()),
+            ("code_verifier", request.pkce_code_verifier.as_str()),
+        ];
+        let mut token_request =
+            self.http_client.post(&metadata.token_endpoint).timeout(Duration::from_secs(OIDC_HTTP_TIMEOUT_SECONDS));
+        match &provider.client_secret {
+            Some(client_secret) => token_request = token_request.basic_auth(&provider.client_id, Some(client_secret)),
+            None => form.push(("client_id", provider.client_id.as_str())),
+        };
+
+        let response = token_request.form(&form).send().await.map_err(|err| LightSpeedError::InternalServerError {
+            message: format!("OIDC provider [{}] - token request failed: {err:?}", provider.name),
+        })?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(LightSpeedError::InvalidTokenError {
+                message: format!(
+                    "OIDC provider [{}] - code exchange failed with status {status}: {body}",
+                    provider.name
+                ),
+            });
+        }
+        let token_response: TokenResponse =
+            response.json().await.map_err(|err| LightSpeedError::InternalServerError {
+                message: format!("OIDC provider [{}] - invalid token response: {err:?}", provider.name),
+            })?;
+
+        let id_token = token_response.id_token.ok_or_else(|| LightSpeedError::InvalidTokenError {
+            message: format!("OIDC provider [{}] - the token response contains no ID token", provider.name),
+        })?;
+        self.verify_id_token(&request.provider, &id_token, &request.nonce).await
+    }
+
+    /// Verifies the signature, the issuer, the audience, the expiration and the nonce of an ID token
+    pub async fn verify_id_token(
+        &self,
+        provider_name: &str,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<OidcIdentity, LightSpeedError> {
+        let provider = self.provider(provider_name)?;
+        let metadata = self.metadata(provider).await?;
+
+        let header = jsonwebtoken::decode_header(id_token)
+            .map_err(|err| LightSpeedError::InvalidTokenError { message: format!("Invalid ID token: {err}") })?;
+        if matches!(header.alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+            return Err(LightSpeedError::InvalidTokenError {
+                message: format!("ID token signature algorithm [{:?}] not supported", header.alg),
+            });
+        }
+
+        let jwk = self.signing_key(provider, &metadata, header.kid.as_deref()).await?;
+        let decoding_key = DecodingKey::from_jwk(&jwk)
+            .map_err(|err| LightSpeedError::InvalidTokenError { message: format!("Invalid JWK: {err}") })?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.validate_exp = false;
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&provider.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        let claims = jsonwebtoken::decode::<IdTokenClaims>(id_token, &decoding_key, &validation)
+            .map_err(|err| LightSpeedError::InvalidTokenError { message: format!("Invalid ID token: {err}") })?
+            .claims;
+
+        if claims.exp + self.allowed_clock_skew_seconds < self.clock.epoch_seconds() {
+            return Err(LightSpeedError::ExpiredTokenError { message: "The ID token has expired".to_owned() });
+        }
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(LightSpeedError::InvalidTokenError {
+                message: "The ID token nonce does not match the authorization request".to_owned(),
+            });
+        }
+        if let Some(azp) = &claims.azp {
+            if azp != &provider.client_id {
+                return Err(LightSpeedError::InvalidTokenError {
+                    message: format!("The ID token has been issued to another party [{azp}]"),
+                });
+            }
+        }
+
+        Ok(OidcIdentity {
+            provider: provider.name.clone(),
+            subject: claims.sub,
+            email: claims.email,
+            email_verified: matches!(&claims.email_verified, Some(Value::Bool(true)))
+                || matches!(&claims.email_verified, Some(Value::String(verified)) if verified == "true"),
+            preferred_username: claims.preferred_username,
+        })
+    }
+
+    async fn metadata(&self, provider: &OidcProviderConfig) -> Result<Arc<ProviderMetadata>, LightSpeedError> {
+        if let Some(metadata) = self.metadata.read().await.get(&provider.name) {
+            return Ok(metadata.clone());
+        }
+
+        let issuer_url = provider.issuer_url.trim_end_matches('/');
+        debug!("Fetch the discovery document of OIDC provider [{}]", provider.name);
+        let metadata: ProviderMetadata =
+            self.get_json(&provider.name, &format!("{issuer_url}/.well-known/openid-configuration")).await?;
+        if metadata.issuer.trim_end_matches('/') != issuer_url {
+            return Err(LightSpeedError::InternalServerError {
+                message: format!(
+                    "OIDC provider [{}] - the discovery document issuer [{}] does not match the configured one",
+                    provider.name, metadata.issuer
+                ),
+            });
+        }
+
+        let metadata = Arc::new(metadata);
+        self.metadata.write().await.insert(provider.name.clone(), metadata.clone());
+        Ok(metadata)
+    }
+
+    async fn signing_key(
+        &self,
+        provider: &OidcProviderConfig,
+        metadata: &ProviderMetadata,
+        kid: Option<&str>,
+    ) -> Result<Jwk, LightSpeedError> {
+        if let Some(jwks) = self.jwks.read().await.get(&provider.name) {
+            if let Some(jwk) = find_key(jwks, kid) {
+                return Ok(jwk);
+            }
+        }
+
+        debug!("Fetch the JWKS of OIDC provider [{}]", provider.name);
+        let jwks: Arc<JwkSet> = Arc::new(self.get_json(&provider.name, &metadata.jwks_uri).await?);
+        self.jwks.write().await.insert(provider.name.clone(), jwks.clone());
+
+        find_key(&jwks, kid).ok_or_else(|| LightSpeedError::InvalidTokenError {
+            message: format!("OIDC provider [{}] - no signing key found with kid [{kid:?}]", provider.name),
+        })
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, provider_name: &str, url: &str) -> Result<T, LightSpeedError> {
+        let response = self
+            .http_client
+            .get(url)
+            .timeout(Duration::from_secs(OIDC_HTTP_TIMEOUT_SECONDS))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| LightSpeedError::InternalServerError {
+                message: format!("OIDC provider [{provider_name}] - request to [{url}] failed: {err:?}"),
+            })?;
+        response.json().await.map_err(|err| LightSpeedError::InternalServerError {
+            message: format!("OIDC provider [{provider_name}] - invalid response from [{url}]: {err:?}"),
+        })
+    }
+}
+
+/// Returns the key with the given kid or, if the ID token has no kid, the only key of the set
+fn find_key(jwks: &JwkSet, kid: Option<&str>) -> Option<Jwk> {
+    match kid {
+        Some(kid) => jwks.find(kid).cloned(),
+        None if jwks.keys.len() == 1 => jwks.keys.first().cloned(),
+        None => None,
+    }
+}
+
+/// Returns the S256 PKCE code challenge of the given code verifier
+pub fn pkce_code_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use lightspeed_core::clock::SystemClock;
+
+    #[test]
+    fn should_generate_the_s256_code_challenge() {
+        assert_eq!(
+            "gLNfosczeXnLWIkXP46_tPD0GqTkGt-xQMUJQdxDJW8",
+            pkce_code_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFIVqBk")
+        );
+    }
+
+    #[tokio::test]
+    async fn should_fail_for_unknown_providers() {
+        let oidc_client = OidcClient::new(&AuthConfig::default(), SystemClock::shared());
+
+        match oidc_client.authorization_request("unknown").await {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ErrorCodes::NOT_FOUND, code),
+            _ => panic!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_reject_a_state_that_does_not_match() {
+        let oidc_client = OidcClient::new(&AuthConfig::default(), SystemClock::shared());
+        let request = OidcAuthorizationRequestDto {
+            provider: "unknown".to_owned(),
+            authorization_url: "".to_owned(),
+            state: "state".to_owned(),
+            nonce: "nonce".to_owned(),
+            pkce_code_verifier: "verifier".to_owned(),
+        };
+
+        let result = oidc_client
+            .exchange_code(&request, &OidcCallbackDto { state: "other".to_owned(), code: "code".to_owned() })
+            .await;
+        assert!(matches!(result, Err(LightSpeedError::InvalidTokenError { .. })));
+    }
+}
@@ -1,26 +1,77 @@
-use bcrypt::{hash, verify};
+use crate::config::AuthConfig;
+use crate::service::password_hasher::{
+    Argon2PasswordHasher, BcryptPasswordHasher, PasswordHashAlgorithm, PasswordHasher, Pbkdf2Sha256PasswordHasher,
+    SaltedShaAlgorithm, SaltedShaPasswordHasher, PBKDF2_SHA256_DEFAULT_ITERATIONS,
+};
 use lightspeed_core::error::LightSpeedError;
+use std::sync::Arc;
 
+/// Hashes the new passwords with the algorithm configured in the AuthConfig.
+/// The existing hashes are verified by the hasher that recognizes their prefix,
+/// so the hashes produced with a previous policy, or imported from other systems
+/// in the PBKDF2-SHA256 and salted SHA formats, are still accepted.
 #[derive(Clone)]
 pub struct PasswordCodecService {
-    hash_cost: u32,
+    current_hasher: Arc<dyn PasswordHasher>,
+    hashers: Vec<Arc<dyn PasswordHasher>>,
 }
 
 impl PasswordCodecService {
-    /// Cost needs to be between 4 and 31
-    /// Java bcrypt lib uses 10 by default
-    pub fn new(hash_cost: u32) -> Self {
-        PasswordCodecService { hash_cost }
+    pub fn new(auth_config: &AuthConfig) -> Self {
+        let argon2: Arc<dyn PasswordHasher> = Arc::new(Argon2PasswordHasher::new(
+            auth_config.argon2_memory_cost_kib,
+            auth_config.argon2_time_cost,
+            auth_config.argon2_parallelism,
+        ));
+        let bcrypt: Arc<dyn PasswordHasher> =
+            Arc::new(BcryptPasswordHasher::new(auth_config.bcrypt_password_hash_cost));
+
+        let current_hasher = match auth_config.password_hash_algorithm {
+            PasswordHashAlgorithm::Argon2id => argon2.clone(),
+            PasswordHashAlgorithm::Bcrypt => bcrypt.clone(),
+        };
+
+        PasswordCodecService {
+            current_hasher,
+            hashers: vec![
+                argon2,
+                bcrypt,
+                Arc::new(Pbkdf2Sha256PasswordHasher::new(PBKDF2_SHA256_DEFAULT_ITERATIONS)),
+                Arc::new(SaltedShaPasswordHasher::new(SaltedShaAlgorithm::Sha512)),
+            ],
+        }
+    }
+
+    /// Registers an additional hasher used to verify the existing hashes.
+    /// The new passwords are always hashed with the configured algorithm.
+    pub fn with_hasher<H: 'static + PasswordHasher>(mut self, hasher: H) -> Self {
+        self.hashers.push(Arc::new(hasher));
+        self
+    }
+
+    /// Returns true if the hash format is recognized by one of the registered hashers
+    /// and the hash can be fully parsed by it
+    pub fn is_supported(&self, hash: &str) -> bool {
+        self.hashers.iter().find(|hasher| hasher.can_verify(hash)).is_some_and(|hasher| hasher.is_valid(hash))
     }
 
     pub fn verify_match(&self, plain_password: &str, hash: &str) -> Result<bool, LightSpeedError> {
-        verify(plain_password, hash)
-            .map_err(|err| LightSpeedError::PasswordEncryptionError { message: format!("{err:?}") })
+        match self.hashers.iter().find(|hasher| hasher.can_verify(hash)) {
+            Some(hasher) => hasher.verify(plain_password, hash),
+            None => Err(LightSpeedError::PasswordEncryptionError {
+                message: "The password hash algorithm is not supported".to_owned(),
+            }),
+        }
     }
 
     pub fn hash_password(&self, plain_password: &str) -> Result<String, LightSpeedError> {
-        hash(plain_password, self.hash_cost)
-            .map_err(|err| LightSpeedError::PasswordEncryptionError { message: format!("{err:?}") })
+        self.current_hasher.hash(plain_password)
+    }
+
+    /// Returns true if the hash has been produced with a different algorithm
+    /// or with weaker parameters than the configured ones
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        !self.current_hasher.can_verify(hash) || self.current_hasher.needs_rehash(hash)
     }
 }
 
@@ -29,21 +80,33 @@ pub mod test {
 
     use super::*;
 
+    fn new_password_codec(password_hash_algorithm: PasswordHashAlgorithm) -> PasswordCodecService {
+        PasswordCodecService::new(&AuthConfig {
+            password_hash_algorithm,
+            bcrypt_password_hash_cost: 4,
+            argon2_memory_cost_kib: 64,
+            argon2_time_cost: 1,
+            ..Default::default()
+        })
+    }
+
     #[test]
     fn should_encrypt_and_decrypt() -> Result<(), LightSpeedError> {
-        let password_codec = PasswordCodecService::new(4);
-        let plain_pass = "wrwdsdfast346n534dfsg5353";
-        let hash = password_codec.hash_password(plain_pass)?;
+        for algorithm in [PasswordHashAlgorithm::Argon2id, PasswordHashAlgorithm::Bcrypt] {
+            let password_codec = new_password_codec(algorithm);
+            let plain_pass = "wrwdsdfast346n534dfsg5353";
+            let hash = password_codec.hash_password(plain_pass)?;
 
-        assert!(password_codec.verify_match(plain_pass, &hash)?);
-        assert!(!password_codec.verify_match(plain_pass, &password_codec.hash_password("asfasfasxcva")?)?);
+            assert!(password_codec.verify_match(plain_pass, &hash)?);
+            assert!(!password_codec.verify_match(plain_pass, &password_codec.hash_password("asfasfasxcva")?)?);
+        }
 
         Ok(())
     }
 
     #[test]
     fn should_decrypt_admin() -> Result<(), LightSpeedError> {
-        let password_codec = PasswordCodecService::new(4);
+        let password_codec = new_password_codec(PasswordHashAlgorithm::Bcrypt);
         let plain_pass = "admin";
         let hash = &password_codec.hash_password(plain_pass)?;
         let java_bcrypt_hash = r#"$2a$10$TkWSZIawgD9tjkmAV2GjGOt30FQktiTlpZTIHbxatakOHf4G0.aA."#;
@@ -53,4 +116,60 @@ pub mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn should_verify_the_hashes_of_all_the_algorithms() -> Result<(), LightSpeedError> {
+        let argon2_codec = new_password_codec(PasswordHashAlgorithm::Argon2id);
+        let bcrypt_codec = new_password_codec(PasswordHashAlgorithm::Bcrypt);
+        let plain_pass = "admin";
+
+        let argon2_hash = argon2_codec.hash_password(plain_pass)?;
+        let bcrypt_hash = bcrypt_codec.hash_password(plain_pass)?;
+        assert!(argon2_hash.starts_with("$argon2id$"));
+
+        assert!(argon2_codec.verify_match(plain_pass, &bcrypt_hash)?);
+        assert!(bcrypt_codec.verify_match(plain_pass, &argon2_hash)?);
+        assert!(argon2_codec.verify_match(plain_pass, "unknown_hash").is_err());
+        assert!(!argon2_codec.is_supported("unknown_hash"));
+        assert!(!argon2_codec.is_supported("pbkdf2_sha256$1000$seasalt$"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_verify_and_rehash_legacy_hashes() -> Result<(), LightSpeedError> {
+        let password_codec = new_password_codec(PasswordHashAlgorithm::Argon2id);
+        let django_hash = "pbkdf2_sha256$1000$seasalt$YIWkt6M1JFXrHg5s0jZjBSc7C2Cz6QvchSJ0h8Y+i7c=";
+        let ldap_hash = "{SSHA}yI6cZwQadOA1e+/f+T+H3eCQQhRzYWx0";
+
+        for hash in [django_hash, ldap_hash] {
+            assert!(password_codec.is_supported(hash));
+            assert!(password_codec.verify_match("password", hash)?);
+            assert!(!password_codec.verify_match("wrong", hash)?);
+            assert!(password_codec.needs_rehash(hash));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_need_rehash_if_the_hash_is_outdated() -> Result<(), LightSpeedError> {
+        let password_codec = new_password_codec(PasswordHashAlgorithm::Argon2id);
+        let java_bcrypt_hash = r#"$2a$10$TkWSZIawgD9tjkmAV2GjGOt30FQktiTlpZTIHbxatakOHf4G0.aA."#;
+
+        assert!(password_codec.needs_rehash(java_bcrypt_hash));
+        assert!(!password_codec.needs_rehash(&password_codec.hash_password("admin")?));
+
+        let stronger_codec = PasswordCodecService::new(&AuthConfig {
+            argon2_memory_cost_kib: 128,
+            argon2_time_cost: 1,
+            ..Default::default()
+        });
+        assert!(stronger_codec.needs_rehash(&password_codec.hash_password("admin")?));
+
+        let bcrypt_codec = new_password_codec(PasswordHashAlgorithm::Bcrypt);
+        assert!(!bcrypt_codec.needs_rehash(java_bcrypt_hash));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,503 @@
+use argon2::password_hash::{PasswordHash, PasswordHasher as _, PasswordVerifier as _, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose, Engine as _};
+use lightspeed_core::error::LightSpeedError;
+use rand::distributions::{Alphanumeric, DistString};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+
+/// The iterations used by Django 4.2 for the PBKDF2-SHA256 hashes
+pub const PBKDF2_SHA256_DEFAULT_ITERATIONS: u32 = 600_000;
+
+/// The password hashing algorithms that can be used to hash new passwords
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PasswordHashAlgorithm {
+    Argon2id,
+    Bcrypt,
+}
+
+/// A password hashing algorithm.
+/// A hasher recognizes its own hashes from their prefix (e.g. "$2b$" or "$argon2id$").
+pub trait PasswordHasher: Send + Sync {
+    /// Returns true if the hash has been produced by this algorithm
+    fn can_verify(&self, hash: &str) -> bool;
+
+    /// Returns true if the hash is well formed, that is all its parts can be parsed
+    /// and the digest has the expected length
+    fn is_valid(&self, hash: &str) -> bool;
+
+    fn verify(&self, plain_password: &str, hash: &str) -> Result<bool, LightSpeedError>;
+
+    fn hash(&self, plain_password: &str) -> Result<String, LightSpeedError>;
+
+    /// Returns true if the hash has been produced with weaker parameters than the ones of this hasher
+    fn needs_rehash(&self, hash: &str) -> bool;
+}
+
+/// Bcrypt hasher. It produces modular crypt format hashes like "$2b$10$...".
+#[derive(Clone)]
+pub struct BcryptPasswordHasher {
+    cost: u32,
+}
+
+impl BcryptPasswordHasher {
+    /// Cost needs to be between 4 and 31
+    /// Java bcrypt lib uses 10 by default
+    pub fn new(cost: u32) -> Self {
+        BcryptPasswordHasher { cost }
+    }
+
+    fn cost_of(hash: &str) -> Option<u32> {
+        hash.split('$').nth(2).and_then(|cost| cost.parse().ok())
+    }
+}
+
+impl PasswordHasher for BcryptPasswordHasher {
+    fn can_verify(&self, hash: &str) -> bool {
+        ["$2a$", "$2b$", "$2x$", "$2y$"].iter().any(|prefix| hash.starts_with(prefix))
+    }
+
+    fn is_valid(&self, hash: &str) -> bool {
+        self.can_verify(hash) && hash.parse::<bcrypt::HashParts>().is_ok()
+    }
+
+    fn verify(&self, plain_password: &str, hash: &str) -> Result<bool, LightSpeedError> {
+        bcrypt::verify(plain_password, hash)
+            .map_err(|err| LightSpeedError::PasswordEncryptionError { message: format!("{err:?}") })
+    }
+
+    fn hash(&self, plain_password: &str) -> Result<String, LightSpeedError> {
+        bcrypt::hash(plain_password, self.cost)
+            .map_err(|err| LightSpeedError::PasswordEncryptionError { message: format!("{err:?}") })
+    }
+
+    fn needs_rehash(&self, hash: &str) -> bool {
+        Self::cost_of(hash).map(|cost| cost < self.cost).unwrap_or(true)
+    }
+}
+
+/// Argon2 hasher. It produces PHC string format hashes like "$argon2id$v=19$m=19456,t=2,p=1$...".
+/// It verifies the argon2i and argon2d hashes too.
+#[derive(Clone)]
+pub struct Argon2PasswordHasher {
+    memory_cost_kib: u32,
+    time_cost: u32,
+    parallelism: u32,
+}
+
+impl Argon2PasswordHasher {
+    /// Creates an Argon2id hasher with the given memory cost (in KiB), number of iterations and degree of parallelism.
+    /// The memory cost needs to be at least 8 times the parallelism.
+    pub fn new(memory_cost_kib: u32, time_cost: u32, parallelism: u32) -> Self {
+        Argon2PasswordHasher { memory_cost_kib, time_cost, parallelism }
+    }
+
+    fn argon2(&self) -> Result<Argon2<'static>, LightSpeedError> {
+        let params = Params::new(self.memory_cost_kib, self.time_cost, self.parallelism, None).map_err(|err| {
+            LightSpeedError::PasswordEncryptionError { message: format!("Invalid Argon2 parameters: {err:?}") }
+        })?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+}
+
+impl PasswordHasher for Argon2PasswordHasher {
+    fn can_verify(&self, hash: &str) -> bool {
+        ["$argon2id$", "$argon2i$", "$argon2d$"].iter().any(|prefix| hash.starts_with(prefix))
+    }
+
+    fn is_valid(&self, hash: &str) -> bool {
+        self.can_verify(hash)
+            && PasswordHash::new(hash)
+                .map(|parsed_hash| parsed_hash.salt.is_some() && parsed_hash.hash.is_some())
+                .unwrap_or(false)
+    }
+
+    fn verify(&self, plain_password: &str, hash: &str) -> Result<bool, LightSpeedError> {
+        let parsed_hash = PasswordHash::new(hash)
+            .map_err(|err| LightSpeedError::PasswordEncryptionError { message: format!("{err:?}") })?;
+        match self.argon2()?.verify_password(plain_password.as_bytes(), &parsed_hash) {
+            Ok(()) => Ok(true),
+            Err(argon2::password_hash::Error::Password) => Ok(false),
+            Err(err) => Err(LightSpeedError::PasswordEncryptionError { message: format!("{err:?}") }),
+        }
+    }
+
+    fn hash(&self, plain_password: &str) -> Result<String, LightSpeedError> {
+        let mut salt = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let salt = SaltString::encode_b64(&salt)
+            .map_err(|err| LightSpeedError::PasswordEncryptionError { message: format!("{err:?}") })?;
+        self.argon2()?
+            .hash_password(plain_password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|err| LightSpeedError::PasswordEncryptionError { message: format!("{err:?}") })
+    }
+
+    fn needs_rehash(&self, hash: &str) -> bool {
+        let parsed_hash = match PasswordHash::new(hash) {
+            Ok(parsed_hash) => parsed_hash,
+            Err(_) => return true,
+        };
+        if parsed_hash.algorithm != Algorithm::Argon2id.ident() || parsed_hash.version != Some(Version::V0x13.into()) {
+            return true;
+        }
+        match Params::try_from(&parsed_hash) {
+            Ok(params) => {
+                params.m_cost() < self.memory_cost_kib
+                    || params.t_cost() < self.time_cost
+                    || params.p_cost() < self.parallelism
+            }
+            Err(_) => true,
+        }
+    }
+}
+
+/// PBKDF2-SHA256 hasher. It produces Django compatible hashes like "pbkdf2_sha256$600000$salt$base64hash".
+/// It is meant to verify the hashes imported from other systems, which are upgraded at the first successful login.
+#[derive(Clone)]
+pub struct Pbkdf2Sha256PasswordHasher {
+    iterations: u32,
+}
+
+impl Pbkdf2Sha256PasswordHasher {
+    const PREFIX: &'static str = "pbkdf2_sha256$";
+    /// The shortest digest accepted, to prevent a truncated hash from matching any password
+    const MIN_HASH_LEN: usize = 32;
+
+    pub fn new(iterations: u32) -> Self {
+        Pbkdf2Sha256PasswordHasher { iterations }
+    }
+
+    fn encode(plain_password: &str, iterations: u32, salt: &str, hash_len: usize) -> Vec<u8> {
+        let mut hash = vec![0u8; hash_len];
+        pbkdf2::pbkdf2_hmac::<Sha256>(plain_password.as_bytes(), salt.as_bytes(), iterations, &mut hash);
+        hash
+    }
+
+    /// Splits the hash into its iterations, salt and digest
+    fn parse(hash: &str) -> Result<(u32, &str, Vec<u8>), LightSpeedError> {
+        let invalid_hash = || LightSpeedError::PasswordEncryptionError { message: "Invalid PBKDF2 hash".to_owned() };
+        let mut parts = hash.strip_prefix(Self::PREFIX).ok_or_else(invalid_hash)?.split('$');
+        let (iterations, salt, expected) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(iterations), Some(salt), Some(expected), None) => (iterations, salt, expected),
+            _ => return Err(invalid_hash()),
+        };
+        let iterations = iterations.parse::<u32>().map_err(|_| invalid_hash())?;
+        if iterations == 0 {
+            return Err(invalid_hash());
+        }
+        let expected = general_purpose::STANDARD.decode(expected).map_err(|_| invalid_hash())?;
+        if expected.len() < Self::MIN_HASH_LEN {
+            return Err(invalid_hash());
+        }
+        Ok((iterations, salt, expected))
+    }
+}
+
+impl PasswordHasher for Pbkdf2Sha256PasswordHasher {
+    fn can_verify(&self, hash: &str) -> bool {
+        hash.starts_with(Self::PREFIX)
+    }
+
+    fn is_valid(&self, hash: &str) -> bool {
+        Self::parse(hash).is_ok()
+    }
+
+    fn verify(&self, plain_password: &str, hash: &str) -> Result<bool, LightSpeedError> {
+        let (iterations, salt, expected) = Self::parse(hash)?;
+        let actual = Self::encode(plain_password, iterations, salt, expected.len());
+        Ok(constant_time_eq(&actual, &expected))
+    }
+
+    fn hash(&self, plain_password: &str) -> Result<String, LightSpeedError> {
+        if self.iterations == 0 {
+            return Err(LightSpeedError::PasswordEncryptionError {
+                message: "PBKDF2 iterations need to be greater than 0".to_owned(),
+            });
+        }
+        let salt = Alphanumeric.sample_string(&mut rand::thread_rng(), 22);
+        let hash = Self::encode(plain_password, self.iterations, &salt, 32);
+        Ok(format!("{}{}${}${}", Self::PREFIX, self.iterations, salt, general_purpose::STANDARD.encode(hash)))
+    }
+
+    fn needs_rehash(&self, hash: &str) -> bool {
+        hash.split('$')
+            .nth(1)
+            .and_then(|iterations| iterations.parse::<u32>().ok())
+            .map(|iterations| iterations < self.iterations)
+            .unwrap_or(true)
+    }
+}
+
+/// The digests supported by the [`SaltedShaPasswordHasher`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaltedShaAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl SaltedShaAlgorithm {
+    const ALL: [SaltedShaAlgorithm; 3] =
+        [SaltedShaAlgorithm::Sha1, SaltedShaAlgorithm::Sha256, SaltedShaAlgorithm::Sha512];
+
+    fn prefix(&self) -> &'static str {
+        match self {
+            SaltedShaAlgorithm::Sha1 => "{SSHA}",
+            SaltedShaAlgorithm::Sha256 => "{SSHA256}",
+            SaltedShaAlgorithm::Sha512 => "{SSHA512}",
+        }
+    }
+
+    fn digest(&self, plain_password: &str, salt: &[u8]) -> Vec<u8> {
+        match self {
+            SaltedShaAlgorithm::Sha1 => Sha1::new().chain_update(plain_password).chain_update(salt).finalize().to_vec(),
+            SaltedShaAlgorithm::Sha256 => {
+                Sha256::new().chain_update(plain_password).chain_update(salt).finalize().to_vec()
+            }
+            SaltedShaAlgorithm::Sha512 => {
+                Sha512::new().chain_update(plain_password).chain_update(salt).finalize().to_vec()
+            }
+        }
+    }
+
+    fn digest_len(&self) -> usize {
+        match self {
+            SaltedShaAlgorithm::Sha1 => 20,
+            SaltedShaAlgorithm::Sha256 => 32,
+            SaltedShaAlgorithm::Sha512 => 64,
+        }
+    }
+}
+
+/// Salted SHA hasher. It produces RFC 2307 (LDAP) hashes like "{SSHA256}base64(digest + salt)",
+/// where the digest is computed on the password followed by the salt.
+/// These hashes are too fast to be safe: they are only meant to be imported from other systems
+/// and upgraded at the first successful login.
+#[derive(Clone)]
+pub struct SaltedShaPasswordHasher {
+    algorithm: SaltedShaAlgorithm,
+}
+
+impl SaltedShaPasswordHasher {
+    const SALT_BYTES: usize = 8;
+
+    pub fn new(algorithm: SaltedShaAlgorithm) -> Self {
+        SaltedShaPasswordHasher { algorithm }
+    }
+
+    fn algorithm_of(hash: &str) -> Option<SaltedShaAlgorithm> {
+        SaltedShaAlgorithm::ALL.into_iter().find(|algorithm| hash.starts_with(algorithm.prefix()))
+    }
+
+    /// Splits the hash into its algorithm and the decoded digest followed by the salt
+    fn parse(hash: &str) -> Result<(SaltedShaAlgorithm, Vec<u8>), LightSpeedError> {
+        let invalid_hash =
+            || LightSpeedError::PasswordEncryptionError { message: "Invalid salted SHA hash".to_owned() };
+        let algorithm = Self::algorithm_of(hash).ok_or_else(invalid_hash)?;
+        let decoded =
+            general_purpose::STANDARD.decode(&hash[algorithm.prefix().len()..]).map_err(|_| invalid_hash())?;
+        if decoded.len() <= algorithm.digest_len() {
+            return Err(invalid_hash());
+        }
+        Ok((algorithm, decoded))
+    }
+}
+
+impl PasswordHasher for SaltedShaPasswordHasher {
+    fn can_verify(&self, hash: &str) -> bool {
+        Self::algorithm_of(hash).is_some()
+    }
+
+    fn is_valid(&self, hash: &str) -> bool {
+        Self::parse(hash).is_ok()
+    }
+
+    fn verify(&self, plain_password: &str, hash: &str) -> Result<bool, LightSpeedError> {
+        let (algorithm, decoded) = Self::parse(hash)?;
+        let (expected, salt) = decoded.split_at(algorithm.digest_len());
+        Ok(constant_time_eq(&algorithm.digest(plain_password, salt), expected))
+    }
+
+    fn hash(&self, plain_password: &str) -> Result<String, LightSpeedError> {
+        let mut salt = [0u8; Self::SALT_BYTES];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let mut hash = self.algorithm.digest(plain_password, &salt);
+        hash.extend_from_slice(&salt);
+        Ok(format!("{}{}", self.algorithm.prefix(), general_purpose::STANDARD.encode(hash)))
+    }
+
+    fn needs_rehash(&self, hash: &str) -> bool {
+        Self::algorithm_of(hash) != Some(self.algorithm)
+    }
+}
+
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    left.len() == right.len() && left.iter().zip(right).fold(0u8, |acc, (l, r)| acc | (l ^ r)) == 0
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn bcrypt_should_hash_and_verify() -> Result<(), LightSpeedError> {
+        let hasher = BcryptPasswordHasher::new(4);
+        let hash = hasher.hash("password")?;
+
+        assert!(hash.starts_with("$2b$04$"));
+        assert!(hasher.can_verify(&hash));
+        assert!(hasher.verify("password", &hash)?);
+        assert!(!hasher.verify("wrong", &hash)?);
+        Ok(())
+    }
+
+    #[test]
+    fn bcrypt_should_need_rehash_if_cost_is_lower() -> Result<(), LightSpeedError> {
+        let hash = BcryptPasswordHasher::new(4).hash("password")?;
+
+        assert!(!BcryptPasswordHasher::new(4).needs_rehash(&hash));
+        assert!(!BcryptPasswordHasher::new(3).needs_rehash(&hash));
+        assert!(BcryptPasswordHasher::new(5).needs_rehash(&hash));
+        Ok(())
+    }
+
+    #[test]
+    fn argon2_should_hash_and_verify() -> Result<(), LightSpeedError> {
+        let hasher = Argon2PasswordHasher::new(64, 1, 1);
+        let hash = hasher.hash("password")?;
+
+        assert!(hash.starts_with("$argon2id$v=19$m=64,t=1,p=1$"));
+        assert!(hasher.can_verify(&hash));
+        assert!(!BcryptPasswordHasher::new(4).can_verify(&hash));
+        assert!(hasher.verify("password", &hash)?);
+        assert!(!hasher.verify("wrong", &hash)?);
+
+        // The parameters are read from the hash
+        assert!(Argon2PasswordHasher::new(128, 2, 1).verify("password", &hash)?);
+        Ok(())
+    }
+
+    #[test]
+    fn argon2_should_verify_argon2i_hashes() -> Result<(), LightSpeedError> {
+        let params = Params::new(64, 1, 1, None).unwrap();
+        let salt = SaltString::encode_b64(b"0123456789abcdef").unwrap();
+        let hash = Argon2::new(Algorithm::Argon2i, Version::V0x13, params)
+            .hash_password(b"password", &salt)
+            .unwrap()
+            .to_string();
+
+        let hasher = Argon2PasswordHasher::new(64, 1, 1);
+        assert!(hasher.can_verify(&hash));
+        assert!(hasher.verify("password", &hash)?);
+        assert!(hasher.needs_rehash(&hash));
+        Ok(())
+    }
+
+    #[test]
+    fn argon2_should_need_rehash_if_parameters_are_lower() -> Result<(), LightSpeedError> {
+        let hash = Argon2PasswordHasher::new(64, 1, 1).hash("password")?;
+
+        assert!(!Argon2PasswordHasher::new(64, 1, 1).needs_rehash(&hash));
+        assert!(!Argon2PasswordHasher::new(32, 1, 1).needs_rehash(&hash));
+        assert!(Argon2PasswordHasher::new(128, 1, 1).needs_rehash(&hash));
+        assert!(Argon2PasswordHasher::new(64, 2, 1).needs_rehash(&hash));
+        assert!(Argon2PasswordHasher::new(64, 1, 2).needs_rehash(&hash));
+        Ok(())
+    }
+
+    #[test]
+    fn argon2_should_not_hash_with_invalid_parameters() {
+        assert!(Argon2PasswordHasher::new(64, 0, 1).hash("password").is_err());
+    }
+
+    #[test]
+    fn pbkdf2_should_verify_django_hashes() -> Result<(), LightSpeedError> {
+        // The Django hash of "password" with salt "seasalt" and 1000 iterations
+        let django_hash = "pbkdf2_sha256$1000$seasalt$YIWkt6M1JFXrHg5s0jZjBSc7C2Cz6QvchSJ0h8Y+i7c=";
+        let hasher = Pbkdf2Sha256PasswordHasher::new(PBKDF2_SHA256_DEFAULT_ITERATIONS);
+
+        assert!(hasher.can_verify(django_hash));
+        assert!(hasher.verify("password", django_hash)?);
+        assert!(!hasher.verify("wrong", django_hash)?);
+        assert!(hasher.needs_rehash(django_hash));
+        assert!(hasher.verify("password", "pbkdf2_sha256$1000$seasalt").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn pbkdf2_should_hash_and_verify() -> Result<(), LightSpeedError> {
+        let hasher = Pbkdf2Sha256PasswordHasher::new(10);
+        let hash = hasher.hash("password")?;
+
+        assert!(hash.starts_with("pbkdf2_sha256$10$"));
+        assert!(hasher.verify("password", &hash)?);
+        assert!(!hasher.verify("wrong", &hash)?);
+        assert!(!hasher.needs_rehash(&hash));
+        assert!(Pbkdf2Sha256PasswordHasher::new(11).needs_rehash(&hash));
+        Ok(())
+    }
+
+    #[test]
+    fn pbkdf2_should_reject_short_digests() {
+        let hasher = Pbkdf2Sha256PasswordHasher::new(10);
+        let empty_digest = "pbkdf2_sha256$1000$seasalt$";
+        let short_digest = "pbkdf2_sha256$1000$seasalt$YIWkt6M1JFXrHg5s0jZjBSc7C2Cz6Qvc";
+
+        assert!(hasher.verify("any", empty_digest).is_err());
+        assert!(hasher.verify("any", short_digest).is_err());
+        assert!(!hasher.is_valid(empty_digest));
+        assert!(!hasher.is_valid(short_digest));
+    }
+
+    #[test]
+    fn should_validate_the_hash_format() -> Result<(), LightSpeedError> {
+        let bcrypt = BcryptPasswordHasher::new(4);
+        let argon2 = Argon2PasswordHasher::new(64, 1, 1);
+        let pbkdf2 = Pbkdf2Sha256PasswordHasher::new(10);
+        let salted_sha = SaltedShaPasswordHasher::new(SaltedShaAlgorithm::Sha256);
+
+        assert!(bcrypt.is_valid(&bcrypt.hash("password")?));
+        assert!(argon2.is_valid(&argon2.hash("password")?));
+        assert!(pbkdf2.is_valid(&pbkdf2.hash("password")?));
+        assert!(salted_sha.is_valid(&salted_sha.hash("password")?));
+
+        assert!(!bcrypt.is_valid("$2b$04$"));
+        assert!(!argon2.is_valid("$argon2id$v=19$m=64,t=1,p=1"));
+        assert!(!pbkdf2.is_valid("pbkdf2_sha256$0$seasalt$YIWkt6M1JFXrHg5s0jZjBSc7C2Cz6QvchSJ0h8Y+i7c="));
+        assert!(!salted_sha.is_valid("{SSHA}c2FsdA=="));
+        Ok(())
+    }
+
+    #[test]
+    fn salted_sha_should_verify_ldap_hashes() -> Result<(), LightSpeedError> {
+        // base64(sha1("password" + "salt") + "salt")
+        let ssha_hash = "{SSHA}yI6cZwQadOA1e+/f+T+H3eCQQhRzYWx0";
+        let hasher = SaltedShaPasswordHasher::new(SaltedShaAlgorithm::Sha256);
+
+        assert!(hasher.can_verify(ssha_hash));
+        assert!(hasher.verify("password", ssha_hash)?);
+        assert!(!hasher.verify("wrong", ssha_hash)?);
+        assert!(hasher.needs_rehash(ssha_hash));
+        assert!(hasher.verify("password", "{SSHA}c2FsdA==").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn salted_sha_should_hash_and_verify() -> Result<(), LightSpeedError> {
+        for algorithm in SaltedShaAlgorithm::ALL {
+            let hasher = SaltedShaPasswordHasher::new(algorithm);
+            let hash = hasher.hash("password")?;
+
+            assert!(hash.starts_with(algorithm.prefix()));
+            assert!(hasher.can_verify(&hash));
+            assert!(!Pbkdf2Sha256PasswordHasher::new(10).can_verify(&hash));
+            assert!(hasher.verify("password", &hash)?);
+            assert!(!hasher.verify("wrong", &hash)?);
+        }
+        Ok(())
+    }
+}
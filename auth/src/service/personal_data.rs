@@ -0,0 +1,102 @@
+use crate::dto::personal_data_dto::PersonalDataExportDto;
+use crate::repository::{
+    AuthAccountRepository, AuthRepositoryManager, AuthSessionRepository, ExternalIdentityRepository,
+    ImpersonationRepository, OAuth2ConsentRepository, WebAuthnCredentialRepository,
+};
+use crate::service::token::TokenService;
+use c3p0::*;
+use lightspeed_core::clock::Clock;
+use lightspeed_core::error::LightSpeedError;
+use log::*;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Contributes the personal data held by another module to the export of an account,
+/// e.g. the files owned by the user.
+#[async_trait::async_trait]
+pub trait PersonalDataContributor: Send + Sync {
+    /// The unique key of the contributed data in the export
+    fn name(&self) -> String;
+
+    async fn export_personal_data(&self, user_id: i64) -> Result<Value, LightSpeedError>;
+}
+
+/// Exports the personal data of an account together with the data contributed by the other modules.
+/// The anonymization of an account is provided by the `AuthAccountService`.
+#[derive(Clone)]
+pub struct PersonalDataService<RepoManager: AuthRepositoryManager> {
+    c3p0: RepoManager::C3P0,
+    token_service: Arc<TokenService<RepoManager>>,
+    auth_repo: RepoManager::AuthAccountRepo,
+    session_repo: RepoManager::AuthSessionRepo,
+    external_identity_repo: RepoManager::ExternalIdentityRepo,
+    oauth2_consent_repo: RepoManager::OAuth2ConsentRepo,
+    webauthn_credential_repo: RepoManager::WebAuthnCredentialRepo,
+    impersonation_repo: RepoManager::ImpersonationRepo,
+    contributors: Vec<Arc<dyn PersonalDataContributor>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<RepoManager: AuthRepositoryManager> PersonalDataService<RepoManager> {
+    pub fn new(
+        repo_manager: &RepoManager,
+        token_service: Arc<TokenService<RepoManager>>,
+        contributors: Vec<Arc<dyn PersonalDataContributor>>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        PersonalDataService {
+            c3p0: repo_manager.c3p0().clone(),
+            token_service,
+            auth_repo: repo_manager.auth_account_repo(),
+            session_repo: repo_manager.auth_session_repo(),
+            external_identity_repo: repo_manager.external_identity_repo(),
+            oauth2_consent_repo: repo_manager.oauth2_consent_repo(),
+            webauthn_credential_repo: repo_manager.webauthn_credential_repo(),
+            impersonation_repo: repo_manager.impersonation_repo(),
+            contributors,
+            clock,
+        }
+    }
+
+    /// Exports the personal data of the user. The contributors are called after the data of this module
+    /// have been read, outside of its transaction.
+    pub async fn export_personal_data(&self, user_id: i64) -> Result<PersonalDataExportDto, LightSpeedError> {
+        let mut export =
+            self.c3p0.transaction(|conn| async { self.export_personal_data_with_conn(conn, user_id).await }).await?;
+
+        for contributor in &self.contributors {
+            let data = contributor.export_personal_data(user_id).await?;
+            export.contributions.insert(contributor.name(), data);
+        }
+        Ok(export)
+    }
+
+    /// Exports the personal data held by this module only, without the contributions of the other modules
+    pub async fn export_personal_data_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        user_id: i64,
+    ) -> Result<PersonalDataExportDto, LightSpeedError> {
+        info!("Export the personal data of user_id [{}]", user_id);
+        let user = self.auth_repo.fetch_by_id(conn, user_id).await?;
+        let tokens = self.token_service.fetch_all_by_username_with_conn(conn, &user.data.username).await?;
+        let sessions = self.session_repo.fetch_all_by_user_id(conn, user_id).await?;
+        let external_identities = self.external_identity_repo.fetch_all_by_user_id(conn, user_id).await?;
+        let oauth2_consents = self.oauth2_consent_repo.fetch_all_by_user_id(conn, user_id).await?;
+        let webauthn_credentials = self.webauthn_credential_repo.fetch_all_by_user_id(conn, user_id).await?;
+        let impersonations = self.impersonation_repo.fetch_all_by_user_id(conn, user_id).await?;
+
+        Ok(PersonalDataExportDto {
+            exported_date_epoch_seconds: self.clock.epoch_seconds(),
+            account: user.into(),
+            tokens: tokens.into_iter().map(|token| token.data.into()).collect(),
+            sessions: sessions.into_iter().map(|session| session.data).collect(),
+            external_identities: external_identities.into_iter().map(|identity| identity.data).collect(),
+            oauth2_consents: oauth2_consents.into_iter().map(|consent| consent.data).collect(),
+            webauthn_credentials: webauthn_credentials.into_iter().map(|credential| credential.data.into()).collect(),
+            impersonations: impersonations.into_iter().map(|impersonation| impersonation.data).collect(),
+            contributions: BTreeMap::new(),
+        })
+    }
+}
@@ -1,59 +1,159 @@
-use crate::config::AuthConfig;
-use crate::model::token::{TokenData, TokenModel, TokenType};
+use crate::config::{AuthConfig, TOKEN_HASH_KEY_MIN_LENGTH};
+use crate::model::token::{InvitationData, TokenData, TokenModel, TokenType};
 use crate::repository::{AuthRepositoryManager, TokenRepository};
+use base64::{engine::general_purpose, Engine as _};
 use c3p0::*;
-use lightspeed_core::error::LightSpeedError;
+use lightspeed_core::clock::Clock;
+use lightspeed_core::error::{ErrorDetails, LightSpeedError};
 use lightspeed_core::service::validator::Validator;
-use lightspeed_core::utils::*;
+use lightspeed_hash::service::hash_service::HashService;
 use log::*;
+use rand::RngCore;
+use std::sync::Arc;
 
+/// The number of random bytes of a token
+const TOKEN_BYTES: usize = 32;
+
+/// Generates and verifies the one-time tokens.
+/// Only a keyed hash of a token is saved; the token itself is known only by its receiver.
 #[derive(Clone)]
 pub struct TokenService<RepoManager: AuthRepositoryManager> {
+    c3p0: RepoManager::C3P0,
     auth_config: AuthConfig,
     token_repo: RepoManager::TokenRepo,
+    hash_service: HashService,
+    clock: Arc<dyn Clock>,
 }
 
 impl<RepoManager: AuthRepositoryManager> TokenService<RepoManager> {
-    pub fn new(auth_config: AuthConfig, token_repo: RepoManager::TokenRepo) -> Self {
-        TokenService { auth_config, token_repo }
+    /// Fails if the `token_hash_key` of the AuthConfig is shorter than [`TOKEN_HASH_KEY_MIN_LENGTH`]
+    pub fn new(
+        c3p0: RepoManager::C3P0,
+        auth_config: AuthConfig,
+        token_repo: RepoManager::TokenRepo,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, LightSpeedError> {
+        if auth_config.token_hash_key.len() < TOKEN_HASH_KEY_MIN_LENGTH {
+            return Err(LightSpeedError::ConfigurationError {
+                message: format!("The token hash key must be at least {TOKEN_HASH_KEY_MIN_LENGTH} bytes long"),
+            });
+        }
+        Ok(TokenService { c3p0, auth_config, token_repo, hash_service: HashService::new(), clock })
+    }
+
+    /// Returns the hash with which the token is saved
+    pub fn hash_token(&self, token: &str) -> String {
+        self.hash_service.keyed_hash(&self.auth_config.token_hash_key, token)
     }
 
+    /// Generates a token with the validity configured for its type
     pub async fn generate_and_save_token_with_conn<S: Into<String>>(
         &self,
         conn: &mut RepoManager::Conn,
         username: S,
         token_type: TokenType,
     ) -> Result<TokenModel, LightSpeedError> {
-        let username = username.into();
+        let validity_minutes = match token_type {
+            TokenType::AccountActivation | TokenType::ResetPassword => {
+                self.auth_config.activation_token_validity_minutes
+            }
+            TokenType::EmailChange => self.auth_config.email_change_token_validity_minutes,
+            TokenType::Invitation => self.auth_config.invitation_token_validity_minutes,
+            TokenType::MagicLogin => self.auth_config.magic_login_token_validity_minutes,
+            TokenType::SecondFactorChallenge => self.auth_config.second_factor_challenge_validity_minutes,
+            TokenType::WebAuthnLogin | TokenType::WebAuthnRegistration => {
+                self.auth_config.webauthn_challenge_validity_minutes
+            }
+        };
+        self.generate_and_save_token_with_validity_with_conn(conn, username, token_type, validity_minutes).await
+    }
+
+    /// Generates a token with the given validity.
+    /// The returned model contains the token to be sent to the user, while only its hash is saved.
+    pub async fn generate_and_save_token_with_validity_with_conn<S: Into<String>>(
+        &self,
+        conn: &mut RepoManager::Conn,
+        username: S,
+        token_type: TokenType,
+        validity_minutes: i64,
+    ) -> Result<TokenModel, LightSpeedError> {
+        self.save_new_token_with_conn(conn, username.into(), token_type, validity_minutes, None).await
+    }
+
+    /// Generates an Invitation token with the given validity.
+    /// The token has no username, so that it is not mixed with the tokens of an account
+    /// whose username is the invited email.
+    pub async fn generate_and_save_invitation_token_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        invitation: InvitationData,
+        validity_minutes: i64,
+    ) -> Result<TokenModel, LightSpeedError> {
+        self.save_new_token_with_conn(conn, String::new(), TokenType::Invitation, validity_minutes, Some(invitation))
+            .await
+    }
+
+    async fn save_new_token_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        username: String,
+        token_type: TokenType,
+        validity_minutes: i64,
+        invitation: Option<InvitationData>,
+    ) -> Result<TokenModel, LightSpeedError> {
         info!("Generate and save token of type [{:?}] for username [{}]", token_type, username);
 
-        let issued_at = current_epoch_seconds();
-        let expire_at_epoch = issued_at + (self.auth_config.activation_token_validity_minutes * 60);
-        let token = NewModel::new(TokenData {
-            token: new_hyphenated_uuid(),
-            token_type,
-            username,
-            expire_at_epoch_seconds: expire_at_epoch,
-        });
-        self.token_repo.save(conn, token).await
+        let mut random_bytes = [0u8; TOKEN_BYTES];
+        rand::thread_rng().fill_bytes(&mut random_bytes);
+        let token = general_purpose::URL_SAFE_NO_PAD.encode(random_bytes);
+
+        let issued_at = self.clock.epoch_seconds();
+        let expire_at_epoch = issued_at + (validity_minutes * 60);
+        let mut token_model = self
+            .token_repo
+            .save(
+                conn,
+                NewModel::new(TokenData {
+                    token: self.hash_token(&token),
+                    token_type,
+                    username,
+                    expire_at_epoch_seconds: expire_at_epoch,
+                    invitation,
+                }),
+            )
+            .await?;
+        token_model.data.token = token;
+        Ok(token_model)
     }
 
+    /// Fetches a token by its hash. The `token` field of the returned model contains the hash.
     pub async fn fetch_by_token_with_conn(
         &self,
         conn: &mut RepoManager::Conn,
         token: &str,
         validate: bool,
     ) -> Result<TokenModel, LightSpeedError> {
-        debug!("Fetch by token [{}]", token);
-        let token_model = self.token_repo.fetch_by_token(conn, token).await?;
+        debug!("Fetch by token");
+        let token_model = self.token_repo.fetch_by_token(conn, &self.hash_token(token)).await?;
 
         if validate {
-            Validator::validate(&token_model.data)?;
+            Validator::validate(&|error_details: &mut ErrorDetails| {
+                token_model.data.validate_with_clock(self.clock.as_ref(), error_details)
+            })?;
         };
 
         Ok(token_model)
     }
 
+    pub async fn fetch_by_id_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        id: i64,
+    ) -> Result<TokenModel, LightSpeedError> {
+        debug!("Fetch by id [{}]", id);
+        self.token_repo.fetch_by_id(conn, id).await
+    }
+
     pub async fn fetch_all_by_username_with_conn(
         &self,
         conn: &mut RepoManager::Conn,
@@ -63,12 +163,41 @@ impl<RepoManager: AuthRepositoryManager> TokenService<RepoManager> {
         self.token_repo.fetch_by_username(conn, username).await
     }
 
+    pub async fn fetch_all_invitations_by_email_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        email: &str,
+    ) -> Result<Vec<TokenModel>, LightSpeedError> {
+        debug!("Fetch the invitations of email [{}]", email);
+        self.token_repo.fetch_invitations_by_email(conn, email).await
+    }
+
+    pub async fn fetch_all_by_type_with_conn(
+        &self,
+        conn: &mut RepoManager::Conn,
+        token_type: TokenType,
+    ) -> Result<Vec<TokenModel>, LightSpeedError> {
+        debug!("Fetch all by type [{:?}]", token_type);
+        self.token_repo.fetch_all_by_type(conn, token_type).await
+    }
+
     pub async fn delete_with_conn(
         &self,
         conn: &mut RepoManager::Conn,
         token_model: TokenModel,
     ) -> Result<TokenModel, LightSpeedError> {
-        debug!("Delete token_model with id [{}] and token [{}]", token_model.id, token_model.data.token);
+        debug!("Delete token_model with id [{}]", token_model.id);
         self.token_repo.delete(conn, token_model).await
     }
+
+    /// Deletes the expired tokens of all the users. Returns the number of deleted tokens.
+    pub async fn purge_expired(&self) -> Result<u64, LightSpeedError> {
+        self.c3p0.transaction(|conn| async { self.purge_expired_with_conn(conn).await }).await
+    }
+
+    pub async fn purge_expired_with_conn(&self, conn: &mut RepoManager::Conn) -> Result<u64, LightSpeedError> {
+        let purged = self.token_repo.delete_expired(conn, self.clock.epoch_seconds()).await?;
+        debug!("Purged [{}] expired tokens", purged);
+        Ok(purged)
+    }
 }
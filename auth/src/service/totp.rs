@@ -0,0 +1,244 @@
+use crate::config::AuthConfig;
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use lightspeed_core::clock::Clock;
+use lightspeed_core::error::LightSpeedError;
+use rand::distributions::{Alphanumeric, DistString};
+use rand::RngCore;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+pub const TOTP_DIGITS: u32 = 6;
+pub const TOTP_PERIOD_SECONDS: i64 = 30;
+pub const TOTP_SECRET_BYTES: usize = 20;
+pub const RECOVERY_CODE_LENGTH: usize = 10;
+
+/// Generates and verifies RFC 6238 time-based one-time passwords (HMAC-SHA1, 6 digits, 30 seconds period)
+/// and the one-time recovery codes of the two-factor authentication.
+#[derive(Clone)]
+pub struct TotpService {
+    issuer: String,
+    allowed_skew_steps: i64,
+    clock: Arc<dyn Clock>,
+}
+
+impl TotpService {
+    pub fn new(auth_config: &AuthConfig, clock: Arc<dyn Clock>) -> Self {
+        TotpService {
+            issuer: auth_config.totp_issuer.clone(),
+            allowed_skew_steps: auth_config.totp_allowed_skew_steps,
+            clock,
+        }
+    }
+
+    /// Generates a new random base32 encoded secret
+    pub fn generate_secret(&self) -> String {
+        let mut secret = [0u8; TOTP_SECRET_BYTES];
+        rand::thread_rng().fill_bytes(&mut secret);
+        BASE32_NOPAD.encode(&secret)
+    }
+
+    /// Returns the otpauth URI to be shown as QR code to the authenticator apps
+    pub fn otpauth_uri(&self, secret: &str, account_name: &str) -> String {
+        let issuer = percent_encode(&self.issuer);
+        let account_name = percent_encode(account_name);
+        format!(
+            "otpauth://totp/{issuer}:{account_name}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={}&period={}",
+            TOTP_DIGITS, TOTP_PERIOD_SECONDS
+        )
+    }
+
+    /// Returns the current RFC 6238 time step
+    pub fn current_time_step(&self) -> i64 {
+        self.clock.epoch_seconds().div_euclid(TOTP_PERIOD_SECONDS)
+    }
+
+    /// Generates the code of the given secret for the given time step
+    pub fn generate_code(&self, secret: &str, time_step: i64) -> Result<String, LightSpeedError> {
+        let key = decode_secret(secret)?;
+        let mut mac = Hmac::<Sha1>::new_from_slice(&key)
+            .map_err(|err| LightSpeedError::InternalServerError { message: format!("{err:?}") })?;
+        mac.update(&time_step.to_be_bytes());
+        let hash = mac.finalize().into_bytes();
+
+        // Dynamic truncation as described in RFC 4226
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([hash[offset] & 0x7f, hash[offset + 1], hash[offset + 2], hash[offset + 3]]);
+
+        Ok(format!("{:0width$}", binary % 10u32.pow(TOTP_DIGITS), width = TOTP_DIGITS as usize))
+    }
+
+    /// Verifies the code against the current time step and the allowed skew.
+    /// The time steps up to `last_used_time_step` are rejected so that a code cannot be used twice.
+    /// Returns the matching time step, if any.
+    pub fn verify_code(
+        &self,
+        secret: &str,
+        code: &str,
+        last_used_time_step: Option<i64>,
+    ) -> Result<Option<i64>, LightSpeedError> {
+        let code = code.trim();
+        let current_time_step = self.current_time_step();
+        for time_step in (current_time_step - self.allowed_skew_steps)..=(current_time_step + self.allowed_skew_steps) {
+            if last_used_time_step.map(|last_used| time_step <= last_used).unwrap_or(false) {
+                continue;
+            }
+            if constant_time_eq(self.generate_code(secret, time_step)?.as_bytes(), code.as_bytes()) {
+                return Ok(Some(time_step));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Generates new random recovery codes in the format XXXXX-XXXXX
+    pub fn generate_recovery_codes(&self, count: u32) -> Vec<String> {
+        let mut rng = rand::thread_rng();
+        (0..count)
+            .map(|_| {
+                let code = Alphanumeric.sample_string(&mut rng, RECOVERY_CODE_LENGTH).to_ascii_uppercase();
+                format!("{}-{}", &code[..RECOVERY_CODE_LENGTH / 2], &code[RECOVERY_CODE_LENGTH / 2..])
+            })
+            .collect()
+    }
+
+    /// Returns the hash to be persisted for a recovery code.
+    /// The hash ignores the case, the whitespaces and the dashes of the code.
+    pub fn hash_recovery_code(&self, recovery_code: &str) -> String {
+        let normalized = recovery_code
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .map(|c| c.to_ascii_uppercase())
+            .collect::<String>();
+        BASE32_NOPAD.encode(&Sha256::digest(normalized.as_bytes()))
+    }
+}
+
+fn decode_secret(secret: &str) -> Result<Vec<u8>, LightSpeedError> {
+    let normalized =
+        secret.chars().filter(|c| !c.is_whitespace() && *c != '=').map(|c| c.to_ascii_uppercase()).collect::<String>();
+    BASE32_NOPAD
+        .decode(normalized.as_bytes())
+        .map_err(|err| LightSpeedError::InternalServerError { message: format!("Invalid TOTP secret: {err:?}") })
+}
+
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    left.len() == right.len() && left.iter().zip(right).fold(0u8, |acc, (l, r)| acc | (l ^ r)) == 0
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use lightspeed_core::clock::MockClock;
+    use std::time::Duration;
+
+    // The base32 encoding of the RFC 6238 SHA1 test secret "12345678901234567890"
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    fn new_service(clock: MockClock) -> TotpService {
+        TotpService::new(&AuthConfig::default(), Arc::new(clock))
+    }
+
+    #[test]
+    fn should_generate_the_rfc_6238_codes() {
+        let service = new_service(MockClock::from_epoch_seconds(0));
+
+        // The RFC test vectors have 8 digits, the 6 digits codes are their last 6 digits
+        for (epoch_seconds, expected) in [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+            (20000000000, "353130"),
+        ] {
+            assert_eq!(expected, service.generate_code(RFC_SECRET, epoch_seconds / TOTP_PERIOD_SECONDS).unwrap());
+        }
+    }
+
+    #[test]
+    fn should_verify_codes_within_the_allowed_skew() {
+        let clock = MockClock::from_epoch_seconds(1111111109);
+        let service = new_service(clock.clone());
+        let time_step = service.current_time_step();
+
+        assert_eq!(Some(time_step), service.verify_code(RFC_SECRET, "081804", None).unwrap());
+        assert_eq!(Some(time_step), service.verify_code(RFC_SECRET, " 081804 ", None).unwrap());
+        assert_eq!(None, service.verify_code(RFC_SECRET, "123456", None).unwrap());
+
+        clock.advance(Duration::from_secs(TOTP_PERIOD_SECONDS as u64));
+        assert_eq!(Some(time_step), service.verify_code(RFC_SECRET, "081804", None).unwrap());
+
+        clock.advance(Duration::from_secs(TOTP_PERIOD_SECONDS as u64));
+        assert_eq!(None, service.verify_code(RFC_SECRET, "081804", None).unwrap());
+    }
+
+    #[test]
+    fn should_not_verify_already_used_codes() {
+        let service = new_service(MockClock::from_epoch_seconds(1111111109));
+        let time_step = service.current_time_step();
+
+        assert_eq!(None, service.verify_code(RFC_SECRET, "081804", Some(time_step)).unwrap());
+        assert_eq!(Some(time_step), service.verify_code(RFC_SECRET, "081804", Some(time_step - 1)).unwrap());
+    }
+
+    #[test]
+    fn should_generate_valid_secrets() {
+        let service = new_service(MockClock::default());
+        let secret = service.generate_secret();
+
+        assert_ne!(secret, service.generate_secret());
+        assert_eq!(TOTP_SECRET_BYTES, decode_secret(&secret).unwrap().len());
+
+        let code = service.generate_code(&secret, service.current_time_step()).unwrap();
+        assert_eq!(TOTP_DIGITS as usize, code.len());
+        assert!(service.verify_code(&secret.to_lowercase(), &code, None).unwrap().is_some());
+    }
+
+    #[test]
+    fn should_build_the_otpauth_uri() {
+        let service = TotpService::new(
+            &AuthConfig { totp_issuer: "Light Speed".to_owned(), ..Default::default() },
+            Arc::new(MockClock::default()),
+        );
+
+        assert_eq!(
+            concat!(
+                "otpauth://totp/Light%20Speed:user%40email.fake",
+                "?secret=ABCD&issuer=Light%20Speed&algorithm=SHA1&digits=6&period=30"
+            ),
+            service.otpauth_uri("ABCD", "user@email.fake")
+        );
+    }
+
+    #[test]
+    fn should_generate_and_hash_recovery_codes() {
+        let service = new_service(MockClock::default());
+        let codes = service.generate_recovery_codes(10);
+
+        assert_eq!(10, codes.len());
+        for code in &codes {
+            assert_eq!(RECOVERY_CODE_LENGTH + 1, code.len());
+            assert_eq!(Some(RECOVERY_CODE_LENGTH / 2), code.find('-'));
+            assert_eq!(1, codes.iter().filter(|other| *other == code).count());
+        }
+
+        let hash = service.hash_recovery_code(&codes[0]);
+        assert_ne!(codes[0], hash);
+        assert_eq!(hash, service.hash_recovery_code(&format!(" {} ", codes[0].to_lowercase())));
+        assert_eq!(hash, service.hash_recovery_code(&codes[0].replace('-', "")));
+        assert_ne!(hash, service.hash_recovery_code(&codes[1]));
+    }
+}
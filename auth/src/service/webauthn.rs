@@ -0,0 +1,574 @@
+use crate::config::AuthConfig;
+use crate::dto::webauthn_dto::{
+    WebAuthnAssertionDto, WebAuthnAssertionOptionsDto, WebAuthnAuthenticatorSelectionDto,
+    WebAuthnCredentialDescriptorDto, WebAuthnCredentialParametersDto, WebAuthnRegistrationOptionsDto,
+    WebAuthnRelyingPartyDto, WebAuthnUserDto,
+};
+use crate::model::webauthn_credential::WebAuthnCredentialData;
+use base64::{engine::general_purpose, Engine as _};
+use ciborium::value::Value;
+use lightspeed_core::error::{ErrorCodes, LightSpeedError};
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// The COSE identifier of ECDSA with the P-256 curve and SHA-256, the only supported algorithm
+pub const COSE_ALGORITHM_ES256: i64 = -7;
+pub const PUBLIC_KEY_CREDENTIAL_TYPE: &str = "public-key";
+
+pub const ERR_INVALID_WEBAUTHN_RESPONSE: &str = "INVALID_WEBAUTHN_RESPONSE";
+pub const ERR_UNSUPPORTED_WEBAUTHN_CREDENTIAL: &str = "UNSUPPORTED_WEBAUTHN_CREDENTIAL";
+pub const ERR_USER_NOT_VERIFIED: &str = "USER_NOT_VERIFIED";
+pub const ERR_SIGN_COUNT_NOT_INCREASED: &str = "SIGN_COUNT_NOT_INCREASED";
+
+const CEREMONY_TYPE_CREATE: &str = "webauthn.create";
+const CEREMONY_TYPE_GET: &str = "webauthn.get";
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+/// The length of the fixed part of the authenticator data: rpIdHash, flags and signCount
+const AUTHENTICATOR_DATA_MIN_LENGTH: usize = 37;
+/// The length of the AAGUID of the attested credential data
+const AAGUID_LENGTH: usize = 16;
+
+/// A credential created by a successful registration ceremony
+pub struct WebAuthnVerifiedCredential {
+    /// The base64url encoded id of the credential
+    pub credential_id: String,
+    pub algorithm: i64,
+    /// The base64url encoded public key in the SEC1 uncompressed format
+    pub public_key: String,
+    pub sign_count: u32,
+}
+
+/// Builds the options and verifies the responses of the WebAuthn ceremonies (W3C Web Authentication Level 2).
+/// Only the ES256 credentials and the attestation "none" are supported, i.e. the authenticator is not verified.
+/// The challenges are generated and checked by the caller.
+#[derive(Clone)]
+pub struct WebAuthnService {
+    rp_id: String,
+    rp_name: String,
+    allowed_origins: Vec<String>,
+    timeout_millis: u64,
+}
+
+#[derive(Deserialize)]
+struct CollectedClientData {
+    #[serde(rename = "type")]
+    ceremony_type: String,
+    challenge: String,
+    origin: String,
+}
+
+struct AuthenticatorData {
+    rp_id_hash: Vec<u8>,
+    flags: u8,
+    sign_count: u32,
+    /// The credential id and the COSE public key; present only in the registration ceremony
+    attested_credential: Option<(Vec<u8>, Value)>,
+}
+
+impl WebAuthnService {
+    pub fn new(auth_config: &AuthConfig) -> Self {
+        WebAuthnService {
+            rp_id: auth_config.webauthn_rp_id.clone(),
+            rp_name: auth_config.webauthn_rp_name.clone(),
+            allowed_origins: auth_config.webauthn_allowed_origins.clone(),
+            timeout_millis: (auth_config.webauthn_challenge_validity_minutes * 60 * 1000) as u64,
+        }
+    }
+
+    /// Returns the user handle bound to the credentials of the user.
+    /// It is the base64url encoded id of the user, so that it carries no personal data.
+    pub fn user_handle(&self, user_id: i64) -> String {
+        general_purpose::URL_SAFE_NO_PAD.encode(user_id.to_string())
+    }
+
+    /// Returns the options of a registration ceremony. The challenge has to be base64url encoded.
+    pub fn registration_options(
+        &self,
+        challenge: &str,
+        user_id: i64,
+        username: &str,
+        exclude_credential_ids: Vec<String>,
+    ) -> WebAuthnRegistrationOptionsDto {
+        WebAuthnRegistrationOptionsDto {
+            rp: WebAuthnRelyingPartyDto { id: self.rp_id.clone(), name: self.rp_name.clone() },
+            user: WebAuthnUserDto {
+                id: self.user_handle(user_id),
+                name: username.to_owned(),
+                display_name: username.to_owned(),
+            },
+            challenge: challenge.to_owned(),
+            pub_key_cred_params: vec![WebAuthnCredentialParametersDto {
+                credential_type: PUBLIC_KEY_CREDENTIAL_TYPE.to_owned(),
+                alg: COSE_ALGORITHM_ES256,
+            }],
+            timeout: self.timeout_millis,
+            exclude_credentials: credential_descriptors(exclude_credential_ids),
+            authenticator_selection: WebAuthnAuthenticatorSelectionDto {
+                resident_key: "preferred".to_owned(),
+                user_verification: "preferred".to_owned(),
+            },
+            attestation: "none".to_owned(),
+        }
+    }
+
+    /// Returns the options of an authentication ceremony. The challenge has to be base64url encoded.
+    pub fn assertion_options(
+        &self,
+        challenge: &str,
+        allow_credential_ids: Vec<String>,
+        user_verification_required: bool,
+    ) -> WebAuthnAssertionOptionsDto {
+        WebAuthnAssertionOptionsDto {
+            challenge: challenge.to_owned(),
+            timeout: self.timeout_millis,
+            rp_id: self.rp_id.clone(),
+            allow_credentials: credential_descriptors(allow_credential_ids),
+            user_verification: if user_verification_required { "required" } else { "preferred" }.to_owned(),
+        }
+    }
+
+    /// Returns the challenge of the base64url encoded client data, to find the ceremony it answers.
+    /// The client data are not verified.
+    pub fn client_data_challenge(&self, client_data_json: &str) -> Result<String, LightSpeedError> {
+        let client_data_json = decode_base64url("client_data_json", client_data_json)?;
+        Ok(parse_client_data(&client_data_json)?.challenge)
+    }
+
+    /// Verifies the response of a registration ceremony and returns the new credential
+    pub fn verify_registration(
+        &self,
+        challenge: &str,
+        client_data_json: &str,
+        attestation_object: &str,
+    ) -> Result<WebAuthnVerifiedCredential, LightSpeedError> {
+        let client_data_json = decode_base64url("client_data_json", client_data_json)?;
+        self.verify_client_data(&client_data_json, CEREMONY_TYPE_CREATE, challenge)?;
+
+        let attestation_object = decode_base64url("attestation_object", attestation_object)?;
+        let attestation_object: Value = ciborium::de::from_reader(attestation_object.as_slice())
+            .map_err(|err| invalid_response(format!("Invalid attestation object: {err:?}")))?;
+
+        let format = map_entry(&attestation_object, &Value::Text("fmt".to_owned()))
+            .and_then(Value::as_text)
+            .ok_or_else(|| invalid_response("The attestation format is missing"))?;
+        let statement_is_empty = map_entry(&attestation_object, &Value::Text("attStmt".to_owned()))
+            .and_then(Value::as_map)
+            .map(|statement| statement.is_empty())
+            .unwrap_or(false);
+        if format != "none" || !statement_is_empty {
+            return Err(LightSpeedError::BadRequest {
+                message: format!("Unsupported attestation format [{format}]"),
+                code: ERR_UNSUPPORTED_WEBAUTHN_CREDENTIAL,
+            });
+        }
+
+        let authenticator_data = map_entry(&attestation_object, &Value::Text("authData".to_owned()))
+            .and_then(Value::as_bytes)
+            .ok_or_else(|| invalid_response("The authenticator data are missing"))?;
+        let authenticator_data = parse_authenticator_data(authenticator_data)?;
+        self.verify_authenticator_data(&authenticator_data, false)?;
+
+        let (credential_id, public_key) = authenticator_data
+            .attested_credential
+            .ok_or_else(|| invalid_response("The attested credential data are missing"))?;
+        let public_key = cose_key_to_sec1(&public_key)?;
+
+        Ok(WebAuthnVerifiedCredential {
+            credential_id: general_purpose::URL_SAFE_NO_PAD.encode(credential_id),
+            algorithm: COSE_ALGORITHM_ES256,
+            public_key: general_purpose::URL_SAFE_NO_PAD.encode(public_key),
+            sign_count: authenticator_data.sign_count,
+        })
+    }
+
+    /// Verifies the response of an authentication ceremony with the given credential.
+    /// Returns the new signature counter of the credential.
+    pub fn verify_assertion(
+        &self,
+        challenge: &str,
+        credential: &WebAuthnCredentialData,
+        assertion: &WebAuthnAssertionDto,
+        user_verification_required: bool,
+    ) -> Result<u32, LightSpeedError> {
+        let client_data_json = decode_base64url("client_data_json", &assertion.client_data_json)?;
+        self.verify_client_data(&client_data_json, CEREMONY_TYPE_GET, challenge)?;
+
+        let raw_authenticator_data = decode_base64url("authenticator_data", &assertion.authenticator_data)?;
+        let authenticator_data = parse_authenticator_data(&raw_authenticator_data)?;
+        self.verify_authenticator_data(&authenticator_data, user_verification_required)?;
+
+        if credential.algorithm != COSE_ALGORITHM_ES256 {
+            return Err(LightSpeedError::BadRequest {
+                message: format!("Unsupported algorithm [{}]", credential.algorithm),
+                code: ERR_UNSUPPORTED_WEBAUTHN_CREDENTIAL,
+            });
+        }
+        let public_key = decode_base64url("public_key", &credential.public_key)?;
+        let verifying_key = VerifyingKey::from_sec1_bytes(&public_key).map_err(|err| {
+            LightSpeedError::InternalServerError { message: format!("Invalid public key of a credential: {err:?}") }
+        })?;
+
+        let signature = decode_base64url("signature", &assertion.signature)?;
+        let mut signed_data = raw_authenticator_data;
+        signed_data.extend_from_slice(&Sha256::digest(&client_data_json));
+        let signature_is_valid = Signature::from_der(&signature)
+            .map(|signature| verifying_key.verify(&signed_data, &signature).is_ok())
+            .unwrap_or(false);
+        if !signature_is_valid {
+            return Err(LightSpeedError::BadRequest {
+                message: "Wrong WebAuthn signature".to_owned(),
+                code: ErrorCodes::WRONG_CREDENTIALS,
+            });
+        }
+
+        // A counter that does not increase reveals that the authenticator could have been cloned
+        let sign_count = authenticator_data.sign_count;
+        if (sign_count != 0 || credential.sign_count != 0) && sign_count <= credential.sign_count {
+            return Err(LightSpeedError::BadRequest {
+                message: format!(
+                    "The sign count [{}] of the credential is not greater than [{}]",
+                    sign_count, credential.sign_count
+                ),
+                code: ERR_SIGN_COUNT_NOT_INCREASED,
+            });
+        }
+
+        Ok(sign_count)
+    }
+
+    fn verify_client_data(
+        &self,
+        client_data_json: &[u8],
+        ceremony_type: &str,
+        challenge: &str,
+    ) -> Result<(), LightSpeedError> {
+        let client_data = parse_client_data(client_data_json)?;
+        if client_data.ceremony_type != ceremony_type {
+            return Err(invalid_response(format!("Wrong ceremony type [{}]", client_data.ceremony_type)));
+        }
+        if client_data.challenge != challenge {
+            return Err(invalid_response("Wrong challenge"));
+        }
+        if !self.allowed_origins.iter().any(|origin| origin == &client_data.origin) {
+            return Err(invalid_response(format!("Origin [{}] not allowed", client_data.origin)));
+        }
+        Ok(())
+    }
+
+    fn verify_authenticator_data(
+        &self,
+        authenticator_data: &AuthenticatorData,
+        user_verification_required: bool,
+    ) -> Result<(), LightSpeedError> {
+        if authenticator_data.rp_id_hash != Sha256::digest(self.rp_id.as_bytes()).as_slice() {
+            return Err(invalid_response("Wrong relying party id"));
+        }
+        if authenticator_data.flags & FLAG_USER_PRESENT == 0 {
+            return Err(invalid_response("The user is not present"));
+        }
+        if user_verification_required && authenticator_data.flags & FLAG_USER_VERIFIED == 0 {
+            return Err(LightSpeedError::BadRequest {
+                message: "The user has not been verified by the authenticator".to_owned(),
+                code: ERR_USER_NOT_VERIFIED,
+            });
+        }
+        Ok(())
+    }
+}
+
+fn credential_descriptors(credential_ids: Vec<String>) -> Vec<WebAuthnCredentialDescriptorDto> {
+    credential_ids
+        .into_iter()
+        .map(|id| WebAuthnCredentialDescriptorDto { credential_type: PUBLIC_KEY_CREDENTIAL_TYPE.to_owned(), id })
+        .collect()
+}
+
+fn parse_client_data(client_data_json: &[u8]) -> Result<CollectedClientData, LightSpeedError> {
+    serde_json::from_slice(client_data_json).map_err(|err| invalid_response(format!("Invalid client data: {err:?}")))
+}
+
+/// Parses the authenticator data. The extensions, if any, are ignored.
+fn parse_authenticator_data(data: &[u8]) -> Result<AuthenticatorData, LightSpeedError> {
+    if data.len() < AUTHENTICATOR_DATA_MIN_LENGTH {
+        return Err(invalid_response("The authenticator data are too short"));
+    }
+    let flags = data[32];
+    let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+
+    let attested_credential = if flags & FLAG_ATTESTED_CREDENTIAL_DATA != 0 {
+        let credential_id_length_offset = AUTHENTICATOR_DATA_MIN_LENGTH + AAGUID_LENGTH;
+        let credential_id_offset = credential_id_length_offset + 2;
+        if data.len() < credential_id_offset {
+            return Err(invalid_response("The attested credential data are too short"));
+        }
+        let credential_id_length =
+            u16::from_be_bytes([data[credential_id_length_offset], data[credential_id_length_offset + 1]]) as usize;
+        let public_key_offset = credential_id_offset + credential_id_length;
+        if data.len() < public_key_offset {
+            return Err(invalid_response("The attested credential data are too short"));
+        }
+        let public_key: Value = ciborium::de::from_reader(&data[public_key_offset..])
+            .map_err(|err| invalid_response(format!("Invalid credential public key: {err:?}")))?;
+        Some((data[credential_id_offset..public_key_offset].to_vec(), public_key))
+    } else {
+        None
+    };
+
+    Ok(AuthenticatorData { rp_id_hash: data[..32].to_vec(), flags, sign_count, attested_credential })
+}
+
+/// Converts an ES256 COSE key to the SEC1 uncompressed format
+fn cose_key_to_sec1(key: &Value) -> Result<Vec<u8>, LightSpeedError> {
+    let integer_entry = |label: i64| map_entry(key, &Value::Integer(label.into())).and_then(Value::as_integer);
+    let bytes_entry = |label: i64| map_entry(key, &Value::Integer(label.into())).and_then(Value::as_bytes);
+
+    // kty: EC2 (2), alg: ES256 (-7), crv: P-256 (1)
+    let is_es256 = integer_entry(1) == Some(2.into())
+        && integer_entry(3) == Some(COSE_ALGORITHM_ES256.into())
+        && integer_entry(-1) == Some(1.into());
+    if !is_es256 {
+        return Err(LightSpeedError::BadRequest {
+            message: "Only the ES256 credentials are supported".to_owned(),
+            code: ERR_UNSUPPORTED_WEBAUTHN_CREDENTIAL,
+        });
+    }
+
+    match (bytes_entry(-2), bytes_entry(-3)) {
+        (Some(x), Some(y)) if x.len() == 32 && y.len() == 32 => {
+            let mut sec1 = Vec::with_capacity(65);
+            sec1.push(0x04);
+            sec1.extend_from_slice(x);
+            sec1.extend_from_slice(y);
+            VerifyingKey::from_sec1_bytes(&sec1)
+                .map_err(|err| invalid_response(format!("Invalid credential public key: {err:?}")))?;
+            Ok(sec1)
+        }
+        _ => Err(invalid_response("Invalid credential public key coordinates")),
+    }
+}
+
+fn map_entry<'a>(map: &'a Value, key: &Value) -> Option<&'a Value> {
+    map.as_map().and_then(|entries| entries.iter().find(|(entry_key, _)| entry_key == key)).map(|(_, value)| value)
+}
+
+fn decode_base64url(field: &str, value: &str) -> Result<Vec<u8>, LightSpeedError> {
+    general_purpose::URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|err| invalid_response(format!("Invalid base64url value of [{field}]: {err:?}")))
+}
+
+fn invalid_response<S: Into<String>>(message: S) -> LightSpeedError {
+    LightSpeedError::BadRequest { message: message.into(), code: ERR_INVALID_WEBAUTHN_RESPONSE }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use p256::ecdsa::signature::Signer;
+    use p256::ecdsa::SigningKey;
+    use rand::RngCore;
+    use serde_json::json;
+
+    const RP_ID: &str = "example.com";
+    const ORIGIN: &str = "https://example.com";
+    const CHALLENGE: &str = "vK3tmn7Jc2QN0pTqYd4gG8NU9gD2nnVV4mB6kKsd3Sk";
+
+    /// An authenticator that keeps its ES256 key in memory
+    struct SoftwareAuthenticator {
+        signing_key: SigningKey,
+        credential_id: Vec<u8>,
+        sign_count: u32,
+    }
+
+    impl SoftwareAuthenticator {
+        fn new() -> Self {
+            let mut credential_id = vec![0u8; 16];
+            rand::thread_rng().fill_bytes(&mut credential_id);
+            SoftwareAuthenticator {
+                signing_key: SigningKey::random(&mut rand::thread_rng()),
+                credential_id,
+                sign_count: 0,
+            }
+        }
+
+        fn authenticator_data(&self, flags: u8, attested_credential: bool) -> Vec<u8> {
+            let mut data = Sha256::digest(RP_ID.as_bytes()).to_vec();
+            data.push(if attested_credential { flags | FLAG_ATTESTED_CREDENTIAL_DATA } else { flags });
+            data.extend_from_slice(&self.sign_count.to_be_bytes());
+            if attested_credential {
+                data.extend_from_slice(&[0u8; AAGUID_LENGTH]);
+                data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+                data.extend_from_slice(&self.credential_id);
+                let point = self.signing_key.verifying_key().to_encoded_point(false);
+                let cose_key = Value::Map(vec![
+                    (Value::Integer(1.into()), Value::Integer(2.into())),
+                    (Value::Integer(3.into()), Value::Integer(COSE_ALGORITHM_ES256.into())),
+                    (Value::Integer((-1).into()), Value::Integer(1.into())),
+                    (Value::Integer((-2).into()), Value::Bytes(point.x().unwrap().to_vec())),
+                    (Value::Integer((-3).into()), Value::Bytes(point.y().unwrap().to_vec())),
+                ]);
+                ciborium::ser::into_writer(&cose_key, &mut data).unwrap();
+            }
+            data
+        }
+
+        fn register(&self, format: &str) -> (String, String) {
+            let client_data_json = json!({ "type": "webauthn.create", "challenge": CHALLENGE, "origin": ORIGIN });
+            let attestation_object = Value::Map(vec![
+                (Value::Text("fmt".to_owned()), Value::Text(format.to_owned())),
+                (Value::Text("attStmt".to_owned()), Value::Map(vec![])),
+                (Value::Text("authData".to_owned()), Value::Bytes(self.authenticator_data(FLAG_USER_PRESENT, true))),
+            ]);
+            let mut attestation_object_bytes = vec![];
+            ciborium::ser::into_writer(&attestation_object, &mut attestation_object_bytes).unwrap();
+            (
+                general_purpose::URL_SAFE_NO_PAD.encode(client_data_json.to_string()),
+                general_purpose::URL_SAFE_NO_PAD.encode(attestation_object_bytes),
+            )
+        }
+
+        fn assert(&mut self, origin: &str, flags: u8) -> WebAuthnAssertionDto {
+            self.sign_count = self.sign_count.wrapping_add(1);
+            let client_data_json =
+                json!({ "type": "webauthn.get", "challenge": CHALLENGE, "origin": origin }).to_string();
+            let authenticator_data = self.authenticator_data(flags, false);
+            let mut signed_data = authenticator_data.clone();
+            signed_data.extend_from_slice(&Sha256::digest(client_data_json.as_bytes()));
+            let signature: Signature = self.signing_key.sign(&signed_data);
+            WebAuthnAssertionDto {
+                credential_id: general_purpose::URL_SAFE_NO_PAD.encode(&self.credential_id),
+                client_data_json: general_purpose::URL_SAFE_NO_PAD.encode(client_data_json),
+                authenticator_data: general_purpose::URL_SAFE_NO_PAD.encode(authenticator_data),
+                signature: general_purpose::URL_SAFE_NO_PAD.encode(signature.to_der().as_bytes()),
+                user_handle: None,
+            }
+        }
+    }
+
+    fn new_service() -> WebAuthnService {
+        WebAuthnService::new(&AuthConfig {
+            webauthn_rp_id: RP_ID.to_owned(),
+            webauthn_allowed_origins: vec![ORIGIN.to_owned()],
+            ..Default::default()
+        })
+    }
+
+    fn register(service: &WebAuthnService, authenticator: &SoftwareAuthenticator) -> WebAuthnCredentialData {
+        let (client_data_json, attestation_object) = authenticator.register("none");
+        let credential = service.verify_registration(CHALLENGE, &client_data_json, &attestation_object).unwrap();
+        WebAuthnCredentialData {
+            user_id: 1,
+            name: "test".to_owned(),
+            credential_id: credential.credential_id,
+            algorithm: credential.algorithm,
+            public_key: credential.public_key,
+            sign_count: credential.sign_count,
+            created_date_epoch_seconds: 0,
+            last_used_date_epoch_seconds: None,
+        }
+    }
+
+    #[test]
+    fn should_register_and_verify_an_assertion() {
+        let service = new_service();
+        let mut authenticator = SoftwareAuthenticator::new();
+
+        let (client_data_json, _) = authenticator.register("none");
+        assert_eq!(CHALLENGE, service.client_data_challenge(&client_data_json).unwrap());
+
+        let mut credential = register(&service, &authenticator);
+        assert_eq!(general_purpose::URL_SAFE_NO_PAD.encode(&authenticator.credential_id), credential.credential_id);
+        assert_eq!(0, credential.sign_count);
+
+        let assertion = authenticator.assert(ORIGIN, FLAG_USER_PRESENT | FLAG_USER_VERIFIED);
+        credential.sign_count = service.verify_assertion(CHALLENGE, &credential, &assertion, true).unwrap();
+        assert_eq!(1, credential.sign_count);
+
+        let assertion = authenticator.assert(ORIGIN, FLAG_USER_PRESENT);
+        assert_eq!(2, service.verify_assertion(CHALLENGE, &credential, &assertion, false).unwrap());
+    }
+
+    #[test]
+    fn should_reject_the_attestation_formats_other_than_none() {
+        let service = new_service();
+        let (client_data_json, attestation_object) = SoftwareAuthenticator::new().register("packed");
+        match service.verify_registration(CHALLENGE, &client_data_json, &attestation_object) {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ERR_UNSUPPORTED_WEBAUTHN_CREDENTIAL, code),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn should_reject_a_wrong_challenge_or_origin() {
+        let service = new_service();
+        let mut authenticator = SoftwareAuthenticator::new();
+        let credential = register(&service, &authenticator);
+
+        let (client_data_json, attestation_object) = authenticator.register("none");
+        assert!(service.verify_registration("other", &client_data_json, &attestation_object).is_err());
+
+        let assertion = authenticator.assert("https://evil.example.com", FLAG_USER_PRESENT);
+        match service.verify_assertion(CHALLENGE, &credential, &assertion, false) {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ERR_INVALID_WEBAUTHN_RESPONSE, code),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn should_reject_a_wrong_signature() {
+        let service = new_service();
+        let mut authenticator = SoftwareAuthenticator::new();
+        let credential = register(&service, &authenticator);
+
+        let mut assertion = authenticator.assert(ORIGIN, FLAG_USER_PRESENT);
+        assertion.signature = SoftwareAuthenticator::new().assert(ORIGIN, FLAG_USER_PRESENT).signature;
+        match service.verify_assertion(CHALLENGE, &credential, &assertion, false) {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ErrorCodes::WRONG_CREDENTIALS, code),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn should_require_the_user_verification() {
+        let service = new_service();
+        let mut authenticator = SoftwareAuthenticator::new();
+        let credential = register(&service, &authenticator);
+
+        let assertion = authenticator.assert(ORIGIN, FLAG_USER_PRESENT);
+        match service.verify_assertion(CHALLENGE, &credential, &assertion, true) {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ERR_USER_NOT_VERIFIED, code),
+            _ => panic!(),
+        }
+
+        let assertion = authenticator.assert(ORIGIN, 0);
+        assert!(service.verify_assertion(CHALLENGE, &credential, &assertion, false).is_err());
+    }
+
+    #[test]
+    fn should_reject_a_sign_count_that_does_not_increase() {
+        let service = new_service();
+        let mut authenticator = SoftwareAuthenticator::new();
+        let mut credential = register(&service, &authenticator);
+        credential.sign_count = 5;
+
+        authenticator.sign_count = 4;
+        let assertion = authenticator.assert(ORIGIN, FLAG_USER_PRESENT);
+        match service.verify_assertion(CHALLENGE, &credential, &assertion, false) {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ERR_SIGN_COUNT_NOT_INCREASED, code),
+            _ => panic!(),
+        }
+
+        // The authenticators without a counter always return zero
+        credential.sign_count = 0;
+        authenticator.sign_count = u32::MAX;
+        let assertion = authenticator.assert(ORIGIN, FLAG_USER_PRESENT);
+        assert_eq!(0, service.verify_assertion(CHALLENGE, &credential, &assertion, false).unwrap());
+    }
+}
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+
+-----------------------------------
+-- Begin - LS_AUTH_LOGIN_ATTEMPT -
+-----------------------------------
+
+create table LS_AUTH_LOGIN_ATTEMPT (
+    ID bigserial primary key,
+    VERSION int not null,
+    create_epoch_millis bigint not null,
+    update_epoch_millis bigint not null,
+    DATA JSONB
+);
+
+CREATE UNIQUE INDEX LS_AUTH_LOGIN_ATTEMPT_UNIQUE_KEY ON LS_AUTH_LOGIN_ATTEMPT( (DATA->>'key_type'), (DATA->>'key') );
+
+-- End - LS_AUTH_LOGIN_ATTEMPT -
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+
+-- The original usernames and emails cannot be restored
+
+DROP INDEX LS_AUTH_ACCOUNT_UNIQUE_USERNAME;
+DROP INDEX LS_AUTH_ACCOUNT_UNIQUE_EMAIL;
+
+CREATE UNIQUE INDEX LS_AUTH_ACCOUNT_UNIQUE_USERNAME ON LS_AUTH_ACCOUNT( (DATA->>'username') );
+CREATE UNIQUE INDEX LS_AUTH_ACCOUNT_UNIQUE_EMAIL ON LS_AUTH_ACCOUNT( (DATA->>'email') );
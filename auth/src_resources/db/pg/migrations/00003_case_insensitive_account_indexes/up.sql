@@ -0,0 +1,69 @@
+-- Your SQL goes here
+
+---------------------------------------------------------
+-- Begin - LS_AUTH_ACCOUNT case-insensitive unique indexes -
+---------------------------------------------------------
+
+-- The usernames and emails are stored in their normalized form: trimmed, NFKC normalized (usernames only)
+-- and lowercase, as done by `normalize_username` and `normalize_email`.
+-- The NFKC normalization requires PostgreSQL 13; on older versions the values are only trimmed and lowercased.
+-- The migration fails listing the conflicting accounts if two of them have the same normalized value.
+-- They need to be merged or renamed before running it again.
+DO $$
+DECLARE
+    username_expr TEXT := $expr$lower(btrim(DATA->>'username', E' \t\r\n'))$expr$;
+    email_expr TEXT := $expr$lower(btrim(DATA->>'email', E' \t\r\n'))$expr$;
+    conflicts TEXT;
+BEGIN
+    IF current_setting('server_version_num')::int >= 130000 THEN
+        username_expr := $expr$lower(normalize(btrim(DATA->>'username', E' \t\r\n'), NFKC))$expr$;
+    END IF;
+
+    EXECUTE format(
+        'SELECT string_agg(format(''[%%s] ids: [%%s]'', normalized, ids), '', '')
+         FROM (
+             SELECT %1$s AS normalized, string_agg(ID::text, '','' ORDER BY ID) AS ids
+             FROM LS_AUTH_ACCOUNT
+             GROUP BY %1$s
+             HAVING count(*) > 1
+         ) AS duplicates', username_expr) INTO conflicts;
+
+    IF conflicts IS NOT NULL THEN
+        RAISE EXCEPTION 'LS_AUTH_ACCOUNT contains usernames with the same normalized form: %', conflicts;
+    END IF;
+
+    EXECUTE format(
+        'SELECT string_agg(format(''[%%s] ids: [%%s]'', normalized, ids), '', '')
+         FROM (
+             SELECT %1$s AS normalized, string_agg(ID::text, '','' ORDER BY ID) AS ids
+             FROM LS_AUTH_ACCOUNT
+             GROUP BY %1$s
+             HAVING count(*) > 1
+         ) AS duplicates', email_expr) INTO conflicts;
+
+    IF conflicts IS NOT NULL THEN
+        RAISE EXCEPTION 'LS_AUTH_ACCOUNT contains emails with the same normalized form: %', conflicts;
+    END IF;
+
+    -- The tokens reference the accounts by username
+    EXECUTE format(
+        'UPDATE LS_AUTH_TOKEN AS token
+         SET DATA = jsonb_set(token.DATA, ''{username}'', to_jsonb(account.normalized))
+         FROM (SELECT DATA->>''username'' AS username, %1$s AS normalized FROM LS_AUTH_ACCOUNT) AS account
+         WHERE token.DATA->>''username'' = account.username AND account.username != account.normalized',
+        username_expr);
+
+    EXECUTE format(
+        'UPDATE LS_AUTH_ACCOUNT
+         SET DATA = jsonb_set(jsonb_set(DATA, ''{username}'', to_jsonb(%1$s)), ''{email}'', to_jsonb(%2$s))
+         WHERE DATA->>''username'' != %1$s OR DATA->>''email'' != %2$s',
+        username_expr, email_expr);
+END $$;
+
+DROP INDEX LS_AUTH_ACCOUNT_UNIQUE_USERNAME;
+DROP INDEX LS_AUTH_ACCOUNT_UNIQUE_EMAIL;
+
+CREATE UNIQUE INDEX LS_AUTH_ACCOUNT_UNIQUE_USERNAME ON LS_AUTH_ACCOUNT( lower(DATA->>'username') );
+CREATE UNIQUE INDEX LS_AUTH_ACCOUNT_UNIQUE_EMAIL ON LS_AUTH_ACCOUNT( lower(DATA->>'email') );
+
+-- End - LS_AUTH_ACCOUNT case-insensitive unique indexes -
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE LS_AUTH_EXTERNAL_IDENTITY CASCADE;
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+
+---------------------------------------
+-- Begin - LS_AUTH_EXTERNAL_IDENTITY -
+---------------------------------------
+
+create table LS_AUTH_EXTERNAL_IDENTITY (
+    ID bigserial primary key,
+    VERSION int not null,
+    create_epoch_millis bigint not null,
+    update_epoch_millis bigint not null,
+    DATA JSONB
+);
+
+CREATE UNIQUE INDEX LS_AUTH_EXTERNAL_IDENTITY_UNIQUE_SUBJECT ON LS_AUTH_EXTERNAL_IDENTITY( (DATA->>'provider'), (DATA->>'subject') );
+CREATE INDEX LS_AUTH_EXTERNAL_IDENTITY_USER_ID ON LS_AUTH_EXTERNAL_IDENTITY( ((DATA->>'user_id')::bigint) );
+
+-- End - LS_AUTH_EXTERNAL_IDENTITY -
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE LS_AUTH_OAUTH2_TOKEN CASCADE;
+DROP TABLE LS_AUTH_OAUTH2_CONSENT CASCADE;
+DROP TABLE LS_AUTH_OAUTH2_CLIENT CASCADE;
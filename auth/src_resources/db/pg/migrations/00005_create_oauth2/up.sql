@@ -0,0 +1,52 @@
+-- Your SQL goes here
+
+-----------------------------------
+-- Begin - LS_AUTH_OAUTH2_CLIENT -
+-----------------------------------
+
+create table LS_AUTH_OAUTH2_CLIENT (
+    ID bigserial primary key,
+    VERSION int not null,
+    create_epoch_millis bigint not null,
+    update_epoch_millis bigint not null,
+    DATA JSONB
+);
+
+CREATE UNIQUE INDEX LS_AUTH_OAUTH2_CLIENT_UNIQUE_CLIENT_ID ON LS_AUTH_OAUTH2_CLIENT( (DATA->>'client_id') );
+
+-- End - LS_AUTH_OAUTH2_CLIENT -
+
+------------------------------------
+-- Begin - LS_AUTH_OAUTH2_CONSENT -
+------------------------------------
+
+create table LS_AUTH_OAUTH2_CONSENT (
+    ID bigserial primary key,
+    VERSION int not null,
+    create_epoch_millis bigint not null,
+    update_epoch_millis bigint not null,
+    DATA JSONB
+);
+
+CREATE UNIQUE INDEX LS_AUTH_OAUTH2_CONSENT_UNIQUE_USER_CLIENT ON LS_AUTH_OAUTH2_CONSENT( ((DATA->>'user_id')::bigint), (DATA->>'client_id') );
+CREATE INDEX LS_AUTH_OAUTH2_CONSENT_CLIENT_ID ON LS_AUTH_OAUTH2_CONSENT( (DATA->>'client_id') );
+
+-- End - LS_AUTH_OAUTH2_CONSENT -
+
+----------------------------------
+-- Begin - LS_AUTH_OAUTH2_TOKEN -
+----------------------------------
+
+create table LS_AUTH_OAUTH2_TOKEN (
+    ID bigserial primary key,
+    VERSION int not null,
+    create_epoch_millis bigint not null,
+    update_epoch_millis bigint not null,
+    DATA JSONB
+);
+
+CREATE UNIQUE INDEX LS_AUTH_OAUTH2_TOKEN_UNIQUE_TOKEN ON LS_AUTH_OAUTH2_TOKEN( (DATA->>'token') );
+CREATE INDEX LS_AUTH_OAUTH2_TOKEN_CLIENT_ID ON LS_AUTH_OAUTH2_TOKEN( (DATA->>'client_id') );
+CREATE INDEX LS_AUTH_OAUTH2_TOKEN_USER_ID ON LS_AUTH_OAUTH2_TOKEN( ((DATA->>'user_id')::bigint) );
+
+-- End - LS_AUTH_OAUTH2_TOKEN -
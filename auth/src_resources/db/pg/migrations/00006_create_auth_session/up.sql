@@ -0,0 +1,19 @@
+-- Your SQL goes here
+
+----------------------------
+-- Begin - LS_AUTH_SESSION -
+----------------------------
+
+create table LS_AUTH_SESSION (
+    ID bigserial primary key,
+    VERSION int not null,
+    create_epoch_millis bigint not null,
+    update_epoch_millis bigint not null,
+    DATA JSONB
+);
+
+CREATE UNIQUE INDEX LS_AUTH_SESSION_UNIQUE_SESSION_ID ON LS_AUTH_SESSION( (DATA->>'session_id') );
+CREATE INDEX LS_AUTH_SESSION_USER_ID ON LS_AUTH_SESSION( ((DATA->>'user_id')::bigint) );
+CREATE INDEX LS_AUTH_SESSION_EXPIRE_AT ON LS_AUTH_SESSION( ((DATA->>'expire_at_epoch_seconds')::bigint) );
+
+-- End - LS_AUTH_SESSION -
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+
+-- The deleted tokens cannot be restored
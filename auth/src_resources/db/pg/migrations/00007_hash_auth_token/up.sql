@@ -0,0 +1,16 @@
+-- Your SQL goes here
+
+-----------------------------------------
+-- Begin - LS_AUTH_TOKEN hashed at rest -
+-----------------------------------------
+
+-- The tokens are now saved as keyed hashes, so the existing plain tokens can no longer be found.
+-- They are deleted together with the pending email changes that reference them;
+-- the users have to request a new token.
+DELETE FROM LS_AUTH_TOKEN;
+
+UPDATE LS_AUTH_ACCOUNT
+SET DATA = jsonb_set(DATA, '{pending_email_change}', 'null'::jsonb)
+WHERE DATA->'pending_email_change' IS NOT NULL AND DATA->'pending_email_change' != 'null'::jsonb;
+
+-- End - LS_AUTH_TOKEN hashed at rest -
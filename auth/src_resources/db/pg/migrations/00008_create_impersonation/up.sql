@@ -0,0 +1,18 @@
+-- Your SQL goes here
+
+----------------------------------
+-- Begin - LS_AUTH_IMPERSONATION -
+----------------------------------
+
+create table LS_AUTH_IMPERSONATION (
+    ID bigserial primary key,
+    VERSION int not null,
+    create_epoch_millis bigint not null,
+    update_epoch_millis bigint not null,
+    DATA JSONB
+);
+
+CREATE INDEX LS_AUTH_IMPERSONATION_USER_ID ON LS_AUTH_IMPERSONATION( ((DATA->>'user_id')::bigint) );
+CREATE INDEX LS_AUTH_IMPERSONATION_IMPERSONATOR_USER_ID ON LS_AUTH_IMPERSONATION( ((DATA->>'impersonator_user_id')::bigint) );
+
+-- End - LS_AUTH_IMPERSONATION -
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+
+------------------------------------------
+-- Begin - LS_AUTH_WEBAUTHN_CREDENTIAL -
+------------------------------------------
+
+create table LS_AUTH_WEBAUTHN_CREDENTIAL (
+    ID bigserial primary key,
+    VERSION int not null,
+    create_epoch_millis bigint not null,
+    update_epoch_millis bigint not null,
+    DATA JSONB
+);
+
+CREATE UNIQUE INDEX LS_AUTH_WEBAUTHN_CREDENTIAL_CREDENTIAL_ID ON LS_AUTH_WEBAUTHN_CREDENTIAL( (DATA->>'credential_id') );
+CREATE INDEX LS_AUTH_WEBAUTHN_CREDENTIAL_USER_ID ON LS_AUTH_WEBAUTHN_CREDENTIAL( ((DATA->>'user_id')::bigint) );
+
+-- End - LS_AUTH_WEBAUTHN_CREDENTIAL -
@@ -3,10 +3,11 @@ use c3p0::postgres::tokio_postgres::NoTls;
 use c3p0::postgres::*;
 use maybe_single::nio::*;
 
-use lightspeed_auth::config::AuthConfig;
+use lightspeed_auth::config::{AccountAttributeConfig, AccountAttributeType, AuthConfig};
 use lightspeed_auth::repository::pg::PgAuthRepositoryManager;
 use lightspeed_auth::AuthModule;
 use lightspeed_core::module::Module;
+use lightspeed_core::utils::new_hyphenated_uuid;
 use once_cell::sync::OnceCell;
 use testcontainers::postgres::Postgres;
 use testcontainers::testcontainers::clients::Cli;
@@ -43,9 +44,35 @@ async fn init() -> MaybeType {
 
     let repo_manager = RepoManager::new(c3p0.clone());
 
-    let auth_config = AuthConfig { bcrypt_password_hash_cost: 4, ..Default::default() };
+    let auth_config = AuthConfig {
+        token_hash_key: new_hyphenated_uuid(),
+        bcrypt_password_hash_cost: 4,
+        argon2_memory_cost_kib: 64,
+        argon2_time_cost: 1,
+        failed_login_base_delay_millis: 1,
+        failed_login_max_delay_millis: 10,
+        account_attributes: vec![
+            AccountAttributeConfig {
+                name: "nickname".to_owned(),
+                max_length: Some(40),
+                searchable: true,
+                ..Default::default()
+            },
+            AccountAttributeConfig {
+                name: "age".to_owned(),
+                attribute_type: AccountAttributeType::Integer,
+                ..Default::default()
+            },
+            AccountAttributeConfig {
+                name: "newsletter".to_owned(),
+                attribute_type: AccountAttributeType::Boolean,
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    };
 
-    let mut auth_module = AuthModule::new(repo_manager, auth_config);
+    let mut auth_module = AuthModule::new(repo_manager, auth_config).unwrap();
     {
         auth_module.start().await.unwrap();
     }
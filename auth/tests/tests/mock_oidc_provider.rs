@@ -0,0 +1,128 @@
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use lightspeed_auth::config::OidcProviderConfig;
+use lightspeed_auth::dto::oidc_dto::OidcAuthorizationRequestDto;
+use lightspeed_auth::repository::AuthRepositoryManager;
+use lightspeed_auth::service::oidc::OidcService;
+use lightspeed_auth::AuthModule;
+use lightspeed_core::utils::current_epoch_seconds;
+use serde_json::{json, Value};
+use wiremock::matchers::{body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+pub const MOCK_OIDC_CLIENT_ID: &str = "lightspeed-client";
+
+const SIGNING_KEY_ID: &str = "test-key";
+const SIGNING_KEY: &str = include_str!("../resources/oidc_private_key.pem");
+const UNKNOWN_SIGNING_KEY: &str = include_str!("../resources/oidc_other_private_key.pem");
+const JWKS: &str = include_str!("../resources/oidc_jwks.json");
+
+/// A local OpenID Connect provider that serves the discovery document, the JWKS and the token endpoint
+pub struct MockOidcProvider {
+    server: MockServer,
+}
+
+impl MockOidcProvider {
+    pub async fn start() -> Self {
+        let server = MockServer::start().await;
+        let issuer = server.uri();
+
+        Mock::given(method("GET"))
+            .and(path("/.well-known/openid-configuration"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "issuer": issuer,
+                "authorization_endpoint": format!("{issuer}/authorize"),
+                "token_endpoint": format!("{issuer}/token"),
+                "jwks_uri": format!("{issuer}/jwks"),
+            })))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/jwks"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(JWKS, "application/json"))
+            .mount(&server)
+            .await;
+
+        MockOidcProvider { server }
+    }
+
+    pub fn issuer(&self) -> String {
+        self.server.uri()
+    }
+
+    pub fn provider_config(&self, name: &str, link_accounts_by_verified_email: bool) -> OidcProviderConfig {
+        OidcProviderConfig {
+            name: name.to_owned(),
+            issuer_url: self.issuer(),
+            client_id: MOCK_OIDC_CLIENT_ID.to_owned(),
+            client_secret: Some("secret".to_owned()),
+            redirect_url: "http://127.0.0.1/oidc/callback".to_owned(),
+            link_accounts_by_verified_email,
+            ..Default::default()
+        }
+    }
+
+    /// Returns valid ID token claims of the subject for the given authorization request
+    pub fn claims(&self, request: &OidcAuthorizationRequestDto, subject: &str, email: &str) -> Value {
+        json!({
+            "iss": self.issuer(),
+            "aud": MOCK_OIDC_CLIENT_ID,
+            "sub": subject,
+            "iat": current_epoch_seconds(),
+            "exp": current_epoch_seconds() + 300,
+            "nonce": request.nonce,
+            "email": email,
+            "email_verified": true,
+        })
+    }
+
+    /// Signs the claims with the key published in the JWKS
+    pub fn id_token(&self, claims: &Value) -> String {
+        sign(SIGNING_KEY, claims)
+    }
+
+    /// Signs the claims with a key that is not published in the JWKS, but with the same kid
+    pub fn id_token_with_unknown_key(&self, claims: &Value) -> String {
+        sign(UNKNOWN_SIGNING_KEY, claims)
+    }
+
+    /// Returns the ID token only for the given code sent together with the PKCE code verifier of the request
+    pub async fn expect_code_exchange(&self, request: &OidcAuthorizationRequestDto, code: &str, id_token: &str) {
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .and(body_string_contains(format!("code={code}")))
+            .and(body_string_contains(format!("code_verifier={}", request.pkce_code_verifier)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "access_token",
+                "token_type": "Bearer",
+                "id_token": id_token,
+            })))
+            .mount(&self.server)
+            .await;
+    }
+}
+
+/// Creates an OidcService that uses the given providers
+pub fn new_oidc_service<RepoManager: AuthRepositoryManager>(
+    auth_module: &AuthModule<RepoManager>,
+    providers: Vec<OidcProviderConfig>,
+) -> OidcService<RepoManager> {
+    let mut auth_config = auth_module.auth_config.clone();
+    auth_config.oidc_providers = providers;
+
+    OidcService::new(
+        auth_module.repo_manager.c3p0().clone(),
+        &auth_config,
+        auth_module.auth_account_service.clone(),
+        auth_module.account_event_publisher.clone(),
+        auth_module.repo_manager.auth_account_repo(),
+        auth_module.repo_manager.external_identity_repo(),
+        auth_module.clock.clone(),
+    )
+}
+
+fn sign(private_key_pem: &str, claims: &Value) -> String {
+    let header = Header { kid: Some(SIGNING_KEY_ID.to_owned()), ..Header::new(Algorithm::RS256) };
+    let key = EncodingKey::from_rsa_pem(private_key_pem.as_bytes()).expect("The test key should be valid");
+    jsonwebtoken::encode(&header, claims, &key).expect("The ID token should be signed")
+}
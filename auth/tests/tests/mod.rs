@@ -1,2 +1,3 @@
+pub mod mock_oidc_provider;
 pub mod service;
 pub mod util;
@@ -0,0 +1,153 @@
+use crate::tests::util::create_user_with_password;
+use crate::{data, test};
+use lightspeed_auth::config::ERR_INVALID_ATTRIBUTE_NAME;
+use lightspeed_auth::dto::search_accounts_dto::SearchAccountsDto;
+use lightspeed_auth::service::auth_account::{
+    ERR_NOT_SEARCHABLE_ATTRIBUTE, ERR_UNKNOWN_ATTRIBUTE, ERR_WRONG_ATTRIBUTE_TYPE,
+};
+use lightspeed_core::error::LightSpeedError;
+use lightspeed_core::service::validator::ERR_VALUE_REQUIRED;
+use lightspeed_core::utils::new_hyphenated_uuid;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+const PASSWORD: &str = "123456789";
+
+#[test]
+fn should_update_and_fetch_the_attributes() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let auth_account_service = &auth_module.auth_account_service;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        assert!(auth_account_service.fetch_attributes_by_user_id(user.id).await?.is_empty());
+
+        let updated_user = auth_account_service
+            .update_attributes(
+                user.id,
+                attributes(&[("nickname", json!("Ada")), ("age", json!(36)), ("newsletter", json!(true))]),
+            )
+            .await?;
+        assert_eq!(json!("Ada"), updated_user.data.attributes["nickname"]);
+        assert_eq!(user.data.username, updated_user.data.username);
+
+        // The attributes are merged and a null value removes an attribute
+        auth_account_service
+            .update_attributes(user.id, attributes(&[("nickname", json!("Grace")), ("age", Value::Null)]))
+            .await?;
+
+        assert_eq!(
+            attributes(&[("nickname", json!("Grace")), ("newsletter", json!(true))]),
+            auth_account_service.fetch_attributes_by_user_id(user.id).await?
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_validate_the_attributes_against_the_schema() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let auth_account_service = &auth_module.auth_account_service;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        match auth_account_service
+            .update_attributes(
+                user.id,
+                attributes(&[
+                    ("phone", json!("123")),
+                    ("age", json!("36")),
+                    ("newsletter", json!(1)),
+                    ("nickname", json!("a".repeat(41))),
+                ]),
+            )
+            .await
+        {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert_eq!(vec![ERR_UNKNOWN_ATTRIBUTE.to_owned()], details.details["attributes.phone"]);
+                assert_eq!(vec![ERR_WRONG_ATTRIBUTE_TYPE.to_owned()], details.details["attributes.age"]);
+                assert_eq!(vec![ERR_WRONG_ATTRIBUTE_TYPE.to_owned()], details.details["attributes.newsletter"]);
+                assert!(details.details.contains_key("attributes.nickname"));
+            }
+            _ => panic!(),
+        }
+
+        assert!(auth_account_service.fetch_attributes_by_user_id(user.id).await?.is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_search_the_accounts_by_a_searchable_attribute() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let auth_account_service = &auth_module.auth_account_service;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let (other_user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        let nickname = new_hyphenated_uuid();
+        auth_account_service.update_attributes(user.id, attributes(&[("nickname", json!(nickname))])).await?;
+        auth_account_service
+            .update_attributes(other_user.id, attributes(&[("nickname", json!(new_hyphenated_uuid()))]))
+            .await?;
+
+        let search =
+            SearchAccountsDto { attributes: BTreeMap::from([("nickname".to_owned(), nickname)]), ..Default::default() };
+        let result = auth_account_service.search_accounts(&search).await?;
+        assert_eq!(1, result.total_count);
+        assert_eq!(user.id, result.accounts[0].user_id);
+
+        let search = SearchAccountsDto {
+            attributes: BTreeMap::from([("age".to_owned(), "36".to_owned())]),
+            ..Default::default()
+        };
+        match auth_account_service.search_accounts(&search).await {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert_eq!(vec![ERR_NOT_SEARCHABLE_ATTRIBUTE.to_owned()], details.details["attributes.age"]);
+            }
+            _ => panic!(),
+        }
+
+        let search = SearchAccountsDto {
+            attributes: BTreeMap::from([("Nickname".to_owned(), "value".to_owned())]),
+            ..Default::default()
+        };
+        match auth_account_service.search_accounts(&search).await {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert!(details.details["attributes.Nickname"].iter().any(|d| ERR_INVALID_ATTRIBUTE_NAME == *d));
+                assert!(!details.details["attributes.Nickname"].iter().any(|d| ERR_VALUE_REQUIRED == *d));
+            }
+            _ => panic!(),
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_remove_the_attributes_when_anonymizing_the_account() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let auth_account_service = &auth_module.auth_account_service;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        auth_account_service.update_attributes(user.id, attributes(&[("nickname", json!("Ada"))])).await?;
+        let export = auth_module.new_personal_data_service(vec![]).export_personal_data(user.id).await?;
+        assert_eq!(Some(&json!("Ada")), export.account.attributes.get("nickname"));
+
+        auth_account_service.anonymize_by_user_id(user.id).await?;
+        assert!(auth_account_service.fetch_attributes_by_user_id(user.id).await?.is_empty());
+
+        Ok(())
+    })
+}
+
+fn attributes(values: &[(&str, Value)]) -> BTreeMap<String, Value> {
+    values.iter().map(|(name, value)| (name.to_string(), value.clone())).collect()
+}
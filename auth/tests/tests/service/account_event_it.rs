@@ -0,0 +1,256 @@
+use crate::tests::mock_oidc_provider::MockOidcProvider;
+use crate::{data, test};
+use lightspeed_auth::dto::change_password_dto::ChangePasswordDto;
+use lightspeed_auth::dto::create_login_dto::CreateLoginDto;
+use lightspeed_auth::dto::oidc_dto::OidcCallbackDto;
+use lightspeed_auth::dto::reset_password_dto::ResetPasswordDto;
+use lightspeed_auth::repository::AuthRepositoryManager;
+use lightspeed_auth::service::account_event::{AccountEvent, AccountEventListener, AccountEventPublisher};
+use lightspeed_auth::service::auth_account::{AuthAccountService, AuthAccountServices};
+use lightspeed_auth::service::oidc::OidcService;
+use lightspeed_auth::AuthModule;
+use lightspeed_core::error::LightSpeedError;
+use lightspeed_core::model::language::Language;
+use lightspeed_core::utils::new_hyphenated_uuid;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct RecordingListener {
+    fail_with_conn: bool,
+    fail_after_commit: bool,
+    with_conn_events: Mutex<Vec<&'static str>>,
+    after_commit_events: Mutex<Vec<&'static str>>,
+}
+
+#[async_trait::async_trait]
+impl<RepoManager: AuthRepositoryManager> AccountEventListener<RepoManager> for RecordingListener {
+    async fn on_event_with_conn(
+        &self,
+        _conn: &mut RepoManager::Conn,
+        event: &AccountEvent,
+    ) -> Result<(), LightSpeedError> {
+        if self.fail_with_conn {
+            return Err(LightSpeedError::InternalServerError { message: "listener failure".to_owned() });
+        }
+        self.with_conn_events.lock().unwrap().push(event_name(event));
+        Ok(())
+    }
+
+    async fn on_event(&self, event: &AccountEvent) -> Result<(), LightSpeedError> {
+        self.after_commit_events.lock().unwrap().push(event_name(event));
+        if self.fail_after_commit {
+            return Err(LightSpeedError::InternalServerError { message: "listener failure".to_owned() });
+        }
+        Ok(())
+    }
+}
+
+fn event_name(event: &AccountEvent) -> &'static str {
+    match event {
+        AccountEvent::Invited { .. } => "Invited",
+        AccountEvent::AccountCreated { .. } => "AccountCreated",
+        AccountEvent::Activated { .. } => "Activated",
+        AccountEvent::PasswordResetRequested { .. } => "PasswordResetRequested",
+        AccountEvent::PasswordChanged { .. } => "PasswordChanged",
+        AccountEvent::RolesChanged { .. } => "RolesChanged",
+        AccountEvent::Disabled { .. } => "Disabled",
+        AccountEvent::Deleted { .. } => "Deleted",
+        AccountEvent::IdentityLinked { .. } => "IdentityLinked",
+    }
+}
+
+#[test]
+fn should_publish_the_account_lifecycle_events() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let listener = Arc::new(RecordingListener::default());
+        let auth_account_service = new_auth_account_service_with_listeners(auth_module, vec![listener.clone()]).await;
+
+        let password = new_hyphenated_uuid();
+        let (user, activation_token) = auth_account_service.create_user(new_create_login_dto(&password)).await?;
+        auth_account_service.activate_user(&activation_token.data.token).await?;
+
+        let (_, reset_token) = auth_account_service.generate_reset_password_token(&user.data.username).await?;
+        let new_password = new_hyphenated_uuid();
+        auth_account_service
+            .reset_password_by_token(ResetPasswordDto {
+                token: reset_token.data.token,
+                password: new_password.clone(),
+                password_confirm: new_password.clone(),
+            })
+            .await?;
+        let other_password = new_hyphenated_uuid();
+        auth_account_service
+            .change_password(
+                ChangePasswordDto {
+                    user_id: user.id,
+                    old_password: new_password,
+                    new_password: other_password.clone(),
+                    new_password_confirm: other_password,
+                },
+                None,
+            )
+            .await?;
+
+        auth_account_service.add_roles(user.id, &[new_hyphenated_uuid()]).await?;
+        auth_account_service.disable_by_user_id(user.id).await?;
+        auth_account_service.delete_by_user_id(user.id).await?;
+
+        let expected = vec![
+            "AccountCreated",
+            "Activated",
+            "PasswordResetRequested",
+            "PasswordChanged",
+            "PasswordChanged",
+            "RolesChanged",
+            "Disabled",
+            "Deleted",
+        ];
+        assert_eq!(expected, *listener.with_conn_events.lock().unwrap());
+        assert_eq!(expected, *listener.after_commit_events.lock().unwrap());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_rollback_the_transaction_if_a_listener_fails() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let listener = Arc::new(RecordingListener { fail_with_conn: true, ..Default::default() });
+        let auth_account_service = new_auth_account_service_with_listeners(auth_module, vec![listener.clone()]).await;
+
+        let create_login_dto = new_create_login_dto(&new_hyphenated_uuid());
+        let username = create_login_dto.username.clone().unwrap();
+        assert!(auth_account_service.create_user(create_login_dto).await.is_err());
+
+        assert!(auth_module.auth_account_service.fetch_by_username(&username).await.is_err());
+        assert!(listener.after_commit_events.lock().unwrap().is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_ignore_the_failures_of_the_listeners_after_commit() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let failing_listener = Arc::new(RecordingListener { fail_after_commit: true, ..Default::default() });
+        let listener = Arc::new(RecordingListener::default());
+        let auth_account_service =
+            new_auth_account_service_with_listeners(auth_module, vec![failing_listener.clone(), listener.clone()])
+                .await;
+
+        let create_login_dto = new_create_login_dto(&new_hyphenated_uuid());
+        let username = create_login_dto.username.clone().unwrap();
+        auth_account_service.create_user(create_login_dto).await?;
+
+        assert!(auth_module.auth_account_service.fetch_by_username(&username).await.is_ok());
+        assert_eq!(vec!["AccountCreated"], *failing_listener.after_commit_events.lock().unwrap());
+        assert_eq!(vec!["AccountCreated"], *listener.after_commit_events.lock().unwrap());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_publish_the_events_of_the_first_oidc_login() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let listener = Arc::new(RecordingListener::default());
+        let account_event_publisher = new_account_event_publisher(vec![listener.clone()]).await;
+        let oidc_provider = MockOidcProvider::start().await;
+        let mut auth_config = auth_module.auth_config.clone();
+        auth_config.oidc_providers = vec![oidc_provider.provider_config("mock", false)];
+        let oidc_service = OidcService::new(
+            auth_module.repo_manager.c3p0().clone(),
+            &auth_config,
+            Arc::new(new_auth_account_service(auth_module, account_event_publisher.clone())),
+            account_event_publisher,
+            auth_module.repo_manager.auth_account_repo(),
+            auth_module.repo_manager.external_identity_repo(),
+            auth_module.clock.clone(),
+        );
+
+        let request = oidc_service.authorization_request("mock").await?;
+        let code = new_hyphenated_uuid();
+        let email = format!("{}@email.fake", new_hyphenated_uuid());
+        let claims = oidc_provider.claims(&request, &new_hyphenated_uuid(), &email);
+        oidc_provider.expect_code_exchange(&request, &code, &oidc_provider.id_token(&claims)).await;
+        oidc_service.login(&request, &OidcCallbackDto { state: request.state.clone(), code }).await?;
+
+        let expected = vec!["AccountCreated", "IdentityLinked"];
+        assert_eq!(expected, *listener.with_conn_events.lock().unwrap());
+        assert_eq!(expected, *listener.after_commit_events.lock().unwrap());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_serialize_the_credentials_and_the_tokens() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, activation_token) =
+            auth_module.auth_account_service.create_user(new_create_login_dto(&new_hyphenated_uuid())).await?;
+
+        let json = serde_json::to_string(&AccountEvent::AccountCreated {
+            user: user.clone().into(),
+            activation_token: Some(activation_token.clone()),
+        })?;
+        assert!(json.contains(&user.data.username));
+        assert!(!json.contains(&user.data.password));
+        assert!(!json.contains(&activation_token.data.token));
+
+        Ok(())
+    })
+}
+
+async fn new_account_event_publisher<RepoManager: AuthRepositoryManager>(
+    listeners: Vec<Arc<RecordingListener>>,
+) -> Arc<AccountEventPublisher<RepoManager>> {
+    let account_event_publisher = Arc::new(AccountEventPublisher::new());
+    for listener in listeners {
+        account_event_publisher.add_listener(listener).await;
+    }
+    account_event_publisher
+}
+
+/// Creates an AuthAccountService whose events are published only to the given listeners
+async fn new_auth_account_service_with_listeners<RepoManager: AuthRepositoryManager>(
+    auth_module: &AuthModule<RepoManager>,
+    listeners: Vec<Arc<RecordingListener>>,
+) -> AuthAccountService<RepoManager> {
+    new_auth_account_service(auth_module, new_account_event_publisher(listeners).await)
+}
+
+fn new_auth_account_service<RepoManager: AuthRepositoryManager>(
+    auth_module: &AuthModule<RepoManager>,
+    account_event_publisher: Arc<AccountEventPublisher<RepoManager>>,
+) -> AuthAccountService<RepoManager> {
+    AuthAccountService::new(
+        &auth_module.repo_manager,
+        auth_module.auth_config.clone(),
+        AuthAccountServices { account_event_publisher, ..auth_module.auth_account_services() },
+        auth_module.clock.clone(),
+    )
+}
+
+fn new_create_login_dto(password: &str) -> CreateLoginDto {
+    let username = new_hyphenated_uuid();
+    CreateLoginDto {
+        email: format!("{username}@email.fake"),
+        username: Some(username),
+        data: HashMap::new(),
+        accept_privacy_policy: true,
+        language: Language::En,
+        password: password.to_owned(),
+        password_confirm: password.to_owned(),
+    }
+}
@@ -0,0 +1,209 @@
+use crate::{data, test};
+use lightspeed_auth::dto::account_dto::AccountDto;
+use lightspeed_auth::dto::create_login_dto::CreateLoginDto;
+use lightspeed_auth::dto::search_accounts_dto::{AccountSortField, SearchAccountsDto, SortDirection};
+use lightspeed_auth::model::auth_account::{AuthAccountModel, AuthAccountStatus};
+use lightspeed_auth::repository::AuthRepositoryManager;
+use lightspeed_auth::AuthModule;
+use lightspeed_core::error::LightSpeedError;
+use lightspeed_core::model::language::Language;
+use lightspeed_core::utils::{current_epoch_seconds, new_hyphenated_uuid};
+use std::collections::HashMap;
+
+#[test]
+fn should_search_accounts_by_username_and_email_prefix() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let prefix = new_hyphenated_uuid();
+
+        let user_b = create_user_with_username(auth_module, &format!("{prefix}_b"), true).await?;
+        let user_a = create_user_with_username(auth_module, &format!("{prefix}_a"), true).await?;
+        let pending_user = create_user_with_username(auth_module, &format!("{prefix}_c"), false).await?;
+
+        let result = auth_module
+            .auth_account_service
+            .search_accounts(&SearchAccountsDto {
+                username_prefix: Some(prefix.to_uppercase()),
+                sort_by: AccountSortField::Username,
+                ..Default::default()
+            })
+            .await?;
+        assert_eq!(3, result.total_count);
+        assert_eq!(vec![user_a.id, user_b.id, pending_user.id], ids(&result.accounts));
+        assert_eq!(user_a.data.username, result.accounts[0].username);
+
+        // The credentials are not returned
+        let json = serde_json::to_string(&result.accounts)?;
+        assert!(!json.contains(&user_a.data.password));
+        assert!(!json.contains("password_history"));
+
+        let result = auth_module
+            .auth_account_service
+            .search_accounts(&SearchAccountsDto { email_prefix: Some(format!("{prefix}_B")), ..Default::default() })
+            .await?;
+        assert_eq!(vec![user_b.id], ids(&result.accounts));
+
+        let result = auth_module
+            .auth_account_service
+            .search_accounts(&SearchAccountsDto {
+                username_prefix: Some(prefix.clone()),
+                status: Some(AuthAccountStatus::PendingActivation),
+                ..Default::default()
+            })
+            .await?;
+        assert_eq!(vec![pending_user.id], ids(&result.accounts));
+
+        // The LIKE wildcards are matched literally
+        let result = auth_module
+            .auth_account_service
+            .search_accounts(&SearchAccountsDto { username_prefix: Some(format!("{prefix}%")), ..Default::default() })
+            .await?;
+        assert_eq!(0, result.total_count);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_search_accounts_by_role_and_creation_date() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let prefix = new_hyphenated_uuid();
+        let role = new_hyphenated_uuid();
+
+        let user = create_user_with_username(auth_module, &format!("{prefix}_a"), true).await?;
+        create_user_with_username(auth_module, &format!("{prefix}_b"), true).await?;
+        auth_module.auth_account_service.add_roles(user.id, &[role.clone()]).await?;
+
+        let result = auth_module
+            .auth_account_service
+            .search_accounts(&SearchAccountsDto { role: Some(role.clone()), ..Default::default() })
+            .await?;
+        assert_eq!(1, result.total_count);
+        assert_eq!(vec![user.id], ids(&result.accounts));
+
+        let created_date = user.data.created_date_epoch_seconds;
+        let search = SearchAccountsDto {
+            username_prefix: Some(prefix),
+            created_from_epoch_seconds: Some(created_date - 60),
+            created_to_epoch_seconds: Some(current_epoch_seconds() + 60),
+            ..Default::default()
+        };
+        assert_eq!(2, auth_module.auth_account_service.search_accounts(&search).await?.total_count);
+
+        let search = SearchAccountsDto {
+            created_from_epoch_seconds: Some(current_epoch_seconds() + 60),
+            created_to_epoch_seconds: None,
+            ..search
+        };
+        assert_eq!(0, auth_module.auth_account_service.search_accounts(&search).await?.total_count);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_paginate_accounts_by_offset_and_by_keyset() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let prefix = new_hyphenated_uuid();
+
+        let mut users = vec![];
+        for suffix in ["a", "b", "c", "d", "e"] {
+            users.push(create_user_with_username(auth_module, &format!("{prefix}_{suffix}"), true).await?);
+        }
+        let expected_ids: Vec<i64> = users.iter().rev().map(|user| user.id).collect();
+
+        let search = SearchAccountsDto {
+            username_prefix: Some(prefix),
+            sort_by: AccountSortField::Username,
+            sort_direction: SortDirection::Desc,
+            limit: 2,
+            ..Default::default()
+        };
+
+        let page = auth_module
+            .auth_account_service
+            .search_accounts(&SearchAccountsDto { offset: 2, ..search.clone() })
+            .await?;
+        assert_eq!(5, page.total_count);
+        assert_eq!(expected_ids[2..4], ids(&page.accounts));
+
+        let mut keyset_ids = vec![];
+        let mut after_user_id = None;
+        loop {
+            let page = auth_module
+                .auth_account_service
+                .search_accounts(&SearchAccountsDto { after_user_id, ..search.clone() })
+                .await?;
+            assert_eq!(5, page.total_count);
+            if page.accounts.is_empty() {
+                break;
+            }
+            keyset_ids.extend(ids(&page.accounts));
+            after_user_id = page.accounts.last().map(|account| account.user_id);
+        }
+        assert_eq!(expected_ids, keyset_ids);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_search_accounts_with_invalid_page() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+
+        for search in [
+            SearchAccountsDto { limit: 0, ..Default::default() },
+            SearchAccountsDto { limit: 1001, ..Default::default() },
+            SearchAccountsDto { after_user_id: Some(1), offset: 10, ..Default::default() },
+            SearchAccountsDto {
+                created_from_epoch_seconds: Some(100),
+                created_to_epoch_seconds: Some(100),
+                ..Default::default()
+            },
+        ] {
+            match auth_module.auth_account_service.search_accounts(&search).await {
+                Err(LightSpeedError::ValidationError { .. }) => {}
+                _ => panic!(),
+            }
+        }
+
+        Ok(())
+    })
+}
+
+async fn create_user_with_username<RepoManager: AuthRepositoryManager>(
+    auth_module: &AuthModule<RepoManager>,
+    username: &str,
+    activate: bool,
+) -> Result<AuthAccountModel, LightSpeedError> {
+    let password = new_hyphenated_uuid();
+    let (user, token) = auth_module
+        .auth_account_service
+        .create_user(CreateLoginDto {
+            username: Some(username.to_owned()),
+            email: format!("{username}@email.fake"),
+            data: HashMap::new(),
+            accept_privacy_policy: true,
+            language: Language::En,
+            password: password.clone(),
+            password_confirm: password,
+        })
+        .await?;
+
+    if activate {
+        auth_module.auth_account_service.activate_user(&token.data.token).await
+    } else {
+        Ok(user)
+    }
+}
+
+fn ids(accounts: &[AccountDto]) -> Vec<i64> {
+    accounts.iter().map(|account| account.user_id).collect()
+}
@@ -1,4 +1,4 @@
-use crate::tests::util::{create_user, create_user_with_password};
+use crate::tests::util::{authenticated, create_user, create_user_with_password};
 use crate::{data, test};
 use c3p0::*;
 use lightspeed_auth::dto::change_password_dto::ChangePasswordDto;
@@ -8,8 +8,10 @@ use lightspeed_auth::model::auth_account::AuthAccountStatus;
 use lightspeed_auth::model::token::TokenType;
 use lightspeed_auth::repository::AuthRepositoryManager;
 use lightspeed_auth::service::auth_account::AuthAccountService;
+use lightspeed_auth::service::password_hasher::{BcryptPasswordHasher, PasswordHasher};
 use lightspeed_core::error::{ErrorCodes, LightSpeedError};
 use lightspeed_core::model::language::Language;
+use lightspeed_core::service::validator::ERR_NOT_UNIQUE;
 use lightspeed_core::utils::{current_epoch_seconds, new_hyphenated_uuid};
 use std::collections::HashMap;
 
@@ -65,11 +67,10 @@ fn should_assign_default_roles_at_account_creation() -> Result<(), LightSpeedErr
         auth_config.default_roles_on_account_creation = vec![new_hyphenated_uuid()];
 
         let auth_account_service = AuthAccountService::new(
-            auth_module.repo_manager.c3p0().clone(),
+            &auth_module.repo_manager,
             auth_config.clone(),
-            auth_module.token_service.clone(),
-            auth_module.password_codec.clone(),
-            auth_module.repo_manager.auth_account_repo(),
+            auth_module.auth_account_services(),
+            auth_module.clock.clone(),
         );
 
         let (user, _) = auth_account_service
@@ -201,6 +202,78 @@ fn should_use_the_email_as_username_if_username_is_empty() -> Result<(), LightSp
     })
 }
 
+#[test]
+fn should_normalize_username_and_email() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+
+        let username = new_hyphenated_uuid();
+        let email = format!("{username}@email.fake");
+        let password = new_hyphenated_uuid();
+
+        let (user, token) = auth_module
+            .auth_account_service
+            .create_user(CreateLoginDto {
+                username: Some(format!(" {} ", username.to_uppercase())),
+                email: email.to_uppercase(),
+                data: HashMap::new(),
+                accept_privacy_policy: true,
+                language: Language::En,
+                password: password.clone(),
+                password_confirm: password.clone(),
+            })
+            .await?;
+
+        assert_eq!(username, user.data.username);
+        assert_eq!(email, user.data.email);
+
+        auth_module.auth_account_service.activate_user(&token.data.token).await?;
+        assert!(auth_module
+            .auth_account_service
+            .login(&username.to_uppercase(), &password)
+            .await
+            .and_then(authenticated)
+            .is_ok());
+        assert!(auth_module.auth_account_service.fetch_by_username(&format!("{username} ")).await.is_ok());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_create_users_that_differ_only_by_case() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user(auth_module, false).await?;
+
+        let password = new_hyphenated_uuid();
+        let result = auth_module
+            .auth_account_service
+            .create_user(CreateLoginDto {
+                username: Some(user.data.username.to_uppercase()),
+                email: user.data.email.to_uppercase(),
+                data: HashMap::new(),
+                accept_privacy_policy: true,
+                language: Language::En,
+                password: password.clone(),
+                password_confirm: password.clone(),
+            })
+            .await;
+
+        match result {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert_eq!(vec![ERR_NOT_UNIQUE.to_owned()], details.details["username"]);
+                assert_eq!(vec![ERR_NOT_UNIQUE.to_owned()], details.details["email"]);
+            }
+            _ => panic!(),
+        }
+
+        Ok(())
+    })
+}
+
 #[test]
 fn should_activate_user() -> Result<(), LightSpeedError> {
     test(async {
@@ -362,9 +435,11 @@ fn should_regenerate_activation_token_even_if_token_expired() -> Result<(), Ligh
         let data = data(false).await;
         let auth_module = &data.0;
         let (_, mut token) = create_user(auth_module, false).await?;
+        let plain_token = token.data.token.clone();
+        token.data.token = auth_module.token_service.hash_token(&plain_token);
         token.data.expire_at_epoch_seconds = 0;
 
-        let token_model = &auth_module
+        auth_module
             .repo_manager
             .c3p0()
             .transaction(|conn| async {
@@ -376,15 +451,15 @@ fn should_regenerate_activation_token_even_if_token_expired() -> Result<(), Ligh
             .repo_manager
             .c3p0()
             .transaction(|conn| async {
-                auth_module.token_service.fetch_by_token_with_conn(conn, &token_model.data.token, true).await
+                auth_module.token_service.fetch_by_token_with_conn(conn, &plain_token, true).await
             })
             .await
             .is_err());
 
-        assert!(auth_module.auth_account_service.activate_user(&token_model.data.token).await.is_err());
+        assert!(auth_module.auth_account_service.activate_user(&plain_token).await.is_err());
 
         let (_, new_token) =
-            auth_module.auth_account_service.generate_new_activation_token_by_token(&token_model.data.token).await?;
+            auth_module.auth_account_service.generate_new_activation_token_by_token(&plain_token).await?;
 
         assert!(auth_module.auth_account_service.activate_user(&new_token.data.token).await.is_ok());
 
@@ -431,7 +506,8 @@ fn should_login_active_user() -> Result<(), LightSpeedError> {
         let auth_validity_seconds = auth_module.auth_config.auth_session_max_validity_minutes * 60;
         let before_login_ts_seconds = current_epoch_seconds();
 
-        let auth = auth_module.auth_account_service.login(&user.data.username, password).await?;
+        let auth =
+            auth_module.auth_account_service.login(&user.data.username, password).await.and_then(authenticated)?;
 
         let after_login_ts_seconds = current_epoch_seconds();
 
@@ -447,6 +523,44 @@ fn should_login_active_user() -> Result<(), LightSpeedError> {
     })
 }
 
+#[test]
+fn should_upgrade_outdated_password_hash_at_login() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let password = "123456789";
+        let (mut user, _) = create_user_with_password(auth_module, password, true).await?;
+        assert!(user.data.password.starts_with("$argon2id$"));
+
+        user.data.password = BcryptPasswordHasher::new(4).hash(password)?;
+        let user = auth_module
+            .repo_manager
+            .c3p0()
+            .transaction(|conn| async { auth_module.repo_manager.auth_account_repo().update(conn, user).await })
+            .await?;
+        assert!(auth_module.password_codec.needs_rehash(&user.data.password));
+
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, password)
+            .await
+            .and_then(authenticated)
+            .is_ok());
+
+        let user = auth_module.auth_account_service.fetch_by_user_id(user.id).await?;
+        assert!(user.data.password.starts_with("$argon2id$"));
+        assert!(!auth_module.password_codec.needs_rehash(&user.data.password));
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, password)
+            .await
+            .and_then(authenticated)
+            .is_ok());
+
+        Ok(())
+    })
+}
+
 #[test]
 fn should_not_login_inactive_user() -> Result<(), LightSpeedError> {
     test(async {
@@ -455,7 +569,8 @@ fn should_not_login_inactive_user() -> Result<(), LightSpeedError> {
         let password = "123456789";
         let (user, _) = create_user_with_password(auth_module, password, false).await?;
 
-        let result = auth_module.auth_account_service.login(&user.data.username, password).await;
+        let result =
+            auth_module.auth_account_service.login(&user.data.username, password).await.and_then(authenticated);
 
         match result {
             Err(LightSpeedError::BadRequest { code, message }) => {
@@ -477,7 +592,8 @@ fn should_return_wrong_credentials_on_login_of_inactive_user_with_wrong_password
         let password = "123456789";
         let (user, _) = create_user_with_password(auth_module, password, false).await?;
 
-        let result = auth_module.auth_account_service.login(&user.data.username, "wrong_password").await;
+        let result =
+            auth_module.auth_account_service.login(&user.data.username, "wrong_password").await.and_then(authenticated);
 
         match result {
             Err(LightSpeedError::BadRequest { code, message }) => {
@@ -499,7 +615,12 @@ fn should_not_login_with_wrong_username() -> Result<(), LightSpeedError> {
         let password = "123456789";
         let (user, _) = create_user_with_password(auth_module, password, true).await?;
 
-        assert!(auth_module.auth_account_service.login(&format!("{}_", user.data.username), password).await.is_err());
+        assert!(auth_module
+            .auth_account_service
+            .login(&format!("{}_", user.data.username), password)
+            .await
+            .and_then(authenticated)
+            .is_err());
 
         Ok(())
     })
@@ -513,7 +634,12 @@ fn should_not_login_with_wrong_password() -> Result<(), LightSpeedError> {
         let password = "123456789";
         let (user, _) = create_user_with_password(auth_module, password, true).await?;
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, &format!("{password}_")).await.is_err());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, &format!("{password}_"))
+            .await
+            .and_then(authenticated)
+            .is_err());
 
         Ok(())
     })
@@ -727,9 +853,19 @@ fn should_reset_password_by_token() -> Result<(), LightSpeedError> {
 
         assert_eq!(user.id, updated_user.id);
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, &password).await.is_err());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, &password)
+            .await
+            .and_then(authenticated)
+            .is_err());
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, &password_new).await.is_ok());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, &password_new)
+            .await
+            .and_then(authenticated)
+            .is_ok());
 
         Ok(())
     })
@@ -895,19 +1031,32 @@ fn should_change_user_password() -> Result<(), LightSpeedError> {
 
         let updated_user = auth_module
             .auth_account_service
-            .change_password(ChangePasswordDto {
-                user_id: user.id,
-                old_password: password.clone(),
-                new_password: password_new.clone(),
-                new_password_confirm: password_new.clone(),
-            })
+            .change_password(
+                ChangePasswordDto {
+                    user_id: user.id,
+                    old_password: password.clone(),
+                    new_password: password_new.clone(),
+                    new_password_confirm: password_new.clone(),
+                },
+                None,
+            )
             .await?;
 
         assert_eq!(updated_user.id, user.id);
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, &password).await.is_err());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, &password)
+            .await
+            .and_then(authenticated)
+            .is_err());
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, &password_new).await.is_ok());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, &password_new)
+            .await
+            .and_then(authenticated)
+            .is_ok());
 
         Ok(())
     })
@@ -926,19 +1075,32 @@ fn should_not_change_user_password_if_wrong_old_password() -> Result<(), LightSp
 
         let result = auth_module
             .auth_account_service
-            .change_password(ChangePasswordDto {
-                user_id: user.id,
-                old_password: format!("__{password}__"),
-                new_password: password_new.clone(),
-                new_password_confirm: password_new.clone(),
-            })
+            .change_password(
+                ChangePasswordDto {
+                    user_id: user.id,
+                    old_password: format!("__{password}__"),
+                    new_password: password_new.clone(),
+                    new_password_confirm: password_new.clone(),
+                },
+                None,
+            )
             .await;
 
         assert!(result.is_err());
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, &password).await.is_ok());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, &password)
+            .await
+            .and_then(authenticated)
+            .is_ok());
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, &password_new).await.is_err());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, &password_new)
+            .await
+            .and_then(authenticated)
+            .is_err());
 
         Ok(())
     })
@@ -957,12 +1119,15 @@ fn should_not_change_user_password_if_inactive_user() -> Result<(), LightSpeedEr
 
         let result = auth_module
             .auth_account_service
-            .change_password(ChangePasswordDto {
-                user_id: user.id,
-                old_password: password,
-                new_password: password_new.clone(),
-                new_password_confirm: password_new.clone(),
-            })
+            .change_password(
+                ChangePasswordDto {
+                    user_id: user.id,
+                    old_password: password,
+                    new_password: password_new.clone(),
+                    new_password_confirm: password_new.clone(),
+                },
+                None,
+            )
             .await;
 
         assert!(result.is_err());
@@ -984,12 +1149,15 @@ fn should_not_change_user_password_if_new_passwords_do_not_match() -> Result<(),
 
         let result = auth_module
             .auth_account_service
-            .change_password(ChangePasswordDto {
-                user_id: user.id,
-                old_password: password,
-                new_password: password_new.clone(),
-                new_password_confirm: format!("__{}", password_new.clone()),
-            })
+            .change_password(
+                ChangePasswordDto {
+                    user_id: user.id,
+                    old_password: password,
+                    new_password: password_new.clone(),
+                    new_password_confirm: format!("__{}", password_new.clone()),
+                },
+                None,
+            )
             .await;
 
         assert!(result.is_err());
@@ -1063,7 +1231,12 @@ fn should_change_username() -> Result<(), LightSpeedError> {
         let password = "123456789";
         let (user, _) = create_user_with_password(auth_module, password, true).await?;
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, password).await.is_ok());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, password)
+            .await
+            .and_then(authenticated)
+            .is_ok());
 
         // Act
         let new_username = new_hyphenated_uuid();
@@ -1079,9 +1252,14 @@ fn should_change_username() -> Result<(), LightSpeedError> {
         assert_eq!(user.data.created_date_epoch_seconds, updated_user.data.created_date_epoch_seconds);
         assert_eq!(user.data.password, updated_user.data.password);
 
-        assert!(auth_module.auth_account_service.login(&new_username, password).await.is_ok());
+        assert!(auth_module.auth_account_service.login(&new_username, password).await.and_then(authenticated).is_ok());
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, password).await.is_err());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, password)
+            .await
+            .and_then(authenticated)
+            .is_err());
 
         Ok(())
     })
@@ -1096,7 +1274,12 @@ fn should_change_email() -> Result<(), LightSpeedError> {
         let password = "123456789";
         let (user, _) = create_user_with_password(auth_module, password, true).await?;
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, password).await.is_ok());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, password)
+            .await
+            .and_then(authenticated)
+            .is_ok());
 
         // Act
         let new_email = format!("{}@test.com", new_hyphenated_uuid());
@@ -1111,7 +1294,12 @@ fn should_change_email() -> Result<(), LightSpeedError> {
         assert_eq!(user.data.created_date_epoch_seconds, updated_user.data.created_date_epoch_seconds);
         assert_eq!(user.data.password, updated_user.data.password);
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, password).await.is_ok());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, password)
+            .await
+            .and_then(authenticated)
+            .is_ok());
 
         Ok(())
     })
@@ -1126,7 +1314,12 @@ fn should_change_username_and_email() -> Result<(), LightSpeedError> {
         let password = "123456789";
         let (user, _) = create_user_with_password(auth_module, password, true).await?;
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, password).await.is_ok());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, password)
+            .await
+            .and_then(authenticated)
+            .is_ok());
 
         // Act
         let new_username = new_hyphenated_uuid();
@@ -1145,9 +1338,46 @@ fn should_change_username_and_email() -> Result<(), LightSpeedError> {
         assert_eq!(user.data.created_date_epoch_seconds, updated_user.data.created_date_epoch_seconds);
         assert_eq!(user.data.password, updated_user.data.password);
 
-        assert!(auth_module.auth_account_service.login(&new_username, password).await.is_ok());
+        assert!(auth_module.auth_account_service.login(&new_username, password).await.and_then(authenticated).is_ok());
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, password).await.is_err());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, password)
+            .await
+            .and_then(authenticated)
+            .is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_change_username_or_email_to_existing_ones() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user(auth_module, true).await?;
+        let (other_user, _) = create_user(auth_module, true).await?;
+
+        let result = auth_module
+            .auth_account_service
+            .change_user_data(user.id, Some(other_user.data.username.clone()), Some(other_user.data.email.clone()))
+            .await;
+
+        match result {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert_eq!(vec![ERR_NOT_UNIQUE.to_owned()], details.details["username"]);
+                assert_eq!(vec![ERR_NOT_UNIQUE.to_owned()], details.details["email"]);
+            }
+            _ => panic!(),
+        }
+
+        // Setting the current values is not a conflict
+        assert!(auth_module
+            .auth_account_service
+            .change_user_data(user.id, Some(user.data.username.clone()), Some(user.data.email.clone()))
+            .await
+            .is_ok());
 
         Ok(())
     })
@@ -1162,7 +1392,12 @@ fn should_disable_an_active_user() -> Result<(), LightSpeedError> {
         let password = "123456789";
         let (user, _) = create_user_with_password(auth_module, password, true).await?;
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, password).await.is_ok());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, password)
+            .await
+            .and_then(authenticated)
+            .is_ok());
 
         // Act
         let updated_user = auth_module.auth_account_service.disable_by_user_id(user.id).await.unwrap();
@@ -1170,7 +1405,12 @@ fn should_disable_an_active_user() -> Result<(), LightSpeedError> {
         // Assert
         assert_eq!(AuthAccountStatus::Disabled, updated_user.data.status);
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, password).await.is_err());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, password)
+            .await
+            .and_then(authenticated)
+            .is_err());
 
         let loaded_user = auth_module.auth_account_service.fetch_by_user_id(user.id).await.unwrap();
 
@@ -1189,7 +1429,12 @@ fn should_fail_disabling_a_pending_user() -> Result<(), LightSpeedError> {
         let password = "123456789";
         let (user, _) = create_user_with_password(auth_module, password, false).await?;
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, password).await.is_err());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, password)
+            .await
+            .and_then(authenticated)
+            .is_err());
 
         // Act
         let result = auth_module.auth_account_service.disable_by_user_id(user.id).await;
@@ -1210,7 +1455,12 @@ fn should_fail_disabling_a_disabled_user() -> Result<(), LightSpeedError> {
         let password = "123456789";
         let (user, _) = create_user_with_password(auth_module, password, true).await?;
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, password).await.is_ok());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, password)
+            .await
+            .and_then(authenticated)
+            .is_ok());
 
         auth_module.auth_account_service.disable_by_user_id(user.id).await.unwrap();
 
@@ -1241,7 +1491,12 @@ fn should_activate_a_disabled_user() -> Result<(), LightSpeedError> {
         // Assert
         assert_eq!(AuthAccountStatus::Active, updated_user.data.status);
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, password).await.is_ok());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, password)
+            .await
+            .and_then(authenticated)
+            .is_ok());
 
         let loaded_user = auth_module.auth_account_service.fetch_by_user_id(user.id).await.unwrap();
 
@@ -1260,7 +1515,12 @@ fn should_fail_reactivating_a_pending_user() -> Result<(), LightSpeedError> {
         let password = "123456789";
         let (user, _) = create_user_with_password(auth_module, password, false).await?;
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, password).await.is_err());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, password)
+            .await
+            .and_then(authenticated)
+            .is_err());
 
         // Act
         let result = auth_module.auth_account_service.reactivate_disabled_user_by_user_id(user.id).await;
@@ -1281,7 +1541,12 @@ fn should_fail_reactivating_an_active_user() -> Result<(), LightSpeedError> {
         let password = "123456789";
         let (user, _) = create_user_with_password(auth_module, password, true).await?;
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, password).await.is_ok());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, password)
+            .await
+            .and_then(authenticated)
+            .is_ok());
 
         // Act
         let result = auth_module.auth_account_service.reactivate_disabled_user_by_user_id(user.id).await;
@@ -1308,7 +1573,12 @@ fn should_delete_a_user() -> Result<(), LightSpeedError> {
         // Assert
         assert_eq!(1, deleted_user_count);
 
-        assert!(auth_module.auth_account_service.login(&user.data.username, password).await.is_err());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, password)
+            .await
+            .and_then(authenticated)
+            .is_err());
 
         assert!(auth_module.auth_account_service.fetch_by_user_id(user.id).await.is_err());
 
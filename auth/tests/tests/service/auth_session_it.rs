@@ -0,0 +1,196 @@
+use crate::tests::util::{authenticated, create_user_with_password, new_auth_account_service};
+use crate::{data, test};
+use lightspeed_auth::dto::change_password_dto::ChangePasswordDto;
+use lightspeed_auth::dto::reset_password_dto::ResetPasswordDto;
+use lightspeed_core::clock::MockClock;
+use lightspeed_core::error::{ErrorCodes, LightSpeedError};
+use lightspeed_core::utils::current_epoch_seconds;
+
+const PASSWORD: &str = "123456789";
+
+#[test]
+fn should_record_the_session_at_login() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let auth_session_service = &auth_module.auth_session_service;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        let auth =
+            auth_module.auth_account_service.login(&user.data.username, PASSWORD).await.and_then(authenticated)?;
+
+        let sessions = auth_session_service.fetch_all_by_user_id(user.id).await?;
+        assert_eq!(1, sessions.len());
+        assert_eq!(auth.session_id, sessions[0].data.session_id);
+        assert_eq!(auth.creation_ts_seconds, sessions[0].data.created_date_epoch_seconds);
+        assert_eq!(auth.creation_ts_seconds, sessions[0].data.last_seen_epoch_seconds);
+        assert_eq!(auth.expiration_ts_seconds, sessions[0].data.expire_at_epoch_seconds);
+        assert!(sessions[0].data.user_agent.is_none());
+
+        let session = auth_session_service.touch(&auth.session_id, Some("Firefox"), Some("127.0.0.1")).await?;
+        assert_eq!(Some("Firefox"), session.data.user_agent.as_deref());
+        assert_eq!(Some("127.0.0.1"), session.data.ip_address.as_deref());
+
+        // Every login opens a new session
+        let other_auth =
+            auth_module.auth_account_service.login(&user.data.username, PASSWORD).await.and_then(authenticated)?;
+        assert_ne!(auth.session_id, other_auth.session_id);
+        assert_eq!(2, auth_session_service.fetch_all_by_user_id(user.id).await?.len());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_touch_a_session_with_concurrent_requests() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let auth_session_service = &auth_module.auth_session_service;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let auth =
+            auth_module.auth_account_service.login(&user.data.username, PASSWORD).await.and_then(authenticated)?;
+
+        let (first, second, third) = tokio::join!(
+            auth_session_service.touch(&auth.session_id, Some("Firefox"), None),
+            auth_session_service.touch(&auth.session_id, None, Some("127.0.0.1")),
+            auth_session_service.touch(&auth.session_id, None, None),
+        );
+        first?;
+        second?;
+        third?;
+
+        // The values that are not provided are preserved
+        let session = auth_session_service.touch(&auth.session_id, None, None).await?;
+        assert_eq!(Some("Firefox"), session.data.user_agent.as_deref());
+        assert_eq!(Some("127.0.0.1"), session.data.ip_address.as_deref());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_revoke_the_sessions() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let auth_session_service = &auth_module.auth_session_service;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let (other_user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        let first_auth =
+            auth_module.auth_account_service.login(&user.data.username, PASSWORD).await.and_then(authenticated)?;
+        let second_auth =
+            auth_module.auth_account_service.login(&user.data.username, PASSWORD).await.and_then(authenticated)?;
+
+        match auth_session_service.revoke(other_user.id, &first_auth.session_id).await {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ErrorCodes::NOT_FOUND, code),
+            _ => panic!(),
+        }
+
+        auth_session_service.revoke(user.id, &first_auth.session_id).await?;
+
+        match auth_session_service.touch(&first_auth.session_id, None, None).await {
+            Err(LightSpeedError::UnauthenticatedError) => {}
+            _ => panic!(),
+        }
+        assert!(auth_session_service.touch(&second_auth.session_id, None, None).await.is_ok());
+
+        assert_eq!(1, auth_session_service.revoke_all_by_user_id(user.id).await?);
+        assert!(auth_session_service.touch(&second_auth.session_id, None, None).await.is_err());
+        assert!(auth_session_service.fetch_all_by_user_id(user.id).await?.is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_revoke_the_sessions_of_a_disabled_user() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        let auth =
+            auth_module.auth_account_service.login(&user.data.username, PASSWORD).await.and_then(authenticated)?;
+        auth_module.auth_account_service.disable_by_user_id(user.id).await?;
+
+        assert!(auth_module.auth_session_service.touch(&auth.session_id, None, None).await.is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_revoke_the_sessions_when_the_password_changes() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let auth_session_service = &auth_module.auth_session_service;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        let current_auth =
+            auth_module.auth_account_service.login(&user.data.username, PASSWORD).await.and_then(authenticated)?;
+        let other_auth =
+            auth_module.auth_account_service.login(&user.data.username, PASSWORD).await.and_then(authenticated)?;
+
+        // The session that changes the password is kept
+        let new_password = format!("{PASSWORD}_new");
+        auth_module
+            .auth_account_service
+            .change_password(
+                ChangePasswordDto {
+                    user_id: user.id,
+                    old_password: PASSWORD.to_owned(),
+                    new_password: new_password.clone(),
+                    new_password_confirm: new_password.clone(),
+                },
+                Some(&current_auth.session_id),
+            )
+            .await?;
+        assert!(auth_session_service.touch(&current_auth.session_id, None, None).await.is_ok());
+        assert!(auth_session_service.touch(&other_auth.session_id, None, None).await.is_err());
+
+        // The reset terminates all the sessions
+        let (_, token) = auth_module.auth_account_service.generate_reset_password_token(&user.data.username).await?;
+        let reset_password = format!("{PASSWORD}_reset");
+        auth_module
+            .auth_account_service
+            .reset_password_by_token(ResetPasswordDto {
+                token: token.data.token,
+                password: reset_password.clone(),
+                password_confirm: reset_password,
+            })
+            .await?;
+        assert!(auth_session_service.fetch_all_by_user_id(user.id).await?.is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_purge_the_expired_sessions() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let auth_session_service = &auth_module.auth_session_service;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        // A session opened in the past that is already expired
+        let validity_seconds = auth_module.auth_config.auth_session_max_validity_minutes * 60;
+        let clock = MockClock::from_epoch_seconds(current_epoch_seconds() - validity_seconds - 10);
+        let auth_account_service = new_auth_account_service(auth_module, auth_module.auth_config.clone(), &clock);
+        let expired_auth = auth_account_service.login(&user.data.username, PASSWORD).await.and_then(authenticated)?;
+
+        assert!(auth_session_service.touch(&expired_auth.session_id, None, None).await.is_err());
+        assert!(auth_session_service.fetch_all_by_user_id(user.id).await?.is_empty());
+        assert!(auth_session_service.purge_expired().await? >= 1);
+
+        let auth =
+            auth_module.auth_account_service.login(&user.data.username, PASSWORD).await.and_then(authenticated)?;
+        assert!(auth_session_service.purge_expired().await.is_ok());
+        assert!(auth_session_service.touch(&auth.session_id, None, None).await.is_ok());
+
+        Ok(())
+    })
+}
@@ -0,0 +1,188 @@
+use crate::tests::util::{authenticated, create_user, create_user_with_password};
+use crate::{data, test};
+use c3p0::*;
+use lightspeed_auth::dto::change_email_dto::ChangeEmailDto;
+use lightspeed_auth::model::token::TokenType;
+use lightspeed_auth::repository::AuthRepositoryManager;
+use lightspeed_core::error::{ErrorCodes, LightSpeedError};
+use lightspeed_core::service::validator::ERR_NOT_UNIQUE;
+use lightspeed_core::utils::new_hyphenated_uuid;
+
+const PASSWORD: &str = "123456789";
+
+#[test]
+fn should_change_email_after_confirmation() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let new_email = format!("{}@email.fake", new_hyphenated_uuid());
+
+        let (pending_user, token) = auth_module
+            .auth_account_service
+            .request_email_change(ChangeEmailDto {
+                user_id: user.id,
+                password: PASSWORD.to_owned(),
+                new_email: new_email.clone(),
+            })
+            .await?;
+
+        assert_eq!(TokenType::EmailChange, token.data.token_type);
+        assert_eq!(user.data.username, token.data.username);
+        assert_eq!(user.data.email, pending_user.data.email);
+        assert_eq!(new_email, pending_user.data.pending_email_change.as_ref().unwrap().email);
+
+        let (updated_user, old_email) =
+            auth_module.auth_account_service.confirm_email_change(&token.data.token).await?;
+
+        assert_eq!(user.data.email, old_email);
+        assert_eq!(new_email, updated_user.data.email);
+        assert!(updated_user.data.pending_email_change.is_none());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, PASSWORD)
+            .await
+            .and_then(authenticated)
+            .is_ok());
+
+        // The token can be used only once
+        assert!(auth_module.auth_account_service.confirm_email_change(&token.data.token).await.is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_request_email_change_with_wrong_password() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        let result = auth_module
+            .auth_account_service
+            .request_email_change(ChangeEmailDto {
+                user_id: user.id,
+                password: format!("{PASSWORD}_wrong"),
+                new_email: format!("{}@email.fake", new_hyphenated_uuid()),
+            })
+            .await;
+
+        match result {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ErrorCodes::WRONG_CREDENTIALS, code),
+            _ => panic!(),
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_change_email_to_an_existing_one() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let (other_user, _) = create_user(auth_module, true).await?;
+
+        let result = auth_module
+            .auth_account_service
+            .request_email_change(ChangeEmailDto {
+                user_id: user.id,
+                password: PASSWORD.to_owned(),
+                new_email: other_user.data.email.clone(),
+            })
+            .await;
+
+        match result {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert_eq!(vec![ERR_NOT_UNIQUE.to_owned()], details.details["new_email"])
+            }
+            _ => panic!(),
+        }
+
+        // The email could be taken between the request and the confirmation
+        let new_email = format!("{}@email.fake", new_hyphenated_uuid());
+        let (_, token) = auth_module
+            .auth_account_service
+            .request_email_change(ChangeEmailDto {
+                user_id: user.id,
+                password: PASSWORD.to_owned(),
+                new_email: new_email.clone(),
+            })
+            .await?;
+        auth_module.auth_account_service.change_user_data(other_user.id, None, Some(new_email)).await?;
+
+        match auth_module.auth_account_service.confirm_email_change(&token.data.token).await {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert_eq!(vec![ERR_NOT_UNIQUE.to_owned()], details.details["email"])
+            }
+            _ => panic!(),
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_confirm_only_the_last_requested_email_change() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        let mut tokens = vec![];
+        for _ in 0..2 {
+            let (_, token) = auth_module
+                .auth_account_service
+                .request_email_change(ChangeEmailDto {
+                    user_id: user.id,
+                    password: PASSWORD.to_owned(),
+                    new_email: format!("{}@email.fake", new_hyphenated_uuid()),
+                })
+                .await?;
+            tokens.push(token);
+        }
+
+        assert!(auth_module.auth_account_service.confirm_email_change(&tokens[0].data.token).await.is_err());
+        assert!(auth_module.auth_account_service.confirm_email_change(&tokens[1].data.token).await.is_ok());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_discard_the_pending_email_change_when_the_email_is_overridden() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        let (_, token) = auth_module
+            .auth_account_service
+            .request_email_change(ChangeEmailDto {
+                user_id: user.id,
+                password: PASSWORD.to_owned(),
+                new_email: format!("{}@email.fake", new_hyphenated_uuid()),
+            })
+            .await?;
+
+        let new_email = format!("{}@email.fake", new_hyphenated_uuid());
+        let updated_user =
+            auth_module.auth_account_service.change_user_data(user.id, None, Some(new_email.clone())).await?;
+
+        assert_eq!(new_email, updated_user.data.email);
+        assert!(updated_user.data.pending_email_change.is_none());
+        let tokens = auth_module
+            .repo_manager
+            .c3p0()
+            .transaction(|conn| async {
+                auth_module.token_service.fetch_all_by_username_with_conn(conn, &user.data.username).await
+            })
+            .await?;
+        assert!(tokens.iter().all(|current| current.id != token.id));
+        assert!(auth_module.auth_account_service.confirm_email_change(&token.data.token).await.is_err());
+
+        Ok(())
+    })
+}
@@ -0,0 +1,192 @@
+use crate::tests::util::{authenticated, create_user_with_password};
+use crate::{data, test};
+use lightspeed_auth::model::auth_account::AuthAccountModel;
+use lightspeed_auth::repository::AuthRepositoryManager;
+use lightspeed_auth::AuthModule;
+use lightspeed_core::error::{ErrorCodes, LightSpeedError};
+use lightspeed_core::service::auth::{Auth, AuthService, InMemoryRolesProvider, Role};
+
+const PASSWORD: &str = "123456789";
+const SUPPORT_ROLE: &str = "impersonation_it_support";
+const ADMIN_ROLE: &str = "impersonation_it_admin";
+
+#[test]
+fn should_impersonate_a_user_and_record_the_impersonation() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let auth_service = new_auth_service(auth_module);
+        let (support_user, support_auth) = create_support_user(auth_module).await?;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        let auth = auth_module
+            .auth_account_service
+            .impersonate(&auth_service.auth(support_auth), user.id, Some("ticket 123".to_owned()))
+            .await?;
+        assert_eq!(user.id, auth.id);
+        assert_eq!(user.data.username, auth.username);
+        assert_eq!(Some(support_user.id), auth.impersonated_by);
+        assert_eq!(
+            auth.creation_ts_seconds + auth_module.auth_config.impersonation_validity_minutes * 60,
+            auth.expiration_ts_seconds
+        );
+
+        let auth_context = auth_service.auth(auth.clone());
+        assert!(auth_context.is_authenticated().is_ok());
+        assert!(auth_context.is_impersonated());
+        assert!(auth_context.is_not_impersonated().is_err());
+        assert!(auth_module.auth_session_service.touch(&auth.session_id, None, None).await.is_ok());
+
+        let impersonations = auth_module.auth_account_service.fetch_impersonations_by_user_id(user.id).await?;
+        assert_eq!(1, impersonations.len());
+        assert_eq!(support_user.id, impersonations[0].data.impersonator_user_id);
+        assert_eq!(auth.session_id, impersonations[0].data.session_id);
+        assert_eq!(Some("ticket 123".to_owned()), impersonations[0].data.reason);
+        assert_eq!(auth.expiration_ts_seconds, impersonations[0].data.expire_at_epoch_seconds);
+
+        let impersonations =
+            auth_module.auth_account_service.fetch_impersonations_by_impersonator_user_id(support_user.id).await?;
+        assert_eq!(1, impersonations.len());
+        assert_eq!(user.id, impersonations[0].data.user_id);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_impersonate_without_the_permission() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let auth_service = new_auth_service(auth_module);
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let (other_user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let auth =
+            auth_module.auth_account_service.login(&user.data.username, PASSWORD).await.and_then(authenticated)?;
+
+        match auth_module.auth_account_service.impersonate(&auth_service.auth(auth), other_user.id, None).await {
+            Err(LightSpeedError::ForbiddenError { .. }) => {}
+            _ => panic!(),
+        }
+        assert!(auth_module.auth_account_service.fetch_impersonations_by_user_id(other_user.id).await?.is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_impersonate_with_an_impersonated_auth() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let auth_service = new_auth_service(auth_module);
+        let (_, support_auth) = create_support_user(auth_module).await?;
+        let (other_support_user, _) = create_support_user(auth_module).await?;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        // The impersonated Auth of another user with the impersonation permission
+        let impersonated_auth = Auth {
+            id: other_support_user.id,
+            username: other_support_user.data.username,
+            impersonated_by: Some(support_auth.id),
+            ..support_auth.clone()
+        };
+
+        match auth_module.auth_account_service.impersonate(&auth_service.auth(impersonated_auth), user.id, None).await {
+            Err(LightSpeedError::ForbiddenError { .. }) => {}
+            _ => panic!(),
+        }
+
+        // The own account cannot be impersonated
+        match auth_module
+            .auth_account_service
+            .impersonate(&auth_service.auth(support_auth.clone()), support_auth.id, None)
+            .await
+        {
+            Err(LightSpeedError::ForbiddenError { .. }) => {}
+            _ => panic!(),
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_impersonate_a_user_with_more_rights() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let auth_service = new_auth_service(auth_module);
+        let (_, support_auth) = create_support_user(auth_module).await?;
+        let (other_support_user, _) = create_support_user(auth_module).await?;
+        let (admin, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        auth_module.auth_account_service.add_roles(admin.id, &[ADMIN_ROLE.to_owned()]).await?;
+
+        // The admin has a role that the support user does not have
+        match auth_module
+            .auth_account_service
+            .impersonate(&auth_service.auth(support_auth.clone()), admin.id, None)
+            .await
+        {
+            Err(LightSpeedError::ForbiddenError { .. }) => {}
+            _ => panic!(),
+        }
+
+        // The other support user has the impersonation permission
+        match auth_module
+            .auth_account_service
+            .impersonate(&auth_service.auth(support_auth.clone()), other_support_user.id, None)
+            .await
+        {
+            Err(LightSpeedError::ForbiddenError { .. }) => {}
+            _ => panic!(),
+        }
+
+        assert!(auth_module
+            .auth_account_service
+            .fetch_impersonations_by_impersonator_user_id(support_auth.id)
+            .await?
+            .is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_impersonate_an_inactive_user() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let auth_service = new_auth_service(auth_module);
+        let (_, support_auth) = create_support_user(auth_module).await?;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        auth_module.auth_account_service.disable_by_user_id(user.id).await?;
+
+        match auth_module.auth_account_service.impersonate(&auth_service.auth(support_auth), user.id, None).await {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ErrorCodes::INACTIVE_USER, code),
+            _ => panic!(),
+        }
+
+        Ok(())
+    })
+}
+
+fn new_auth_service<RepoManager: AuthRepositoryManager>(
+    auth_module: &AuthModule<RepoManager>,
+) -> AuthService<InMemoryRolesProvider> {
+    let roles = vec![Role {
+        name: SUPPORT_ROLE.to_owned(),
+        permissions: vec![auth_module.auth_config.impersonation_permission.clone()],
+    }];
+    AuthService::new(InMemoryRolesProvider::new(roles.into()))
+}
+
+/// Creates a user with the permission to impersonate the other users and logs it in
+async fn create_support_user<RepoManager: AuthRepositoryManager>(
+    auth_module: &AuthModule<RepoManager>,
+) -> Result<(AuthAccountModel, Auth), LightSpeedError> {
+    let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+    let user = auth_module.auth_account_service.add_roles(user.id, &[SUPPORT_ROLE.to_owned()]).await?;
+    let auth = auth_module.auth_account_service.login(&user.data.username, PASSWORD).await.and_then(authenticated)?;
+    Ok((user, auth))
+}
@@ -0,0 +1,134 @@
+use crate::tests::util::authenticated;
+use crate::{data, test};
+use lightspeed_auth::dto::import_account_dto::ImportAccountDto;
+use lightspeed_auth::model::auth_account::AuthAccountStatus;
+use lightspeed_auth::service::auth_account::ERR_UNSUPPORTED_HASH;
+use lightspeed_core::error::{ErrorCodes, LightSpeedError};
+use lightspeed_core::service::validator::ERR_NOT_UNIQUE;
+use lightspeed_core::utils::new_hyphenated_uuid;
+
+// The Django hash of "password" with salt "seasalt" and 1000 iterations
+const DJANGO_HASH: &str = "pbkdf2_sha256$1000$seasalt$YIWkt6M1JFXrHg5s0jZjBSc7C2Cz6QvchSJ0h8Y+i7c=";
+// The LDAP salted SHA1 hash of "password" with salt "salt"
+const LDAP_HASH: &str = "{SSHA}yI6cZwQadOA1e+/f+T+H3eCQQhRzYWx0";
+const JAVA_BCRYPT_HASH: &str = "$2a$10$TkWSZIawgD9tjkmAV2GjGOt30FQktiTlpZTIHbxatakOHf4G0.aA.";
+
+#[test]
+fn should_import_accounts_and_upgrade_legacy_hashes_at_login() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+
+        let accounts = vec![
+            new_import_account_dto(DJANGO_HASH, AuthAccountStatus::Active),
+            new_import_account_dto(LDAP_HASH, AuthAccountStatus::Active),
+            new_import_account_dto(JAVA_BCRYPT_HASH, AuthAccountStatus::Active),
+        ];
+        let imported = auth_module.auth_account_service.import_accounts(accounts.clone()).await?;
+        assert_eq!(3, imported.len());
+
+        for (account, user) in accounts.iter().zip(&imported) {
+            assert_eq!(account.username, user.data.username);
+            assert_eq!(account.email, user.data.email);
+            assert_eq!(account.roles, user.data.roles);
+            assert_eq!(account.password_hash, user.data.password);
+            assert_eq!(123, user.data.created_date_epoch_seconds);
+
+            let password = if account.password_hash == JAVA_BCRYPT_HASH { "admin" } else { "password" };
+            assert!(auth_module
+                .auth_account_service
+                .login(&user.data.username, "wrong")
+                .await
+                .and_then(authenticated)
+                .is_err());
+            assert!(auth_module
+                .auth_account_service
+                .login(&user.data.username, password)
+                .await
+                .and_then(authenticated)
+                .is_ok());
+
+            let user = auth_module.auth_account_service.fetch_by_user_id(user.id).await?;
+            assert!(user.data.password.starts_with("$argon2id$"));
+            assert!(auth_module
+                .auth_account_service
+                .login(&user.data.username, password)
+                .await
+                .and_then(authenticated)
+                .is_ok());
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_keep_the_status_of_imported_accounts() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+
+        let imported = auth_module
+            .auth_account_service
+            .import_accounts(vec![new_import_account_dto(DJANGO_HASH, AuthAccountStatus::Disabled)])
+            .await?;
+        let user = &imported[0];
+        assert_eq!(AuthAccountStatus::Disabled, user.data.status);
+
+        match auth_module.auth_account_service.login(&user.data.username, "password").await.and_then(authenticated) {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ErrorCodes::INACTIVE_USER, code),
+            _ => panic!(),
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_import_duplicated_accounts_or_unsupported_hashes() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+
+        let existing = new_import_account_dto(DJANGO_HASH, AuthAccountStatus::Active);
+        auth_module.auth_account_service.import_accounts(vec![existing.clone()]).await?;
+
+        let valid = new_import_account_dto(LDAP_HASH, AuthAccountStatus::Active);
+        let mut duplicated_in_batch = new_import_account_dto(LDAP_HASH, AuthAccountStatus::Active);
+        duplicated_in_batch.username = valid.username.clone();
+        let unsupported = new_import_account_dto("md5$salt$hash", AuthAccountStatus::Active);
+
+        let result = auth_module
+            .auth_account_service
+            .import_accounts(vec![valid.clone(), existing.clone(), duplicated_in_batch, unsupported])
+            .await;
+
+        match result {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert_eq!(4, details.details.len());
+                assert_eq!(vec![ERR_NOT_UNIQUE.to_owned()], details.details["accounts[1].username"]);
+                assert_eq!(vec![ERR_NOT_UNIQUE.to_owned()], details.details["accounts[1].email"]);
+                assert_eq!(vec![ERR_NOT_UNIQUE.to_owned()], details.details["accounts[2].username"]);
+                assert_eq!(vec![ERR_UNSUPPORTED_HASH.to_owned()], details.details["accounts[3].password_hash"]);
+            }
+            _ => panic!(),
+        }
+
+        // Nothing is imported when an account is not valid
+        assert!(auth_module.auth_account_service.fetch_by_username(&valid.username).await.is_err());
+
+        Ok(())
+    })
+}
+
+fn new_import_account_dto(password_hash: &str, status: AuthAccountStatus) -> ImportAccountDto {
+    let username = new_hyphenated_uuid();
+    ImportAccountDto {
+        email: format!("{username}@email.fake"),
+        username,
+        password_hash: password_hash.to_owned(),
+        roles: vec![new_hyphenated_uuid()],
+        status,
+        created_date_epoch_seconds: Some(123),
+    }
+}
@@ -0,0 +1,280 @@
+use crate::tests::util::{authenticated, create_user_with_password};
+use crate::{data, test};
+use c3p0::*;
+use lightspeed_auth::dto::create_login_dto::CreateLoginDto;
+use lightspeed_auth::dto::invitation_dto::{AcceptInvitationDto, InviteAccountDto};
+use lightspeed_auth::model::auth_account::AuthAccountStatus;
+use lightspeed_auth::model::token::{InvitationData, TokenType};
+use lightspeed_auth::repository::AuthRepositoryManager;
+use lightspeed_auth::AuthModule;
+use lightspeed_core::error::LightSpeedError;
+use lightspeed_core::model::language::Language;
+use lightspeed_core::service::auth::{Auth, AuthService, InMemoryRolesProvider};
+use lightspeed_core::service::validator::ERR_NOT_UNIQUE;
+use lightspeed_core::utils::new_hyphenated_uuid;
+use std::collections::HashMap;
+
+const PASSWORD: &str = "123456789";
+
+#[test]
+fn should_create_an_active_account_with_the_invited_roles() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let auth_account_service = &auth_module.auth_account_service;
+        let role = new_hyphenated_uuid();
+        let admin_auth = login_inviter(auth_module, &[role.clone()]).await?;
+
+        let email = format!("{}@email.fake", new_hyphenated_uuid());
+        let invitation_token = auth_account_service
+            .invite(
+                &new_auth_service().auth(admin_auth.clone()),
+                InviteAccountDto { email: email.to_uppercase(), roles: vec![role.clone()], validity_minutes: Some(60) },
+            )
+            .await?;
+        assert_eq!(TokenType::Invitation, invitation_token.data.token_type);
+        assert_eq!(email, invitation_token.data.invitation.as_ref().unwrap().email);
+
+        let invitation = auth_account_service
+            .fetch_all_invitations()
+            .await?
+            .into_iter()
+            .find(|invitation| invitation.invitation_id == invitation_token.id)
+            .unwrap();
+        assert_eq!(email, invitation.email);
+        assert_eq!(vec![role.clone()], invitation.roles);
+        assert_eq!(Some(admin_auth.id), invitation.invited_by_user_id);
+
+        let username = new_hyphenated_uuid();
+        let user = auth_account_service
+            .accept_invitation(new_accept_invitation_dto(&invitation_token.data.token, &username))
+            .await?;
+        assert_eq!(username, user.data.username);
+        assert_eq!(email, user.data.email);
+        assert_eq!(AuthAccountStatus::Active, user.data.status);
+        assert_eq!(vec![role.clone()], user.data.roles);
+
+        let auth = auth_account_service.login(&username, PASSWORD).await.and_then(authenticated)?;
+        assert_eq!(vec![role], auth.roles);
+
+        // The invitation can be used only once
+        assert!(auth_account_service
+            .fetch_all_invitations()
+            .await?
+            .iter()
+            .all(|invitation| invitation.invitation_id != invitation_token.id));
+        assert!(auth_account_service
+            .accept_invitation(new_accept_invitation_dto(&invitation_token.data.token, &new_hyphenated_uuid()))
+            .await
+            .is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_revoke_an_invitation() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let auth_account_service = &auth_module.auth_account_service;
+
+        let inviter_auth = login_inviter(auth_module, &[]).await?;
+
+        let invitation_token =
+            auth_account_service.invite(&new_auth_service().auth(inviter_auth), new_invite_account_dto()).await?;
+        auth_account_service.revoke_invitation(invitation_token.id).await?;
+
+        assert!(auth_account_service
+            .fetch_all_invitations()
+            .await?
+            .iter()
+            .all(|invitation| invitation.invitation_id != invitation_token.id));
+        assert!(auth_account_service
+            .accept_invitation(new_accept_invitation_dto(&invitation_token.data.token, &new_hyphenated_uuid()))
+            .await
+            .is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_invite_an_email_already_used() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let inviter_auth = login_inviter(auth_module, &[]).await?;
+
+        match auth_module
+            .auth_account_service
+            .invite(
+                &new_auth_service().auth(inviter_auth),
+                InviteAccountDto { email: user.data.email, ..new_invite_account_dto() },
+            )
+            .await
+        {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert_eq!(vec![ERR_NOT_UNIQUE.to_owned()], details.details["email"]);
+            }
+            _ => panic!(),
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_accept_an_expired_invitation() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let email = format!("{}@email.fake", new_hyphenated_uuid());
+
+        let invitation_token = auth_module
+            .repo_manager
+            .c3p0()
+            .transaction(|conn| async {
+                auth_module
+                    .token_service
+                    .generate_and_save_invitation_token_with_conn(
+                        conn,
+                        InvitationData {
+                            email: email.clone(),
+                            roles: vec![],
+                            invited_by_user_id: None,
+                            created_date_epoch_seconds: 0,
+                        },
+                        -1,
+                    )
+                    .await
+            })
+            .await?;
+
+        assert!(auth_module
+            .auth_account_service
+            .accept_invitation(new_accept_invitation_dto(&invitation_token.data.token, &new_hyphenated_uuid()))
+            .await
+            .is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_accept_a_token_of_another_type() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (_, activation_token) = create_user_with_password(auth_module, PASSWORD, false).await?;
+
+        assert!(auth_module
+            .auth_account_service
+            .accept_invitation(new_accept_invitation_dto(&activation_token.data.token, &new_hyphenated_uuid()))
+            .await
+            .is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_invite_with_roles_the_inviter_does_not_have() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let auth_service = new_auth_service();
+        let role = new_hyphenated_uuid();
+        let inviter_auth = login_inviter(auth_module, &[role.clone()]).await?;
+
+        let dto = InviteAccountDto { roles: vec![role, new_hyphenated_uuid()], ..new_invite_account_dto() };
+        match auth_module.auth_account_service.invite(&auth_service.auth(inviter_auth.clone()), dto).await {
+            Err(LightSpeedError::ForbiddenError { .. }) => {}
+            _ => panic!(),
+        }
+
+        // An impersonated inviter cannot invite
+        let impersonated_auth = Auth { impersonated_by: Some(inviter_auth.id), ..inviter_auth };
+        match auth_module
+            .auth_account_service
+            .invite(&auth_service.auth(impersonated_auth), new_invite_account_dto())
+            .await
+        {
+            Err(LightSpeedError::ForbiddenError { .. }) => {}
+            _ => panic!(),
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_return_the_invitations_among_the_tokens_of_an_account() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let inviter_auth = login_inviter(auth_module, &[]).await?;
+
+        // The invited email is the username of an account with another email
+        let (user, _) = auth_module
+            .auth_account_service
+            .create_user(CreateLoginDto {
+                username: Some(format!("{}@email.fake", new_hyphenated_uuid())),
+                email: format!("{}@email.fake", new_hyphenated_uuid()),
+                data: HashMap::new(),
+                accept_privacy_policy: true,
+                language: Language::En,
+                password: PASSWORD.to_owned(),
+                password_confirm: PASSWORD.to_owned(),
+            })
+            .await?;
+        let invitation_token = auth_module
+            .auth_account_service
+            .invite(
+                &new_auth_service().auth(inviter_auth),
+                InviteAccountDto { email: user.data.username.clone(), ..new_invite_account_dto() },
+            )
+            .await?;
+        assert!(invitation_token.data.username.is_empty());
+
+        let tokens = auth_module
+            .repo_manager
+            .c3p0()
+            .transaction(|conn| async {
+                auth_module.token_service.fetch_all_by_username_with_conn(conn, &user.data.username).await
+            })
+            .await?;
+        assert!(tokens.iter().all(|token| token.id != invitation_token.id));
+
+        Ok(())
+    })
+}
+
+fn new_invite_account_dto() -> InviteAccountDto {
+    InviteAccountDto { email: format!("{}@email.fake", new_hyphenated_uuid()), roles: vec![], validity_minutes: None }
+}
+
+fn new_auth_service() -> AuthService<InMemoryRolesProvider> {
+    AuthService::new(InMemoryRolesProvider::new(vec![].into()))
+}
+
+/// Creates a user with the given roles and logs it in
+async fn login_inviter<RepoManager: AuthRepositoryManager>(
+    auth_module: &AuthModule<RepoManager>,
+    roles: &[String],
+) -> Result<Auth, LightSpeedError> {
+    let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+    auth_module.auth_account_service.add_roles(user.id, roles).await?;
+    auth_module.auth_account_service.login(&user.data.username, PASSWORD).await.and_then(authenticated)
+}
+
+fn new_accept_invitation_dto(token: &str, username: &str) -> AcceptInvitationDto {
+    AcceptInvitationDto {
+        token: token.to_owned(),
+        username: Some(username.to_owned()),
+        password: PASSWORD.to_owned(),
+        password_confirm: PASSWORD.to_owned(),
+        accept_privacy_policy: true,
+    }
+}
@@ -0,0 +1,201 @@
+use crate::tests::util::{assert_error_code, authenticated, create_user_with_password, new_auth_account_service};
+use crate::{data, test, RepoManager};
+use c3p0::*;
+use lightspeed_auth::config::AuthConfig;
+use lightspeed_auth::model::login_attempt::LoginAttemptKeyType;
+use lightspeed_auth::repository::{AuthRepositoryManager, LoginAttemptRepository};
+use lightspeed_auth::service::login_attempt::LoginAttemptService;
+use lightspeed_core::clock::MockClock;
+use lightspeed_core::error::{ErrorCodes, LightSpeedError};
+use lightspeed_core::utils::new_hyphenated_uuid;
+use std::sync::Arc;
+use std::time::Duration;
+
+const PASSWORD: &str = "123456789";
+
+#[test]
+fn should_lock_username_after_max_failed_logins() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let username = &user.data.username;
+
+        for _ in 0..auth_module.auth_config.max_failed_logins_per_username {
+            assert_error_code(
+                ErrorCodes::WRONG_CREDENTIALS,
+                auth_module.auth_account_service.login(username, "wrong").await.and_then(authenticated),
+            );
+        }
+
+        assert_error_code(
+            ErrorCodes::LOCKED_USER,
+            auth_module.auth_account_service.login(username, PASSWORD).await.and_then(authenticated),
+        );
+
+        auth_module.auth_account_service.unlock_by_username(username).await?;
+        assert!(auth_module.auth_account_service.login(username, PASSWORD).await.and_then(authenticated).is_ok());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_count_the_concurrent_failed_logins() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let username = &user.data.username;
+
+        let auth_config = AuthConfig { max_failed_logins_per_username: 5, ..auth_module.auth_config.clone() };
+        let auth_account_service = new_auth_account_service(auth_module, auth_config, &MockClock::default());
+
+        let results = tokio::join!(
+            auth_account_service.login(username, "wrong"),
+            auth_account_service.login(username, "wrong"),
+            auth_account_service.login(username, "wrong"),
+            auth_account_service.login(username, "wrong"),
+            auth_account_service.login(username, "wrong"),
+        );
+        for result in [results.0, results.1, results.2, results.3, results.4] {
+            assert_error_code(ErrorCodes::WRONG_CREDENTIALS, result);
+        }
+
+        assert_error_code(
+            ErrorCodes::LOCKED_USER,
+            auth_account_service.login(username, PASSWORD).await.and_then(authenticated),
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_unlock_username_when_the_lockout_expires() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let username = &user.data.username;
+
+        let clock = MockClock::default();
+        let auth_account_service = new_auth_account_service(auth_module, auth_module.auth_config.clone(), &clock);
+
+        for _ in 0..auth_module.auth_config.max_failed_logins_per_username {
+            assert!(auth_account_service.login(username, "wrong").await.and_then(authenticated).is_err());
+        }
+
+        clock.advance(Duration::from_secs(auth_module.auth_config.failed_login_lockout_minutes as u64 * 60 - 1));
+        assert_error_code(
+            ErrorCodes::LOCKED_USER,
+            auth_account_service.login(username, PASSWORD).await.and_then(authenticated),
+        );
+
+        clock.advance(Duration::from_secs(1));
+        assert!(auth_account_service.login(username, PASSWORD).await.and_then(authenticated).is_ok());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_reset_failed_logins_after_successful_login() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let username = &user.data.username;
+
+        for _ in 1..auth_module.auth_config.max_failed_logins_per_username {
+            assert!(auth_module.auth_account_service.login(username, "wrong").await.and_then(authenticated).is_err());
+        }
+
+        assert!(auth_module.auth_account_service.login(username, PASSWORD).await.and_then(authenticated).is_ok());
+
+        for _ in 1..auth_module.auth_config.max_failed_logins_per_username {
+            assert!(auth_module.auth_account_service.login(username, "wrong").await.and_then(authenticated).is_err());
+        }
+
+        assert!(auth_module.auth_account_service.login(username, PASSWORD).await.and_then(authenticated).is_ok());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_lock_client_after_max_failed_logins() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let username = &user.data.username;
+        let client_key = new_hyphenated_uuid();
+
+        let auth_config = AuthConfig { max_failed_logins_per_client: 3, ..auth_module.auth_config.clone() };
+        let auth_account_service = new_auth_account_service(auth_module, auth_config, &MockClock::default());
+
+        for _ in 0..3 {
+            let other_username = new_hyphenated_uuid();
+            assert_error_code(
+                ErrorCodes::WRONG_CREDENTIALS,
+                auth_account_service.authenticate(&other_username, "wrong", Some(&client_key)).await,
+            );
+        }
+
+        assert_error_code(
+            ErrorCodes::LOCKED_USER,
+            auth_account_service.authenticate(username, PASSWORD, Some(&client_key)).await,
+        );
+        assert!(auth_account_service.authenticate(username, PASSWORD, Some(&new_hyphenated_uuid())).await.is_ok());
+
+        auth_account_service.unlock_by_client_key(&client_key).await?;
+        assert!(auth_account_service.authenticate(username, PASSWORD, Some(&client_key)).await.is_ok());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_purge_the_expired_failed_logins() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (expired_user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let (recent_user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        // The failures are dated before the epoch so that the purge does not remove the counters of the other tests
+        let clock = MockClock::from_epoch_seconds(-1_000_000);
+        let auth_account_service = new_auth_account_service(auth_module, auth_module.auth_config.clone(), &clock);
+        let login_attempt_service = LoginAttemptService::<RepoManager>::new(
+            auth_module.repo_manager.c3p0().clone(),
+            auth_module.auth_config.clone(),
+            auth_module.repo_manager.login_attempt_repo(),
+            Arc::new(clock.clone()),
+        );
+
+        assert!(auth_account_service.login(&expired_user.data.username, "wrong").await.is_err());
+        clock.advance(Duration::from_secs(auth_module.auth_config.failed_login_lockout_minutes as u64 * 60 + 1));
+        assert!(auth_account_service.login(&recent_user.data.username, "wrong").await.is_err());
+
+        assert!(login_attempt_service.purge_expired().await? >= 1);
+
+        let login_attempt_repo = auth_module.repo_manager.login_attempt_repo();
+        auth_module
+            .repo_manager
+            .c3p0()
+            .transaction(|conn| async {
+                let key_type = LoginAttemptKeyType::Username;
+                assert!(login_attempt_repo
+                    .fetch_by_key_optional(conn, key_type, &expired_user.data.username)
+                    .await?
+                    .is_none());
+                assert!(login_attempt_repo
+                    .fetch_by_key_optional(conn, key_type, &recent_user.data.username)
+                    .await?
+                    .is_some());
+                Ok::<_, LightSpeedError>(())
+            })
+            .await
+    })
+}
@@ -0,0 +1,165 @@
+use crate::tests::util::{assert_error_code, create_user, new_auth_account_service};
+use crate::{data, test};
+use lightspeed_auth::config::AuthConfig;
+use lightspeed_auth::model::token::TokenType;
+use lightspeed_auth::service::auth_account::LoginOutcome;
+use lightspeed_core::clock::MockClock;
+use lightspeed_core::error::{ErrorCodes, LightSpeedError};
+use lightspeed_core::utils::{current_epoch_seconds, new_hyphenated_uuid};
+use std::time::Duration;
+
+#[test]
+fn should_login_with_magic_token() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user(auth_module, true).await?;
+
+        let (token_user, token) = auth_module
+            .auth_account_service
+            .generate_magic_login_token(&user.data.email.to_uppercase())
+            .await?
+            .unwrap();
+
+        assert_eq!(user.id, token_user.id);
+        assert_eq!(TokenType::MagicLogin, token.data.token_type);
+        assert_eq!(user.data.username, token.data.username);
+        assert!(
+            token.data.expire_at_epoch_seconds
+                <= current_epoch_seconds() + auth_module.auth_config.magic_login_token_validity_minutes * 60
+        );
+
+        let auth = match auth_module.auth_account_service.login_with_magic_token(&token.data.token).await? {
+            LoginOutcome::Authenticated(auth) => auth,
+            _ => panic!(),
+        };
+        assert_eq!(user.id, auth.id);
+        assert_eq!(user.data.username, auth.username);
+
+        // The token can be used only once
+        assert!(auth_module.auth_account_service.login_with_magic_token(&token.data.token).await.is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_consume_all_the_magic_tokens_at_login() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user(auth_module, true).await?;
+
+        let (_, first_token) =
+            auth_module.auth_account_service.generate_magic_login_token(&user.data.email).await?.unwrap();
+        let (_, second_token) =
+            auth_module.auth_account_service.generate_magic_login_token(&user.data.email).await?.unwrap();
+
+        assert!(auth_module.auth_account_service.login_with_magic_token(&second_token.data.token).await.is_ok());
+        assert!(auth_module.auth_account_service.login_with_magic_token(&first_token.data.token).await.is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_limit_the_magic_tokens_per_account() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user(auth_module, true).await?;
+
+        let auth_config = AuthConfig { magic_login_max_tokens_per_account: 2, ..auth_module.auth_config.clone() };
+        let clock = MockClock::default();
+        let auth_account_service = new_auth_account_service(auth_module, auth_config.clone(), &clock);
+
+        auth_account_service.generate_magic_login_token(&user.data.email).await?;
+        auth_account_service.generate_magic_login_token(&user.data.email).await?;
+
+        match auth_account_service.generate_magic_login_token(&user.data.email).await {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ErrorCodes::TOO_MANY_REQUESTS, code),
+            _ => panic!(),
+        }
+
+        // The expired tokens are not counted
+        clock.advance(Duration::from_secs((auth_config.magic_login_token_validity_minutes * 60 + 1) as u64));
+        assert!(auth_account_service.generate_magic_login_token(&user.data.email).await.is_ok());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_generate_magic_token_for_inactive_user() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user(auth_module, false).await?;
+
+        match auth_module.auth_account_service.generate_magic_login_token(&user.data.email).await {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ErrorCodes::INACTIVE_USER, code),
+            _ => panic!(),
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_login_with_a_token_of_another_type() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user(auth_module, true).await?;
+
+        let (_, token) = auth_module.auth_account_service.generate_reset_password_token(&user.data.username).await?;
+
+        match auth_module.auth_account_service.login_with_magic_token(&token.data.token).await {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert!(details.details.contains_key("token_type"))
+            }
+            _ => panic!(),
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_reveal_whether_an_email_is_registered() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+
+        let email = format!("{}@email.fake", new_hyphenated_uuid());
+        assert!(auth_module.auth_account_service.generate_magic_login_token(&email).await?.is_none());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_use_magic_tokens_of_a_locked_user() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user(auth_module, true).await?;
+
+        let (_, token) = auth_module.auth_account_service.generate_magic_login_token(&user.data.email).await?.unwrap();
+
+        for _ in 0..auth_module.auth_config.max_failed_logins_per_username {
+            assert!(auth_module.auth_account_service.login(&user.data.username, "wrong").await.is_err());
+        }
+
+        assert_error_code(
+            ErrorCodes::LOCKED_USER,
+            auth_module.auth_account_service.generate_magic_login_token(&user.data.email).await,
+        );
+        assert_error_code(
+            ErrorCodes::LOCKED_USER,
+            auth_module.auth_account_service.login_with_magic_token(&token.data.token).await,
+        );
+
+        Ok(())
+    })
+}
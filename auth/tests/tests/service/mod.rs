@@ -1,2 +1,18 @@
+pub mod account_attributes_it;
+pub mod account_event_it;
+pub mod account_search_it;
 pub mod auth_account_it;
+pub mod auth_session_it;
+pub mod email_change_it;
+pub mod impersonation_it;
+pub mod import_account_it;
+pub mod invitation_it;
+pub mod login_attempt_it;
+pub mod magic_login_it;
+pub mod oauth2_it;
+pub mod oidc_it;
+pub mod password_policy_it;
+pub mod personal_data_it;
 pub mod token_it;
+pub mod two_factor_it;
+pub mod webauthn_it;
@@ -0,0 +1,357 @@
+use crate::tests::util::{assert_error_code, create_user};
+use crate::{data, test};
+use c3p0::*;
+use lightspeed_auth::config::OAuth2ScopeConfig;
+use lightspeed_auth::dto::oauth2_dto::{OAuth2AuthorizationRequestDto, OAuth2TokenRequestDto, RegisterOAuth2ClientDto};
+use lightspeed_auth::model::oauth2_client::OAuth2GrantType;
+use lightspeed_auth::repository::{AuthRepositoryManager, OAuth2TokenRepository};
+use lightspeed_auth::service::oauth2::*;
+use lightspeed_auth::service::oidc_client::pkce_code_challenge;
+use lightspeed_auth::AuthModule;
+use lightspeed_core::clock::{Clock, MockClock};
+use lightspeed_core::error::LightSpeedError;
+use lightspeed_core::service::auth::{AuthService, InMemoryRolesProvider, Role};
+use lightspeed_core::utils::new_hyphenated_uuid;
+use std::sync::Arc;
+use std::time::Duration;
+
+const REDIRECT_URI: &str = "https://client.example.com/callback";
+const READ_SCOPE: &str = "orders:read";
+const WRITE_SCOPE: &str = "orders:write";
+
+#[test]
+fn should_issue_an_access_token_with_the_authorization_code_grant() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let oauth2_service = new_oauth2_service(auth_module);
+        let (client, client_secret) = oauth2_service.register_client(public_client()).await?;
+        assert!(client_secret.is_none());
+        let (user, _) = create_user(auth_module, true).await?;
+
+        let code_verifier = new_hyphenated_uuid();
+        let request = authorization_request(&client.data.client_id, &code_verifier, Some(READ_SCOPE));
+
+        let details = oauth2_service.validate_authorization_request(user.id, &request).await?;
+        assert!(details.consent_required);
+        assert_eq!(client.data.name, details.client_name);
+        assert_eq!(1, details.scopes.len());
+        assert_eq!(READ_SCOPE, details.scopes[0].name);
+
+        let response = oauth2_service.authorize(user.id, &request).await?;
+        assert_eq!(
+            format!("{REDIRECT_URI}?code={}&state={}", response.code, request.state.as_deref().unwrap()),
+            response.redirect_url
+        );
+
+        // The consent is remembered
+        assert!(!oauth2_service.validate_authorization_request(user.id, &request).await?.consent_required);
+        let consents = oauth2_service.fetch_consents_by_user_id(user.id).await?;
+        assert_eq!(1, consents.len());
+        assert_eq!(vec![READ_SCOPE.to_owned()], consents[0].data.scopes);
+
+        // The code verifier is required
+        let mut token_request = OAuth2TokenRequestDto {
+            grant_type: "authorization_code".to_owned(),
+            client_id: client.data.client_id.clone(),
+            code: Some(response.code.clone()),
+            redirect_uri: Some(REDIRECT_URI.to_owned()),
+            code_verifier: Some(new_hyphenated_uuid()),
+            ..Default::default()
+        };
+        assert_error_code(OAUTH2_INVALID_GRANT, oauth2_service.token(&token_request).await);
+
+        // The code is consumed by the failed grant
+        token_request.code_verifier = Some(code_verifier);
+        assert_error_code(OAUTH2_INVALID_GRANT, oauth2_service.token(&token_request).await);
+
+        token_request.code = Some(oauth2_service.authorize(user.id, &request).await?.code);
+        let token = oauth2_service.token(&token_request).await?;
+        assert_eq!("Bearer", token.token_type);
+        assert_eq!(READ_SCOPE, token.scope);
+        assert_eq!(auth_module.auth_config.oauth2_access_token_validity_minutes * 60, token.expires_in);
+
+        // The code can be used only once
+        assert_error_code(OAUTH2_INVALID_GRANT, oauth2_service.token(&token_request).await);
+
+        let auth = oauth2_service.validate_access_token(&token.access_token).await?;
+        assert_eq!(user.id, auth.id);
+        assert_eq!(user.data.username, auth.username);
+        assert_eq!(Some(vec!["orders_read".to_owned()]), auth.scope_permissions);
+
+        // The revoked token is not valid anymore
+        oauth2_service.revoke(&client.data.client_id, None, &token.access_token).await?;
+        assert!(oauth2_service.validate_access_token(&token.access_token).await.is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_issue_an_access_token_with_the_client_credentials_grant() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let oauth2_service = new_oauth2_service(auth_module);
+        let (client, client_secret) = oauth2_service
+            .register_client(RegisterOAuth2ClientDto {
+                grant_types: vec![OAuth2GrantType::ClientCredentials],
+                redirect_uris: vec![],
+                roles: vec!["ADMIN".to_owned()],
+                confidential: true,
+                ..public_client()
+            })
+            .await?;
+        let client_secret = client_secret.unwrap();
+
+        let mut token_request = OAuth2TokenRequestDto {
+            grant_type: "client_credentials".to_owned(),
+            client_id: client.data.client_id.clone(),
+            client_secret: Some(new_hyphenated_uuid()),
+            scope: Some(READ_SCOPE.to_owned()),
+            ..Default::default()
+        };
+        assert_error_code(OAUTH2_INVALID_CLIENT, oauth2_service.token(&token_request).await);
+
+        token_request.client_secret = None;
+        assert_error_code(OAUTH2_INVALID_CLIENT, oauth2_service.token(&token_request).await);
+
+        token_request.client_secret = Some(client_secret.clone());
+        token_request.scope = Some("admin".to_owned());
+        assert_error_code(OAUTH2_INVALID_SCOPE, oauth2_service.token(&token_request).await);
+
+        token_request.grant_type = "password".to_owned();
+        assert_error_code(OAUTH2_UNSUPPORTED_GRANT_TYPE, oauth2_service.token(&token_request).await);
+
+        token_request.grant_type = "authorization_code".to_owned();
+        assert_error_code(OAUTH2_UNAUTHORIZED_CLIENT, oauth2_service.token(&token_request).await);
+
+        token_request.grant_type = "client_credentials".to_owned();
+        token_request.scope = Some(READ_SCOPE.to_owned());
+        let token = oauth2_service.token(&token_request).await?;
+
+        // The permissions are the ones of the client roles restricted by the scopes
+        let auth = oauth2_service.validate_access_token(&token.access_token).await?;
+        assert_eq!(client.data.client_id, auth.username);
+        let auth_service = AuthService::new(InMemoryRolesProvider::new(
+            vec![Role {
+                name: "ADMIN".to_owned(),
+                permissions: vec!["orders_read".to_owned(), "orders_write".to_owned()],
+            }]
+            .into(),
+        ));
+        let auth_context = auth_service.auth(auth);
+        assert!(auth_context.has_permission("orders_read").is_ok());
+        assert!(auth_context.has_permission("orders_write").is_err());
+        assert!(auth_context.has_role("ADMIN").is_err());
+
+        // Only the confidential clients can introspect the tokens
+        let introspection =
+            oauth2_service.introspect(&client.data.client_id, Some(&client_secret), &token.access_token).await?;
+        assert!(introspection.active);
+        assert_eq!(Some(READ_SCOPE.to_owned()), introspection.scope);
+        assert_eq!(Some(client.data.client_id.clone()), introspection.client_id);
+
+        let (other_client, _) = oauth2_service.register_client(public_client()).await?;
+        assert_error_code(
+            OAUTH2_UNAUTHORIZED_CLIENT,
+            oauth2_service.introspect(&other_client.data.client_id, None, &token.access_token).await,
+        );
+
+        // A client cannot revoke the tokens of another client
+        oauth2_service.revoke(&other_client.data.client_id, None, &token.access_token).await?;
+        assert!(oauth2_service.validate_access_token(&token.access_token).await.is_ok());
+
+        oauth2_service.revoke(&client.data.client_id, Some(&client_secret), &token.access_token).await?;
+        let introspection =
+            oauth2_service.introspect(&client.data.client_id, Some(&client_secret), &token.access_token).await?;
+        assert!(!introspection.active);
+        assert!(introspection.scope.is_none());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_accept_invalid_authorization_requests() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let oauth2_service = new_oauth2_service(auth_module);
+        let (client, _) = oauth2_service.register_client(public_client()).await?;
+        let (user, _) = create_user(auth_module, true).await?;
+        let request = authorization_request(&client.data.client_id, &new_hyphenated_uuid(), None);
+
+        let invalid_requests = [
+            (
+                OAUTH2_INVALID_CLIENT,
+                OAuth2AuthorizationRequestDto { client_id: new_hyphenated_uuid(), ..request.clone() },
+            ),
+            (
+                OAUTH2_INVALID_REQUEST,
+                OAuth2AuthorizationRequestDto { redirect_uri: "https://attacker.com".to_owned(), ..request.clone() },
+            ),
+            (
+                OAUTH2_UNSUPPORTED_RESPONSE_TYPE,
+                OAuth2AuthorizationRequestDto { response_type: "token".to_owned(), ..request.clone() },
+            ),
+            (OAUTH2_INVALID_REQUEST, OAuth2AuthorizationRequestDto { code_challenge: None, ..request.clone() }),
+            (
+                OAUTH2_INVALID_REQUEST,
+                OAuth2AuthorizationRequestDto { code_challenge_method: Some("plain".to_owned()), ..request.clone() },
+            ),
+            (
+                OAUTH2_INVALID_SCOPE,
+                OAuth2AuthorizationRequestDto { scope: Some(format!("{READ_SCOPE} admin")), ..request.clone() },
+            ),
+        ];
+        for (code, invalid_request) in invalid_requests {
+            assert_error_code(code, oauth2_service.authorize(user.id, &invalid_request).await);
+        }
+
+        // All the scopes of the client are requested if none is given
+        let details = oauth2_service.validate_authorization_request(user.id, &request).await?;
+        assert_eq!(
+            vec![READ_SCOPE, WRITE_SCOPE],
+            details.scopes.iter().map(|scope| scope.name.as_str()).collect::<Vec<_>>()
+        );
+
+        // The unknown scopes cannot be allowed to a client
+        assert!(oauth2_service
+            .register_client(RegisterOAuth2ClientDto { scopes: vec!["admin".to_owned()], ..public_client() })
+            .await
+            .is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_revoke_the_tokens_with_the_consent() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let oauth2_service = new_oauth2_service(auth_module);
+        let (client, _) = oauth2_service.register_client(public_client()).await?;
+        let (user, _) = create_user(auth_module, true).await?;
+
+        let code_verifier = new_hyphenated_uuid();
+        let request = authorization_request(&client.data.client_id, &code_verifier, None);
+        let response = oauth2_service.authorize(user.id, &request).await?;
+        let token = oauth2_service
+            .token(&OAuth2TokenRequestDto {
+                grant_type: "authorization_code".to_owned(),
+                client_id: client.data.client_id.clone(),
+                code: Some(response.code),
+                redirect_uri: Some(REDIRECT_URI.to_owned()),
+                code_verifier: Some(code_verifier),
+                ..Default::default()
+            })
+            .await?;
+        assert!(oauth2_service.validate_access_token(&token.access_token).await.is_ok());
+
+        // The tokens of a disabled user are not valid
+        auth_module.auth_account_service.disable_by_user_id(user.id).await?;
+        assert!(oauth2_service.validate_access_token(&token.access_token).await.is_err());
+        auth_module.auth_account_service.reactivate_disabled_user_by_user_id(user.id).await?;
+        assert!(oauth2_service.validate_access_token(&token.access_token).await.is_ok());
+
+        oauth2_service.revoke_consent(user.id, &client.data.client_id).await?;
+        assert!(oauth2_service.fetch_consents_by_user_id(user.id).await?.is_empty());
+        assert!(oauth2_service.validate_access_token(&token.access_token).await.is_err());
+
+        // The consents are removed together with the account
+        let request = authorization_request(&client.data.client_id, &new_hyphenated_uuid(), None);
+        oauth2_service.authorize(user.id, &request).await?;
+        assert_eq!(1, oauth2_service.fetch_consents_by_user_id(user.id).await?.len());
+        auth_module.auth_account_service.delete_by_user_id(user.id).await?;
+        assert!(oauth2_service.fetch_consents_by_user_id(user.id).await?.is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_purge_the_expired_tokens() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+
+        // The tokens are dated before the epoch so that the purge does not remove the tokens of the other tests
+        let clock = MockClock::from_epoch_seconds(-1_000_000);
+        let oauth2_service = new_oauth2_service_with_clock(auth_module, Arc::new(clock.clone()));
+        let (client, _) = oauth2_service.register_client(public_client()).await?;
+        let (user, _) = create_user(auth_module, true).await?;
+
+        let request = authorization_request(&client.data.client_id, &new_hyphenated_uuid(), None);
+        let expired_code = oauth2_service.authorize(user.id, &request).await?.code;
+        clock.advance(Duration::from_secs(
+            auth_module.auth_config.oauth2_authorization_code_validity_minutes as u64 * 60 + 1,
+        ));
+        let valid_code = oauth2_service.authorize(user.id, &request).await?.code;
+
+        assert!(oauth2_service.purge_expired().await? >= 1);
+
+        let token_repo = auth_module.repo_manager.oauth2_token_repo();
+        auth_module
+            .repo_manager
+            .c3p0()
+            .transaction(|conn| async {
+                assert!(token_repo.fetch_by_token_optional(conn, &expired_code).await?.is_none());
+                assert!(token_repo.fetch_by_token_optional(conn, &valid_code).await?.is_some());
+                Ok::<_, LightSpeedError>(())
+            })
+            .await
+    })
+}
+
+fn new_oauth2_service<RepoManager: AuthRepositoryManager>(
+    auth_module: &AuthModule<RepoManager>,
+) -> OAuth2Service<RepoManager> {
+    new_oauth2_service_with_clock(auth_module, auth_module.clock.clone())
+}
+
+fn new_oauth2_service_with_clock<RepoManager: AuthRepositoryManager>(
+    auth_module: &AuthModule<RepoManager>,
+    clock: Arc<dyn Clock>,
+) -> OAuth2Service<RepoManager> {
+    let mut auth_config = auth_module.auth_config.clone();
+    auth_config.oauth2_scopes = vec![
+        OAuth2ScopeConfig {
+            name: READ_SCOPE.to_owned(),
+            description: "Read your orders".to_owned(),
+            permissions: vec!["orders_read".to_owned()],
+        },
+        OAuth2ScopeConfig {
+            name: WRITE_SCOPE.to_owned(),
+            description: "Create and update your orders".to_owned(),
+            permissions: vec!["orders_read".to_owned(), "orders_write".to_owned()],
+        },
+    ];
+    auth_config.oauth2_access_token_secret = new_hyphenated_uuid();
+
+    OAuth2Service::new(&auth_module.repo_manager, auth_config, auth_module.password_codec.clone(), clock).unwrap()
+}
+
+fn public_client() -> RegisterOAuth2ClientDto {
+    RegisterOAuth2ClientDto {
+        name: new_hyphenated_uuid(),
+        redirect_uris: vec![REDIRECT_URI.to_owned()],
+        grant_types: vec![OAuth2GrantType::AuthorizationCode],
+        scopes: vec![READ_SCOPE.to_owned(), WRITE_SCOPE.to_owned()],
+        roles: vec![],
+        confidential: false,
+    }
+}
+
+fn authorization_request(client_id: &str, code_verifier: &str, scope: Option<&str>) -> OAuth2AuthorizationRequestDto {
+    OAuth2AuthorizationRequestDto {
+        response_type: "code".to_owned(),
+        client_id: client_id.to_owned(),
+        redirect_uri: REDIRECT_URI.to_owned(),
+        scope: scope.map(|scope| scope.to_owned()),
+        state: Some(new_hyphenated_uuid()),
+        code_challenge: Some(pkce_code_challenge(code_verifier)),
+        code_challenge_method: Some("S256".to_owned()),
+    }
+}
@@ -0,0 +1,261 @@
+use crate::tests::mock_oidc_provider::{new_oidc_service, MockOidcProvider, MOCK_OIDC_CLIENT_ID};
+use crate::tests::util::create_user;
+use crate::{data, test};
+use lightspeed_auth::dto::oidc_dto::OidcCallbackDto;
+use lightspeed_auth::model::auth_account::AuthAccountStatus;
+use lightspeed_auth::service::auth_account::LoginOutcome;
+use lightspeed_auth::service::oidc::ERR_EMAIL_NOT_VERIFIED;
+use lightspeed_auth::service::oidc_client::pkce_code_challenge;
+use lightspeed_core::error::LightSpeedError;
+use lightspeed_core::service::validator::ERR_NOT_UNIQUE;
+use lightspeed_core::utils::{current_epoch_seconds, new_hyphenated_uuid};
+use serde_json::json;
+
+const PROVIDER: &str = "mock";
+
+#[test]
+fn should_create_an_account_at_the_first_login_and_reuse_it() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let oidc_provider = MockOidcProvider::start().await;
+        let oidc_service = new_oidc_service(auth_module, vec![oidc_provider.provider_config(PROVIDER, false)]);
+
+        let subject = new_hyphenated_uuid();
+        let username = new_hyphenated_uuid();
+        let email = format!("{username}@Email.fake");
+
+        let request = oidc_service.authorization_request(PROVIDER).await?;
+        assert!(request.authorization_url.starts_with(&format!("{}/authorize?", oidc_provider.issuer())));
+        assert!(request.authorization_url.contains(&format!("client_id={MOCK_OIDC_CLIENT_ID}")));
+        assert!(request.authorization_url.contains(&format!("state={}", request.state)));
+        assert!(request.authorization_url.contains(&format!("nonce={}", request.nonce)));
+        assert!(request
+            .authorization_url
+            .contains(&format!("code_challenge={}", pkce_code_challenge(&request.pkce_code_verifier))));
+        assert!(request.authorization_url.contains("code_challenge_method=S256"));
+
+        let mut claims = oidc_provider.claims(&request, &subject, &email);
+        claims["preferred_username"] = username.clone().into();
+        let code = new_hyphenated_uuid();
+        oidc_provider.expect_code_exchange(&request, &code, &oidc_provider.id_token(&claims)).await;
+
+        let auth = match oidc_service.login(&request, &OidcCallbackDto { state: request.state.clone(), code }).await? {
+            LoginOutcome::Authenticated(auth) => auth,
+            _ => panic!(),
+        };
+
+        let user = auth_module.auth_account_service.fetch_by_user_id(auth.id).await?;
+        assert_eq!(username, user.data.username);
+        assert_eq!(email.to_lowercase(), user.data.email);
+        assert_eq!(AuthAccountStatus::Active, user.data.status);
+        assert_eq!(auth_module.auth_config.default_roles_on_account_creation, user.data.roles);
+
+        let identities = oidc_service.fetch_identities_by_user_id(user.id).await?;
+        assert_eq!(1, identities.len());
+        assert_eq!(PROVIDER, identities[0].data.provider);
+        assert_eq!(subject, identities[0].data.subject);
+
+        // The second login of the same subject uses the linked account
+        let request = oidc_service.authorization_request(PROVIDER).await?;
+        let code = new_hyphenated_uuid();
+        let claims = oidc_provider.claims(&request, &subject, &format!("{}@email.fake", new_hyphenated_uuid()));
+        oidc_provider.expect_code_exchange(&request, &code, &oidc_provider.id_token(&claims)).await;
+
+        match oidc_service.login(&request, &OidcCallbackDto { state: request.state.clone(), code }).await? {
+            LoginOutcome::Authenticated(auth) => assert_eq!(user.id, auth.id),
+            _ => panic!(),
+        };
+
+        // The identities are removed together with the account
+        auth_module.auth_account_service.delete_by_user_id(user.id).await?;
+        assert!(oidc_service.fetch_identities_by_user_id(user.id).await?.is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_create_an_account_if_the_email_is_not_verified() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let oidc_provider = MockOidcProvider::start().await;
+        let oidc_service = new_oidc_service(auth_module, vec![oidc_provider.provider_config(PROVIDER, false)]);
+        let email = format!("{}@email.fake", new_hyphenated_uuid());
+
+        let request = oidc_service.authorization_request(PROVIDER).await?;
+        let code = new_hyphenated_uuid();
+        let mut claims = oidc_provider.claims(&request, &new_hyphenated_uuid(), &email);
+        claims["email_verified"] = false.into();
+        oidc_provider.expect_code_exchange(&request, &code, &oidc_provider.id_token(&claims)).await;
+
+        match oidc_service.login(&request, &OidcCallbackDto { state: request.state.clone(), code }).await {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert_eq!(vec![ERR_EMAIL_NOT_VERIFIED.to_owned()], details.details["email"]);
+            }
+            _ => panic!(),
+        };
+        assert!(auth_module.auth_account_service.fetch_by_username(&email).await.is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_link_the_existing_account_with_the_same_verified_email() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let oidc_provider = MockOidcProvider::start().await;
+        let oidc_service = new_oidc_service(
+            auth_module,
+            vec![oidc_provider.provider_config(PROVIDER, false), oidc_provider.provider_config("linking", true)],
+        );
+        let (user, _) = create_user(auth_module, true).await?;
+
+        // The provider does not allow the linking by email
+        let request = oidc_service.authorization_request(PROVIDER).await?;
+        let code = new_hyphenated_uuid();
+        let claims = oidc_provider.claims(&request, &new_hyphenated_uuid(), &user.data.email);
+        oidc_provider.expect_code_exchange(&request, &code, &oidc_provider.id_token(&claims)).await;
+
+        match oidc_service.login(&request, &OidcCallbackDto { state: request.state.clone(), code }).await {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert_eq!(vec![ERR_NOT_UNIQUE.to_owned()], details.details["email"]);
+            }
+            _ => panic!(),
+        };
+
+        // The email is not verified
+        let request = oidc_service.authorization_request("linking").await?;
+        let code = new_hyphenated_uuid();
+        let mut claims = oidc_provider.claims(&request, &new_hyphenated_uuid(), &user.data.email);
+        claims["email_verified"] = false.into();
+        oidc_provider.expect_code_exchange(&request, &code, &oidc_provider.id_token(&claims)).await;
+
+        match oidc_service.login(&request, &OidcCallbackDto { state: request.state.clone(), code }).await {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert_eq!(vec![ERR_EMAIL_NOT_VERIFIED.to_owned()], details.details["email"]);
+            }
+            _ => panic!(),
+        };
+
+        // The email is verified
+        let request = oidc_service.authorization_request("linking").await?;
+        let code = new_hyphenated_uuid();
+        let subject = new_hyphenated_uuid();
+        let claims = oidc_provider.claims(&request, &subject, &user.data.email);
+        oidc_provider.expect_code_exchange(&request, &code, &oidc_provider.id_token(&claims)).await;
+
+        match oidc_service.login(&request, &OidcCallbackDto { state: request.state.clone(), code }).await? {
+            LoginOutcome::Authenticated(auth) => assert_eq!(user.id, auth.id),
+            _ => panic!(),
+        };
+
+        let identities = oidc_service.fetch_identities_by_user_id(user.id).await?;
+        assert_eq!(1, identities.len());
+        assert_eq!(subject, identities[0].data.subject);
+
+        assert_eq!(1, oidc_service.unlink_identities(user.id, "linking").await?);
+        assert!(oidc_service.fetch_identities_by_user_id(user.id).await?.is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_link_an_identity_to_an_account() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let oidc_provider = MockOidcProvider::start().await;
+        let oidc_service = new_oidc_service(auth_module, vec![oidc_provider.provider_config(PROVIDER, false)]);
+        let (user, _) = create_user(auth_module, true).await?;
+        let (other_user, _) = create_user(auth_module, true).await?;
+        let subject = new_hyphenated_uuid();
+
+        let request = oidc_service.authorization_request(PROVIDER).await?;
+        let code = new_hyphenated_uuid();
+        let claims = oidc_provider.claims(&request, &subject, &format!("{}@email.fake", new_hyphenated_uuid()));
+        oidc_provider.expect_code_exchange(&request, &code, &oidc_provider.id_token(&claims)).await;
+
+        let identity = oidc_service
+            .link_identity(user.id, &request, &OidcCallbackDto { state: request.state.clone(), code })
+            .await?;
+        assert_eq!(user.id, identity.data.user_id);
+
+        // The identity cannot be linked to another account
+        let request = oidc_service.authorization_request(PROVIDER).await?;
+        let code = new_hyphenated_uuid();
+        let claims = oidc_provider.claims(&request, &subject, &format!("{}@email.fake", new_hyphenated_uuid()));
+        oidc_provider.expect_code_exchange(&request, &code, &oidc_provider.id_token(&claims)).await;
+
+        match oidc_service
+            .link_identity(other_user.id, &request, &OidcCallbackDto { state: request.state.clone(), code })
+            .await
+        {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert_eq!(vec![ERR_NOT_UNIQUE.to_owned()], details.details["subject"]);
+            }
+            _ => panic!(),
+        };
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_login_with_an_invalid_callback_or_id_token() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let oidc_provider = MockOidcProvider::start().await;
+        let oidc_service = new_oidc_service(auth_module, vec![oidc_provider.provider_config(PROVIDER, false)]);
+        let email = format!("{}@email.fake", new_hyphenated_uuid());
+
+        let invalid_claims = [
+            ("nonce", json!("wrong_nonce")),
+            ("aud", json!("another_client")),
+            ("iss", json!("http://another.issuer")),
+            ("exp", json!(current_epoch_seconds() - 3600)),
+        ];
+        for (claim, value) in invalid_claims {
+            let request = oidc_service.authorization_request(PROVIDER).await?;
+            let code = new_hyphenated_uuid();
+            let mut claims = oidc_provider.claims(&request, &new_hyphenated_uuid(), &email);
+            claims[claim] = value;
+            oidc_provider.expect_code_exchange(&request, &code, &oidc_provider.id_token(&claims)).await;
+
+            let result = oidc_service.login(&request, &OidcCallbackDto { state: request.state.clone(), code }).await;
+            assert!(result.is_err(), "The login should fail with a wrong [{claim}]");
+        }
+
+        // The ID token is not signed by the provider
+        let request = oidc_service.authorization_request(PROVIDER).await?;
+        let code = new_hyphenated_uuid();
+        let claims = oidc_provider.claims(&request, &new_hyphenated_uuid(), &email);
+        oidc_provider.expect_code_exchange(&request, &code, &oidc_provider.id_token_with_unknown_key(&claims)).await;
+        assert!(oidc_service.login(&request, &OidcCallbackDto { state: request.state.clone(), code }).await.is_err());
+
+        // The state does not match
+        let request = oidc_service.authorization_request(PROVIDER).await?;
+        let code = new_hyphenated_uuid();
+        let claims = oidc_provider.claims(&request, &new_hyphenated_uuid(), &email);
+        oidc_provider.expect_code_exchange(&request, &code, &oidc_provider.id_token(&claims)).await;
+        assert!(oidc_service.login(&request, &OidcCallbackDto { state: new_hyphenated_uuid(), code }).await.is_err());
+
+        // The PKCE code verifier does not match the one of the authorization request
+        let mut request = oidc_service.authorization_request(PROVIDER).await?;
+        let code = new_hyphenated_uuid();
+        let claims = oidc_provider.claims(&request, &new_hyphenated_uuid(), &email);
+        oidc_provider.expect_code_exchange(&request, &code, &oidc_provider.id_token(&claims)).await;
+        request.pkce_code_verifier = new_hyphenated_uuid();
+        assert!(oidc_service.login(&request, &OidcCallbackDto { state: request.state.clone(), code }).await.is_err());
+
+        // No account has been created
+        assert!(auth_module.auth_account_service.fetch_by_username(&email).await.is_err());
+
+        Ok(())
+    })
+}
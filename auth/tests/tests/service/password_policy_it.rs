@@ -0,0 +1,187 @@
+use crate::tests::util::{authenticated, create_user_with_password, new_auth_account_service};
+use crate::{data, test, RepoManager};
+use lightspeed_auth::config::AuthConfig;
+use lightspeed_auth::dto::change_password_dto::ChangePasswordDto;
+use lightspeed_auth::dto::reset_password_dto::ResetPasswordDto;
+use lightspeed_auth::model::auth_account::AuthAccountModel;
+use lightspeed_auth::model::token::TokenType;
+use lightspeed_auth::service::auth_account::{LoginOutcome, ERR_PASSWORD_ALREADY_USED};
+use lightspeed_auth::AuthModule;
+use lightspeed_core::clock::MockClock;
+use lightspeed_core::error::LightSpeedError;
+use std::time::Duration;
+
+const PASSWORD: &str = "123456789";
+
+#[test]
+fn should_not_change_password_to_a_recently_used_one() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let history_size = auth_module.auth_config.password_history_size as usize;
+
+        let mut passwords = vec![PASSWORD.to_owned()];
+        for count in 1..history_size {
+            let new_password = format!("{PASSWORD}_{count}");
+            change_password(auth_module, user.id, &passwords[count - 1], &new_password).await?;
+            passwords.push(new_password);
+        }
+
+        let current_password = passwords.last().unwrap().clone();
+        for password in &passwords {
+            match change_password(auth_module, user.id, &current_password, password).await {
+                Err(LightSpeedError::ValidationError { details }) => {
+                    assert_eq!(vec![ERR_PASSWORD_ALREADY_USED.to_owned()], details.details["new_password"])
+                }
+                _ => panic!(),
+            }
+        }
+
+        // The oldest password is not in the history anymore after one more change
+        let new_password = format!("{PASSWORD}_{history_size}");
+        change_password(auth_module, user.id, &current_password, &new_password).await?;
+        let user = change_password(auth_module, user.id, &new_password, PASSWORD).await?;
+
+        assert_eq!(history_size - 1, user.data.password_history.len());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, PASSWORD)
+            .await
+            .and_then(authenticated)
+            .is_ok());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_reset_password_to_a_recently_used_one() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        let (_, token) = auth_module.auth_account_service.generate_reset_password_token(&user.data.username).await?;
+
+        match reset_password(auth_module, &token.data.token, PASSWORD).await {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert_eq!(vec![ERR_PASSWORD_ALREADY_USED.to_owned()], details.details["password"])
+            }
+            _ => panic!(),
+        }
+
+        // The token is still valid after a rejected password
+        let new_password = format!("{PASSWORD}_new");
+        let user = reset_password(auth_module, &token.data.token, &new_password).await?;
+        assert_eq!(1, user.data.password_history.len());
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, &new_password)
+            .await
+            .and_then(authenticated)
+            .is_ok());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_allow_password_reuse_if_history_is_disabled() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        let auth_config = AuthConfig { password_history_size: 0, ..auth_module.auth_config.clone() };
+        let auth_account_service = new_auth_account_service(auth_module, auth_config, &MockClock::default());
+
+        let user = auth_account_service
+            .change_password(
+                ChangePasswordDto {
+                    user_id: user.id,
+                    old_password: PASSWORD.to_owned(),
+                    new_password: PASSWORD.to_owned(),
+                    new_password_confirm: PASSWORD.to_owned(),
+                },
+                None,
+            )
+            .await?;
+        assert!(user.data.password_history.is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_return_a_reset_token_when_the_password_expires() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let username = &user.data.username;
+
+        let clock = MockClock::default();
+        let auth_config = AuthConfig { max_password_age_days: Some(30), ..auth_module.auth_config.clone() };
+        let auth_account_service = new_auth_account_service(auth_module, auth_config, &clock);
+
+        assert!(auth_account_service.login(username, PASSWORD).await.and_then(authenticated).is_ok());
+
+        clock.advance(Duration::from_secs(30 * 24 * 60 * 60));
+
+        let reset_token = match auth_account_service.login(username, PASSWORD).await? {
+            LoginOutcome::PasswordExpired { reset_token } => reset_token,
+            _ => panic!(),
+        };
+        assert_eq!(TokenType::ResetPassword, reset_token.data.token_type);
+        assert_eq!(*username, reset_token.data.username);
+
+        let new_password = format!("{PASSWORD}_new");
+        auth_account_service
+            .reset_password_by_token(ResetPasswordDto {
+                token: reset_token.data.token,
+                password: new_password.clone(),
+                password_confirm: new_password.clone(),
+            })
+            .await?;
+
+        assert!(auth_account_service.login(username, &new_password).await.and_then(authenticated).is_ok());
+
+        Ok(())
+    })
+}
+
+async fn change_password(
+    auth_module: &AuthModule<RepoManager>,
+    user_id: i64,
+    old_password: &str,
+    new_password: &str,
+) -> Result<AuthAccountModel, LightSpeedError> {
+    auth_module
+        .auth_account_service
+        .change_password(
+            ChangePasswordDto {
+                user_id,
+                old_password: old_password.to_owned(),
+                new_password: new_password.to_owned(),
+                new_password_confirm: new_password.to_owned(),
+            },
+            None,
+        )
+        .await
+}
+
+async fn reset_password(
+    auth_module: &AuthModule<RepoManager>,
+    token: &str,
+    password: &str,
+) -> Result<AuthAccountModel, LightSpeedError> {
+    auth_module
+        .auth_account_service
+        .reset_password_by_token(ResetPasswordDto {
+            token: token.to_owned(),
+            password: password.to_owned(),
+            password_confirm: password.to_owned(),
+        })
+        .await
+}
@@ -0,0 +1,206 @@
+use crate::tests::util::{authenticated, create_user_with_password, new_auth_account_service};
+use crate::{data, test};
+use c3p0::*;
+use lightspeed_auth::model::auth_account::AuthAccountStatus;
+use lightspeed_auth::model::impersonation::ImpersonationData;
+use lightspeed_auth::model::token::TokenType;
+use lightspeed_auth::model::webauthn_credential::WebAuthnCredentialData;
+use lightspeed_auth::repository::{AuthRepositoryManager, ImpersonationRepository, WebAuthnCredentialRepository};
+use lightspeed_auth::service::personal_data::PersonalDataContributor;
+use lightspeed_core::clock::MockClock;
+use lightspeed_core::error::{ErrorCodes, LightSpeedError};
+use lightspeed_core::utils::current_epoch_seconds;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+const PASSWORD: &str = "123456789";
+
+struct FileStoreContributor;
+
+#[async_trait::async_trait]
+impl PersonalDataContributor for FileStoreContributor {
+    fn name(&self) -> String {
+        "file_store".to_owned()
+    }
+
+    async fn export_personal_data(&self, user_id: i64) -> Result<Value, LightSpeedError> {
+        Ok(json!({ "owner_id": user_id, "files": ["avatar.png"] }))
+    }
+}
+
+#[test]
+fn should_anonymize_the_account_and_keep_its_id() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let auth_account_service = &auth_module.auth_account_service;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        let auth = auth_account_service.login(&user.data.username, PASSWORD).await.and_then(authenticated)?;
+        auth_account_service.generate_reset_password_token(&user.data.username).await?;
+
+        let anonymized_user = auth_account_service.anonymize_by_user_id(user.id).await?;
+        assert_eq!(user.id, anonymized_user.id);
+        assert!(anonymized_user.data.is_anonymized());
+        assert_eq!(AuthAccountStatus::Disabled, anonymized_user.data.status);
+        assert_ne!(user.data.username, anonymized_user.data.username);
+        assert_ne!(user.data.email, anonymized_user.data.email);
+        assert!(anonymized_user.data.disabled_date_epoch_seconds.is_some());
+
+        assert!(auth_account_service.login(&user.data.username, PASSWORD).await.and_then(authenticated).is_err());
+        assert!(auth_module.auth_session_service.touch(&auth.session_id, None, None).await.is_err());
+
+        let export = auth_module.new_personal_data_service(vec![]).export_personal_data(user.id).await?;
+        assert!(export.tokens.is_empty());
+        assert!(export.sessions.is_empty());
+
+        // Anonymizing twice has no effect
+        let anonymized_again = auth_account_service.anonymize_by_user_id(user.id).await?;
+        assert_eq!(
+            anonymized_user.data.anonymized_date_epoch_seconds,
+            anonymized_again.data.anonymized_date_epoch_seconds
+        );
+
+        match auth_account_service.reactivate_disabled_user_by_user_id(user.id).await {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ErrorCodes::ANONYMIZED_USER, code),
+            _ => panic!(),
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_export_the_personal_data() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        let auth =
+            auth_module.auth_account_service.login(&user.data.username, PASSWORD).await.and_then(authenticated)?;
+        auth_module.auth_account_service.generate_reset_password_token(&user.data.username).await?;
+        let (support_user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        let now = current_epoch_seconds();
+        auth_module
+            .repo_manager
+            .c3p0()
+            .transaction(|conn| async {
+                auth_module
+                    .repo_manager
+                    .webauthn_credential_repo()
+                    .save(
+                        conn,
+                        NewModel::new(WebAuthnCredentialData {
+                            user_id: user.id,
+                            name: "Security key".to_owned(),
+                            credential_id: "credential_id".to_owned(),
+                            algorithm: -7,
+                            public_key: "public_key".to_owned(),
+                            sign_count: 0,
+                            created_date_epoch_seconds: now,
+                            last_used_date_epoch_seconds: Some(now + 1),
+                        }),
+                    )
+                    .await?;
+                auth_module
+                    .repo_manager
+                    .impersonation_repo()
+                    .save(
+                        conn,
+                        NewModel::new(ImpersonationData {
+                            user_id: user.id,
+                            impersonator_user_id: support_user.id,
+                            session_id: "session_id".to_owned(),
+                            reason: Some("ticket 123".to_owned()),
+                            created_date_epoch_seconds: now,
+                            expire_at_epoch_seconds: now + 60,
+                        }),
+                    )
+                    .await
+            })
+            .await?;
+
+        let personal_data_service = auth_module.new_personal_data_service(vec![Arc::new(FileStoreContributor)]);
+        let export = personal_data_service.export_personal_data(user.id).await?;
+
+        assert_eq!(user.id, export.account.user_id);
+        assert_eq!(user.data.username, export.account.username);
+        assert_eq!(user.data.email, export.account.email);
+
+        assert_eq!(1, export.tokens.len());
+        assert_eq!(TokenType::ResetPassword, export.tokens[0].token_type);
+
+        assert_eq!(1, export.sessions.len());
+        assert_eq!(auth.session_id, export.sessions[0].session_id);
+
+        assert_eq!(1, export.webauthn_credentials.len());
+        assert_eq!("Security key", export.webauthn_credentials[0].name);
+        assert_eq!(now, export.webauthn_credentials[0].created_date_epoch_seconds);
+        assert_eq!(Some(now + 1), export.webauthn_credentials[0].last_used_date_epoch_seconds);
+
+        assert_eq!(1, export.impersonations.len());
+        assert_eq!(support_user.id, export.impersonations[0].impersonator_user_id);
+        assert_eq!(Some("ticket 123".to_owned()), export.impersonations[0].reason);
+
+        assert_eq!(
+            Some(&json!({ "owner_id": user.id, "files": ["avatar.png"] })),
+            export.contributions.get("file_store")
+        );
+
+        // The secrets are not exported
+        let exported_json = serde_json::to_string(&export)?;
+        assert!(!exported_json.contains(&user.data.password));
+        assert!(!exported_json.contains("public_key"));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_anonymize_the_accounts_disabled_for_longer_than_the_retention() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (expired_user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let (recent_user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        let mut auth_config = auth_module.auth_config.clone();
+        auth_config.anonymize_disabled_accounts_after_days = Some(1);
+
+        // Disabled two days ago
+        let past_clock = MockClock::from_epoch_seconds(current_epoch_seconds() - 2 * 24 * 60 * 60);
+        new_auth_account_service(auth_module, auth_config.clone(), &past_clock)
+            .disable_by_user_id(expired_user.id)
+            .await?;
+        auth_module.auth_account_service.disable_by_user_id(recent_user.id).await?;
+
+        let auth_account_service = new_auth_account_service(auth_module, auth_config, &MockClock::default());
+        assert!(auth_account_service.anonymize_expired_disabled_accounts().await? >= 1);
+
+        assert!(auth_account_service.fetch_by_user_id(expired_user.id).await?.data.is_anonymized());
+
+        let recent_user = auth_account_service.fetch_by_user_id(recent_user.id).await?;
+        assert!(!recent_user.data.is_anonymized());
+        assert_eq!(AuthAccountStatus::Disabled, recent_user.data.status);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_anonymize_the_accounts_without_a_retention() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+
+        let mut auth_config = auth_module.auth_config.clone();
+        auth_config.anonymize_disabled_accounts_after_days = None;
+
+        let auth_account_service = new_auth_account_service(auth_module, auth_config, &MockClock::default());
+        assert_eq!(0, auth_account_service.anonymize_expired_disabled_accounts().await?);
+
+        Ok(())
+    })
+}
@@ -1,9 +1,13 @@
-use crate::{data, test};
+use crate::{data, test, RepoManager};
 use c3p0::*;
+use lightspeed_auth::config::{AuthConfig, TOKEN_HASH_KEY_MIN_LENGTH};
 use lightspeed_auth::model::token::{TokenData, TokenType};
-use lightspeed_auth::repository::AuthRepositoryManager;
+use lightspeed_auth::repository::{AuthRepositoryManager, TokenRepository};
+use lightspeed_auth::service::token::TokenService;
+use lightspeed_core::clock::MockClock;
 use lightspeed_core::error::LightSpeedError;
 use lightspeed_core::utils::{current_epoch_seconds, new_hyphenated_uuid};
+use std::sync::Arc;
 
 #[test]
 fn should_delete_token() -> Result<(), LightSpeedError> {
@@ -21,11 +25,11 @@ fn should_delete_token() -> Result<(), LightSpeedError> {
                 expire_at_epoch_seconds: 9999999999999,
                 token_type: TokenType::ResetPassword,
                 username: "test@test.com".to_owned(),
+                invitation: None,
             },
         };
 
         c3p0.transaction(|conn| async {
-
             let saved_token = token_repo.save(conn, token).await?;
 
             assert!(token_repo.exists_by_id(conn, &saved_token.id).await?);
@@ -86,23 +90,22 @@ fn should_validate_token_on_fetch() -> Result<(), LightSpeedError> {
         let token_repo = auth_module.repo_manager.token_repo();
 
         c3p0.transaction(|conn| async {
+            let plain_token = new_hyphenated_uuid();
             let token = NewModel {
                 version: 0,
                 data: TokenData {
-                    token: new_hyphenated_uuid(),
+                    token: auth_module.token_service.hash_token(&plain_token),
                     expire_at_epoch_seconds: current_epoch_seconds() - 1,
                     token_type: TokenType::ResetPassword,
                     username: "test@test.com".to_owned(),
+                    invitation: None,
                 },
             };
 
-            let saved_token = token_repo.save(conn, token).await?;
+            token_repo.save(conn, token).await?;
 
-            assert!(auth_module
-                .token_service
-                .fetch_by_token_with_conn(conn, &saved_token.data.token, true)
-                .await
-                .is_err());
+            assert!(auth_module.token_service.fetch_by_token_with_conn(conn, &plain_token, false).await.is_ok());
+            assert!(auth_module.token_service.fetch_by_token_with_conn(conn, &plain_token, true).await.is_err());
 
             Ok(())
         })
@@ -137,6 +140,7 @@ fn should_return_all_tokens_by_username() -> Result<(), LightSpeedError> {
                             expire_at_epoch_seconds: current_epoch_seconds() - 1,
                             token_type: TokenType::ResetPassword,
                             username: username_1.clone(),
+                            invitation: None,
                         },
                     },
                 )
@@ -154,6 +158,7 @@ fn should_return_all_tokens_by_username() -> Result<(), LightSpeedError> {
                             expire_at_epoch_seconds: current_epoch_seconds() - 1,
                             token_type: TokenType::AccountActivation,
                             username: username_1.clone(),
+                            invitation: None,
                         },
                     },
                 )
@@ -172,3 +177,115 @@ fn should_return_all_tokens_by_username() -> Result<(), LightSpeedError> {
         .await
     })
 }
+
+#[test]
+fn should_purge_the_expired_tokens() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+
+        let c3p0 = auth_module.repo_manager.c3p0();
+        let token_repo = auth_module.repo_manager.token_repo();
+
+        // The tokens are dated before the epoch so that the purge does not remove the tokens of the other tests
+        let now = -1_000_000;
+        let token_service = TokenService::<RepoManager>::new(
+            c3p0.clone(),
+            auth_module.auth_config.clone(),
+            token_repo.clone(),
+            Arc::new(MockClock::from_epoch_seconds(now)),
+        )?;
+
+        let (expired_token, valid_token) = c3p0
+            .transaction(|conn| async {
+                let mut tokens = vec![];
+                for expire_at_epoch_seconds in [now - 1, now + 60] {
+                    tokens.push(
+                        token_repo
+                            .save(
+                                conn,
+                                NewModel::new(TokenData {
+                                    token: new_hyphenated_uuid(),
+                                    expire_at_epoch_seconds,
+                                    token_type: TokenType::ResetPassword,
+                                    username: new_hyphenated_uuid(),
+                                    invitation: None,
+                                }),
+                            )
+                            .await?,
+                    );
+                }
+                let valid_token = tokens.pop().unwrap();
+                Ok::<_, LightSpeedError>((tokens.pop().unwrap(), valid_token))
+            })
+            .await?;
+
+        assert!(token_service.purge_expired().await? >= 1);
+
+        c3p0.transaction(|conn| async {
+            assert!(!token_repo.exists_by_id(conn, &expired_token.id).await?);
+            assert!(token_repo.exists_by_id(conn, &valid_token.id).await?);
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_save_only_the_hash_of_the_token() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+
+        let c3p0 = auth_module.repo_manager.c3p0();
+        let token_repo = auth_module.repo_manager.token_repo();
+
+        c3p0.transaction(|conn| async {
+            let username = new_hyphenated_uuid();
+            let token = auth_module
+                .token_service
+                .generate_and_save_token_with_conn(conn, username.clone(), TokenType::ResetPassword)
+                .await?;
+
+            let saved_tokens = token_repo.fetch_by_username(conn, &username).await?;
+            assert_eq!(1, saved_tokens.len());
+            assert_ne!(token.data.token, saved_tokens[0].data.token);
+            assert_eq!(auth_module.token_service.hash_token(&token.data.token), saved_tokens[0].data.token);
+
+            assert!(token_repo.fetch_by_token(conn, &token.data.token).await.is_err());
+            let fetched_token =
+                auth_module.token_service.fetch_by_token_with_conn(conn, &token.data.token, true).await?;
+            assert_eq!(token.id, fetched_token.id);
+
+            // The tokens are longer than a UUID
+            assert!(token.data.token.len() > 36);
+
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_not_create_the_token_service_with_a_short_hash_key() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+
+        let auth_config =
+            AuthConfig { token_hash_key: "a".repeat(TOKEN_HASH_KEY_MIN_LENGTH - 1), ..auth_module.auth_config.clone() };
+        let result = TokenService::<RepoManager>::new(
+            auth_module.repo_manager.c3p0().clone(),
+            auth_config,
+            auth_module.repo_manager.token_repo(),
+            auth_module.clock.clone(),
+        );
+
+        match result {
+            Err(LightSpeedError::ConfigurationError { .. }) => {}
+            _ => panic!(),
+        }
+
+        Ok(())
+    })
+}
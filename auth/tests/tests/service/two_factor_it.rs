@@ -0,0 +1,290 @@
+use crate::tests::util::{authenticated, create_user_with_password};
+use crate::{data, test};
+use lightspeed_auth::dto::two_factor_dto::TotpCodeDto;
+use lightspeed_auth::model::auth_account::AuthAccountModel;
+use lightspeed_auth::model::token::TokenType;
+use lightspeed_auth::service::auth_account::LoginOutcome;
+use lightspeed_auth::AuthModule;
+use lightspeed_core::error::{ErrorCodes, LightSpeedError};
+
+const PASSWORD: &str = "123456789";
+
+#[test]
+fn should_enroll_and_confirm_totp() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        let enrollment = auth_module.auth_account_service.enroll_totp(user.id).await?;
+        assert!(enrollment.otpauth_uri.starts_with("otpauth://totp/"));
+        assert!(enrollment.otpauth_uri.contains(&format!("secret={}", enrollment.secret)));
+
+        // The second factor is not required until the enrollment is confirmed
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, PASSWORD)
+            .await
+            .and_then(authenticated)
+            .is_ok());
+
+        let code = current_code(auth_module, &enrollment.secret, 0);
+        let (user, recovery_codes) =
+            auth_module.auth_account_service.confirm_totp_enrollment(TotpCodeDto { user_id: user.id, code }).await?;
+
+        assert!(user.data.is_two_factor_enabled());
+        assert_eq!(auth_module.auth_config.recovery_codes_count as usize, recovery_codes.len());
+
+        let two_factor = user.data.two_factor.as_ref().unwrap();
+        assert_eq!(recovery_codes.len(), two_factor.recovery_code_hashes.len());
+        for recovery_code in &recovery_codes {
+            assert!(!two_factor.recovery_code_hashes.contains(recovery_code));
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_confirm_totp_enrollment_with_wrong_code() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+
+        let enrollment = auth_module.auth_account_service.enroll_totp(user.id).await?;
+        let wrong_code = wrong_code(&current_code(auth_module, &enrollment.secret, 0));
+
+        match auth_module
+            .auth_account_service
+            .confirm_totp_enrollment(TotpCodeDto { user_id: user.id, code: wrong_code })
+            .await
+        {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ErrorCodes::WRONG_CREDENTIALS, code),
+            _ => panic!(),
+        }
+
+        assert!(!auth_module.auth_account_service.fetch_by_user_id(user.id).await?.data.is_two_factor_enabled());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_enroll_totp_twice() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _, _) = create_user_with_two_factor(auth_module).await?;
+
+        match auth_module.auth_account_service.enroll_totp(user.id).await {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ErrorCodes::TWO_FACTOR_ALREADY_ENABLED, code),
+            _ => panic!(),
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_require_the_second_factor_to_login() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, secret, _) = create_user_with_two_factor(auth_module).await?;
+
+        let outcome = auth_module.auth_account_service.login(&user.data.username, PASSWORD).await?;
+        let challenge = match outcome {
+            LoginOutcome::SecondFactorRequired { challenge } => challenge,
+            _ => panic!(),
+        };
+
+        assert_eq!(TokenType::SecondFactorChallenge, challenge.data.token_type);
+        assert_eq!(user.data.username, challenge.data.username);
+
+        let code = current_code(auth_module, &secret, 1);
+        let auth = auth_module.auth_account_service.login_with_totp_code(&challenge.data.token, &code).await?;
+
+        assert_eq!(user.id, auth.id);
+        assert_eq!(user.data.username, auth.username);
+
+        // The challenge can be used only once
+        assert!(auth_module.auth_account_service.login_with_totp_code(&challenge.data.token, &code).await.is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_login_with_wrong_or_reused_totp_code() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, secret, _) = create_user_with_two_factor(auth_module).await?;
+
+        let challenge = new_challenge(auth_module, &user).await?;
+
+        let code = current_code(auth_module, &secret, 1);
+        match auth_module.auth_account_service.login_with_totp_code(&challenge, &wrong_code(&code)).await {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ErrorCodes::WRONG_CREDENTIALS, code),
+            _ => panic!(),
+        }
+
+        // The challenge is still valid after a wrong code
+        assert!(auth_module.auth_account_service.login_with_totp_code(&challenge, &code).await.is_ok());
+
+        let challenge = new_challenge(auth_module, &user).await?;
+        assert!(auth_module.auth_account_service.login_with_totp_code(&challenge, &code).await.is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_login_with_recovery_code_only_once() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _, recovery_codes) = create_user_with_two_factor(auth_module).await?;
+
+        let challenge = new_challenge(auth_module, &user).await?;
+        let auth = auth_module.auth_account_service.login_with_recovery_code(&challenge, &recovery_codes[0]).await?;
+        assert_eq!(user.id, auth.id);
+
+        let user = auth_module.auth_account_service.fetch_by_user_id(user.id).await?;
+        assert_eq!(recovery_codes.len() - 1, user.data.two_factor.as_ref().unwrap().recovery_code_hashes.len());
+
+        let challenge = new_challenge(auth_module, &user).await?;
+        match auth_module.auth_account_service.login_with_recovery_code(&challenge, &recovery_codes[0]).await {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ErrorCodes::WRONG_CREDENTIALS, code),
+            _ => panic!(),
+        }
+
+        assert!(auth_module
+            .auth_account_service
+            .login_with_recovery_code(&challenge, &recovery_codes[1].to_lowercase())
+            .await
+            .is_ok());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_regenerate_recovery_codes() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, secret, old_recovery_codes) = create_user_with_two_factor(auth_module).await?;
+
+        let code = current_code(auth_module, &secret, 1);
+        let (user, recovery_codes) =
+            auth_module.auth_account_service.regenerate_recovery_codes(TotpCodeDto { user_id: user.id, code }).await?;
+
+        assert_eq!(old_recovery_codes.len(), recovery_codes.len());
+        assert_ne!(old_recovery_codes, recovery_codes);
+
+        let challenge = new_challenge(auth_module, &user).await?;
+        assert!(auth_module
+            .auth_account_service
+            .login_with_recovery_code(&challenge, &old_recovery_codes[0])
+            .await
+            .is_err());
+        assert!(auth_module
+            .auth_account_service
+            .login_with_recovery_code(&challenge, &recovery_codes[0])
+            .await
+            .is_ok());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_disable_two_factor_with_valid_code() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, secret, _) = create_user_with_two_factor(auth_module).await?;
+
+        let code = current_code(auth_module, &secret, 1);
+        assert!(auth_module
+            .auth_account_service
+            .disable_two_factor(TotpCodeDto { user_id: user.id, code: wrong_code(&code) })
+            .await
+            .is_err());
+
+        let user = auth_module.auth_account_service.disable_two_factor(TotpCodeDto { user_id: user.id, code }).await?;
+        assert!(user.data.two_factor.is_none());
+
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, PASSWORD)
+            .await
+            .and_then(authenticated)
+            .is_ok());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn admin_should_reset_two_factor() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _, _) = create_user_with_two_factor(auth_module).await?;
+
+        let challenge = new_challenge(auth_module, &user).await?;
+
+        let user = auth_module.auth_account_service.reset_two_factor_by_user_id(user.id).await?;
+        assert!(!user.data.is_two_factor_enabled());
+
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, PASSWORD)
+            .await
+            .and_then(authenticated)
+            .is_ok());
+
+        // The pending challenges cannot be used anymore
+        match auth_module.auth_account_service.login_with_recovery_code(&challenge, "any").await {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ErrorCodes::TWO_FACTOR_NOT_ENABLED, code),
+            _ => panic!(),
+        }
+
+        Ok(())
+    })
+}
+
+async fn create_user_with_two_factor(
+    auth_module: &AuthModule<crate::RepoManager>,
+) -> Result<(AuthAccountModel, String, Vec<String>), LightSpeedError> {
+    let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+    let enrollment = auth_module.auth_account_service.enroll_totp(user.id).await?;
+    let code = current_code(auth_module, &enrollment.secret, 0);
+    let (user, recovery_codes) =
+        auth_module.auth_account_service.confirm_totp_enrollment(TotpCodeDto { user_id: user.id, code }).await?;
+    Ok((user, enrollment.secret, recovery_codes))
+}
+
+async fn new_challenge(
+    auth_module: &AuthModule<crate::RepoManager>,
+    user: &AuthAccountModel,
+) -> Result<String, LightSpeedError> {
+    match auth_module.auth_account_service.authenticate(&user.data.username, PASSWORD, None).await? {
+        LoginOutcome::SecondFactorRequired { challenge } => Ok(challenge.data.token),
+        _ => panic!(),
+    }
+}
+
+/// Returns the code of the current time step plus the given offset.
+/// The offset permits to use a code not yet consumed by a previous verification.
+fn current_code(auth_module: &AuthModule<crate::RepoManager>, secret: &str, offset: i64) -> String {
+    let totp_service = &auth_module.totp_service;
+    totp_service.generate_code(secret, totp_service.current_time_step() + offset).unwrap()
+}
+
+fn wrong_code(code: &str) -> String {
+    code.chars().map(|c| if c == '9' { '0' } else { char::from(c as u8 + 1) }).collect()
+}
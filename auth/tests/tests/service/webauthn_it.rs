@@ -0,0 +1,282 @@
+use crate::tests::util::{authenticated, create_user_with_password};
+use crate::{data, test};
+use base64::engine::general_purpose;
+use base64::Engine;
+use ciborium::Value;
+use lightspeed_auth::dto::webauthn_dto::{WebAuthnAssertionDto, WebAuthnRegistrationDto};
+use lightspeed_auth::model::auth_account::AuthAccountModel;
+use lightspeed_auth::model::webauthn_credential::WebAuthnCredentialModel;
+use lightspeed_auth::repository::AuthRepositoryManager;
+use lightspeed_auth::service::auth_account::LoginOutcome;
+use lightspeed_auth::AuthModule;
+use lightspeed_core::error::{ErrorCodes, LightSpeedError};
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::{Signature, SigningKey};
+use rand::RngCore;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+const PASSWORD: &str = "123456789";
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+#[test]
+fn should_register_a_passkey_and_login_without_password() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let mut authenticator = SoftwareAuthenticator::new(auth_module);
+
+        let credential = register(auth_module, &user, &authenticator).await?;
+        assert_eq!(user.id, credential.data.user_id);
+        assert_eq!("Security key", credential.data.name);
+        assert_eq!(authenticator.credential_id(), credential.data.credential_id);
+        assert!(credential.data.last_used_date_epoch_seconds.is_none());
+
+        let options = auth_module.auth_account_service.start_webauthn_login(Some(&user.data.username)).await?;
+        assert_eq!("required", options.user_verification);
+        assert_eq!(1, options.allow_credentials.len());
+        assert_eq!(credential.data.credential_id, options.allow_credentials[0].id);
+
+        let auth = auth_module
+            .auth_account_service
+            .login_with_webauthn(authenticator.assert(&options.challenge, FLAG_USER_PRESENT | FLAG_USER_VERIFIED))
+            .await?;
+        assert_eq!(user.id, auth.id);
+        assert_eq!(user.data.username, auth.username);
+
+        let credentials = auth_module.auth_account_service.fetch_webauthn_credentials_by_user_id(user.id).await?;
+        assert_eq!(1, credentials.len());
+        assert_eq!(1, credentials[0].data.sign_count);
+        assert!(credentials[0].data.last_used_date_epoch_seconds.is_some());
+
+        // A discoverable credential can be used without the username
+        let options = auth_module.auth_account_service.start_webauthn_login(None).await?;
+        assert!(options.allow_credentials.is_empty());
+
+        let mut assertion = authenticator.assert(&options.challenge, FLAG_USER_PRESENT | FLAG_USER_VERIFIED);
+        assertion.user_handle = Some(auth_module.webauthn_service.user_handle(user.id));
+        let auth = auth_module.auth_account_service.login_with_webauthn(assertion.clone()).await?;
+        assert_eq!(user.id, auth.id);
+
+        // The challenge cannot be used twice
+        assert!(auth_module.auth_account_service.login_with_webauthn(assertion).await.is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_require_user_verification_for_the_passwordless_login() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let mut authenticator = SoftwareAuthenticator::new(auth_module);
+        register(auth_module, &user, &authenticator).await?;
+
+        let options = auth_module.auth_account_service.start_webauthn_login(Some(&user.data.username)).await?;
+        assert!(auth_module
+            .auth_account_service
+            .login_with_webauthn(authenticator.assert(&options.challenge, FLAG_USER_PRESENT))
+            .await
+            .is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_require_the_passkey_as_second_factor() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let mut authenticator = SoftwareAuthenticator::new(auth_module);
+        register(auth_module, &user, &authenticator).await?;
+
+        let outcome = auth_module.auth_account_service.authenticate(&user.data.username, PASSWORD, None).await?;
+        let challenge = match outcome {
+            LoginOutcome::SecondFactorRequired { challenge } => challenge,
+            _ => panic!(),
+        };
+
+        let options = auth_module.auth_account_service.start_webauthn_second_factor(&challenge.data.token).await?;
+        assert_eq!(challenge.data.token, options.challenge);
+        assert_eq!(1, options.allow_credentials.len());
+
+        let auth = auth_module
+            .auth_account_service
+            .login_with_webauthn_second_factor(
+                &challenge.data.token,
+                authenticator.assert(&challenge.data.token, FLAG_USER_PRESENT),
+            )
+            .await?;
+        assert_eq!(user.id, auth.id);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_not_login_with_a_wrong_signature_or_an_unknown_credential() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let mut authenticator = SoftwareAuthenticator::new(auth_module);
+        register(auth_module, &user, &authenticator).await?;
+
+        let options = auth_module.auth_account_service.start_webauthn_login(Some(&user.data.username)).await?;
+        let mut assertion = authenticator.assert(&options.challenge, FLAG_USER_PRESENT | FLAG_USER_VERIFIED);
+        assertion.signature =
+            authenticator.assert(&options.challenge, FLAG_USER_PRESENT | FLAG_USER_VERIFIED).signature;
+        match auth_module.auth_account_service.login_with_webauthn(assertion).await {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ErrorCodes::WRONG_CREDENTIALS, code),
+            _ => panic!(),
+        }
+
+        let options = auth_module.auth_account_service.start_webauthn_login(None).await?;
+        let mut unknown_authenticator = SoftwareAuthenticator::new(auth_module);
+        match auth_module
+            .auth_account_service
+            .login_with_webauthn(
+                unknown_authenticator.assert(&options.challenge, FLAG_USER_PRESENT | FLAG_USER_VERIFIED),
+            )
+            .await
+        {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ErrorCodes::WRONG_CREDENTIALS, code),
+            _ => panic!(),
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_delete_a_passkey() -> Result<(), LightSpeedError> {
+    test(async {
+        let data = data(false).await;
+        let auth_module = &data.0;
+        let (user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let (other_user, _) = create_user_with_password(auth_module, PASSWORD, true).await?;
+        let credential = register(auth_module, &user, &SoftwareAuthenticator::new(auth_module)).await?;
+
+        match auth_module.auth_account_service.delete_webauthn_credential(other_user.id, credential.id).await {
+            Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ErrorCodes::NOT_FOUND, code),
+            _ => panic!(),
+        }
+
+        auth_module.auth_account_service.delete_webauthn_credential(user.id, credential.id).await?;
+        assert!(auth_module.auth_account_service.fetch_webauthn_credentials_by_user_id(user.id).await?.is_empty());
+
+        // Without credentials the second factor is no longer required
+        assert!(auth_module
+            .auth_account_service
+            .login(&user.data.username, PASSWORD)
+            .await
+            .and_then(authenticated)
+            .is_ok());
+
+        Ok(())
+    })
+}
+
+async fn register<RepoManager: AuthRepositoryManager>(
+    auth_module: &AuthModule<RepoManager>,
+    user: &AuthAccountModel,
+    authenticator: &SoftwareAuthenticator,
+) -> Result<WebAuthnCredentialModel, LightSpeedError> {
+    let options = auth_module.auth_account_service.start_webauthn_registration(user.id).await?;
+    assert_eq!(auth_module.webauthn_service.user_handle(user.id), options.user.id);
+    let (client_data_json, attestation_object) = authenticator.register(&options.challenge);
+    auth_module
+        .auth_account_service
+        .finish_webauthn_registration(
+            user.id,
+            WebAuthnRegistrationDto { name: "Security key".to_owned(), client_data_json, attestation_object },
+        )
+        .await
+}
+
+/// An authenticator that keeps its ES256 key in memory
+struct SoftwareAuthenticator {
+    rp_id: String,
+    origin: String,
+    signing_key: SigningKey,
+    credential_id: Vec<u8>,
+    sign_count: u32,
+}
+
+impl SoftwareAuthenticator {
+    fn new<RepoManager: AuthRepositoryManager>(auth_module: &AuthModule<RepoManager>) -> Self {
+        let mut credential_id = vec![0u8; 16];
+        rand::thread_rng().fill_bytes(&mut credential_id);
+        SoftwareAuthenticator {
+            rp_id: auth_module.auth_config.webauthn_rp_id.clone(),
+            origin: auth_module.auth_config.webauthn_allowed_origins[0].clone(),
+            signing_key: SigningKey::random(&mut rand::thread_rng()),
+            credential_id,
+            sign_count: 0,
+        }
+    }
+
+    fn credential_id(&self) -> String {
+        general_purpose::URL_SAFE_NO_PAD.encode(&self.credential_id)
+    }
+
+    fn authenticator_data(&self, flags: u8, attested_credential: bool) -> Vec<u8> {
+        let mut data = Sha256::digest(self.rp_id.as_bytes()).to_vec();
+        data.push(if attested_credential { flags | FLAG_ATTESTED_CREDENTIAL_DATA } else { flags });
+        data.extend_from_slice(&self.sign_count.to_be_bytes());
+        if attested_credential {
+            data.extend_from_slice(&[0u8; 16]);
+            data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+            data.extend_from_slice(&self.credential_id);
+            let point = self.signing_key.verifying_key().to_encoded_point(false);
+            let cose_key = Value::Map(vec![
+                (Value::Integer(1.into()), Value::Integer(2.into())),
+                (Value::Integer(3.into()), Value::Integer((-7).into())),
+                (Value::Integer((-1).into()), Value::Integer(1.into())),
+                (Value::Integer((-2).into()), Value::Bytes(point.x().unwrap().to_vec())),
+                (Value::Integer((-3).into()), Value::Bytes(point.y().unwrap().to_vec())),
+            ]);
+            ciborium::ser::into_writer(&cose_key, &mut data).unwrap();
+        }
+        data
+    }
+
+    fn register(&self, challenge: &str) -> (String, String) {
+        let client_data_json = json!({ "type": "webauthn.create", "challenge": challenge, "origin": self.origin });
+        let attestation_object = Value::Map(vec![
+            (Value::Text("fmt".to_owned()), Value::Text("none".to_owned())),
+            (Value::Text("attStmt".to_owned()), Value::Map(vec![])),
+            (Value::Text("authData".to_owned()), Value::Bytes(self.authenticator_data(FLAG_USER_PRESENT, true))),
+        ]);
+        let mut attestation_object_bytes = vec![];
+        ciborium::ser::into_writer(&attestation_object, &mut attestation_object_bytes).unwrap();
+        (
+            general_purpose::URL_SAFE_NO_PAD.encode(client_data_json.to_string()),
+            general_purpose::URL_SAFE_NO_PAD.encode(attestation_object_bytes),
+        )
+    }
+
+    fn assert(&mut self, challenge: &str, flags: u8) -> WebAuthnAssertionDto {
+        self.sign_count += 1;
+        let client_data_json =
+            json!({ "type": "webauthn.get", "challenge": challenge, "origin": self.origin }).to_string();
+        let authenticator_data = self.authenticator_data(flags, false);
+        let mut signed_data = authenticator_data.clone();
+        signed_data.extend_from_slice(&Sha256::digest(client_data_json.as_bytes()));
+        let signature: Signature = self.signing_key.sign(&signed_data);
+        WebAuthnAssertionDto {
+            credential_id: self.credential_id(),
+            client_data_json: general_purpose::URL_SAFE_NO_PAD.encode(client_data_json),
+            authenticator_data: general_purpose::URL_SAFE_NO_PAD.encode(authenticator_data),
+            signature: general_purpose::URL_SAFE_NO_PAD.encode(signature.to_der().as_bytes()),
+            user_handle: None,
+        }
+    }
+}
@@ -1,12 +1,19 @@
+use lightspeed_auth::config::AuthConfig;
 use lightspeed_auth::dto::create_login_dto::CreateLoginDto;
 use lightspeed_auth::model::auth_account::AuthAccountModel;
 use lightspeed_auth::model::token::TokenModel;
 use lightspeed_auth::repository::AuthRepositoryManager;
+use lightspeed_auth::service::auth_account::{AuthAccountService, AuthAccountServices, LoginOutcome};
+use lightspeed_auth::service::auth_session::AuthSessionService;
+use lightspeed_auth::service::login_attempt::LoginAttemptService;
 use lightspeed_auth::AuthModule;
-use lightspeed_core::error::LightSpeedError;
+use lightspeed_core::clock::{Clock, MockClock};
+use lightspeed_core::error::{ErrorCodes, LightSpeedError};
 use lightspeed_core::model::language::Language;
+use lightspeed_core::service::auth::Auth;
 use lightspeed_core::utils::new_hyphenated_uuid;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 pub async fn create_user<RepoManager: AuthRepositoryManager>(
     auth_module: &AuthModule<RepoManager>,
@@ -43,3 +50,53 @@ pub async fn create_user_with_password<RepoManager: AuthRepositoryManager>(
         Ok((user, token))
     }
 }
+
+/// Asserts that the result is a BadRequest with the expected code
+pub fn assert_error_code<T>(expected_code: &str, result: Result<T, LightSpeedError>) {
+    match result {
+        Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(expected_code, code),
+        _ => panic!("Expected a BadRequest with code [{expected_code}]"),
+    }
+}
+
+/// Creates an AuthAccountService with the given config whose services read the time from the given clock
+pub fn new_auth_account_service<RepoManager: AuthRepositoryManager>(
+    auth_module: &AuthModule<RepoManager>,
+    auth_config: AuthConfig,
+    clock: &MockClock,
+) -> AuthAccountService<RepoManager> {
+    let clock: Arc<dyn Clock> = Arc::new(clock.clone());
+    let login_attempt_service = Arc::new(LoginAttemptService::new(
+        auth_module.repo_manager.c3p0().clone(),
+        auth_config.clone(),
+        auth_module.repo_manager.login_attempt_repo(),
+        clock.clone(),
+    ));
+    let auth_session_service = Arc::new(AuthSessionService::new(
+        auth_module.repo_manager.c3p0().clone(),
+        auth_module.repo_manager.auth_session_repo(),
+        clock.clone(),
+    ));
+
+    AuthAccountService::new(
+        &auth_module.repo_manager,
+        auth_config,
+        AuthAccountServices { login_attempt_service, auth_session_service, ..auth_module.auth_account_services() },
+        clock,
+    )
+}
+
+/// Returns the Auth of a completed login, or a SECOND_FACTOR_REQUIRED / PASSWORD_EXPIRED error otherwise
+pub fn authenticated(outcome: LoginOutcome) -> Result<Auth, LightSpeedError> {
+    match outcome {
+        LoginOutcome::Authenticated(auth) => Ok(auth),
+        LoginOutcome::SecondFactorRequired { .. } => Err(LightSpeedError::BadRequest {
+            message: "A second factor is required to login".to_owned(),
+            code: ErrorCodes::SECOND_FACTOR_REQUIRED,
+        }),
+        LoginOutcome::PasswordExpired { .. } => Err(LightSpeedError::BadRequest {
+            message: "The password has expired".to_owned(),
+            code: ErrorCodes::PASSWORD_EXPIRED,
+        }),
+    }
+}
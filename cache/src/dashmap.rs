@@ -1,28 +1,34 @@
-use chrono::prelude::Local;
 use dashmap::{mapref::entry::Entry, DashMap};
+use lightspeed_clock::{Clock, SystemClock};
 use std::hash::Hash;
 use std::sync::Arc;
 
 pub struct Cache<K: Hash + Eq, V> {
     map: Arc<DashMap<K, (Arc<V>, i64)>>,
     ttl_ms: i64,
+    clock: Arc<dyn Clock>,
 }
 
 impl<K: Hash + Eq, V> Clone for Cache<K, V> {
     fn clone(&self) -> Self {
-        Self { map: self.map.clone(), ttl_ms: self.ttl_ms }
+        Self { map: self.map.clone(), ttl_ms: self.ttl_ms, clock: self.clock.clone() }
     }
 }
 
 impl<K: Hash + Eq, V> Cache<K, V> {
     pub fn new(ttl_seconds: u32) -> Self {
-        Self { map: Arc::new(DashMap::default()), ttl_ms: (ttl_seconds as i64) * 1000 }
+        Self::new_with_clock(ttl_seconds, SystemClock::shared())
+    }
+
+    /// Creates a Cache whose entries expire according to the given clock
+    pub fn new_with_clock(ttl_seconds: u32, clock: Arc<dyn Clock>) -> Self {
+        Self { map: Arc::new(DashMap::default()), ttl_ms: (ttl_seconds as i64) * 1000, clock }
     }
 
     pub fn get(&self, key: &K) -> Option<Arc<V>> {
         match self.map.get(key) {
             Some(value) => {
-                if value.1 < current_epoch_mills() {
+                if value.1 < self.clock.epoch_millis() {
                     drop(value);
                     self.map.remove(key);
                     None
@@ -79,14 +85,10 @@ impl<K: Hash + Eq, V> Cache<K, V> {
 
     #[inline]
     fn to_value(&self, value: Arc<V>) -> (Arc<V>, i64) {
-        (value, current_epoch_mills() + self.ttl_ms)
+        (value, self.clock.epoch_millis() + self.ttl_ms)
     }
 }
 
-fn current_epoch_mills() -> i64 {
-    Local::now().timestamp_millis()
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
@@ -119,6 +121,20 @@ mod test {
         assert!(result.is_none());
     }
 
+    #[tokio::test]
+    async fn should_expire_entries_according_to_the_clock() {
+        let clock = lightspeed_clock::MockClock::default();
+        let cache = Cache::new_with_clock(10, Arc::new(clock.clone()));
+
+        cache.insert("hello", "world");
+
+        clock.advance(Duration::from_secs(10));
+        assert!(cache.get(&"hello").is_some());
+
+        clock.advance(Duration::from_millis(1));
+        assert!(cache.get(&"hello").is_none());
+    }
+
     #[tokio::test]
     async fn should_not_insert_if_exists_on_get() {
         let cache = Cache::new(1000);
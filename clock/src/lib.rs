@@ -0,0 +1,122 @@
+use chrono::{DateTime, TimeZone, Utc};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A source of the current time.
+/// Services that depend on time should read it from a Clock so that tests can control it.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Returns the number of non-leap seconds since January 1, 1970 0:00:00 UTC
+    fn epoch_seconds(&self) -> i64 {
+        self.now().timestamp()
+    }
+
+    /// Returns the number of non-leap milliseconds since January 1, 1970 0:00:00 UTC
+    fn epoch_millis(&self) -> i64 {
+        self.now().timestamp_millis()
+    }
+}
+
+/// A Clock that returns the current system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    pub fn shared() -> Arc<dyn Clock> {
+        Arc::new(SystemClock)
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A Clock that is moved only manually.
+/// The clones of a MockClock share the same time.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    epoch_millis: Arc<AtomicI64>,
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new(Utc::now())
+    }
+}
+
+impl MockClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self { epoch_millis: Arc::new(AtomicI64::new(now.timestamp_millis())) }
+    }
+
+    pub fn from_epoch_seconds(epoch_seconds: i64) -> Self {
+        Self { epoch_millis: Arc::new(AtomicI64::new(epoch_seconds * 1000)) }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        self.epoch_millis.store(now.timestamp_millis(), Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.epoch_millis.fetch_add(duration.as_millis() as i64, Ordering::SeqCst);
+    }
+
+    pub fn rewind(&self, duration: Duration) {
+        self.epoch_millis.fetch_sub(duration.as_millis() as i64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc.timestamp_millis_opt(self.epoch_millis.load(Ordering::SeqCst)).unwrap()
+    }
+
+    fn epoch_millis(&self) -> i64 {
+        self.epoch_millis.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn system_clock_should_return_the_current_time() {
+        let before = Utc::now().timestamp_millis();
+        let now = SystemClock.epoch_millis();
+        let after = Utc::now().timestamp_millis();
+        assert!(before <= now && now <= after);
+    }
+
+    #[test]
+    fn mock_clock_should_move_only_manually() {
+        let clock = MockClock::from_epoch_seconds(1000);
+        assert_eq!(1000, clock.epoch_seconds());
+        assert_eq!(1000, clock.epoch_seconds());
+
+        clock.advance(Duration::from_millis(1500));
+        assert_eq!(1_001_500, clock.epoch_millis());
+        assert_eq!(1001, clock.epoch_seconds());
+
+        clock.rewind(Duration::from_secs(1));
+        assert_eq!(1_000_500, clock.epoch_millis());
+
+        clock.set(Utc.timestamp_opt(50, 0).unwrap());
+        assert_eq!(50, clock.epoch_seconds());
+    }
+
+    #[test]
+    fn mock_clock_clones_should_share_the_time() {
+        let clock = MockClock::from_epoch_seconds(0);
+        let shared: Arc<dyn Clock> = Arc::new(clock.clone());
+
+        clock.advance(Duration::from_secs(10));
+
+        assert_eq!(10, shared.epoch_seconds());
+    }
+}
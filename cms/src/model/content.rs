@@ -1,12 +1,15 @@
 use crate::model::schema::{LocalizableOptions, Schema, SchemaField, SchemaFieldArity, SchemaFieldType};
 use c3p0::Model;
 use lightspeed_core::error::ErrorDetails;
+use lightspeed_core::model::locale::Locale;
+use lightspeed_core::service::validator::locale::validate_locale;
 use lightspeed_core::service::validator::order::{validate_ge, validate_le};
 use lightspeed_core::service::validator::{ERR_UNKNOWN_FIELD, ERR_VALUE_REQUIRED};
 use once_cell::sync::OnceCell;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
 
 pub const SLUG_VALIDATION_REGEX: &str = r#"^[a-z0-9]+(?:-[a-z0-9]+)*$"#;
 
@@ -192,8 +195,15 @@ fn validate_arity<T, F: Fn(&str, &Option<T>, &mut ErrorDetails)>(
                 match options {
                     LocalizableOptions::Languages { languages } => {
                         if required {
+                            // Languages are compared in their canonical form, so "en_us" matches "en-US"
+                            let value_locales =
+                                values.keys().filter_map(|key| Locale::from_str(key).ok()).collect::<Vec<_>>();
                             languages.iter().for_each(|language| {
-                                if !values.contains_key(language) {
+                                let is_present = values.contains_key(language)
+                                    || Locale::from_str(language)
+                                        .map(|locale| value_locales.contains(&locale))
+                                        .unwrap_or(false);
+                                if !is_present {
                                     error_details
                                         .add_detail(format!("{full_field_name}[{language}]"), ERR_VALUE_REQUIRED)
                                 }
@@ -201,8 +211,15 @@ fn validate_arity<T, F: Fn(&str, &Option<T>, &mut ErrorDetails)>(
                         }
                     }
                 }
+                // The languages declared by the schema are accepted as they are,
+                // so the content stored before the locales were validated can still be updated
+                let LocalizableOptions::Languages { languages } = options;
                 values.iter().for_each(|(key, value)| {
-                    value_validation(&format!("{full_field_name}[{key}]"), value, error_details)
+                    let field_name = format!("{full_field_name}[{key}]");
+                    if !languages.contains(key) {
+                        validate_locale(error_details, field_name.as_str(), key);
+                    }
+                    value_validation(&field_name, value, error_details)
                 })
             }
             _ => error_details.add_detail(full_field_name, SHOULD_HAVE_LOCALIZABLE_ARITY),
@@ -281,6 +298,7 @@ mod test {
     use super::*;
     use crate::model::schema::{SchemaField, SchemaFieldArity, SchemaFieldType};
     use lightspeed_core::error::{ErrorDetail, LightSpeedError};
+    use lightspeed_core::service::validator::locale::NOT_VALID_LOCALE;
     use lightspeed_core::service::validator::order::{MUST_BE_GREATER_OR_EQUAL, MUST_BE_LESS_OR_EQUAL};
     use lightspeed_core::service::validator::Validator;
 
@@ -852,6 +870,113 @@ mod test {
         };
     }
 
+    #[test]
+    fn validation_should_compare_localizable_languages_in_canonical_form() {
+        let schema = Schema {
+            created_ms: 0,
+            updated_ms: 0,
+            fields: vec![SchemaField {
+                name: "one".to_owned(),
+                required: true,
+                description: "".to_owned(),
+                field_type: SchemaFieldType::Boolean {
+                    default: None,
+                    arity: SchemaFieldArity::Localizable {
+                        options: LocalizableOptions::Languages { languages: vec!["en-US".to_owned(), "IT".to_owned()] },
+                    },
+                },
+            }],
+        };
+        let content = Content {
+            updated_ms: 0,
+            created_ms: 0,
+            fields: HashMap::from([(
+                "one".to_owned(),
+                ContentFieldValue::Boolean {
+                    value: ContentFieldValueArity::Localizable {
+                        values: HashMap::from([("en_us".to_owned(), Some(true)), ("it".to_owned(), Some(false))]),
+                    },
+                },
+            )]),
+        };
+
+        assert!(validate_content(&schema, &content).is_ok());
+    }
+
+    #[test]
+    fn validation_should_fail_if_localizable_value_has_invalid_language() {
+        let schema = Schema {
+            created_ms: 0,
+            updated_ms: 0,
+            fields: vec![SchemaField {
+                name: "one".to_owned(),
+                required: false,
+                description: "".to_owned(),
+                field_type: SchemaFieldType::Boolean {
+                    default: None,
+                    arity: SchemaFieldArity::Localizable {
+                        options: LocalizableOptions::Languages { languages: vec![] },
+                    },
+                },
+            }],
+        };
+        let content = Content {
+            updated_ms: 0,
+            created_ms: 0,
+            fields: HashMap::from([(
+                "one".to_owned(),
+                ContentFieldValue::Boolean {
+                    value: ContentFieldValueArity::Localizable {
+                        values: HashMap::from([("not a language".to_owned(), Some(true))]),
+                    },
+                },
+            )]),
+        };
+
+        match validate_content(&schema, &content) {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert_eq!(
+                    details.details["fields[one].value[not a language]"],
+                    vec![ErrorDetail::new(NOT_VALID_LOCALE, vec![])]
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn validation_should_accept_the_languages_declared_by_the_schema() {
+        let schema = Schema {
+            created_ms: 0,
+            updated_ms: 0,
+            fields: vec![SchemaField {
+                name: "one".to_owned(),
+                required: true,
+                description: "".to_owned(),
+                field_type: SchemaFieldType::Boolean {
+                    default: None,
+                    arity: SchemaFieldArity::Localizable {
+                        options: LocalizableOptions::Languages { languages: vec!["english".to_owned()] },
+                    },
+                },
+            }],
+        };
+        let content = Content {
+            updated_ms: 0,
+            created_ms: 0,
+            fields: HashMap::from([(
+                "one".to_owned(),
+                ContentFieldValue::Boolean {
+                    value: ContentFieldValueArity::Localizable {
+                        values: HashMap::from([("english".to_owned(), Some(true))]),
+                    },
+                },
+            )]),
+        };
+
+        assert!(validate_content(&schema, &content).is_ok());
+    }
+
     #[test]
     fn validation_should_fail_if_localizable_required_languages_missing() {
         let schema = Schema {
@@ -1,5 +1,6 @@
 use c3p0::Model;
 use lightspeed_core::error::{ErrorDetails, LightSpeedError};
+use lightspeed_core::service::validator::locale::validate_locale;
 use lightspeed_core::service::validator::order::{validate_ge, validate_le};
 use lightspeed_core::service::validator::{Validable, ERR_NOT_UNIQUE};
 use once_cell::sync::OnceCell;
@@ -75,6 +76,14 @@ impl Validable for SchemaField {
             error_details.add_detail("name", NOT_VALID_FIELD_NAME);
         }
 
+        if let SchemaFieldArity::Localizable { options: LocalizableOptions::Languages { languages } } =
+            self.field_type.get_arity()
+        {
+            for (count, language) in languages.iter().enumerate() {
+                validate_locale(error_details, format!("languages[{count}]"), language);
+            }
+        }
+
         Ok(())
     }
 }
@@ -114,6 +123,7 @@ pub enum LocalizableOptions {
 mod test {
     use super::*;
     use lightspeed_core::error::ErrorDetail;
+    use lightspeed_core::service::validator::locale::NOT_VALID_LOCALE;
     use lightspeed_core::service::validator::order::MUST_BE_GREATER_OR_EQUAL;
     use lightspeed_core::service::validator::Validator;
 
@@ -206,6 +216,41 @@ mod test {
         }
     }
 
+    #[test]
+    fn schema_validation_should_fail_if_localizable_languages_are_not_valid_locales() {
+        let schema = Schema {
+            updated_ms: 0,
+            created_ms: 0,
+            fields: vec![SchemaField {
+                name: "label1".to_owned(),
+                description: "".to_owned(),
+                field_type: SchemaFieldType::Boolean {
+                    arity: SchemaFieldArity::Localizable {
+                        options: LocalizableOptions::Languages {
+                            languages: vec!["en".to_owned(), "de_CH".to_owned(), "not valid".to_owned()],
+                        },
+                    },
+                    default: None,
+                },
+                required: false,
+            }],
+        };
+
+        // Act
+        let result = Validator::validate(&schema);
+
+        match result {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert_eq!(details.details.len(), 1);
+                assert_eq!(
+                    details.details.get("fields[0].languages[2]"),
+                    Some(&vec![ErrorDetail::new(NOT_VALID_LOCALE, vec![])])
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
     #[test]
     fn schema_field_names_should_contain_only_lowercased_chars_and_undercores() {
         let schema = Schema {
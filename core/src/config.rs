@@ -1,7 +1,12 @@
+use crate::error::{ErrorDetails, LightSpeedError};
+use crate::model::language::Language;
+use crate::model::locale::{Locale, SupportedLocales};
+use crate::service::validator::order::validate_ge;
+use crate::service::validator::{Validable, ERR_VALUE_REQUIRED};
 use jsonwebtoken::Algorithm;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct JwtConfig {
     /// The secret key used to encode and decode the JWT
@@ -31,17 +36,60 @@ impl Default for JwtConfig {
     }
 }
 
+impl Validable for JwtConfig {
+    fn validate(&self, error_details: &mut ErrorDetails) -> Result<(), LightSpeedError> {
+        if self.secret.is_empty() {
+            error_details.add_detail("secret", ERR_VALUE_REQUIRED);
+        }
+        validate_ge(error_details, "token_validity_minutes", 1, self.token_validity_minutes);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocaleConfig {
+    /// The locale used when none of the requested locales is supported
+    pub default_locale: Locale,
+
+    /// The supported locales in BCP-47 format (e.g. "en", "de-CH").
+    /// The default locale is always supported.
+    pub supported_locales: Vec<Locale>,
+}
+
+impl Default for LocaleConfig {
+    fn default() -> Self {
+        Self { default_locale: Language::En.into(), supported_locales: vec![] }
+    }
+}
+
+impl From<&LocaleConfig> for SupportedLocales {
+    fn from(config: &LocaleConfig) -> Self {
+        SupportedLocales::new(config.default_locale.clone(), config.supported_locales.clone())
+    }
+}
+
 /// Defines the Logger configuration.
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct CoreConfig {
     #[serde(default)]
     pub jwt: JwtConfig,
+
+    #[serde(default)]
+    pub locale: LocaleConfig,
+}
+
+impl Validable for CoreConfig {
+    fn validate(&self, error_details: &mut ErrorDetails) -> Result<(), LightSpeedError> {
+        self.jwt.validate(&mut error_details.with_scope("jwt"))
+    }
 }
 
 #[cfg(test)]
 mod test {
 
     use super::*;
+    use crate::service::validator::Validator;
     use config::Config;
 
     #[test]
@@ -49,4 +97,42 @@ mod test {
         let config: CoreConfig = Config::builder().build().unwrap().try_deserialize().unwrap();
         assert!(config.jwt.token_validity_minutes > 0);
     }
+
+    #[test]
+    fn should_not_validate_empty_jwt_secret() {
+        let config = CoreConfig::default();
+
+        match Validator::validate(&config) {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert_eq!(ERR_VALUE_REQUIRED, details.details["jwt.secret"][0])
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn should_validate_jwt_config() {
+        let config = CoreConfig {
+            jwt: JwtConfig { secret: "secret".to_owned(), ..Default::default() },
+            locale: LocaleConfig::default(),
+        };
+        assert!(Validator::validate(&config).is_ok());
+    }
+
+    #[test]
+    fn should_build_locale_config() {
+        let config: CoreConfig = Config::builder()
+            .set_override("locale.default_locale", "de_ch")
+            .unwrap()
+            .set_override("locale.supported_locales", vec!["it", "fr-CH"])
+            .unwrap()
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+
+        let supported_locales = SupportedLocales::from(&config.locale);
+        assert_eq!("de-CH", supported_locales.default_locale().to_string());
+        assert_eq!(3, supported_locales.locales().len());
+    }
 }
@@ -8,13 +8,20 @@ pub struct ErrorCodes {}
 
 impl ErrorCodes {
     pub const ACTIVE_USER: &'static str = "ACTIVE_USER";
+    pub const ANONYMIZED_USER: &'static str = "ANONYMIZED_USER";
     pub const INACTIVE_USER: &'static str = "INACTIVE_USER";
     pub const INCOMPLETE_REQUEST: &'static str = "INCOMPLETE_REQUEST";
     pub const IO_ERROR: &'static str = "IO_ERROR";
     pub const JSON_PARSE_ERROR: &'static str = "JSON_PARSE_ERROR";
+    pub const LOCKED_USER: &'static str = "LOCKED_USER";
     pub const NOT_FOUND: &'static str = "NOT_FOUND";
     pub const NOT_PENDING_USER: &'static str = "NOT_PENDING_USER";
     pub const PARSE_ERROR: &'static str = "PARSE_ERROR";
+    pub const PASSWORD_EXPIRED: &'static str = "PASSWORD_EXPIRED";
+    pub const SECOND_FACTOR_REQUIRED: &'static str = "SECOND_FACTOR_REQUIRED";
+    pub const TOO_MANY_REQUESTS: &'static str = "TOO_MANY_REQUESTS";
+    pub const TWO_FACTOR_ALREADY_ENABLED: &'static str = "TWO_FACTOR_ALREADY_ENABLED";
+    pub const TWO_FACTOR_NOT_ENABLED: &'static str = "TWO_FACTOR_NOT_ENABLED";
     pub const WRONG_CREDENTIALS: &'static str = "WRONG_CREDENTIALS";
 }
 
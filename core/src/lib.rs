@@ -3,12 +3,17 @@ pub mod error;
 pub mod model;
 pub mod module;
 pub mod service;
+pub mod shutdown;
 pub mod utils;
 
 #[cfg(feature = "web")]
 pub mod web;
 
+pub use lightspeed_clock as clock;
+
+use crate::clock::{Clock, SystemClock};
 use crate::error::LightSpeedError;
+use crate::model::locale::SupportedLocales;
 use crate::service::auth::InMemoryRolesProvider;
 use log::info;
 use std::sync::Arc;
@@ -17,16 +22,27 @@ use std::sync::Arc;
 pub struct CoreModule {
     pub auth: Arc<service::auth::AuthService<InMemoryRolesProvider>>,
     pub jwt: Arc<service::jwt::JwtService>,
+    pub clock: Arc<dyn Clock>,
+    pub locales: Arc<SupportedLocales>,
 }
 
 impl CoreModule {
     pub fn new(config: config::CoreConfig) -> Result<CoreModule, LightSpeedError> {
+        Self::new_with_clock(config, SystemClock::shared())
+    }
+
+    /// Creates a CoreModule whose services read the time from the given clock
+    pub fn new_with_clock(config: config::CoreConfig, clock: Arc<dyn Clock>) -> Result<CoreModule, LightSpeedError> {
         println!("Creating CoreModule");
         info!("Creating CoreModule");
 
-        let jwt = Arc::new(service::jwt::JwtService::new(&config.jwt)?);
-        let auth = Arc::new(service::auth::AuthService::new(InMemoryRolesProvider::new(vec![].into())));
-        Ok(CoreModule { jwt, auth })
+        let jwt = Arc::new(service::jwt::JwtService::new_with_clock(&config.jwt, clock.clone())?);
+        let auth = Arc::new(service::auth::AuthService::new_with_clock(
+            InMemoryRolesProvider::new(vec![].into()),
+            clock.clone(),
+        ));
+        let locales = Arc::new(SupportedLocales::from(&config.locale));
+        Ok(CoreModule { jwt, auth, clock, locales })
     }
 }
 
@@ -0,0 +1,339 @@
+use crate::error::{ErrorCodes, LightSpeedError};
+use crate::model::language::Language;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// A BCP-47 language tag made of a language, an optional script and an optional region subtag.
+/// For example: "en", "de-CH", "zh-Hant-TW".
+///
+/// The parsing is case-insensitive and accepts both '-' and '_' as separators;
+/// variants, extensions and private use subtags are ignored.
+/// The canonical form has a lowercase language, a titlecase script and an uppercase region.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Locale {
+    language: String,
+    script: Option<String>,
+    region: Option<String>,
+}
+
+impl Locale {
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    pub fn script(&self) -> Option<&str> {
+        self.script.as_deref()
+    }
+
+    pub fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    /// Returns the locale without its most specific subtag.
+    /// For example, the parent of "zh-Hant-TW" is "zh-Hant" and the parent of "zh-Hant" is "zh".
+    pub fn parent(&self) -> Option<Locale> {
+        if self.region.is_some() {
+            Some(Locale { language: self.language.clone(), script: self.script.clone(), region: None })
+        } else if self.script.is_some() {
+            Some(Locale { language: self.language.clone(), script: None, region: None })
+        } else {
+            None
+        }
+    }
+
+    /// Returns this locale followed by all its parents. For example: [de-CH, de]
+    pub fn fallback_chain(&self) -> Vec<Locale> {
+        let mut chain = vec![self.clone()];
+        while let Some(parent) = chain.last().and_then(|locale| locale.parent()) {
+            chain.push(parent);
+        }
+        chain
+    }
+}
+
+impl FromStr for Locale {
+    type Err = LightSpeedError;
+
+    fn from_str(locale: &str) -> Result<Self, Self::Err> {
+        // The locales come from user input too (e.g. the content keys), so a malformed one is a bad request
+        let parse_error = || LightSpeedError::BadRequest {
+            message: format!("Could not parse locale [{locale}]"),
+            code: ErrorCodes::PARSE_ERROR,
+        };
+
+        let mut subtags = locale.trim().split(['-', '_']).peekable();
+
+        let language = match subtags.next() {
+            Some(language)
+                if matches!(language.len(), 2..=3 | 5..=8) && language.chars().all(|c| c.is_ascii_alphabetic()) =>
+            {
+                language.to_ascii_lowercase()
+            }
+            _ => return Err(parse_error()),
+        };
+
+        let script = match subtags.peek() {
+            Some(script) if script.len() == 4 && script.chars().all(|c| c.is_ascii_alphabetic()) => {
+                let script = script.to_ascii_lowercase();
+                subtags.next();
+                Some(format!("{}{}", script[..1].to_ascii_uppercase(), &script[1..]))
+            }
+            _ => None,
+        };
+
+        let region = match subtags.peek() {
+            Some(region)
+                if (region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic()))
+                    || (region.len() == 3 && region.chars().all(|c| c.is_ascii_digit())) =>
+            {
+                let region = region.to_ascii_uppercase();
+                subtags.next();
+                Some(region)
+            }
+            _ => None,
+        };
+
+        if subtags
+            .any(|subtag| subtag.is_empty() || subtag.len() > 8 || !subtag.chars().all(|c| c.is_ascii_alphanumeric()))
+        {
+            return Err(parse_error());
+        }
+
+        Ok(Locale { language, script, region })
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.language)?;
+        if let Some(script) = &self.script {
+            write!(f, "-{script}")?;
+        }
+        if let Some(region) = &self.region {
+            write!(f, "-{region}")?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for Locale {
+    type Error = LightSpeedError;
+
+    fn try_from(locale: String) -> Result<Self, Self::Error> {
+        Locale::from_str(&locale)
+    }
+}
+
+impl From<Locale> for String {
+    fn from(locale: Locale) -> Self {
+        locale.to_string()
+    }
+}
+
+impl From<Language> for Locale {
+    fn from(language: Language) -> Self {
+        Locale { language: language.as_ref().to_ascii_lowercase(), script: None, region: None }
+    }
+}
+
+/// The set of locales supported by an application
+#[derive(Clone, Debug)]
+pub struct SupportedLocales {
+    default_locale: Locale,
+    locales: Vec<Locale>,
+}
+
+impl SupportedLocales {
+    /// Creates a new SupportedLocales. The default locale is always supported.
+    pub fn new(default_locale: Locale, locales: Vec<Locale>) -> Self {
+        let mut supported = vec![];
+        for locale in locales.into_iter().chain(std::iter::once(default_locale.clone())) {
+            if !supported.contains(&locale) {
+                supported.push(locale);
+            }
+        }
+        Self { default_locale, locales: supported }
+    }
+
+    pub fn default_locale(&self) -> &Locale {
+        &self.default_locale
+    }
+
+    pub fn locales(&self) -> &[Locale] {
+        &self.locales
+    }
+
+    pub fn is_supported(&self, locale: &Locale) -> bool {
+        self.locales.contains(locale)
+    }
+
+    /// Returns the supported locales of the fallback chain of the given locale, always terminated by the default locale.
+    /// For example, if "de-CH", "de" and "en" are supported and "en" is the default, the chain of "de-CH" is [de-CH, de, en].
+    pub fn fallback_chain(&self, locale: &Locale) -> Vec<&Locale> {
+        let mut chain: Vec<&Locale> = vec![];
+        for locale in locale.fallback_chain() {
+            if let Some(supported) = self.find(&locale) {
+                if !chain.contains(&supported) {
+                    chain.push(supported);
+                }
+            }
+        }
+        if !chain.contains(&&self.default_locale) {
+            chain.push(&self.default_locale);
+        }
+        chain
+    }
+
+    /// Returns the most specific supported locale of the fallback chain of the given locale
+    pub fn resolve(&self, locale: &Locale) -> &Locale {
+        self.fallback_chain(locale)[0]
+    }
+
+    /// Returns the supported locale that best matches the value of an Accept-Language header.
+    /// If none of the requested locales is supported, the default locale is returned.
+    pub fn negotiate(&self, accept_language: &str) -> &Locale {
+        let requested = parse_accept_language(accept_language);
+
+        for locale in &requested {
+            for candidate in locale.fallback_chain() {
+                if let Some(supported) = self.find(&candidate) {
+                    return supported;
+                }
+            }
+        }
+
+        // e.g. "de" is requested and only "de-CH" is supported
+        for locale in &requested {
+            if let Some(supported) = self.locales.iter().find(|supported| supported.language == locale.language) {
+                return supported;
+            }
+        }
+
+        &self.default_locale
+    }
+
+    fn find(&self, locale: &Locale) -> Option<&Locale> {
+        self.locales.iter().find(|supported| *supported == locale)
+    }
+}
+
+impl Default for SupportedLocales {
+    fn default() -> Self {
+        Self::new(Language::En.into(), vec![])
+    }
+}
+
+/// Parses the value of an Accept-Language header.
+/// Returns the requested locales ordered by their quality value; the invalid entries,
+/// the wildcard and the entries with a quality of zero are discarded.
+pub fn parse_accept_language(accept_language: &str) -> Vec<Locale> {
+    let mut requested = accept_language
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let tag = parts.next()?.trim();
+            let quality = parts
+                .find_map(|param| param.trim().strip_prefix("q=").map(|q| q.trim().parse::<f32>().unwrap_or(0.0)))
+                .unwrap_or(1.0);
+            if tag == "*" || quality <= 0.0 {
+                return None;
+            }
+            Locale::from_str(tag).ok().map(|locale| (locale, quality))
+        })
+        .collect::<Vec<_>>();
+
+    // The sort is stable, so the entries with the same quality keep the header order
+    requested.sort_by(|(_, q1), (_, q2)| q2.total_cmp(q1));
+    requested.into_iter().map(|(locale, _)| locale).collect()
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn locale(locale: &str) -> Locale {
+        Locale::from_str(locale).unwrap()
+    }
+
+    #[test]
+    fn should_parse_and_canonicalize_locales() {
+        assert_eq!("en", locale("EN").to_string());
+        assert_eq!("de-CH", locale("de_ch").to_string());
+        assert_eq!("zh-Hant-TW", locale("ZH-hant-tw").to_string());
+        assert_eq!("es-419", locale("es-419").to_string());
+        assert_eq!("sr-Latn", locale("sr-latn").to_string());
+        assert_eq!("de-CH", locale("de-CH-1996").to_string());
+
+        let zh = locale("zh-Hant-TW");
+        assert_eq!("zh", zh.language());
+        assert_eq!(Some("Hant"), zh.script());
+        assert_eq!(Some("TW"), zh.region());
+    }
+
+    #[test]
+    fn should_not_parse_invalid_locales() {
+        for invalid in ["", "e", "english!", "en--US", "1n", "en-US-", "abcd"] {
+            match Locale::from_str(invalid) {
+                Err(LightSpeedError::BadRequest { code, .. }) => assert_eq!(ErrorCodes::PARSE_ERROR, code),
+                _ => panic!("{invalid} should not be valid"),
+            }
+        }
+    }
+
+    #[test]
+    fn should_serialize_as_canonical_string() {
+        let json = serde_json::to_string(&locale("de_ch")).unwrap();
+        assert_eq!(r#""de-CH""#, json);
+        assert_eq!(locale("de-CH"), serde_json::from_str::<Locale>(r#""DE-ch""#).unwrap());
+        assert!(serde_json::from_str::<Locale>(r#""not a locale""#).is_err());
+    }
+
+    #[test]
+    fn should_convert_from_language() {
+        assert_eq!(locale("it"), Language::It.into());
+    }
+
+    #[test]
+    fn should_build_the_fallback_chain() {
+        assert_eq!(vec![locale("zh-Hant-TW"), locale("zh-Hant"), locale("zh")], locale("zh-Hant-TW").fallback_chain());
+
+        let supported = SupportedLocales::new(locale("en"), vec![locale("de"), locale("de-CH"), locale("fr")]);
+        assert_eq!(vec![&locale("de-CH"), &locale("de"), &locale("en")], supported.fallback_chain(&locale("de-CH")));
+        assert_eq!(vec![&locale("de"), &locale("en")], supported.fallback_chain(&locale("de-AT")));
+        assert_eq!(vec![&locale("en")], supported.fallback_chain(&locale("it")));
+        assert_eq!(vec![&locale("en")], supported.fallback_chain(&locale("en-GB")));
+    }
+
+    #[test]
+    fn should_resolve_locales() {
+        let supported = SupportedLocales::new(locale("en"), vec![locale("de"), locale("de-CH")]);
+        assert_eq!(&locale("de-CH"), supported.resolve(&locale("de-CH")));
+        assert_eq!(&locale("de"), supported.resolve(&locale("de-AT")));
+        assert_eq!(&locale("en"), supported.resolve(&locale("it-IT")));
+        assert!(supported.is_supported(&locale("en")));
+    }
+
+    #[test]
+    fn should_parse_accept_language() {
+        assert_eq!(
+            vec![locale("fr-CH"), locale("fr"), locale("en"), locale("de")],
+            parse_accept_language("fr-CH, fr;q=0.9, en;q=0.8, de;q=0.7, *;q=0.5")
+        );
+        assert_eq!(vec![locale("de"), locale("en")], parse_accept_language("en;q=0.5, de, it;q=0, ???"));
+        assert!(parse_accept_language("").is_empty());
+    }
+
+    #[test]
+    fn should_negotiate_the_locale() {
+        let supported = SupportedLocales::new(locale("en"), vec![locale("de"), locale("fr-CH"), locale("it")]);
+
+        assert_eq!(&locale("de"), supported.negotiate("de-CH, en;q=0.9"));
+        assert_eq!(&locale("it"), supported.negotiate("es, it;q=0.8, de;q=0.5"));
+        assert_eq!(&locale("fr-CH"), supported.negotiate("fr"));
+        assert_eq!(&locale("en"), supported.negotiate("es, pt"));
+        assert_eq!(&locale("en"), supported.negotiate(""));
+    }
+}
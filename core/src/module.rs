@@ -9,6 +9,11 @@ pub trait ModuleBuilder<T: Module> {
 #[async_trait::async_trait]
 pub trait Module {
     async fn start(&mut self) -> Result<(), LightSpeedError>;
+
+    /// Releases the module resources at shutdown. By default it does nothing.
+    async fn stop(&mut self) -> Result<(), LightSpeedError> {
+        Ok(())
+    }
 }
 
 pub async fn start(modules: &mut [&mut dyn Module]) -> Result<(), LightSpeedError> {
@@ -1,5 +1,5 @@
 use crate::error::LightSpeedError;
-use crate::utils::current_epoch_seconds;
+use lightspeed_clock::{Clock, SystemClock};
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::collections::{BTreeMap, HashMap};
@@ -14,6 +14,14 @@ pub struct Auth {
     pub roles: Vec<String>,
     pub creation_ts_seconds: i64,
     pub expiration_ts_seconds: i64,
+    /// The permissions granted to a delegated Auth, e.g. by the scopes of an OAuth2 access token.
+    /// When present, only the permissions of the roles that are also in this list are granted,
+    /// while the role and owner checks always fail because they are not restricted by the scopes.
+    #[serde(default)]
+    pub scope_permissions: Option<Vec<String>>,
+    /// The id of the user who is impersonating the owner of this Auth, e.g. a member of the support staff
+    #[serde(default)]
+    pub impersonated_by: Option<i64>,
 }
 
 impl Auth {
@@ -25,7 +33,16 @@ impl Auth {
         expiration_ts_seconds: i64,
     ) -> Self {
         let session_id = format!("{id}_{creation_ts_seconds}");
-        Self { id, username: username.into(), session_id, roles, creation_ts_seconds, expiration_ts_seconds }
+        Self {
+            id,
+            username: username.into(),
+            session_id,
+            roles,
+            creation_ts_seconds,
+            expiration_ts_seconds,
+            scope_permissions: None,
+            impersonated_by: None,
+        }
     }
 }
 
@@ -38,6 +55,8 @@ impl Default for Auth {
             roles: vec![],
             creation_ts_seconds: 0,
             expiration_ts_seconds: 0,
+            scope_permissions: None,
+            impersonated_by: None,
         }
     }
 }
@@ -69,18 +88,25 @@ impl<T: Owned + Clone + serde::ser::Serialize + Send> Owned for c3p0_common::Mod
 pub struct AuthService<T: RolesProvider> {
     roles_provider: T,
     permission_roles_map: BTreeMap<String, Vec<String>>,
+    clock: Arc<dyn Clock>,
 }
 
 impl<T: RolesProvider> AuthService<T> {
     pub fn new(roles_provider: T) -> AuthService<T> {
+        Self::new_with_clock(roles_provider, SystemClock::shared())
+    }
+
+    /// Creates an AuthService whose AuthContexts verify the Auth expiration against the given clock
+    pub fn new_with_clock(roles_provider: T, clock: Arc<dyn Clock>) -> AuthService<T> {
         AuthService {
             permission_roles_map: AuthService::<T>::roles_map_to_permissions_map(roles_provider.fetch_all().as_ref()),
             roles_provider,
+            clock,
         }
     }
 
     pub fn auth(&self, auth: Auth) -> AuthContext {
-        AuthContext { auth, permission_roles_map: &self.permission_roles_map }
+        AuthContext { auth, permission_roles_map: &self.permission_roles_map, clock: self.clock.as_ref() }
     }
 
     /// Creates a permission_roles_map from an array of Roles
@@ -98,11 +124,12 @@ impl<T: RolesProvider> AuthService<T> {
 pub struct AuthContext<'a> {
     pub auth: Auth,
     permission_roles_map: &'a BTreeMap<String, Vec<String>>,
+    clock: &'a dyn Clock,
 }
 
 impl<'a> AuthContext<'a> {
     pub fn is_authenticated(&self) -> Result<&AuthContext, LightSpeedError> {
-        if self.auth.username.is_empty() || self.auth.expiration_ts_seconds < current_epoch_seconds() {
+        if self.auth.username.is_empty() || self.auth.expiration_ts_seconds < self.clock.epoch_seconds() {
             return Err(LightSpeedError::UnauthenticatedError {});
         };
         Ok(self)
@@ -179,7 +206,7 @@ impl<'a> AuthContext<'a> {
     }
 
     pub fn is_owner<T: Owned>(&self, obj: &T) -> Result<&AuthContext, LightSpeedError> {
-        if self.auth.id == obj.get_owner_id() {
+        if self.is_owner_bool(obj) {
             Ok(self)
         } else {
             Err(LightSpeedError::ForbiddenError {
@@ -194,7 +221,7 @@ impl<'a> AuthContext<'a> {
     }
 
     pub fn is_owner_or_has_role<T: Owned>(&self, obj: &T, role: &str) -> Result<&AuthContext, LightSpeedError> {
-        if (self.auth.id == obj.get_owner_id()) || self.has_role_bool(role) {
+        if self.is_owner_bool(obj) || self.has_role_bool(role) {
             Ok(self)
         } else {
             Err(LightSpeedError::ForbiddenError {
@@ -214,7 +241,7 @@ impl<'a> AuthContext<'a> {
         obj: &T,
         permission: &str,
     ) -> Result<&AuthContext, LightSpeedError> {
-        if (self.auth.id == obj.get_owner_id()) || self.has_permission_bool(permission) {
+        if self.is_owner_bool(obj) || self.has_permission_bool(permission) {
             Ok(self)
         } else {
             Err(LightSpeedError::ForbiddenError {
@@ -229,19 +256,47 @@ impl<'a> AuthContext<'a> {
         }
     }
 
+    /// Returns true if the Auth has been issued to another user impersonating its owner
+    pub fn is_impersonated(&self) -> bool {
+        self.auth.impersonated_by.is_some()
+    }
+
+    /// Fails if the Auth is impersonated. It protects the operations that only the owner of the account
+    /// is allowed to perform, e.g. changing the password.
+    pub fn is_not_impersonated(&self) -> Result<&AuthContext, LightSpeedError> {
+        match self.auth.impersonated_by {
+            None => Ok(self),
+            Some(impersonator_id) => Err(LightSpeedError::ForbiddenError {
+                message: format!("User [{}] is impersonated by user [{}]", self.auth.id, impersonator_id),
+            }),
+        }
+    }
+
+    /// Returns true if one of the given roles grants the permission, regardless of the roles of the Auth.
+    /// It checks the roles of another user, e.g. the target of an impersonation.
+    pub fn roles_have_permission(&self, roles: &[String], permission: &str) -> bool {
+        self.permission_roles_map
+            .get(permission)
+            .is_some_and(|roles_with_permission| roles.iter().any(|role| roles_with_permission.contains(role)))
+    }
+
+    /// A delegated Auth is never the owner, otherwise it would get all the rights of the user
+    fn is_owner_bool<T: Owned>(&self, obj: &T) -> bool {
+        self.auth.scope_permissions.is_none() && self.auth.id == obj.get_owner_id()
+    }
+
+    /// A delegated Auth has no roles, otherwise it would get all the permissions of the user
     fn has_role_bool(&self, role: &str) -> bool {
-        self.auth.roles.iter().any(|x| x == role)
+        self.auth.scope_permissions.is_none() && self.auth.roles.iter().any(|x| x == role)
     }
 
     fn has_permission_bool(&self, permission: &str) -> bool {
-        if let Some(roles_with_permission) = self.permission_roles_map.get(permission) {
-            for user_role in &self.auth.roles {
-                if roles_with_permission.contains(user_role) {
-                    return true;
-                }
+        if let Some(scope_permissions) = &self.auth.scope_permissions {
+            if !scope_permissions.iter().any(|x| x == permission) {
+                return false;
             }
-        };
-        false
+        }
+        self.roles_have_permission(&self.auth.roles, permission)
     }
 }
 
@@ -307,6 +362,8 @@ mod test {
             roles: vec![],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.is_authenticated().is_ok());
@@ -323,6 +380,8 @@ mod test {
             roles: vec![],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
 
@@ -343,6 +402,8 @@ mod test {
             roles: vec![],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() - 1,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
 
@@ -352,6 +413,49 @@ mod test {
         }
     }
 
+    #[test]
+    fn should_block_the_impersonated_auth() {
+        let provider = super::InMemoryRolesProvider::new(vec![].into());
+        let auth_service = super::AuthService::new(provider);
+        let user = Auth {
+            id: 10,
+            username: "name".to_string(),
+            expiration_ts_seconds: current_epoch_seconds() + 100,
+            ..Default::default()
+        };
+
+        let auth_context = auth_service.auth(user.clone());
+        assert!(!auth_context.is_impersonated());
+        assert!(auth_context.is_not_impersonated().is_ok());
+
+        let auth_context = auth_service.auth(Auth { impersonated_by: Some(1), ..user });
+        assert!(auth_context.is_impersonated());
+        assert!(auth_context.is_authenticated().is_ok());
+        match auth_context.is_not_impersonated() {
+            Err(LightSpeedError::ForbiddenError { .. }) => {}
+            _ => panic!("Should return ForbiddenError if impersonated"),
+        }
+    }
+
+    #[test]
+    fn should_verify_the_expiration_against_the_clock() {
+        let clock = lightspeed_clock::MockClock::from_epoch_seconds(1000);
+        let provider = super::InMemoryRolesProvider::new(vec![].into());
+        let auth_service = super::AuthService::new_with_clock(provider, Arc::new(clock.clone()));
+        let user = Auth { id: 10, username: "name".to_string(), expiration_ts_seconds: 1100, ..Default::default() };
+
+        assert!(auth_service.auth(user.clone()).is_authenticated().is_ok());
+
+        clock.advance(std::time::Duration::from_secs(100));
+        assert!(auth_service.auth(user.clone()).is_authenticated().is_ok());
+
+        clock.advance(std::time::Duration::from_secs(1));
+        match auth_service.auth(user).is_authenticated() {
+            Err(LightSpeedError::UnauthenticatedError) => {}
+            _ => panic!("Should return UnauthenticatedError if expired"),
+        }
+    }
+
     #[test]
     fn should_be_not_authenticated_even_if_has_role() {
         let provider = super::InMemoryRolesProvider::new(vec![].into());
@@ -363,6 +467,8 @@ mod test {
             roles: vec!["ADMIN".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.has_role("ADMIN").is_err());
@@ -379,6 +485,8 @@ mod test {
             roles: vec!["ADMIN".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.has_role("ADMIN").is_ok());
@@ -395,6 +503,8 @@ mod test {
             roles: vec!["ADMIN".to_string(), "USER".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.has_role("USER").is_ok());
@@ -411,6 +521,8 @@ mod test {
             roles: vec!["ADMIN".to_string(), "USER".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth = auth_service.auth(user);
         assert!(auth.has_role("USER").and_then(|auth| auth.has_role("USER")).is_ok());
@@ -427,6 +539,8 @@ mod test {
             roles: vec!["ADMIN".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.has_role("USER").is_err());
@@ -443,6 +557,8 @@ mod test {
             roles: vec!["ADMIN".to_string(), "USER".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.has_any_role(&["USER", "FRIEND"]).is_ok());
@@ -459,6 +575,8 @@ mod test {
             roles: vec!["ADMIN".to_string(), "OWNER".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.has_any_role(&["USER", "FRIEND"]).is_err());
@@ -475,6 +593,8 @@ mod test {
             roles: vec!["ADMIN".to_string(), "USER".to_string(), "FRIEND".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.has_all_roles(&["USER", "FRIEND"]).is_ok());
@@ -491,6 +611,8 @@ mod test {
             roles: vec!["ADMIN".to_string(), "USER".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.has_all_roles(&["USER", "FRIEND"]).is_err());
@@ -508,6 +630,8 @@ mod test {
             roles: vec!["ADMIN".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.has_permission("delete").is_err());
@@ -528,11 +652,39 @@ mod test {
             roles: vec!["ADMIN".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.has_permission("delete").is_ok());
     }
 
+    #[test]
+    fn should_check_the_permission_of_other_roles() {
+        let roles = vec![
+            Role { name: "ADMIN".to_string(), permissions: vec!["delete".to_string()] },
+            Role { name: "OWNER".to_string(), permissions: vec!["create".to_string()] },
+        ];
+        let provider = super::InMemoryRolesProvider::new(roles.into());
+        let auth_service = super::AuthService::new(provider);
+        let user = Auth {
+            id: 0,
+            username: "name".to_string(),
+            session_id: "".to_string(),
+            roles: vec!["OWNER".to_string()],
+            creation_ts_seconds: 0,
+            expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
+        };
+        let auth_context = auth_service.auth(user);
+        assert!(auth_context.roles_have_permission(&["ADMIN".to_string()], "delete"));
+        assert!(!auth_context.roles_have_permission(&["OWNER".to_string()], "delete"));
+        assert!(!auth_context.roles_have_permission(&[], "delete"));
+        assert!(!auth_context.roles_have_permission(&["ADMIN".to_string()], "unknown"));
+        assert!(auth_context.has_permission("delete").is_err());
+    }
+
     #[test]
     fn should_have_permission_2() {
         let roles = vec![
@@ -548,6 +700,8 @@ mod test {
             roles: vec!["ADMIN".to_string(), "OWNER".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.has_permission("delete").is_ok());
@@ -568,6 +722,8 @@ mod test {
             roles: vec!["USER".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.has_permission("delete").is_err());
@@ -588,6 +744,8 @@ mod test {
             roles: vec!["USER".to_string(), "ADMIN".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.has_any_permission(&["delete", "superDelete"]).is_ok());
@@ -608,6 +766,8 @@ mod test {
             roles: vec!["USER".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.has_any_permission(&["delete", "superAdmin"]).is_err());
@@ -629,6 +789,8 @@ mod test {
             roles: vec!["USER".to_string(), "ADMIN".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.has_all_permissions(&["delete", "superDelete"]).is_ok());
@@ -649,11 +811,70 @@ mod test {
             roles: vec!["USER".to_string(), "ADMIN".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.has_all_permissions(&["delete", "superDelete"]).is_err());
     }
 
+    #[test]
+    fn should_have_only_the_scope_permissions() {
+        let roles =
+            vec![Role { name: "ADMIN".to_string(), permissions: vec!["read".to_string(), "delete".to_string()] }];
+        let provider = super::InMemoryRolesProvider::new(roles.into());
+        let auth_service = super::AuthService::new(provider);
+        let user = Auth {
+            id: 0,
+            username: "name".to_string(),
+            session_id: "".to_string(),
+            roles: vec!["ADMIN".to_string()],
+            creation_ts_seconds: 0,
+            expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: Some(vec!["read".to_string(), "create".to_string()]),
+            impersonated_by: None,
+        };
+        let auth_context = auth_service.auth(user);
+        assert!(auth_context.has_permission("read").is_ok());
+        assert!(auth_context.has_permission("delete").is_err());
+        assert!(auth_context.has_permission("create").is_err());
+    }
+
+    #[test]
+    fn should_fail_the_role_and_owner_checks_of_a_delegated_auth() {
+        let roles =
+            vec![Role { name: "ADMIN".to_string(), permissions: vec!["read".to_string(), "delete".to_string()] }];
+        let provider = super::InMemoryRolesProvider::new(roles.into());
+        let auth_service = super::AuthService::new(provider);
+        let user = Auth {
+            id: 0,
+            username: "name".to_string(),
+            session_id: "".to_string(),
+            roles: vec!["ADMIN".to_string()],
+            creation_ts_seconds: 0,
+            expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: Some(vec!["read".to_string()]),
+            impersonated_by: None,
+        };
+        let auth_context = auth_service.auth(user);
+        assert!(auth_context.has_role("ADMIN").is_err());
+        assert!(auth_context.has_any_role(&["ADMIN"]).is_err());
+        assert!(auth_context.has_all_roles(&["ADMIN"]).is_err());
+        assert!(auth_context.is_owner(&0).is_err());
+        assert!(auth_context.is_owner_or_has_role(&0, "ADMIN").is_err());
+        assert!(auth_context.is_owner_or_has_permission(&0, "delete").is_err());
+        assert!(auth_context.is_owner_or_has_permission(&0, "read").is_ok());
+    }
+
+    #[test]
+    fn should_deserialize_an_auth_without_scope_permissions() -> Result<(), LightSpeedError> {
+        let auth: Auth = serde_json::from_str(
+            r#"{"id":1,"username":"name","session_id":"1_0","roles":[],"creation_ts_seconds":0,"expiration_ts_seconds":0}"#,
+        )?;
+        assert!(auth.scope_permissions.is_none());
+        Ok(())
+    }
+
     #[test]
     fn should_be_the_owner() {
         let roles = vec![];
@@ -666,6 +887,8 @@ mod test {
             roles: vec!["USER".to_string(), "ADMIN".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.is_owner(&Ownable { owner_id: 0 }).is_ok());
@@ -683,6 +906,8 @@ mod test {
             roles: vec!["USER".to_string(), "ADMIN".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.is_owner(&Ownable { owner_id: 1 }).is_err());
@@ -700,6 +925,8 @@ mod test {
             roles: vec!["ROLE_1".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.is_owner_or_has_role(&Ownable { owner_id: 1 }, "ROLE_1").is_ok());
@@ -717,6 +944,8 @@ mod test {
             roles: vec!["ROLE_1".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.is_owner_or_has_role(&Ownable { owner_id: 0 }, "ROLE_2").is_ok());
@@ -734,6 +963,8 @@ mod test {
             roles: vec!["ROLE_1".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.is_owner_or_has_role(&Ownable { owner_id: 1 }, "ROLE_2").is_err());
@@ -751,6 +982,8 @@ mod test {
             roles: vec!["ROLE_1".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.is_owner_or_has_permission(&Ownable { owner_id: 1 }, "access_1").is_ok());
@@ -768,6 +1001,8 @@ mod test {
             roles: vec!["ROLE_1".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.is_owner_or_has_permission(&Ownable { owner_id: 0 }, "access_2").is_ok());
@@ -785,6 +1020,8 @@ mod test {
             roles: vec!["ROLE_1".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
         assert!(auth_context.is_owner_or_has_permission(&Ownable { owner_id: 1 }, "access_2").is_err());
@@ -802,6 +1039,8 @@ mod test {
             roles: vec!["ROLE_1".to_string(), "ROLE_2".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
 
@@ -825,6 +1064,8 @@ mod test {
             roles: vec!["ROLE_1".to_string(), "ROLE_2".to_string()],
             creation_ts_seconds: 0,
             expiration_ts_seconds: current_epoch_seconds() + 100,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let auth_context = auth_service.auth(user);
 
@@ -1,8 +1,9 @@
 use crate::config::JwtConfig;
 use crate::error::LightSpeedError;
-use crate::utils::current_epoch_seconds;
 use jsonwebtoken::{DecodingKey, EncodingKey};
+use lightspeed_clock::{Clock, SystemClock};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JWT<T> {
@@ -24,10 +25,17 @@ pub struct JwtService {
     token_validity_seconds: i64,
     header_default: jsonwebtoken::Header,
     validation_default: jsonwebtoken::Validation,
+    clock: Arc<dyn Clock>,
 }
 
 impl JwtService {
     pub fn new(jwt_config: &JwtConfig) -> Result<JwtService, LightSpeedError> {
+        Self::new_with_clock(jwt_config, SystemClock::shared())
+    }
+
+    /// Creates a JwtService that reads the time from the given clock.
+    /// The token expiration is verified against the clock instead of the system time.
+    pub fn new_with_clock(jwt_config: &JwtConfig, clock: Arc<dyn Clock>) -> Result<JwtService, LightSpeedError> {
         if jwt_config.secret.is_empty() {
             return Err(LightSpeedError::ConfigurationError { message: "JWT secret key cannot be empty".to_owned() });
         }
@@ -35,6 +43,7 @@ impl JwtService {
         let alg = jwt_config.signature_algorithm;
         let mut validation_default = jsonwebtoken::Validation::new(alg);
         validation_default.leeway = 0;
+        validation_default.validate_exp = false;
 
         Ok(JwtService {
             encoding_key: EncodingKey::from_secret(jwt_config.secret.as_ref()),
@@ -42,6 +51,7 @@ impl JwtService {
             token_validity_seconds: i64::from(jwt_config.token_validity_minutes) * 60,
             header_default: jsonwebtoken::Header { alg, ..jsonwebtoken::Header::default() },
             validation_default,
+            clock,
         })
     }
 
@@ -49,7 +59,7 @@ impl JwtService {
         &self,
         payload: &'a T,
     ) -> Result<(JWT<&'a T>, String), LightSpeedError> {
-        let issued_at = current_epoch_seconds();
+        let issued_at = self.clock.epoch_seconds();
         let token = JWT { payload, sub: "".to_string(), exp: issued_at + self.token_validity_seconds, iat: issued_at };
         self.generate_from_token(&token).map(|jwt| (token, jwt))
     }
@@ -77,7 +87,16 @@ impl JwtService {
         let result: Result<jsonwebtoken::TokenData<JWT<T>>, jsonwebtoken::errors::Error> =
             jsonwebtoken::decode(jwt_string, &DecodingKey::from_secret(self.secret.as_ref()), &self.validation_default);
         match result {
-            Ok(t) => Ok(t.claims),
+            Ok(t) => {
+                if t.claims.exp < self.clock.epoch_seconds() {
+                    Err(LightSpeedError::ExpiredTokenError {
+                        message: jsonwebtoken::errors::Error::from(jsonwebtoken::errors::ErrorKind::ExpiredSignature)
+                            .to_string(),
+                    })
+                } else {
+                    Ok(t.claims)
+                }
+            }
             Err(e) => match *e.kind() {
                 jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
                     Err(LightSpeedError::ExpiredTokenError { message: e.to_string() })
@@ -220,6 +239,32 @@ mod test {
         assert!(is_expired)
     }
 
+    #[test]
+    fn should_verify_the_expiration_against_the_clock() {
+        let clock = lightspeed_clock::MockClock::default();
+        let jwt = super::JwtService::new_with_clock(
+            &JwtConfig {
+                secret: "mySecret".to_string(),
+                signature_algorithm: jsonwebtoken::Algorithm::HS512,
+                token_validity_minutes: 60,
+            },
+            Arc::new(clock.clone()),
+        )
+        .unwrap();
+
+        let payload = MyTestClaym { id: 1, name: "Red".to_string() };
+        let jwt_string = jwt.generate_from_payload(&payload).unwrap().1;
+
+        clock.advance(std::time::Duration::from_secs(60 * 60));
+        assert!(jwt.parse_payload::<MyTestClaym>(&jwt_string).is_ok());
+
+        clock.advance(std::time::Duration::from_secs(1));
+        match jwt.parse_payload::<MyTestClaym>(&jwt_string) {
+            Err(super::LightSpeedError::ExpiredTokenError { .. }) => {}
+            _ => panic!(),
+        }
+    }
+
     #[test]
     fn should_not_build_if_secret_key_empty() {
         assert!(super::JwtService::new(&JwtConfig {
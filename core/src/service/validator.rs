@@ -4,6 +4,7 @@ pub mod boolean;
 pub mod contains;
 pub mod email;
 pub mod ip;
+pub mod locale;
 pub mod must_match;
 pub mod order;
 pub mod ownership;
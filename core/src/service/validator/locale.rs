@@ -0,0 +1,35 @@
+use crate::error::{ErrorDetail, ErrorDetails};
+use crate::model::locale::Locale;
+use std::str::FromStr;
+
+pub const NOT_VALID_LOCALE: &str = "NOT_VALID_LOCALE";
+
+/// Validates whether the given string is a BCP-47 language tag
+#[inline]
+pub fn validate_locale<S: Into<String>>(error_details: &mut ErrorDetails, field_name: S, val: &str) {
+    if Locale::from_str(val).is_err() {
+        error_details.add_detail(field_name.into(), ErrorDetail::new(NOT_VALID_LOCALE, vec![]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::error::ErrorDetails;
+
+    #[test]
+    fn should_validate_and_return_no_errors() {
+        let mut error_details = ErrorDetails::default();
+        validate_locale(&mut error_details, "locale", "de-CH");
+        assert!(error_details.details().is_empty())
+    }
+
+    #[test]
+    fn should_validate_and_return_errors() {
+        let mut error_details = ErrorDetails::default();
+        validate_locale(&mut error_details, "locale", "de CH");
+        assert_eq!(1, error_details.details().len());
+        assert_eq!(ErrorDetail::new(NOT_VALID_LOCALE, vec![]), error_details.details()["locale"][0])
+    }
+}
@@ -0,0 +1,320 @@
+use crate::error::LightSpeedError;
+use crate::module::Module;
+use log::*;
+use std::any::Any;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::sync::watch;
+
+pub type ShutdownFuture = Pin<Box<dyn Future<Output = Result<(), LightSpeedError>> + Send>>;
+
+type ShutdownTaskFn = Box<dyn FnOnce() -> ShutdownFuture + Send>;
+
+struct ShutdownTask {
+    name: String,
+    timeout: Duration,
+    task: ShutdownTaskFn,
+}
+
+/// Notifies the shutdown to the interested parties.
+/// For example, it can be used to stop a web server from accepting new requests.
+#[derive(Clone)]
+pub struct ShutdownSignal {
+    receiver: watch::Receiver<bool>,
+}
+
+impl ShutdownSignal {
+    /// Returns true if the shutdown has started
+    pub fn is_shutting_down(&self) -> bool {
+        *self.receiver.borrow()
+    }
+
+    /// Waits until the shutdown starts
+    pub async fn wait(mut self) {
+        while !*self.receiver.borrow_and_update() {
+            if self.receiver.changed().await.is_err() {
+                // The coordinator was dropped, no shutdown will ever be notified
+                std::future::pending::<()>().await;
+            }
+        }
+    }
+}
+
+/// The outcome of the shutdown tasks
+#[derive(Debug, Default)]
+pub struct ShutdownReport {
+    pub completed: Vec<String>,
+    pub failed: Vec<(String, LightSpeedError)>,
+    pub timed_out: Vec<String>,
+}
+
+impl ShutdownReport {
+    /// Returns true if all the shutdown tasks completed successfully
+    pub fn is_clean(&self) -> bool {
+        self.failed.is_empty() && self.timed_out.is_empty()
+    }
+}
+
+/// Coordinates the graceful shutdown of the application.
+///
+/// When the shutdown starts:
+/// 1. all the [`ShutdownSignal`]s are notified, so the web servers can stop accepting requests
+/// 2. the registered tasks are executed in reverse registration order, each one with its own deadline
+/// 3. the resources kept until shutdown (e.g. the logger guards) are dropped in reverse registration order
+pub struct ShutdownCoordinator {
+    sender: watch::Sender<bool>,
+    default_timeout: Duration,
+    tasks: Vec<ShutdownTask>,
+    resources: Vec<Box<dyn Any + Send>>,
+}
+
+impl Default for ShutdownCoordinator {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(30))
+    }
+}
+
+impl ShutdownCoordinator {
+    /// Creates a new ShutdownCoordinator.
+    /// The `default_timeout` is the deadline of the tasks registered without an explicit timeout.
+    pub fn new(default_timeout: Duration) -> Self {
+        let (sender, _) = watch::channel(false);
+        Self { sender, default_timeout, tasks: vec![], resources: vec![] }
+    }
+
+    pub fn signal(&self) -> ShutdownSignal {
+        ShutdownSignal { receiver: self.sender.subscribe() }
+    }
+
+    /// Registers a task executed at shutdown with the default timeout
+    pub fn add_task<N, F, Fut>(&mut self, name: N, task: F)
+    where
+        N: Into<String>,
+        F: 'static + Send + FnOnce() -> Fut,
+        Fut: 'static + Send + Future<Output = Result<(), LightSpeedError>>,
+    {
+        let timeout = self.default_timeout;
+        self.add_task_with_timeout(name, timeout, task)
+    }
+
+    /// Registers a task executed at shutdown. The task is cancelled if not completed within the timeout.
+    pub fn add_task_with_timeout<N, F, Fut>(&mut self, name: N, timeout: Duration, task: F)
+    where
+        N: Into<String>,
+        F: 'static + Send + FnOnce() -> Fut,
+        Fut: 'static + Send + Future<Output = Result<(), LightSpeedError>>,
+    {
+        let name = name.into();
+        debug!("Register shutdown task [{}] with timeout {:?}", name, timeout);
+        self.tasks.push(ShutdownTask { name, timeout, task: Box::new(move || Box::pin(task())) });
+    }
+
+    /// Registers a module to be stopped at shutdown.
+    /// Modules should be registered in the same order they are started, so they are stopped in reverse order.
+    pub fn add_module<N: Into<String>, M: 'static + Module + Send>(&mut self, name: N, mut module: M) {
+        self.add_task(name, move || async move { module.stop().await });
+    }
+
+    /// Keeps the resource alive until the end of the shutdown.
+    /// This is useful for objects like the logger guards that need to be dropped as last.
+    pub fn keep_until_shutdown<R: 'static + Send>(&mut self, resource: R) {
+        self.resources.push(Box::new(resource));
+    }
+
+    /// Waits for a SIGINT or SIGTERM (only SIGINT on non-unix systems) and then starts the shutdown.
+    pub async fn shutdown_on_os_signal(self) -> Result<ShutdownReport, LightSpeedError> {
+        wait_for_os_signal().await?;
+        Ok(self.shutdown().await)
+    }
+
+    /// Starts the shutdown
+    pub async fn shutdown(mut self) -> ShutdownReport {
+        info!("Begin application shutdown");
+        self.sender.send_replace(true);
+
+        let mut report = ShutdownReport::default();
+
+        while let Some(ShutdownTask { name, timeout, task }) = self.tasks.pop() {
+            info!("Execute shutdown task [{}]", name);
+            match tokio::time::timeout(timeout, (task)()).await {
+                Ok(Ok(())) => report.completed.push(name),
+                Ok(Err(err)) => {
+                    error!("Shutdown task [{}] failed. Err: {:?}", name, err);
+                    report.failed.push((name, err));
+                }
+                Err(_) => {
+                    warn!("Shutdown task [{}] not completed within {:?}", name, timeout);
+                    report.timed_out.push(name);
+                }
+            }
+        }
+
+        if report.is_clean() {
+            info!("Application shutdown completed");
+        } else {
+            warn!(
+                "Application shutdown completed with errors. Failed tasks: {:?}. Timed out tasks: {:?}",
+                report.failed.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+                report.timed_out
+            );
+        }
+
+        while let Some(resource) = self.resources.pop() {
+            drop(resource);
+        }
+
+        report
+    }
+}
+
+/// Waits for a SIGINT or SIGTERM (only SIGINT on non-unix systems)
+pub async fn wait_for_os_signal() -> Result<(), LightSpeedError> {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.map_err(|err| LightSpeedError::InternalServerError {
+            message: format!("Cannot listen for the SIGINT signal: {err:?}"),
+        })
+    };
+
+    #[cfg(unix)]
+    {
+        let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()).map_err(|err| {
+            LightSpeedError::InternalServerError { message: format!("Cannot listen for the SIGTERM signal: {err:?}") }
+        })?;
+
+        tokio::select! {
+            result = ctrl_c => result?,
+            _ = sigterm.recv() => {},
+        }
+    }
+
+    #[cfg(not(unix))]
+    ctrl_c.await?;
+
+    info!("Shutdown signal received");
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    #[tokio::test]
+    async fn should_execute_tasks_in_reverse_order() {
+        let output = Arc::new(Mutex::new(vec![]));
+        let mut coordinator = ShutdownCoordinator::default();
+
+        for name in ["one", "two", "three"] {
+            let output = output.clone();
+            coordinator.add_task(name, move || async move {
+                output.lock().await.push(name);
+                Ok(())
+            });
+        }
+
+        let report = coordinator.shutdown().await;
+
+        assert!(report.is_clean());
+        assert_eq!(vec!["three", "two", "one"], *output.lock().await);
+        assert_eq!(vec!["three", "two", "one"], report.completed);
+    }
+
+    #[tokio::test]
+    async fn should_report_failed_and_timed_out_tasks() {
+        let mut coordinator = ShutdownCoordinator::default();
+
+        coordinator.add_task("ok", || async { Ok(()) });
+        coordinator.add_task("failed", || async {
+            Err(LightSpeedError::InternalServerError { message: "failed".to_owned() })
+        });
+        coordinator.add_task_with_timeout("slow", Duration::from_millis(10), || async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok(())
+        });
+
+        let report = coordinator.shutdown().await;
+
+        assert!(!report.is_clean());
+        assert_eq!(vec!["ok"], report.completed);
+        assert_eq!("failed", report.failed[0].0);
+        assert_eq!(vec!["slow"], report.timed_out);
+    }
+
+    #[tokio::test]
+    async fn should_notify_the_signal_before_executing_the_tasks() {
+        let mut coordinator = ShutdownCoordinator::default();
+        let signal = coordinator.signal();
+        assert!(!signal.is_shutting_down());
+
+        let task_signal = coordinator.signal();
+        coordinator.add_task("check_signal", move || async move {
+            assert!(task_signal.is_shutting_down());
+            Ok(())
+        });
+
+        let waiting = tokio::spawn(signal.clone().wait());
+
+        assert!(coordinator.shutdown().await.is_clean());
+        assert!(signal.is_shutting_down());
+        waiting.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_drop_resources_after_the_tasks() {
+        let output = Arc::new(std::sync::Mutex::new(vec![]));
+        let mut coordinator = ShutdownCoordinator::default();
+
+        coordinator.keep_until_shutdown(DropRecorder { name: "guard", output: output.clone() });
+
+        let task_output = output.clone();
+        coordinator.add_task("task", move || async move {
+            task_output.lock().unwrap().push("task");
+            Ok(())
+        });
+
+        coordinator.shutdown().await;
+
+        assert_eq!(vec!["task", "guard"], *output.lock().unwrap());
+    }
+
+    #[tokio::test]
+    async fn should_stop_modules() {
+        let stopped = Arc::new(Mutex::new(false));
+        let mut coordinator = ShutdownCoordinator::default();
+
+        coordinator.add_module("module", StoppableModule { stopped: stopped.clone() });
+        assert!(coordinator.shutdown().await.is_clean());
+
+        assert!(*stopped.lock().await);
+    }
+
+    struct DropRecorder {
+        name: &'static str,
+        output: Arc<std::sync::Mutex<Vec<&'static str>>>,
+    }
+
+    impl Drop for DropRecorder {
+        fn drop(&mut self) {
+            self.output.lock().unwrap().push(self.name);
+        }
+    }
+
+    struct StoppableModule {
+        stopped: Arc<Mutex<bool>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Module for StoppableModule {
+        async fn start(&mut self) -> Result<(), LightSpeedError> {
+            Ok(())
+        }
+
+        async fn stop(&mut self) -> Result<(), LightSpeedError> {
+            *self.stopped.lock().await = true;
+            Ok(())
+        }
+    }
+}
@@ -92,6 +92,8 @@ mod test {
                 roles: vec![],
                 creation_ts_seconds: 0,
                 expiration_ts_seconds: i64::MAX,
+                scope_permissions: None,
+                impersonated_by: None,
             },
             exp: 0,
             iat: 0,
@@ -123,6 +125,8 @@ mod test {
             roles: vec![],
             creation_ts_seconds: 0,
             expiration_ts_seconds: i64::MAX,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let token = new_service().token_from_auth(&auth).unwrap();
 
@@ -150,6 +154,8 @@ mod test {
             roles: vec![],
             creation_ts_seconds: 0,
             expiration_ts_seconds: i64::MAX,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let token = new_service().token_from_auth(&auth).unwrap();
 
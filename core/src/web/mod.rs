@@ -1,4 +1,5 @@
 use crate::error::LightSpeedError;
+use crate::model::locale::{Locale, SupportedLocales};
 use crate::service::auth::{Auth, AuthContext, AuthService, RolesProvider};
 use crate::service::jwt::JwtService;
 use http::{HeaderMap, HeaderValue, Request};
@@ -15,6 +16,7 @@ pub mod poem;
 pub const JWT_TOKEN_HEADER: &str = "Authorization";
 pub const JWT_TOKEN_HEADER_SUFFIX: &str = "Bearer ";
 pub const JWT_TOKEN_HEADER_SUFFIX_LEN: usize = JWT_TOKEN_HEADER_SUFFIX.len();
+pub const ACCEPT_LANGUAGE_HEADER: &str = "Accept-Language";
 
 pub trait Headers {
     fn get(&self, header_name: &str) -> Option<&HeaderValue>;
@@ -32,6 +34,15 @@ impl<T> Headers for Request<T> {
     }
 }
 
+/// Returns the supported locale that best matches the Accept-Language header of the request.
+/// The default locale is returned if the header is missing or none of the requested locales is supported.
+pub fn locale_from_request<'a, H: Headers>(supported_locales: &'a SupportedLocales, req: &H) -> &'a Locale {
+    match req.get(ACCEPT_LANGUAGE_HEADER).and_then(|header| header.to_str().ok()) {
+        Some(accept_language) => supported_locales.negotiate(accept_language),
+        None => supported_locales.default_locale(),
+    }
+}
+
 #[derive(Clone)]
 pub struct WebAuthService<T: RolesProvider> {
     auth_service: Arc<AuthService<T>>,
@@ -70,9 +81,58 @@ impl<T: RolesProvider> WebAuthService<T> {
         self.token_string_from_request(req).and_then(|token| self.auth_from_token_string(token))
     }
 
+    /// Builds the AuthContext of a session token.
+    /// The delegated tokens, e.g. the OAuth2 access tokens, are rejected: they have to be verified by their issuer.
     pub fn auth_from_token_string(&self, token: &str) -> Result<AuthContext, LightSpeedError> {
         let auth = self.jwt_service.parse_payload::<Auth>(token);
         trace!("Auth built from request: [{:?}]", auth);
-        Ok(self.auth_service.auth(auth?))
+        let auth = auth?;
+        if auth.scope_permissions.is_some() {
+            return Err(LightSpeedError::InvalidTokenError {
+                message: "A delegated token cannot be used as a session token".to_owned(),
+            });
+        }
+        Ok(self.auth_service.auth(auth))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::config::JwtConfig;
+    use crate::service::auth::InMemoryRolesProvider;
+    use std::str::FromStr;
+
+    #[test]
+    fn should_negotiate_the_locale_from_the_request_headers() {
+        let supported_locales = SupportedLocales::new(
+            Locale::from_str("en").unwrap(),
+            vec![Locale::from_str("de").unwrap(), Locale::from_str("it").unwrap()],
+        );
+
+        let mut headers = HeaderMap::new();
+        assert_eq!("en", locale_from_request(&supported_locales, &headers).to_string());
+
+        headers.insert(ACCEPT_LANGUAGE_HEADER, HeaderValue::from_static("de-CH, it;q=0.9"));
+        assert_eq!("de", locale_from_request(&supported_locales, &headers).to_string());
+    }
+
+    #[test]
+    fn should_reject_the_delegated_tokens() -> Result<(), LightSpeedError> {
+        let jwt_service = Arc::new(JwtService::new(&JwtConfig { secret: "secret".to_owned(), ..Default::default() })?);
+        let auth_service = Arc::new(AuthService::new(InMemoryRolesProvider::new(vec![].into())));
+        let web_auth_service = WebAuthService::new(auth_service, jwt_service);
+        let auth = Auth { username: "name".to_owned(), ..Default::default() };
+
+        let token = web_auth_service.token_from_auth(&auth)?;
+        assert!(web_auth_service.auth_from_token_string(&token).is_ok());
+
+        let token = web_auth_service.token_from_auth(&Auth { scope_permissions: Some(vec![]), ..auth })?;
+        assert!(matches!(
+            web_auth_service.auth_from_token_string(&token),
+            Err(LightSpeedError::InvalidTokenError { .. })
+        ));
+        Ok(())
     }
 }
@@ -194,6 +194,8 @@ mod test {
                 roles: vec![],
                 creation_ts_seconds: 0,
                 expiration_ts_seconds: i64::MAX,
+                scope_permissions: None,
+                impersonated_by: None,
             },
             exp: 0,
             iat: 0,
@@ -222,6 +224,8 @@ mod test {
             roles: vec![],
             creation_ts_seconds: 0,
             expiration_ts_seconds: i64::MAX,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let token = new_service().token_from_auth(&auth).unwrap();
 
@@ -246,6 +250,8 @@ mod test {
             roles: vec![],
             creation_ts_seconds: 0,
             expiration_ts_seconds: i64::MAX,
+            scope_permissions: None,
+            impersonated_by: None,
         };
         let token = new_service().token_from_auth(&auth).unwrap();
 
@@ -388,6 +394,8 @@ mod test {
                     roles: vec![],
                     creation_ts_seconds: 0,
                     expiration_ts_seconds: i64::MAX,
+                    scope_permissions: None,
+                    impersonated_by: None,
                 },
                 exp: 0,
                 iat: 0,
@@ -422,6 +430,8 @@ mod test {
                 roles: vec![],
                 creation_ts_seconds: 0,
                 expiration_ts_seconds: i64::MAX,
+                scope_permissions: None,
+                impersonated_by: None,
             };
             let token = new_service().token_from_auth(&auth).unwrap();
 
@@ -452,6 +462,8 @@ mod test {
                 roles: vec![],
                 creation_ts_seconds: 0,
                 expiration_ts_seconds: i64::MAX,
+                scope_permissions: None,
+                impersonated_by: None,
             };
             let token = new_service().token_from_auth(&auth).unwrap();
 
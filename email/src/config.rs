@@ -1,7 +1,9 @@
 use crate::repository::email::EmailClientType;
-use serde::Deserialize;
+use lightspeed_core::error::{ErrorDetails, LightSpeedError};
+use lightspeed_core::service::validator::{Validable, ERR_VALUE_REQUIRED};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct EmailClientConfig {
     pub email_client_type: EmailClientType,
@@ -29,10 +31,25 @@ impl Default for EmailClientConfig {
     }
 }
 
+impl Validable for EmailClientConfig {
+    fn validate(&self, error_details: &mut ErrorDetails) -> Result<(), LightSpeedError> {
+        if let Some(recipients) = &self.forward_all_emails_to_fixed_recipients {
+            if recipients.is_empty() {
+                error_details.add_detail("forward_all_emails_to_fixed_recipients", ERR_VALUE_REQUIRED);
+            }
+        }
+        if self.email_client_type == EmailClientType::Full && self.email_server_address.is_empty() {
+            error_details.add_detail("email_server_address", ERR_VALUE_REQUIRED);
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
 
     use super::*;
+    use lightspeed_core::service::validator::Validator;
 
     #[test]
     fn should_build_config() {
@@ -40,6 +57,18 @@ mod test {
         assert!(config.forward_all_emails_to_fixed_recipients.is_none());
     }
 
+    #[test]
+    fn should_not_validate_empty_fixed_recipients() {
+        let config = EmailClientConfig { forward_all_emails_to_fixed_recipients: Some(vec![]), ..Default::default() };
+
+        match Validator::validate(&config) {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert_eq!(ERR_VALUE_REQUIRED, details.details["forward_all_emails_to_fixed_recipients"][0])
+            }
+            _ => panic!(),
+        }
+    }
+
     /*
     #[test]
     fn should_build_optional_fixed_recipients() {
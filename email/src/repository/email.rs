@@ -6,7 +6,7 @@ use crate::repository::in_memory_email::InMemoryEmailClient;
 use crate::repository::no_ops_email::NoOpsEmailClient;
 use lightspeed_core::error::LightSpeedError;
 use log::*;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::sync::Arc;
 
@@ -39,7 +39,7 @@ pub fn new(email_config: EmailClientConfig) -> Result<Arc<dyn EmailClient>, Ligh
     }
 }
 
-#[derive(Debug, PartialEq, Copy, Clone, Deserialize)]
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum EmailClientType {
     Full,
     InMemory,
@@ -1,6 +1,8 @@
-use serde::Deserialize;
+use lightspeed_core::error::{ErrorDetails, LightSpeedError};
+use lightspeed_core::service::validator::{Validable, ERR_VALUE_REQUIRED};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(default)]
 pub struct FileStoreConfig {
     /// The base folder used in case of 'FS' FileStoreType.
@@ -8,7 +10,18 @@ pub struct FileStoreConfig {
     pub fs_repo_base_folders: Vec<(String, String)>,
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+impl Validable for FileStoreConfig {
+    fn validate(&self, error_details: &mut ErrorDetails) -> Result<(), LightSpeedError> {
+        for (repository_name, base_folder) in &self.fs_repo_base_folders {
+            if repository_name.is_empty() || base_folder.is_empty() {
+                error_details.add_detail("fs_repo_base_folders", ERR_VALUE_REQUIRED);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct FsStore {
     pub key: String,
     pub folder: String,
@@ -1,11 +1,11 @@
-use lightspeed_core::model::language::Language;
+use lightspeed_core::model::locale::Locale;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct ValidationCodeRequestDto<Data> {
     pub to_be_validated: Data,
     pub code: String,
-    pub language: Option<Language>,
+    pub language: Option<Locale>,
     pub validation_code_validity_seconds: i64,
 }
 
@@ -1,4 +1,5 @@
 use base64::{engine::general_purpose, Engine as _};
+use hmac::{Hmac, Mac};
 use sha2::Digest;
 
 #[derive(Clone, Default)]
@@ -19,6 +20,14 @@ impl HashService {
     pub fn verify_hash(&self, text: &str, expected_hash: &str) -> bool {
         self.hash(text).eq(expected_hash)
     }
+
+    /// Returns the HMAC-SHA256 of the text. Unlike `hash`, the result cannot be computed without the key.
+    pub fn keyed_hash(&self, key: &str, text: &str) -> String {
+        let mut mac =
+            Hmac::<sha2::Sha256>::new_from_slice(key.as_bytes()).expect("HMAC should accept keys of any length");
+        mac.update(text.as_bytes());
+        general_purpose::STANDARD_NO_PAD.encode(mac.finalize().into_bytes())
+    }
 }
 
 #[cfg(test)]
@@ -44,4 +53,20 @@ mod test {
         assert!(!hash_service.verify_hash(&template, &format!("{first_hash}1")));
         assert!(!hash_service.verify_hash(&template, &template));
     }
+
+    #[test]
+    fn should_hash_a_string_with_a_key() {
+        let hash_service = HashService::new();
+
+        // RFC 4231, test case 2
+        assert_eq!(
+            "W9zBRr9gdU5qBCQmCJV1x1oAPwidJzmDnexYuWTsOEM",
+            hash_service.keyed_hash("Jefe", "what do ya want for nothing?")
+        );
+
+        let text = new_hyphenated_uuid();
+        assert_eq!(hash_service.keyed_hash("key", &text), hash_service.keyed_hash("key", &text));
+        assert_ne!(hash_service.keyed_hash("key", &text), hash_service.keyed_hash("other_key", &text));
+        assert_ne!(hash_service.hash(&text), hash_service.keyed_hash("", &text));
+    }
 }
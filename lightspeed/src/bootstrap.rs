@@ -0,0 +1,454 @@
+use crate::config::LightspeedConfig;
+use lightspeed_core::clock::{Clock, SystemClock};
+use lightspeed_core::error::LightSpeedError;
+use lightspeed_core::module::Module;
+use lightspeed_core::service::auth::{AuthService, InMemoryRolesProvider, Role};
+use lightspeed_core::service::validator::Validator;
+use lightspeed_core::shutdown::ShutdownCoordinator;
+use lightspeed_core::CoreModule;
+use log::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "c3p0")]
+use c3p0::postgres::PgC3p0Pool;
+
+#[cfg(feature = "auth")]
+use lightspeed_auth::{repository::pg::PgAuthRepositoryManager, service::oauth2::OAuth2Service, AuthModule};
+
+#[cfg(feature = "cms")]
+use lightspeed_cms::{repository::pg::PgCmsRepositoryManager, CmsModule};
+
+#[cfg(feature = "email")]
+use lightspeed_email::EmailClientModule;
+
+#[cfg(feature = "file_store")]
+use lightspeed_file_store::{repository::db::pg::PgFileStoreRepositoryManager, FileStoreModule};
+
+#[cfg(feature = "hash")]
+use lightspeed_hash::HashModule;
+
+#[cfg(feature = "scheduler")]
+use lightspeed_scheduler::JobExecutor;
+
+#[cfg(all(feature = "auth", feature = "scheduler"))]
+use lightspeed_scheduler::job::Job;
+
+/// Entry point to build and start a lightspeed application.
+///
+/// Example:
+/// ```rust,ignore
+/// let app = Lightspeed::builder()
+///     .with_config(ConfigLoader::new().load()?)
+///     .with_c3p0(c3p0)
+///     .with_auth()
+///     .with_file_store()
+///     .build()
+///     .await?;
+/// ```
+pub struct Lightspeed {}
+
+impl Lightspeed {
+    pub fn builder() -> LightspeedBuilder {
+        LightspeedBuilder::default()
+    }
+}
+
+/// Builds the modules enabled on it from the unified [`LightspeedConfig`], shares the same
+/// c3p0 pool between them and starts them in order.
+#[derive(Default)]
+pub struct LightspeedBuilder {
+    config: LightspeedConfig,
+    roles: Vec<Role>,
+    shutdown_timeout: Option<Duration>,
+    clock: Option<Arc<dyn Clock>>,
+
+    #[cfg(feature = "c3p0")]
+    c3p0: Option<PgC3p0Pool>,
+
+    #[cfg(feature = "auth")]
+    auth: bool,
+    #[cfg(feature = "cms")]
+    cms: bool,
+    #[cfg(feature = "email")]
+    email: bool,
+    #[cfg(feature = "file_store")]
+    file_store: bool,
+    #[cfg(feature = "hash")]
+    hash: bool,
+    #[cfg(feature = "logger")]
+    logger: bool,
+    #[cfg(feature = "scheduler")]
+    scheduler: bool,
+}
+
+impl LightspeedBuilder {
+    pub fn with_config(mut self, config: LightspeedConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Sets the roles used by the AuthService to map the permissions
+    pub fn with_roles(mut self, roles: Vec<Role>) -> Self {
+        self.roles = roles;
+        self
+    }
+
+    /// Sets the clock used by all the modules to read the current time.
+    /// The default is the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Sets the deadline of each shutdown step, for example the wait for the running jobs to complete
+    pub fn with_shutdown_timeout(mut self, shutdown_timeout: Duration) -> Self {
+        self.shutdown_timeout = Some(shutdown_timeout);
+        self
+    }
+
+    /// Sets the c3p0 pool shared by all the modules that require a database
+    #[cfg(feature = "c3p0")]
+    pub fn with_c3p0(mut self, c3p0: PgC3p0Pool) -> Self {
+        self.c3p0 = Some(c3p0);
+        self
+    }
+
+    #[cfg(feature = "auth")]
+    pub fn with_auth(mut self) -> Self {
+        self.auth = true;
+        self
+    }
+
+    #[cfg(feature = "cms")]
+    pub fn with_cms(mut self) -> Self {
+        self.cms = true;
+        self
+    }
+
+    #[cfg(feature = "email")]
+    pub fn with_email(mut self) -> Self {
+        self.email = true;
+        self
+    }
+
+    #[cfg(feature = "file_store")]
+    pub fn with_file_store(mut self) -> Self {
+        self.file_store = true;
+        self
+    }
+
+    #[cfg(feature = "hash")]
+    pub fn with_hash(mut self) -> Self {
+        self.hash = true;
+        self
+    }
+
+    /// Configures the global logger when the application is built.
+    /// The logger is configured before any other module is created.
+    #[cfg(feature = "logger")]
+    pub fn with_logger(mut self) -> Self {
+        self.logger = true;
+        self
+    }
+
+    /// Creates a JobExecutor that is started after all the other modules.
+    #[cfg(feature = "scheduler")]
+    pub fn with_scheduler(mut self) -> Self {
+        self.scheduler = true;
+        self
+    }
+
+    /// Validates the configuration, then creates and starts all the enabled modules
+    pub async fn build(self) -> Result<LightspeedApp, LightSpeedError> {
+        Validator::validate(&self.config)?;
+
+        #[cfg(feature = "logger")]
+        let log_guard = if self.logger {
+            lightspeed_logger::setup_logger(&self.config.logger)
+                .map_err(|err| LightSpeedError::ModuleBuilderError { message: format!("{err}") })?
+        } else {
+            None
+        };
+
+        info!("Building lightspeed application");
+
+        let clock = self.clock.unwrap_or_else(SystemClock::shared);
+
+        let mut core = CoreModule::new_with_clock(self.config.core.clone(), clock.clone())?;
+        core.auth = Arc::new(AuthService::new_with_clock(InMemoryRolesProvider::new(self.roles.into()), clock.clone()));
+
+        #[cfg(feature = "auth")]
+        let mut auth = if self.auth {
+            let c3p0 = required_c3p0(&self.c3p0, "AuthModule")?;
+            Some(AuthModule::new_with_clock(
+                PgAuthRepositoryManager::new(c3p0),
+                self.config.auth.clone(),
+                clock.clone(),
+            )?)
+        } else {
+            None
+        };
+
+        #[cfg(feature = "file_store")]
+        let mut file_store = if self.file_store {
+            let c3p0 = required_c3p0(&self.c3p0, "FileStoreModule")?;
+            Some(FileStoreModule::new(PgFileStoreRepositoryManager::new(c3p0), self.config.file_store.clone())?)
+        } else {
+            None
+        };
+
+        #[cfg(feature = "cms")]
+        let mut cms = if self.cms {
+            let c3p0 = required_c3p0(&self.c3p0, "CmsModule")?;
+            Some(CmsModule::new(PgCmsRepositoryManager::new(c3p0), self.config.cms.clone()))
+        } else {
+            None
+        };
+
+        #[cfg(feature = "email")]
+        let mut email = if self.email { Some(EmailClientModule::new(self.config.email.clone())?) } else { None };
+
+        #[cfg(feature = "hash")]
+        let mut hash = if self.hash { Some(HashModule::new(&core)?) } else { None };
+
+        {
+            let mut modules: Vec<&mut dyn Module> = vec![&mut core];
+
+            #[cfg(feature = "auth")]
+            if let Some(module) = auth.as_mut() {
+                modules.push(module);
+            }
+            #[cfg(feature = "file_store")]
+            if let Some(module) = file_store.as_mut() {
+                modules.push(module);
+            }
+            #[cfg(feature = "cms")]
+            if let Some(module) = cms.as_mut() {
+                modules.push(module);
+            }
+            #[cfg(feature = "email")]
+            if let Some(module) = email.as_mut() {
+                modules.push(module);
+            }
+            #[cfg(feature = "hash")]
+            if let Some(module) = hash.as_mut() {
+                modules.push(module);
+            }
+
+            lightspeed_core::module::start(&mut modules).await?;
+        }
+
+        #[cfg(feature = "scheduler")]
+        let job_executor = if self.scheduler {
+            let job_executor = JobExecutor::new_with_tz_and_clock(Some(chrono_tz::UTC), clock.clone());
+            job_executor.run().await.map_err(|err| LightSpeedError::ModuleStartError { message: format!("{err}") })?;
+            Some(job_executor)
+        } else {
+            None
+        };
+
+        #[cfg(all(feature = "auth", feature = "scheduler"))]
+        if let (Some(job_executor), Some(module)) = (job_executor.as_ref(), auth.as_ref()) {
+            if module.auth_config.purge_expired_tokens_job_enabled {
+                let token_service = module.token_service.clone();
+                let auth_session_service = module.auth_session_service.clone();
+                let login_attempt_service = module.login_attempt_service.clone();
+                // Without a secret no OAuth2 token can be issued
+                let oauth2_service = if module.auth_config.oauth2_access_token_secret.is_empty() {
+                    None
+                } else {
+                    Some(Arc::new(module.new_oauth2_service()?))
+                };
+                let interval = Duration::from_secs(module.auth_config.maintenance_job_interval_minutes * 60);
+                job_executor
+                    .add_job(
+                        &interval,
+                        Job::new("auth", "purge_expired_tokens", None, move || {
+                            let token_service = token_service.clone();
+                            let auth_session_service = auth_session_service.clone();
+                            let login_attempt_service = login_attempt_service.clone();
+                            let oauth2_service = oauth2_service.clone();
+                            Box::pin(async move {
+                                token_service.purge_expired().await?;
+                                auth_session_service.purge_expired().await?;
+                                login_attempt_service.purge_expired().await?;
+                                if let Some(oauth2_service) = oauth2_service {
+                                    oauth2_service.purge_expired().await?;
+                                }
+                                Ok(())
+                            })
+                        }),
+                    )
+                    .await
+                    .map_err(|err| LightSpeedError::ModuleStartError { message: format!("{err}") })?;
+            }
+            if module.auth_config.anonymize_disabled_accounts_after_days.is_some() {
+                let auth_account_service = module.auth_account_service.clone();
+                let interval = Duration::from_secs(module.auth_config.maintenance_job_interval_minutes * 60);
+                job_executor
+                    .add_job(
+                        &interval,
+                        Job::new("auth", "anonymize_disabled_accounts", None, move || {
+                            let auth_account_service = auth_account_service.clone();
+                            Box::pin(async move {
+                                auth_account_service.anonymize_expired_disabled_accounts().await?;
+                                Ok(())
+                            })
+                        }),
+                    )
+                    .await
+                    .map_err(|err| LightSpeedError::ModuleStartError { message: format!("{err}") })?;
+            }
+        }
+
+        let mut shutdown = self.shutdown_timeout.map(ShutdownCoordinator::new).unwrap_or_default();
+
+        #[cfg(feature = "logger")]
+        if let Some(log_guard) = log_guard {
+            shutdown.keep_until_shutdown(log_guard);
+        }
+
+        // The lightspeed modules hold no resources to release, so they are not registered:
+        // the c3p0 pool is owned by the caller and the other modules are dropped with the LightspeedApp.
+        #[cfg(feature = "scheduler")]
+        if let Some(job_executor) = job_executor.clone() {
+            shutdown.add_task("JobExecutor", move || async move {
+                job_executor
+                    .stop(true)
+                    .await
+                    .map_err(|err| LightSpeedError::InternalServerError { message: format!("{err}") })
+            });
+        }
+
+        info!("Lightspeed application started");
+
+        Ok(LightspeedApp {
+            shutdown,
+            config: self.config,
+            core,
+            #[cfg(feature = "auth")]
+            auth,
+            #[cfg(feature = "cms")]
+            cms,
+            #[cfg(feature = "email")]
+            email,
+            #[cfg(feature = "file_store")]
+            file_store,
+            #[cfg(feature = "hash")]
+            hash,
+            #[cfg(feature = "scheduler")]
+            job_executor,
+        })
+    }
+}
+
+#[cfg(feature = "c3p0")]
+fn required_c3p0(c3p0: &Option<PgC3p0Pool>, module_name: &str) -> Result<PgC3p0Pool, LightSpeedError> {
+    c3p0.clone().ok_or_else(|| LightSpeedError::ModuleBuilderError {
+        message: format!("Cannot build the {module_name}: the c3p0 pool is missing"),
+    })
+}
+
+/// The started lightspeed application.
+/// A module is present only if it was enabled on the [`LightspeedBuilder`].
+pub struct LightspeedApp {
+    pub config: LightspeedConfig,
+    pub core: CoreModule,
+
+    #[cfg(feature = "auth")]
+    pub auth: Option<AuthModule<PgAuthRepositoryManager>>,
+    #[cfg(feature = "cms")]
+    pub cms: Option<CmsModule<PgCmsRepositoryManager>>,
+    #[cfg(feature = "email")]
+    pub email: Option<EmailClientModule>,
+    #[cfg(feature = "file_store")]
+    pub file_store: Option<FileStoreModule<PgFileStoreRepositoryManager>>,
+    #[cfg(feature = "hash")]
+    pub hash: Option<HashModule>,
+    #[cfg(feature = "scheduler")]
+    pub job_executor: Option<JobExecutor>,
+
+    /// Stops the job executor, if any, and then drops the logger guards.
+    /// The lightspeed modules have nothing to stop; the c3p0 pool passed to the builder is owned by the caller.
+    /// Additional tasks, like stopping the web server or the application modules, can be registered on it.
+    pub shutdown: ShutdownCoordinator,
+}
+
+impl LightspeedApp {
+    /// Returns a WebAuthService that uses the AuthService and JwtService of the CoreModule
+    #[cfg(any(feature = "actix_web", feature = "axum", feature = "poem"))]
+    pub fn web_auth_service(&self) -> lightspeed_core::web::WebAuthService<InMemoryRolesProvider> {
+        lightspeed_core::web::WebAuthService::new(self.core.auth.clone(), self.core.jwt.clone())
+    }
+
+    /// Returns an OAuth2Service that signs the access tokens with the `auth.oauth2_access_token_secret`,
+    /// so that they are not accepted by the WebAuthService. It is None if the AuthModule is not enabled.
+    /// It fails if the secret is empty.
+    #[cfg(feature = "auth")]
+    pub fn oauth2_service(&self) -> Result<Option<OAuth2Service<PgAuthRepositoryManager>>, LightSpeedError> {
+        self.auth.as_ref().map(|auth| auth.new_oauth2_service()).transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use lightspeed_core::config::JwtConfig;
+
+    #[tokio::test]
+    async fn should_build_and_shutdown_core_application() {
+        let app = Lightspeed::builder()
+            .with_config(config())
+            .with_roles(vec![Role { name: "admin".to_owned(), permissions: vec!["delete".to_owned()] }])
+            .build()
+            .await
+            .unwrap();
+
+        let auth = lightspeed_core::service::auth::Auth {
+            username: "user".to_owned(),
+            roles: vec!["admin".to_owned()],
+            expiration_ts_seconds: i64::MAX,
+            ..Default::default()
+        };
+        assert!(app.core.auth.auth(auth).has_permission("delete").is_ok());
+
+        assert!(app.shutdown.shutdown().await.is_clean());
+    }
+
+    #[tokio::test]
+    async fn should_fail_if_config_is_not_valid() {
+        match Lightspeed::builder().build().await {
+            Err(LightSpeedError::ValidationError { .. }) => {}
+            _ => panic!(),
+        }
+    }
+
+    #[cfg(feature = "auth")]
+    #[tokio::test]
+    async fn should_fail_if_db_module_has_no_c3p0_pool() {
+        match Lightspeed::builder().with_config(config()).with_auth().build().await {
+            Err(LightSpeedError::ModuleBuilderError { .. }) => {}
+            _ => panic!(),
+        }
+    }
+
+    #[cfg(feature = "scheduler")]
+    #[tokio::test]
+    async fn should_start_and_stop_the_job_executor() {
+        let app = Lightspeed::builder().with_config(config()).with_scheduler().build().await.unwrap();
+        assert!(app.job_executor.is_some());
+        assert!(app.shutdown.shutdown().await.is_clean());
+    }
+
+    fn config() -> LightspeedConfig {
+        let mut config = LightspeedConfig::default();
+        config.core.jwt = JwtConfig { secret: "secret".to_owned(), ..Default::default() };
+        #[cfg(feature = "auth")]
+        {
+            config.auth.token_hash_key = "a".repeat(lightspeed_auth::config::TOKEN_HASH_KEY_MIN_LENGTH);
+        }
+        config
+    }
+}
@@ -0,0 +1,477 @@
+use ::config::{Config, Environment, File};
+use lightspeed_core::error::{ErrorDetails, LightSpeedError};
+use lightspeed_core::service::validator::{Validable, Validator};
+use log::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The default prefix of the environment variables read by the ConfigLoader
+pub const DEFAULT_ENV_PREFIX: &str = "LS";
+
+/// The default separator between the nested keys in the environment variables.
+/// E.g. the `LS__CORE__JWT__SECRET` variable maps to the `core.jwt.secret` key.
+pub const DEFAULT_ENV_SEPARATOR: &str = "__";
+
+/// The suffix of the environment variables that point to a file containing a secret.
+/// E.g. the `LS__CORE__JWT__SECRET_FILE=/run/secrets/jwt` variable sets the `core.jwt.secret` key
+/// to the content of the `/run/secrets/jwt` file.
+pub const SECRET_FILE_SUFFIX: &str = "_FILE";
+
+/// The keys whose values are masked in the effective configuration dump.
+/// A key is masked if its name is one of these values or ends with `_` followed by one of them.
+pub const DEFAULT_SECRET_KEYS: &[&str] = &["secret", "password", "private_key", "api_key", "token_hash_key"];
+
+const MASKED_VALUE: &str = "******";
+
+/// The unified configuration of all the lightspeed modules enabled by the crate features.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LightspeedConfig {
+    pub core: lightspeed_core::config::CoreConfig,
+
+    #[cfg(feature = "auth")]
+    pub auth: lightspeed_auth::config::AuthConfig,
+
+    #[cfg(feature = "cms")]
+    pub cms: lightspeed_cms::config::CmsConfig,
+
+    #[cfg(feature = "email")]
+    pub email: lightspeed_email::config::EmailClientConfig,
+
+    #[cfg(feature = "file_store")]
+    pub file_store: lightspeed_file_store::config::FileStoreConfig,
+
+    #[cfg(feature = "logger")]
+    pub logger: lightspeed_logger::config::LoggerConfig,
+}
+
+impl Validable for LightspeedConfig {
+    fn validate(&self, error_details: &mut ErrorDetails) -> Result<(), LightSpeedError> {
+        self.core.validate(&mut error_details.with_scope("core"))?;
+
+        #[cfg(feature = "auth")]
+        self.auth.validate(&mut error_details.with_scope("auth"))?;
+
+        #[cfg(feature = "email")]
+        self.email.validate(&mut error_details.with_scope("email"))?;
+
+        #[cfg(feature = "file_store")]
+        self.file_store.validate(&mut error_details.with_scope("file_store"))?;
+
+        Ok(())
+    }
+}
+
+/// The profile of the running application.
+/// It determines which profile specific configuration file is loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    Dev,
+    Test,
+    Prod,
+}
+
+impl Profile {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Profile::Dev => "dev",
+            Profile::Test => "test",
+            Profile::Prod => "prod",
+        }
+    }
+}
+
+impl Display for Profile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Profile {
+    type Err = LightSpeedError;
+
+    fn from_str(profile: &str) -> Result<Self, Self::Err> {
+        match profile.trim().to_lowercase().as_ref() {
+            "dev" => Ok(Profile::Dev),
+            "test" => Ok(Profile::Test),
+            "prod" => Ok(Profile::Prod),
+            _ => Err(LightSpeedError::ConfigurationError { message: format!("Could not parse profile [{profile}]") }),
+        }
+    }
+}
+
+/// Loads a configuration merging, in order of increasing priority:
+/// - the default values of the configuration struct
+/// - the `{config_dir}/{base_name}` file (any format supported by the `config` crate)
+/// - the `{config_dir}/{base_name}-{profile}` file
+/// - the environment variables starting with the `{env_prefix}{env_separator}` prefix
+/// - the secrets read from files, both the explicitly registered ones and the ones pointed by
+///   the environment variables ending with [`SECRET_FILE_SUFFIX`]
+///
+/// The active profile is the one explicitly set or, if missing, the value of the `{env_prefix}_PROFILE`
+/// environment variable. It defaults to [`Profile::Dev`].
+#[derive(Debug, Clone)]
+pub struct ConfigLoader {
+    config_dir: PathBuf,
+    base_name: String,
+    profile: Option<Profile>,
+    env_prefix: String,
+    env_separator: String,
+    env_list_keys: Vec<String>,
+    env_source: Option<HashMap<String, String>>,
+    secret_files: Vec<(String, PathBuf)>,
+}
+
+impl Default for ConfigLoader {
+    fn default() -> Self {
+        Self {
+            config_dir: PathBuf::from("./config"),
+            base_name: "application".to_owned(),
+            profile: None,
+            env_prefix: DEFAULT_ENV_PREFIX.to_owned(),
+            env_separator: DEFAULT_ENV_SEPARATOR.to_owned(),
+            env_list_keys: vec![],
+            env_source: None,
+            secret_files: vec![],
+        }
+    }
+}
+
+impl ConfigLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the folder that contains the configuration files. Default is `./config`.
+    pub fn with_config_dir<P: Into<PathBuf>>(mut self, config_dir: P) -> Self {
+        self.config_dir = config_dir.into();
+        self
+    }
+
+    /// Sets the name, without extension, of the configuration files. Default is `application`.
+    pub fn with_base_name<S: Into<String>>(mut self, base_name: S) -> Self {
+        self.base_name = base_name.into();
+        self
+    }
+
+    pub fn with_profile(mut self, profile: Profile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    pub fn with_env_prefix<S: Into<String>>(mut self, env_prefix: S) -> Self {
+        self.env_prefix = env_prefix.into();
+        self
+    }
+
+    pub fn with_env_separator<S: Into<String>>(mut self, env_separator: S) -> Self {
+        self.env_separator = env_separator.into();
+        self
+    }
+
+    /// Declares a key whose environment variable value is a comma separated list.
+    /// E.g. `auth.default_roles_on_account_creation`
+    pub fn with_env_list_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.env_list_keys.push(key.into());
+        self
+    }
+
+    /// Uses the provided map instead of the process environment variables.
+    pub fn with_env_source(mut self, env_source: HashMap<String, String>) -> Self {
+        self.env_source = Some(env_source);
+        self
+    }
+
+    /// Sets the value of the key to the content of the file. The content is trimmed.
+    pub fn with_secret_file<K: Into<String>, P: Into<PathBuf>>(mut self, key: K, path: P) -> Self {
+        self.secret_files.push((key.into(), path.into()));
+        self
+    }
+
+    /// Returns the active profile
+    pub fn profile(&self) -> Result<Profile, LightSpeedError> {
+        if let Some(profile) = self.profile {
+            return Ok(profile);
+        }
+        match self.env_var(&format!("{}_PROFILE", self.env_prefix)) {
+            Some(profile) => profile.parse(),
+            None => Ok(Profile::Dev),
+        }
+    }
+
+    /// Loads and validates the configuration
+    pub fn load<T: DeserializeOwned + Validable>(&self) -> Result<T, LightSpeedError> {
+        let config = self.load_unvalidated()?;
+        Validator::validate(&config)?;
+        Ok(config)
+    }
+
+    /// Loads the configuration without validating it
+    pub fn load_unvalidated<T: DeserializeOwned>(&self) -> Result<T, LightSpeedError> {
+        let profile = self.profile()?;
+        info!("Load configuration with profile [{}] from [{}]", profile, self.config_dir.display());
+
+        let mut builder = Config::builder()
+            .add_source(File::from(self.config_dir.join(&self.base_name)).required(false))
+            .add_source(File::from(self.config_dir.join(format!("{}-{}", self.base_name, profile))).required(false))
+            .add_source(self.environment());
+
+        for (key, path) in self.secret_files_from_env().iter().chain(self.secret_files.iter()) {
+            debug!("Read secret for key [{}] from file [{}]", key, path.display());
+            builder = builder.set_override(key.as_str(), read_secret(path)?).map_err(|err| {
+                LightSpeedError::ConfigurationError { message: format!("Cannot set secret for key [{key}]: {err:?}") }
+            })?;
+        }
+
+        builder.build().and_then(|config| config.try_deserialize()).map_err(|err| LightSpeedError::ConfigurationError {
+            message: format!("Cannot load the configuration: {err:?}"),
+        })
+    }
+
+    fn environment(&self) -> Environment {
+        let mut environment = Environment::with_prefix(&self.env_prefix)
+            .prefix_separator(&self.env_separator)
+            .separator(&self.env_separator)
+            .try_parsing(true)
+            .ignore_empty(true)
+            .source(self.env_source.clone());
+
+        if !self.env_list_keys.is_empty() {
+            environment = environment.list_separator(",");
+            for key in &self.env_list_keys {
+                environment = environment.with_list_parse_key(key);
+            }
+        }
+
+        environment
+    }
+
+    fn env_var(&self, name: &str) -> Option<String> {
+        match &self.env_source {
+            Some(source) => source.get(name).cloned(),
+            None => std::env::var(name).ok(),
+        }
+    }
+
+    fn env_vars(&self) -> Vec<(String, String)> {
+        match &self.env_source {
+            Some(source) => source.iter().map(|(key, value)| (key.clone(), value.clone())).collect(),
+            None => std::env::vars().collect(),
+        }
+    }
+
+    fn secret_files_from_env(&self) -> Vec<(String, PathBuf)> {
+        let prefix = format!("{}{}", self.env_prefix, self.env_separator).to_lowercase();
+        let suffix = SECRET_FILE_SUFFIX.to_lowercase();
+
+        self.env_vars()
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let name = name.to_lowercase();
+                let key = name.strip_prefix(&prefix)?.strip_suffix(&suffix)?;
+                Some((key.replace(&self.env_separator, "."), PathBuf::from(value)))
+            })
+            .collect()
+    }
+}
+
+fn read_secret(path: &Path) -> Result<String, LightSpeedError> {
+    std::fs::read_to_string(path).map(|secret| secret.trim().to_owned()).map_err(|err| {
+        LightSpeedError::ConfigurationError {
+            message: format!("Cannot read secret file [{}]: {err:?}", path.display()),
+        }
+    })
+}
+
+/// Returns a pretty printed json of the configuration where the values
+/// of the keys matching [`DEFAULT_SECRET_KEYS`] are masked.
+pub fn masked_dump<T: Serialize>(config: &T) -> Result<String, LightSpeedError> {
+    masked_dump_with_secret_keys(config, DEFAULT_SECRET_KEYS)
+}
+
+/// Returns a pretty printed json of the configuration where the values of the keys
+/// equal to one of the `secret_keys`, or ending with `_` followed by one of them, are masked.
+pub fn masked_dump_with_secret_keys<T: Serialize>(config: &T, secret_keys: &[&str]) -> Result<String, LightSpeedError> {
+    let mut value = serde_json::to_value(config)?;
+    mask_secrets(&mut value, secret_keys);
+    serde_json::to_string_pretty(&value).map_err(LightSpeedError::from)
+}
+
+/// Logs the effective configuration with the secrets masked
+pub fn log_effective_config<T: Serialize>(config: &T) -> Result<(), LightSpeedError> {
+    info!("Effective configuration:\n{}", masked_dump(config)?);
+    Ok(())
+}
+
+fn mask_secrets(value: &mut Value, secret_keys: &[&str]) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                let key = key.to_lowercase();
+                if secret_keys.iter().any(|secret_key| is_secret_key(&key, secret_key)) {
+                    mask_value(value);
+                } else {
+                    mask_secrets(value, secret_keys);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(|value| mask_secrets(value, secret_keys)),
+        _ => {}
+    }
+}
+
+fn is_secret_key(key: &str, secret_key: &str) -> bool {
+    key == secret_key || key.strip_suffix(secret_key).map(|prefix| prefix.ends_with('_')).unwrap_or(false)
+}
+
+fn mask_value(value: &mut Value) {
+    match value {
+        Value::Null => {}
+        Value::String(text) if text.is_empty() => {}
+        Value::Array(values) => values.iter_mut().for_each(mask_value),
+        Value::Object(map) => map.values_mut().for_each(mask_value),
+        _ => *value = Value::String(MASKED_VALUE.to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use lightspeed_core::service::validator::ERR_VALUE_REQUIRED;
+    use std::io::Write;
+
+    const TOKEN_HASH_KEY: &str = "0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn should_parse_profile() {
+        assert_eq!(Profile::Prod, "PROD".parse::<Profile>().unwrap());
+        assert_eq!(Profile::Dev, " dev".parse::<Profile>().unwrap());
+        assert!("staging".parse::<Profile>().is_err());
+    }
+
+    #[test]
+    fn should_fail_validation_if_jwt_secret_is_empty() {
+        let result = ConfigLoader::new()
+            .with_config_dir(tempfile::tempdir().unwrap().path())
+            .with_env_source(HashMap::new())
+            .load::<LightspeedConfig>();
+
+        match result {
+            Err(LightSpeedError::ValidationError { details }) => {
+                assert_eq!(ERR_VALUE_REQUIRED, details.details["core.jwt.secret"][0])
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn should_merge_files_profile_and_env() {
+        let dir = tempfile::tempdir().unwrap();
+        write_file(dir.path(), "application.toml", "[core.jwt]\nsecret = \"base\"\ntoken_validity_minutes = 10\n");
+        write_file(dir.path(), "application-test.toml", "[core.jwt]\nsecret = \"test\"\n");
+        write_file(dir.path(), "application-prod.toml", "[core.jwt]\nsecret = \"prod\"\n");
+
+        let env = HashMap::from([
+            ("LS_PROFILE".to_owned(), "test".to_owned()),
+            ("LS__CORE__JWT__TOKEN_VALIDITY_MINUTES".to_owned(), "20".to_owned()),
+            ("LS__AUTH__TOKEN_HASH_KEY".to_owned(), TOKEN_HASH_KEY.to_owned()),
+        ]);
+
+        let config: LightspeedConfig =
+            ConfigLoader::new().with_config_dir(dir.path()).with_env_source(env).load().unwrap();
+
+        assert_eq!("test", config.core.jwt.secret);
+        assert_eq!(20, config.core.jwt.token_validity_minutes);
+    }
+
+    #[test]
+    fn should_read_secrets_from_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let secret_path = write_file(dir.path(), "jwt_secret", "  my_secret\n");
+
+        let env = HashMap::from([
+            ("LS__CORE__JWT__SECRET".to_owned(), "from_env".to_owned()),
+            ("LS__CORE__JWT__SECRET_FILE".to_owned(), secret_path.display().to_string()),
+            ("LS__AUTH__TOKEN_HASH_KEY".to_owned(), TOKEN_HASH_KEY.to_owned()),
+        ]);
+
+        let config: LightspeedConfig =
+            ConfigLoader::new().with_config_dir(dir.path()).with_env_source(env).load().unwrap();
+        assert_eq!("my_secret", config.core.jwt.secret);
+
+        let config: LightspeedConfig = ConfigLoader::new()
+            .with_config_dir(dir.path())
+            .with_env_source(HashMap::from([("LS__AUTH__TOKEN_HASH_KEY".to_owned(), TOKEN_HASH_KEY.to_owned())]))
+            .with_secret_file("core.jwt.secret", &secret_path)
+            .load()
+            .unwrap();
+        assert_eq!("my_secret", config.core.jwt.secret);
+    }
+
+    #[test]
+    fn should_fail_if_secret_file_is_missing() {
+        let result = ConfigLoader::new()
+            .with_env_source(HashMap::new())
+            .with_secret_file("core.jwt.secret", "./not/existing/file")
+            .load::<LightspeedConfig>();
+
+        match result {
+            Err(LightSpeedError::ConfigurationError { .. }) => {}
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn should_mask_secrets_in_dump() {
+        let mut config = LightspeedConfig::default();
+        config.core.jwt.secret = "my_secret".to_owned();
+
+        let dump = masked_dump(&config).unwrap();
+
+        assert!(!dump.contains("my_secret"));
+        assert!(dump.contains(MASKED_VALUE));
+        assert!(dump.contains("token_validity_minutes"));
+    }
+
+    #[test]
+    fn should_mask_the_token_hash_key_in_dump() {
+        let config =
+            serde_json::json!({ "auth": { "token_hash_key": "my_key", "invitation_token_validity_minutes": 60 } });
+
+        let dump = masked_dump(&config).unwrap();
+
+        assert!(!dump.contains("my_key"));
+        assert!(dump.contains(MASKED_VALUE));
+        assert!(dump.contains("invitation_token_validity_minutes"));
+    }
+
+    #[test]
+    fn should_mask_only_the_keys_ending_with_a_secret_key() {
+        let config = serde_json::json!({ "auth": {
+            "oauth2_access_token_secret": "my_secret",
+            "email_server_password": "my_password",
+            "password_history_size": 5,
+            "password_hash_algorithm": "Argon2id",
+        } });
+
+        let dump = masked_dump(&config).unwrap();
+
+        assert!(!dump.contains("my_secret"));
+        assert!(!dump.contains("my_password"));
+        assert!(dump.contains("\"password_history_size\": 5"));
+        assert!(dump.contains("Argon2id"));
+    }
+
+    fn write_file(dir: &Path, name: &str, content: &str) -> PathBuf {
+        let path = dir.join(name);
+        let mut file = std::fs::File::create(&path).unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        path
+    }
+}
@@ -1,3 +1,8 @@
+#[cfg(feature = "core")]
+pub mod bootstrap;
+#[cfg(feature = "core")]
+pub mod config;
+
 #[cfg(feature = "c3p0")]
 pub use c3p0;
 
@@ -1,9 +1,9 @@
 use crate::LoggerError;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
 /// Defines the Logger configuration.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct LoggerConfig {
     /// Sets the logger [`EnvFilter`].
@@ -24,7 +24,7 @@ impl Default for LoggerConfig {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct StandardOutputConfig {
     /// Determines whether the Logger should print to standard output.
@@ -39,7 +39,7 @@ impl Default for StandardOutputConfig {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct FileOutputConfig {
     /// Determines whether the Logger should print to a file.
@@ -67,7 +67,7 @@ impl Default for FileOutputConfig {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Rotation {
     Minutely,
     Hourly,
@@ -1,11 +1,12 @@
 pub mod config;
 pub mod utils;
 
+pub use tracing_appender::non_blocking::WorkerGuard;
+
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 use tracing::Subscriber;
-use tracing_appender::non_blocking::WorkerGuard;
 use tracing_appender::rolling::RollingFileAppender;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::{filter::Targets, fmt::Layer, layer::SubscriberExt};
@@ -2,6 +2,7 @@ use crate::error::SchedulerError;
 use crate::scheduler::Scheduler;
 use chrono::{DateTime, Utc};
 use chrono_tz::Tz;
+use lightspeed_clock::Clock;
 use log::*;
 use std::{future::Future, sync::Arc};
 use std::pin::Pin;
@@ -13,18 +14,20 @@ pub struct JobScheduler {
     timezone: Option<Tz>,
     next_run_at: Mutex<Option<DateTime<Utc>>>,
     last_run_at: Mutex<Option<DateTime<Utc>>>,
+    clock: Arc<dyn Clock>,
 }
 
 impl JobScheduler {
-    pub fn new(mut schedule: Scheduler, timezone: Option<Tz>, job: Job) -> Self {
+    pub fn new(mut schedule: Scheduler, timezone: Option<Tz>, job: Job, clock: Arc<dyn Clock>) -> Self {
         // Determine the next time it should run
-        let next_run_at = schedule.next(&Utc::now(), timezone);
+        let next_run_at = schedule.next(&clock.now(), timezone);
         JobScheduler {
             job,
             schedule: Mutex::new(schedule),
             timezone,
             next_run_at: Mutex::new(next_run_at),
             last_run_at: Mutex::new(None),
+            clock,
         }
     }
 
@@ -37,7 +40,7 @@ impl JobScheduler {
 
         // Check if NOW is on or after next_run_at
         if let Some(next_run_at) = self.next_run_at.lock().await.as_ref() {
-            *next_run_at < Utc::now()
+            *next_run_at < self.clock.now()
         } else {
             false
         }
@@ -48,7 +51,7 @@ impl JobScheduler {
         // Execute the job function
         let run_result = self.job.run().await;
 
-        let now = Utc::now();
+        let now = self.clock.now();
 
         let mut schedule = self.schedule.lock().await;
 
@@ -195,6 +198,7 @@ pub mod test {
                     Ok(())
                 })
             }),
+            Arc::new(lightspeed_clock::SystemClock),
         ));
 
         assert!(!job_scheduler.job.is_running().await);
@@ -2,8 +2,8 @@ use crate::error::SchedulerError;
 use crate::job::{Job, JobScheduler};
 use crate::scheduler::{Scheduler, TryToScheduler};
 use atomic::Atomic;
-use chrono::Utc;
 use chrono_tz::{Tz, UTC};
+use lightspeed_clock::{Clock, SystemClock};
 use log::*;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -26,6 +26,7 @@ struct JobExecutorInternal {
     running: AtomicBool,
     timezone: Option<Tz>,
     jobs: RwLock<Vec<Arc<JobScheduler>>>,
+    clock: Arc<dyn Clock>,
 }
 
 impl JobExecutorInternal {
@@ -86,7 +87,7 @@ impl JobExecutorInternal {
                 if !job_scheduler.job.is_running().await {
                     let job_clone = job_scheduler.clone();
 
-                    let timestamp = Utc::now().timestamp();
+                    let timestamp = self.clock.epoch_seconds();
                     let group = job_clone.job.group();
                     let name = job_clone.job.name();
                     let span = tracing::error_span!("run_pending", group, name, timestamp);
@@ -143,7 +144,7 @@ impl JobExecutorInternal {
     async fn add_job_with_scheduler<S: Into<Scheduler>>(&self, schedule: S, job: Job) {
         info!("Add job to scheduler. Group [{}] - Name [{}]", job.group(), job.name());
         let mut jobs = self.jobs.write().await;
-        jobs.push(Arc::new(JobScheduler::new(schedule.into(), self.timezone, job)));
+        jobs.push(Arc::new(JobScheduler::new(schedule.into(), self.timezone, job, self.clock.clone())));
     }
 }
 
@@ -164,12 +165,19 @@ impl JobExecutor {
     /// Creates a new Executor that uses a custom time zone for the execution times evaluation.
     /// For example, the cron expressions will refer to the specified time zone.
     pub fn new_with_tz(timezone: Option<Tz>) -> JobExecutor {
+        Self::new_with_tz_and_clock(timezone, SystemClock::shared())
+    }
+
+    /// Creates a new Executor that uses a custom time zone for the execution times evaluation
+    /// and reads the current time from the given clock.
+    pub fn new_with_tz_and_clock(timezone: Option<Tz>, clock: Arc<dyn Clock>) -> JobExecutor {
         JobExecutor {
             executor: Arc::new(JobExecutorInternal {
                 sleep_between_checks: Atomic::new(Duration::new(1, 0)),
                 running: AtomicBool::new(false),
                 timezone,
                 jobs: RwLock::new(vec![]),
+                clock,
             }),
         }
 }
@@ -375,6 +383,37 @@ pub mod test {
         assert_eq!(count_3.load(Ordering::SeqCst), 1);
     }
 
+    #[tokio::test]
+    async fn should_run_jobs_according_to_the_clock() {
+        let clock = lightspeed_clock::MockClock::default();
+        let executor = JobExecutor::new_with_tz_and_clock(Some(UTC), Arc::new(clock.clone()));
+
+        let (tx, mut rx) = channel(1000);
+
+        executor
+            .add_job(
+                &Duration::from_secs(60 * 60),
+                Job::new("g", "n", None, move || {
+                    let tx = tx.clone();
+                    Box::pin(async move {
+                        tx.send("").await.unwrap();
+                        Ok(())
+                    })
+                }),
+            )
+            .await
+            .unwrap();
+
+        executor.executor.run_pending_jobs().await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(rx.try_recv().is_err());
+
+        clock.advance(Duration::from_secs(60 * 60 + 1));
+
+        executor.executor.run_pending_jobs().await;
+        rx.recv().await.unwrap();
+    }
+
     #[tokio::test]
     async fn should_gracefully_shutdown_the_job_executor() {
         let executor = JobExecutor::new_with_utc_tz();